@@ -0,0 +1,316 @@
+//! Annotations.
+//!
+//! Annotations are parsed along with the elements they decorate. This module collects them by element, so that they
+//! can be looked up from a symbol, and checks the annotations given a meaning by the language: the arguments they
+//! take, the elements they apply to, and that they are not repeated. It also computes the control-plane names of the
+//! tables, actions and instances, which `@name` overrides, and reports the names given to several of them.
+
+use std::collections::HashMap;
+
+use crate::{
+	ast::{
+		visit::{self, Visitor},
+		*,
+	},
+	base_abstractions::*,
+	lints, parse, Fs,
+};
+
+/// The annotations of a translation unit.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Annotations {
+	/// The annotations of each annotated element, by the location of its name, or of the whole element for the key
+	/// elements, entries, table properties and statements.
+	elements: HashMap<Location, Vec<Annotation>>,
+	/// The fully qualified control-plane names of the tables, actions and instances, by the location of their names.
+	control_plane_names: HashMap<Location, String>,
+}
+
+impl Annotations {
+	/// Returns the annotations of the element at `location`.
+	pub fn get(&self, location: &Location) -> &[Annotation] { self.elements.get(location).map_or(&[], Vec::as_slice) }
+
+	/// Returns the annotation named `name` of the element at `location`.
+	pub fn find(&self, location: &Location, name: &str) -> Option<&Annotation> { annotation(self.get(location), name) }
+
+	/// Returns the control-plane name of the table, action or instance whose name is at `location`, e.g.
+	/// `MyIngress.forward`.
+	pub fn control_plane_name(&self, location: &Location) -> Option<&str> {
+		self.control_plane_names.get(location).map(String::as_str)
+	}
+}
+
+/// The kinds of elements that can be annotated.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Element {
+	Declaration,
+	Function,
+	State,
+	Field,
+	Parameter,
+	ActionReference,
+	Property,
+	Key,
+	Entry,
+	Statement,
+}
+
+impl Element {
+	fn describe(self) -> &'static str {
+		match self {
+			Element::Declaration => "a declaration",
+			Element::Function => "a function",
+			Element::State => "a parser state",
+			Element::Field => "a field",
+			Element::Parameter => "a parameter",
+			Element::ActionReference => "an action of a table",
+			Element::Property => "a table property",
+			Element::Key => "a key element",
+			Element::Entry => "an entry",
+			Element::Statement => "a statement",
+		}
+	}
+}
+
+/// The arguments taken by a well-known annotation.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Arguments {
+	None,
+	String,
+	Integer,
+}
+
+/// Every element but statements, which the control-plane annotations apply to.
+const NAMED: &[Element] = &[
+	Element::Declaration,
+	Element::Function,
+	Element::State,
+	Element::Field,
+	Element::Parameter,
+	Element::ActionReference,
+	Element::Property,
+	Element::Key,
+	Element::Entry,
+];
+
+/// The annotations given a meaning by the language, with the arguments they take and the elements they apply to, or
+/// `None` if they apply to any element.
+static WELL_KNOWN: &[(&str, Arguments, Option<&[Element]>)] = &[
+	("name", Arguments::String, Some(NAMED)),
+	("id", Arguments::Integer, Some(NAMED)),
+	("hidden", Arguments::None, Some(NAMED)),
+	("deprecated", Arguments::String, None),
+	("noWarn", Arguments::String, None),
+	("brief", Arguments::String, None),
+	("description", Arguments::String, None),
+	("defaultonly", Arguments::None, Some(&[Element::ActionReference])),
+	("tableonly", Arguments::None, Some(&[Element::ActionReference])),
+	("optional", Arguments::None, Some(&[Element::Parameter])),
+	("atomic", Arguments::None, Some(&[Element::Statement])),
+	("priority", Arguments::Integer, Some(&[Element::Entry])),
+	("pure", Arguments::None, Some(&[Element::Function])),
+	("noSideEffects", Arguments::None, Some(&[Element::Function])),
+];
+
+/// Collects and checks the annotations of the translation unit rooted at `file_id`.
+#[salsa::tracked(return_ref)]
+pub fn annotations(db: &dyn crate::Db, fs: Fs, file_id: FileId) -> Option<Annotations> {
+	let program = parse(db, fs, file_id).as_ref()?;
+
+	let mut collector =
+		Collector { annotations: Annotations::default(), prefixes: vec![], objects: vec![], diagnostics: vec![] };
+	visit::walk_program(&mut collector, program);
+	collector.check_control_plane_names();
+
+	for diagnostic in collector.diagnostics {
+		Diagnostics::push(db, diagnostic);
+	}
+	Some(collector.annotations)
+}
+
+struct Collector<'a> {
+	annotations: Annotations,
+	/// The control-plane names of the parsers and controls enclosing the current declaration.
+	prefixes: Vec<String>,
+	/// The names of the control-plane objects, with their `@name` annotations.
+	objects: Vec<(&'a Identifier, Option<&'a Annotation>)>,
+	diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> Collector<'a> {
+	fn element(&mut self, location: &Location, element: Element, annotations: &'a [Annotation]) {
+		if annotations.is_empty() {
+			return;
+		}
+
+		let mut seen: HashMap<&str, &Annotation> = HashMap::new();
+		for annotation in annotations {
+			let name = annotation.name.name.as_str();
+			let Some((_, arguments, elements)) = WELL_KNOWN.iter().find(|(known, ..)| *known == name) else { continue };
+
+			let valid = match arguments {
+				Arguments::None => match &annotation.body {
+					AnnotationBody::Empty => true,
+					AnnotationBody::Unstructured(tokens) => tokens.is_empty(),
+					_ => false,
+				},
+				Arguments::String => annotation.string().is_some(),
+				Arguments::Integer => annotation.integer().is_some(),
+			};
+			if !valid {
+				let message = match arguments {
+					Arguments::None => format!("the annotation `@{name}` takes no arguments"),
+					Arguments::String => format!("the annotation `@{name}` expects a string literal"),
+					Arguments::Integer => format!("the annotation `@{name}` expects an integer literal"),
+				};
+				self.error(&annotation.location, message);
+			}
+
+			if elements.map_or(false, |elements| !elements.contains(&element)) {
+				let message = format!("the annotation `@{name}` has no effect on {}", element.describe());
+				self.diagnostics.push(lints::MISPLACED_ANNOTATION.diagnostic(&annotation.name.location, message));
+			}
+
+			// several `@noWarn`s silence several warnings
+			if name != "noWarn" {
+				if let Some(previous) = seen.insert(name, annotation) {
+					let Location { file, span } = annotation.location.clone();
+					let message = format!("the annotation `@{name}` is repeated");
+					self.diagnostics.push(
+						Diagnostic::new(file, span, Severity::Error, message)
+							.with_related(previous.location.clone(), "first given here".to_string()),
+					);
+				}
+			}
+		}
+		if let (Some(_), Some(tableonly)) = (seen.get("defaultonly"), seen.get("tableonly")) {
+			let message = "an action cannot be both `@defaultonly` and `@tableonly`".to_string();
+			self.error(&tableonly.location, message);
+		}
+
+		self.annotations.elements.insert(location.clone(), annotations.to_vec());
+	}
+
+	/// Returns the fully qualified control-plane name of a declaration named `name` in the current scope.
+	fn control_plane_name(&self, name: &Identifier, annotations: &[Annotation]) -> String {
+		let local = annotation(annotations, "name").and_then(Annotation::string).unwrap_or(&name.name);
+		match (local.strip_prefix('.'), self.prefixes.last()) {
+			(Some(absolute), _) => absolute.to_string(),
+			(None, Some(prefix)) => format!("{prefix}.{local}"),
+			(None, None) => local.to_string(),
+		}
+	}
+
+	/// Reports the control-plane names given to several objects by a `@name` annotation. The objects named the same
+	/// without annotations are already reported as duplicate declarations.
+	fn check_control_plane_names(&mut self) {
+		let mut seen: HashMap<&str, (&Identifier, Option<&Annotation>)> = HashMap::new();
+		for (name, annotation) in std::mem::take(&mut self.objects) {
+			let Some(cp_name) = self.annotations.control_plane_names.get(&name.location) else { continue };
+			let Some((previous, previous_annotation)) = seen.get(cp_name.as_str()).copied() else {
+				seen.insert(cp_name, (name, annotation));
+				continue;
+			};
+			if annotation.is_none() && previous_annotation.is_none() {
+				continue;
+			}
+
+			let location = annotation.map_or(&name.location, |annotation| &annotation.location);
+			let Location { file, span } = location.clone();
+			let message = format!("the control-plane name `{cp_name}` is already given to `{}`", previous.name);
+			let related = previous_annotation.map_or(&previous.location, |annotation| &annotation.location);
+			self.diagnostics.push(
+				Diagnostic::new(file, span, Severity::Error, message)
+					.with_related(related.clone(), "given here".to_string()),
+			);
+		}
+	}
+
+	fn error(&mut self, location: &Location, message: String) {
+		let Location { file, span } = location.clone();
+		self.diagnostics.push(Diagnostic::new(file, span, Severity::Error, message));
+	}
+}
+
+impl<'a> Visitor<'a> for Collector<'a> {
+	fn visit_declaration(&mut self, declaration: &'a Declaration) {
+		let element = match declaration {
+			Declaration::ExternFunction(_) | Declaration::Function(_) => Element::Function,
+			_ => Element::Declaration,
+		};
+		let location = declaration.name().map_or(declaration.location(), |name| &name.location);
+		self.element(location, element, declaration.annotations());
+
+		match declaration {
+			Declaration::Action(_) | Declaration::Table(_) | Declaration::Instantiation(_) => {
+				let name = declaration.name().expect("actions, tables and instances are named");
+				let cp_name = self.control_plane_name(name, declaration.annotations());
+				self.annotations.control_plane_names.insert(name.location.clone(), cp_name);
+				self.objects.push((name, annotation(declaration.annotations(), "name")));
+			}
+			_ => (),
+		}
+
+		match declaration {
+			Declaration::Struct(s) => {
+				for field in &s.fields {
+					self.element(&field.name.location, Element::Field, &field.annotations);
+				}
+			}
+			Declaration::ExternObject(e) => {
+				for method in &e.methods {
+					self.element(&method.name.location, Element::Function, &method.annotations);
+				}
+			}
+			Declaration::Parser(p) => {
+				for state in &p.states {
+					self.element(&state.name.location, Element::State, &state.annotations);
+				}
+			}
+			Declaration::Table(t) => {
+				for property in &t.properties {
+					self.element(property.location(), Element::Property, property.annotations());
+					match property {
+						TableProperty::Key(elements, _) => {
+							for element in elements {
+								self.element(&element.location, Element::Key, &element.annotations);
+							}
+						}
+						TableProperty::Actions(actions, _) => {
+							for action in actions {
+								self.element(&action.name.location, Element::ActionReference, &action.annotations);
+							}
+						}
+						TableProperty::Entries { entries, .. } => {
+							for entry in entries {
+								self.element(&entry.location, Element::Entry, &entry.annotations);
+							}
+						}
+						_ => (),
+					}
+				}
+			}
+			_ => (),
+		}
+
+		match declaration {
+			Declaration::Parser(Parser { name, annotations, .. })
+			| Declaration::Control(Control { name, annotations, .. }) => {
+				self.prefixes.push(self.control_plane_name(name, annotations));
+				visit::walk_declaration(self, declaration);
+				self.prefixes.pop();
+			}
+			_ => visit::walk_declaration(self, declaration),
+		}
+	}
+
+	fn visit_parameter(&mut self, parameter: &'a Parameter) {
+		self.element(&parameter.name.location, Element::Parameter, &parameter.annotations);
+		visit::walk_parameter(self, parameter);
+	}
+
+	fn visit_statement(&mut self, statement: &'a Statement) {
+		self.element(&statement.location, Element::Statement, &statement.annotations);
+		visit::walk_statement(self, statement);
+	}
+}
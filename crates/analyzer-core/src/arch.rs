@@ -0,0 +1,400 @@
+//! Models of the target architectures.
+//!
+//! A program targets an architecture by including its header, e.g. `#include <v1model.p4>`, which declares the
+//! programmable blocks of the target's pipeline, the packages composing them, and the externs, metadata and match
+//! kinds the target provides. The headers of the v1model, PSA and PNA architectures are embedded in the analyzer like
+//! the core library, and the preprocessor includes the embedded header in place of one that cannot be found.
+//!
+//! The [`ArchModel`] of a program collects the declarations of its architecture, against which the instantiation of
+//! `main` is checked: every block passed to a package must have the parameters of the block type the architecture
+//! expects in its place.
+
+use std::collections::HashMap;
+
+use logos::Logos;
+
+use crate::{
+	ast::*,
+	base_abstractions::*,
+	lexer::{Lextras, Token},
+	parse, preprocess,
+	symbols::{self, SymbolId, SymbolKind, SymbolTable},
+	types::{self, Type, TypeInfo},
+	Fs,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Architecture {
+	V1Model,
+	/// The Portable Switch Architecture.
+	Psa,
+	/// The Portable NIC Architecture.
+	Pna,
+}
+
+impl Architecture {
+	pub const ALL: [Architecture; 3] = [Architecture::V1Model, Architecture::Psa, Architecture::Pna];
+
+	pub fn name(&self) -> &'static str {
+		match self {
+			Architecture::V1Model => "v1model",
+			Architecture::Psa => "PSA",
+			Architecture::Pna => "PNA",
+		}
+	}
+
+	/// Returns the file name of the architecture's header.
+	pub fn header(&self) -> &'static str {
+		match self {
+			Architecture::V1Model => "v1model.p4",
+			Architecture::Psa => "psa.p4",
+			Architecture::Pna => "pna.p4",
+		}
+	}
+
+	/// Returns the path of the embedded header.
+	pub fn path(&self) -> &'static str {
+		match self {
+			Architecture::V1Model => "builtin:///v1model.p4",
+			Architecture::Psa => "builtin:///psa.p4",
+			Architecture::Pna => "builtin:///pna.p4",
+		}
+	}
+
+	/// Returns the source of the embedded header.
+	pub fn source(&self) -> &'static str {
+		match self {
+			Architecture::V1Model => include_str!("../resources/v1model.p4"),
+			Architecture::Psa => include_str!("../resources/psa.p4"),
+			Architecture::Pna => include_str!("../resources/pna.p4"),
+		}
+	}
+
+	/// Returns the architecture whose header is at `path`, if any.
+	pub fn from_path(path: &str) -> Option<Self> {
+		let name = path.rsplit(['/', '\\']).next()?;
+		Self::ALL.into_iter().find(|architecture| architecture.header() == name)
+	}
+}
+
+/// The architecture header included by a program.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ArchInclude {
+	pub architecture: Architecture,
+	pub file_id: FileId,
+	/// `false` if the header cannot be found, in which case the embedded header is used in its place.
+	pub is_resolved: bool,
+}
+
+/// Finds the architecture header included by the translation unit rooted at `file_id`.
+#[salsa::tracked]
+pub fn include(db: &dyn crate::Db, fs: Fs, file_id: FileId) -> Option<ArchInclude> {
+	preprocess::accumulated::<IncludedDependencies>(db, fs, file_id).into_iter().find_map(|dependency| {
+		Some(ArchInclude {
+			architecture: Architecture::from_path(&dependency.file_id.path(db))?,
+			file_id: dependency.file_id,
+			is_resolved: dependency.is_resolved,
+		})
+	})
+}
+
+/// Lexes the embedded architecture header or core library at `file_id`, if it is one.
+#[salsa::tracked(return_ref)]
+pub fn embedded_lexemes(db: &dyn crate::Db, file_id: FileId) -> Option<Vec<(Token, Span)>> {
+	let source = crate::builtin_source(&file_id.path(db))?;
+	Some(Token::lexer_with_extras(source, Lextras { db: None, file_id }).spanned().collect())
+}
+
+/// The declarations of the architecture a program targets.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ArchModel {
+	pub architecture: Architecture,
+	/// The file declaring the architecture: the included header, or the embedded one if it cannot be found.
+	pub file: FileId,
+	/// The packages `main` may instantiate, which are not part of another package (e.g. `V1Switch`).
+	pub main_packages: Vec<SymbolId>,
+	/// All the packages, including those composing others (e.g. the `IngressPipeline` of PSA).
+	pub packages: Vec<SymbolId>,
+	/// The parser and control types of the programmable blocks.
+	pub blocks: Vec<SymbolId>,
+	/// The extern objects and functions.
+	pub externs: Vec<SymbolId>,
+	/// The metadata structs the programmable blocks are given (e.g. `standard_metadata_t`).
+	pub metadata: Vec<SymbolId>,
+	/// The match kinds added to those of the core library.
+	pub match_kinds: Vec<SymbolId>,
+	/// The parsers and controls of the program passed to each package instantiated by `main`, including the packages
+	/// passed to it in turn (e.g. the ingress and egress pipelines of PSA).
+	pub main_blocks: Vec<Vec<SymbolId>>,
+}
+
+impl ArchModel {
+	/// Returns `true` if the architecture declares the symbol.
+	pub fn declares(&self, table: &SymbolTable, id: SymbolId) -> bool { table.symbol(id).location.file == self.file }
+}
+
+/// Builds the architecture model of the translation unit rooted at `file_id`, and checks its `main` package
+/// instantiation.
+#[salsa::tracked(return_ref)]
+pub fn model(db: &dyn crate::Db, fs: Fs, file_id: FileId) -> Option<ArchModel> {
+	let include = include(db, fs, file_id)?;
+	let program = parse(db, fs, file_id).as_ref()?;
+	let table = symbols::symbols(db, fs, file_id).as_ref()?;
+	let info = types::types(db, fs, file_id).as_ref()?;
+
+	let file = match include.is_resolved {
+		true => include.file_id,
+		false => FileId::new(db, include.architecture.path().to_string()),
+	};
+	let mut checker = Checker {
+		table,
+		info,
+		declarations: HashMap::new(),
+		symbols: table.symbols().map(|(id, symbol)| (symbol.location.clone(), id)).collect(),
+		bindings: vec![],
+		main_blocks: vec![],
+		diagnostics: vec![],
+	};
+	for declaration in &program.declarations {
+		if let Some(id) = declaration.name().and_then(|name| checker.symbols.get(&name.location)) {
+			checker.declarations.insert(*id, declaration);
+		}
+	}
+
+	let mut model = checker.model(include.architecture, file);
+	checker.check_main(&model);
+	model.main_blocks = checker.main_blocks;
+	for diagnostic in checker.diagnostics {
+		Diagnostics::push(db, diagnostic);
+	}
+	Some(model)
+}
+
+struct Checker<'a> {
+	table: &'a SymbolTable,
+	info: &'a TypeInfo,
+	/// The top-level declarations of the program.
+	declarations: HashMap<SymbolId, &'a Declaration>,
+	/// The symbols declared by the names at a given location.
+	symbols: HashMap<Location, SymbolId>,
+	/// The types bound to the type parameters of the packages and blocks of the architecture so far.
+	bindings: Vec<(SymbolId, Type)>,
+	/// The blocks passed to each package instantiated so far.
+	main_blocks: Vec<Vec<SymbolId>>,
+	diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> Checker<'a> {
+	fn model(&self, architecture: Architecture, file: FileId) -> ArchModel {
+		let mut model = ArchModel {
+			architecture,
+			file,
+			main_packages: vec![],
+			packages: vec![],
+			blocks: vec![],
+			externs: vec![],
+			metadata: vec![],
+			match_kinds: vec![],
+			main_blocks: vec![],
+		};
+
+		let table = self.table;
+		let declared = table.symbols().filter(|(_, symbol)| symbol.location.file == file && symbol.scope == table.root);
+		for (id, symbol) in declared {
+			match symbol.kind {
+				SymbolKind::Package => model.packages.push(id),
+				SymbolKind::ParserType | SymbolKind::ControlType => model.blocks.push(id),
+				SymbolKind::ExternObject | SymbolKind::ExternFunction => model.externs.push(id),
+				SymbolKind::MatchKind => model.match_kinds.push(id),
+				_ => (),
+			}
+		}
+
+		for block in &model.blocks {
+			for param in self.params(*block) {
+				if let Some(Type::Struct(id, _)) = self.info.type_ref(&param.ty.location) {
+					if model.declares(table, *id) && !model.metadata.contains(id) {
+						model.metadata.push(*id);
+					}
+				}
+			}
+		}
+
+		let components: Vec<_> = model
+			.packages
+			.iter()
+			.flat_map(|package| self.params(*package))
+			.filter_map(|param| match self.info.type_ref(&param.ty.location) {
+				Some(Type::Package(id, _)) => Some(*id),
+				_ => None,
+			})
+			.collect();
+		model.main_packages = model.packages.iter().copied().filter(|id| !components.contains(id)).collect();
+		model
+	}
+
+	/// Returns the parameters of a parser, control or package declaration (not those of its constructor).
+	fn params(&self, id: SymbolId) -> &'a [Parameter] {
+		match self.declarations.get(&id) {
+			Some(Declaration::Parser(p)) => &p.params,
+			Some(Declaration::Control(c)) => &c.params,
+			Some(Declaration::Package(p)) => &p.params,
+			_ => &[],
+		}
+	}
+
+	fn type_params(&self, id: SymbolId) -> Vec<SymbolId> {
+		let names = match self.declarations.get(&id) {
+			Some(Declaration::Parser(p)) => &p.type_params,
+			Some(Declaration::Control(c)) => &c.type_params,
+			Some(Declaration::Package(p)) => &p.type_params,
+			_ => return vec![],
+		};
+		names.iter().filter_map(|name| self.symbols.get(&name.location).copied()).collect()
+	}
+
+	fn check_main(&mut self, model: &ArchModel) {
+		let table = self.table;
+		let Some(&main) = table.members(table.root, "main").first() else { return };
+		let Some(Declaration::Instantiation(instance)) = self.declarations.get(&main).copied() else { return };
+
+		let package = match self.info.type_ref(&instance.ty.location) {
+			Some(Type::Package(id, _)) => *id,
+			Some(ty) if !ty.is_unknown() => {
+				let message = format!("`main` must be an instance of a package, found `{}`", ty.display(table));
+				return self.error(&instance.ty.location, message);
+			}
+			_ => return,
+		};
+		if model.packages.contains(&package) && !model.main_packages.contains(&package) {
+			let expected: Vec<_> =
+				model.main_packages.iter().map(|id| format!("`{}`", table.symbol(*id).name)).collect();
+			let message = format!(
+				"`{}` is a part of another package of the {} architecture; `main` must instantiate {}",
+				table.symbol(package).name,
+				model.architecture.name(),
+				expected.join(" or ")
+			);
+			return self.error(&instance.ty.location, message);
+		}
+
+		self.bind_type_args(package, &instance.ty);
+		self.package(package, &instance.args);
+	}
+
+	/// Checks the blocks passed to a package.
+	fn package(&mut self, package: SymbolId, args: &[Argument]) {
+		let params = self.params(package);
+		// the number of arguments is checked by the type checker
+		if params.len() != args.len() {
+			return;
+		}
+
+		let instance = self.main_blocks.len();
+		self.main_blocks.push(vec![]);
+		for (param, arg) in params.iter().zip(args) {
+			let expected = self.info.type_ref(&param.ty.location);
+			let actual = self.info.expression_type(&arg.value.location);
+			match (expected, actual) {
+				(Some(Type::Parser(block)), Some(Type::Parser(user)))
+				| (Some(Type::Control(block)), Some(Type::Control(user))) => {
+					self.bind_type_args(*block, &param.ty);
+					self.block(package, *block, *user, &arg.value.location);
+					self.main_blocks[instance].push(*user);
+				}
+				(Some(Type::Package(inner, _)), Some(Type::Package(..))) => {
+					if let ExpressionKind::Call(_, _, args) = &arg.value.kind {
+						self.bind_type_args(*inner, &param.ty);
+						self.package(*inner, args);
+					}
+				}
+				_ => (),
+			}
+		}
+	}
+
+	/// Checks that the parameters of the block `user` match those of the block type `block` expected by `package`.
+	fn block(&mut self, package: SymbolId, block: SymbolId, user: SymbolId, location: &Location) {
+		let table = self.table;
+		let context = format!("to match `{}` in `{}`", table.symbol(block).name, table.symbol(package).name);
+		let (expected, actual) = (self.params(block), self.params(user));
+		if expected.len() != actual.len() {
+			let name = &table.symbol(user).name;
+			let message =
+				format!("`{name}` should have {} parameters {context}, found {}", expected.len(), actual.len());
+			return self.error(location, message);
+		}
+
+		for (expected, actual) in expected.iter().zip(actual) {
+			let name = &actual.name.name;
+			if expected.direction != actual.direction {
+				let message = format!(
+					"the parameter `{name}` should be {} {context}, found {}",
+					direction_text(expected.direction),
+					direction_text(actual.direction)
+				);
+				self.error(&actual.location, message);
+				continue;
+			}
+
+			let (Some(expected), Some(actual_type)) =
+				(self.info.type_ref(&expected.ty.location), self.info.type_ref(&actual.ty.location))
+			else {
+				continue;
+			};
+			if actual_type.is_unknown() {
+				continue;
+			}
+			match self.bound(expected) {
+				Type::TypeParameter(param) => self.bindings.push((param, actual_type.clone())),
+				expected if !expected.is_unknown() && expected != *actual_type => {
+					let message = format!(
+						"the parameter `{name}` should have type `{}` {context}, found `{}`",
+						expected.display(table),
+						actual_type.display(table)
+					);
+					self.error(&actual.ty.location, message);
+				}
+				_ => (),
+			}
+		}
+	}
+
+	/// Binds the type parameters of a generic package or block to the type arguments given to it in `ty`.
+	fn bind_type_args(&mut self, id: SymbolId, ty: &TypeRef) {
+		let TypeRefKind::Named(_, args) = &ty.kind else { return };
+		for (param, arg) in self.type_params(id).into_iter().zip(args) {
+			if let Some(arg) = self.info.type_ref(&arg.location) {
+				let arg = self.bound(arg);
+				self.bindings.retain(|(bound, _)| *bound != param);
+				self.bindings.push((param, arg));
+			}
+		}
+	}
+
+	/// Substitutes the bound type parameters in `ty`, which may be bound to other type parameters in turn.
+	fn bound(&self, ty: &Type) -> Type {
+		let mut ty = ty.clone();
+		loop {
+			let substituted = types::substitute(&ty, &self.bindings);
+			if substituted == ty {
+				return ty;
+			}
+			ty = substituted;
+		}
+	}
+
+	fn error(&mut self, location: &Location, message: String) {
+		let Location { file, span } = location.clone();
+		self.diagnostics.push(Diagnostic::new(file, span, Severity::Error, message));
+	}
+}
+
+fn direction_text(direction: Option<Direction>) -> &'static str {
+	match direction {
+		Some(Direction::In) => "`in`",
+		Some(Direction::Out) => "`out`",
+		Some(Direction::InOut) => "`inout`",
+		None => "directionless",
+	}
+}
@@ -0,0 +1,648 @@
+//! The abstract syntax tree of a P4 program.
+//!
+//! Every node records the [`Location`] of the source text it was parsed from. Since the tree is built from a
+//! preprocessed token stream, the nodes of a single tree may originate from several files.
+
+pub mod parser;
+pub mod visit;
+
+use num_traits::ToPrimitive;
+
+use crate::{
+	base_abstractions::*,
+	lexer::{Literal, Token},
+};
+
+pub use parser::parse;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Identifier {
+	pub name: String,
+	pub location: Location,
+}
+
+/// An annotation, such as `@name("x")`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Annotation {
+	pub name: Identifier,
+	pub body: AnnotationBody,
+	pub location: Location,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AnnotationBody {
+	/// `@name`
+	Empty,
+	/// `@name(...)`, or the rest of the line after `@pragma`: the tokens of the body, which are only given a meaning by
+	/// the annotation itself.
+	Unstructured(Vec<(Token, Location)>),
+	/// `@name[a, b]`
+	Expressions(Vec<Expression>),
+	/// `@name[key = a, other = b]`
+	KeyValues(Vec<(Identifier, Expression)>),
+}
+
+impl Annotation {
+	/// Returns the argument of an annotation whose body is a single string literal, such as `@name("x")`.
+	pub fn string(&self) -> Option<&str> {
+		match &self.body {
+			AnnotationBody::Unstructured(tokens) => match tokens.as_slice() {
+				[(Token::StringLiteral(value), _)] => Some(value),
+				_ => None,
+			},
+			AnnotationBody::Expressions(expressions) => match expressions.as_slice() {
+				[Expression { kind: ExpressionKind::String(value), .. }] => Some(value),
+				_ => None,
+			},
+			_ => None,
+		}
+	}
+
+	/// Returns the argument of an annotation whose body is a single integer literal, such as `@id(1)`.
+	pub fn integer(&self) -> Option<i64> {
+		match &self.body {
+			AnnotationBody::Unstructured(tokens) => match tokens.as_slice() {
+				[(Token::Integer(literal), _)] => literal.value.to_i64(),
+				_ => None,
+			},
+			AnnotationBody::Expressions(expressions) => match expressions.as_slice() {
+				[Expression { kind: ExpressionKind::Integer(literal), .. }] => literal.value.to_i64(),
+				_ => None,
+			},
+			_ => None,
+		}
+	}
+}
+
+/// Returns the first of `annotations` named `name`.
+pub fn annotation<'a>(annotations: &'a [Annotation], name: &str) -> Option<&'a Annotation> {
+	annotations.iter().find(|annotation| annotation.name.name == name)
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct Program {
+	pub declarations: Vec<Declaration>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Declaration {
+	Constant(Constant),
+	Variable(Variable),
+	Instantiation(Instantiation),
+	Typedef(Typedef),
+	Struct(Struct),
+	Enum(Enum),
+	Error(Members),
+	MatchKind(Members),
+	ExternObject(ExternObject),
+	ExternFunction(FunctionPrototype),
+	Parser(Parser),
+	Control(Control),
+	Package(Package),
+	Action(Action),
+	Function(Function),
+	Table(Table),
+}
+
+impl Declaration {
+	/// Returns the name of the declaration, if it introduces one. `error` and `match_kind` declarations only add
+	/// members to existing namespaces.
+	pub fn name(&self) -> Option<&Identifier> {
+		match self {
+			Declaration::Constant(c) => Some(&c.name),
+			Declaration::Variable(v) => Some(&v.name),
+			Declaration::Instantiation(i) => Some(&i.name),
+			Declaration::Typedef(t) => Some(&t.name),
+			Declaration::Struct(s) => Some(&s.name),
+			Declaration::Enum(e) => Some(&e.name),
+			Declaration::Error(_) | Declaration::MatchKind(_) => None,
+			Declaration::ExternObject(e) => Some(&e.name),
+			Declaration::ExternFunction(f) => Some(&f.name),
+			Declaration::Parser(p) => Some(&p.name),
+			Declaration::Control(c) => Some(&c.name),
+			Declaration::Package(p) => Some(&p.name),
+			Declaration::Action(a) => Some(&a.name),
+			Declaration::Function(f) => Some(&f.prototype.name),
+			Declaration::Table(t) => Some(&t.name),
+		}
+	}
+
+	/// Returns the annotations of the declaration, which are those of its prototype for a function.
+	pub fn annotations(&self) -> &[Annotation] {
+		match self {
+			Declaration::Constant(c) => &c.annotations,
+			Declaration::Variable(v) => &v.annotations,
+			Declaration::Instantiation(i) => &i.annotations,
+			Declaration::Typedef(t) => &t.annotations,
+			Declaration::Struct(s) => &s.annotations,
+			Declaration::Enum(e) => &e.annotations,
+			Declaration::Error(m) | Declaration::MatchKind(m) => &m.annotations,
+			Declaration::ExternObject(e) => &e.annotations,
+			Declaration::ExternFunction(f) => &f.annotations,
+			Declaration::Parser(p) => &p.annotations,
+			Declaration::Control(c) => &c.annotations,
+			Declaration::Package(p) => &p.annotations,
+			Declaration::Action(a) => &a.annotations,
+			Declaration::Function(f) => &f.prototype.annotations,
+			Declaration::Table(t) => &t.annotations,
+		}
+	}
+
+	fn annotations_mut(&mut self) -> &mut Vec<Annotation> {
+		match self {
+			Declaration::Constant(c) => &mut c.annotations,
+			Declaration::Variable(v) => &mut v.annotations,
+			Declaration::Instantiation(i) => &mut i.annotations,
+			Declaration::Typedef(t) => &mut t.annotations,
+			Declaration::Struct(s) => &mut s.annotations,
+			Declaration::Enum(e) => &mut e.annotations,
+			Declaration::Error(m) | Declaration::MatchKind(m) => &mut m.annotations,
+			Declaration::ExternObject(e) => &mut e.annotations,
+			Declaration::ExternFunction(f) => &mut f.annotations,
+			Declaration::Parser(p) => &mut p.annotations,
+			Declaration::Control(c) => &mut c.annotations,
+			Declaration::Package(p) => &mut p.annotations,
+			Declaration::Action(a) => &mut a.annotations,
+			Declaration::Function(f) => &mut f.prototype.annotations,
+			Declaration::Table(t) => &mut t.annotations,
+		}
+	}
+
+	/// Returns the location of the whole declaration.
+	pub fn location(&self) -> &Location {
+		match self {
+			Declaration::Constant(c) => &c.location,
+			Declaration::Variable(v) => &v.location,
+			Declaration::Instantiation(i) => &i.location,
+			Declaration::Typedef(t) => &t.location,
+			Declaration::Struct(s) => &s.location,
+			Declaration::Enum(e) => &e.location,
+			Declaration::Error(m) | Declaration::MatchKind(m) => &m.location,
+			Declaration::ExternObject(e) => &e.location,
+			Declaration::ExternFunction(f) => &f.location,
+			Declaration::Parser(p) => &p.location,
+			Declaration::Control(c) => &c.location,
+			Declaration::Package(p) => &p.location,
+			Declaration::Action(a) => &a.location,
+			Declaration::Function(f) => &f.location,
+			Declaration::Table(t) => &t.location,
+		}
+	}
+}
+
+/// `const T name = value;`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Constant {
+	pub ty: TypeRef,
+	pub name: Identifier,
+	pub value: Expression,
+	pub annotations: Vec<Annotation>,
+	pub location: Location,
+}
+
+/// `T name;` or `T name = value;`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Variable {
+	pub ty: TypeRef,
+	pub name: Identifier,
+	pub initializer: Option<Expression>,
+	pub annotations: Vec<Annotation>,
+	pub location: Location,
+}
+
+/// `T(args) name;`, optionally followed by an initializer block implementing abstract methods.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Instantiation {
+	pub ty: TypeRef,
+	pub args: Vec<Argument>,
+	pub name: Identifier,
+	pub initializer: Vec<Declaration>,
+	pub annotations: Vec<Annotation>,
+	pub location: Location,
+}
+
+/// `typedef T name;` or `type T name;`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Typedef {
+	/// `true` for a `type` declaration, which introduces a new type rather than an alias.
+	pub is_new_type: bool,
+	pub ty: TypeRef,
+	pub name: Identifier,
+	pub annotations: Vec<Annotation>,
+	pub location: Location,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum StructKind {
+	Header,
+	HeaderUnion,
+	Struct,
+}
+
+/// A `header`, `header_union` or `struct` declaration.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Struct {
+	pub kind: StructKind,
+	pub name: Identifier,
+	pub type_params: Vec<Identifier>,
+	pub fields: Vec<Field>,
+	pub annotations: Vec<Annotation>,
+	pub location: Location,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Field {
+	pub ty: TypeRef,
+	pub name: Identifier,
+	pub annotations: Vec<Annotation>,
+	pub location: Location,
+}
+
+/// An `enum` declaration. Serializable enums have an underlying type, and may give their members values.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Enum {
+	pub underlying_type: Option<TypeRef>,
+	pub name: Identifier,
+	pub members: Vec<EnumMember>,
+	pub annotations: Vec<Annotation>,
+	pub location: Location,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EnumMember {
+	pub name: Identifier,
+	pub value: Option<Expression>,
+}
+
+/// The members of an `error` or `match_kind` declaration.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Members {
+	pub members: Vec<Identifier>,
+	pub annotations: Vec<Annotation>,
+	pub location: Location,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExternObject {
+	pub name: Identifier,
+	pub type_params: Vec<Identifier>,
+	/// The constructors and methods of the extern. Constructors have no return type.
+	pub methods: Vec<FunctionPrototype>,
+	pub annotations: Vec<Annotation>,
+	pub location: Location,
+}
+
+/// The signature of an extern function or method, or of a function.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FunctionPrototype {
+	/// The return type, which is absent for constructors.
+	pub return_type: Option<TypeRef>,
+	pub name: Identifier,
+	pub type_params: Vec<Identifier>,
+	pub params: Vec<Parameter>,
+	pub is_abstract: bool,
+	pub annotations: Vec<Annotation>,
+	pub location: Location,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Function {
+	pub prototype: FunctionPrototype,
+	pub body: Block,
+	pub location: Location,
+}
+
+/// A `parser` declaration. A parser type declaration has no body, so it has neither locals nor states.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Parser {
+	pub name: Identifier,
+	pub type_params: Vec<Identifier>,
+	pub params: Vec<Parameter>,
+	pub constructor_params: Vec<Parameter>,
+	pub is_type_declaration: bool,
+	pub locals: Vec<Declaration>,
+	pub states: Vec<ParserState>,
+	pub annotations: Vec<Annotation>,
+	pub location: Location,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParserState {
+	pub name: Identifier,
+	pub statements: Vec<Statement>,
+	pub transition: Option<Transition>,
+	pub annotations: Vec<Annotation>,
+	pub location: Location,
+}
+
+impl ParserState {
+	/// Returns the names of the states the state transitions to, which may include `accept` and `reject`.
+	pub fn targets(&self) -> Vec<&Identifier> {
+		match self.transition.as_ref().map(|transition| &transition.kind) {
+			Some(TransitionKind::Direct(target)) => vec![target],
+			Some(TransitionKind::Select(_, cases)) => cases.iter().map(|case| &case.state).collect(),
+			None => vec![],
+		}
+	}
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Transition {
+	pub kind: TransitionKind,
+	pub location: Location,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TransitionKind {
+	Direct(Identifier),
+	Select(Vec<Expression>, Vec<SelectCase>),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SelectCase {
+	/// The keyset expressions; a single element unless the keyset is a tuple.
+	pub keyset: Vec<Expression>,
+	pub state: Identifier,
+	pub location: Location,
+}
+
+/// A `control` declaration. A control type declaration has no body, so it has neither locals nor an apply block.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Control {
+	pub name: Identifier,
+	pub type_params: Vec<Identifier>,
+	pub params: Vec<Parameter>,
+	pub constructor_params: Vec<Parameter>,
+	pub is_type_declaration: bool,
+	pub locals: Vec<Declaration>,
+	pub apply: Option<Block>,
+	pub annotations: Vec<Annotation>,
+	pub location: Location,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Package {
+	pub name: Identifier,
+	pub type_params: Vec<Identifier>,
+	pub params: Vec<Parameter>,
+	pub annotations: Vec<Annotation>,
+	pub location: Location,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Action {
+	pub name: Identifier,
+	pub params: Vec<Parameter>,
+	pub body: Block,
+	pub annotations: Vec<Annotation>,
+	pub location: Location,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Table {
+	pub name: Identifier,
+	pub properties: Vec<TableProperty>,
+	pub annotations: Vec<Annotation>,
+	pub location: Location,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TableProperty {
+	Key(Vec<KeyElement>, Location),
+	Actions(Vec<ActionReference>, Location),
+	DefaultAction {
+		is_const: bool,
+		action: ActionReference,
+		annotations: Vec<Annotation>,
+		location: Location,
+	},
+	Entries {
+		is_const: bool,
+		entries: Vec<Entry>,
+		annotations: Vec<Annotation>,
+		location: Location,
+	},
+	/// Any other property, such as `size = 1024;`.
+	Custom {
+		is_const: bool,
+		name: Identifier,
+		value: Expression,
+		annotations: Vec<Annotation>,
+		location: Location,
+	},
+}
+
+impl Table {
+	/// Returns the actions of the table, followed by its default action.
+	pub fn actions(&self) -> impl Iterator<Item = &ActionReference> {
+		self.properties.iter().flat_map(|property| match property {
+			TableProperty::Actions(actions, _) => actions.iter().collect(),
+			TableProperty::DefaultAction { action, .. } => vec![action],
+			_ => vec![],
+		})
+	}
+}
+
+impl TableProperty {
+	pub fn location(&self) -> &Location {
+		match self {
+			TableProperty::Key(_, location) | TableProperty::Actions(_, location) => location,
+			TableProperty::DefaultAction { location, .. }
+			| TableProperty::Entries { location, .. }
+			| TableProperty::Custom { location, .. } => location,
+		}
+	}
+
+	/// Returns the annotations of the property. Only the `default_action`, `entries` and custom properties may be
+	/// annotated.
+	pub fn annotations(&self) -> &[Annotation] {
+		match self {
+			TableProperty::Key(..) | TableProperty::Actions(..) => &[],
+			TableProperty::DefaultAction { annotations, .. }
+			| TableProperty::Entries { annotations, .. }
+			| TableProperty::Custom { annotations, .. } => annotations,
+		}
+	}
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KeyElement {
+	pub expression: Expression,
+	pub match_kind: Identifier,
+	pub annotations: Vec<Annotation>,
+	pub location: Location,
+}
+
+/// A reference to an action from a table, optionally binding some of its arguments.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ActionReference {
+	pub name: Identifier,
+	pub args: Option<Vec<Argument>>,
+	pub annotations: Vec<Annotation>,
+	pub location: Location,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Entry {
+	pub keyset: Vec<Expression>,
+	pub action: ActionReference,
+	pub annotations: Vec<Annotation>,
+	pub location: Location,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Direction {
+	In,
+	Out,
+	InOut,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Parameter {
+	pub direction: Option<Direction>,
+	pub ty: TypeRef,
+	pub name: Identifier,
+	pub default: Option<Expression>,
+	pub annotations: Vec<Annotation>,
+	pub location: Location,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TypeRef {
+	pub kind: TypeRefKind,
+	pub location: Location,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TypeRefKind {
+	/// `bit<W>`, where a missing width means `bit<1>`.
+	Bit(Option<Box<Expression>>),
+	/// `int<W>`, where a missing width means an arbitrary-precision integer.
+	Int(Option<Box<Expression>>),
+	Varbit(Box<Expression>),
+	Bool,
+	String,
+	Void,
+	Error,
+	MatchKind,
+	/// The `_` placeholder type.
+	DontCare,
+	Named(Identifier, Vec<TypeRef>),
+	/// A header stack, `T[N]`.
+	Stack(Box<TypeRef>, Box<Expression>),
+	Tuple(Vec<TypeRef>),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Argument {
+	pub name: Option<Identifier>,
+	pub value: Expression,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Expression {
+	pub kind: ExpressionKind,
+	pub location: Location,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ExpressionKind {
+	Integer(Literal),
+	Boolean(bool),
+	String(String),
+	Name(Identifier),
+	Member(Box<Expression>, Identifier),
+	Index(Box<Expression>, Box<Expression>),
+	/// A bit slice, `e[hi:lo]`.
+	Slice(Box<Expression>, Box<Expression>, Box<Expression>),
+	Call(Box<Expression>, Vec<TypeRef>, Vec<Argument>),
+	Unary(UnaryOp, Box<Expression>),
+	Binary(BinaryOp, Box<Expression>, Box<Expression>),
+	Ternary(Box<Expression>, Box<Expression>, Box<Expression>),
+	Cast(TypeRef, Box<Expression>),
+	/// A list expression, `{ a, b }`.
+	List(Vec<Expression>),
+	/// A structure-valued expression, `{ a = x, b = y }`.
+	StructInitializer(Vec<(Identifier, Expression)>),
+	/// A type used in expression position, such as the target of a constructor call or of a `.minSizeInBits()`.
+	Type(TypeRef),
+	/// The `_` placeholder in keysets and arguments.
+	DontCare,
+	/// The `default` keyset.
+	Default,
+	/// A placeholder for an expression that could not be parsed.
+	Missing,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum UnaryOp {
+	Not,
+	Complement,
+	Negate,
+	Plus,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum BinaryOp {
+	Multiply,
+	Divide,
+	Modulo,
+	Add,
+	Subtract,
+	SaturatingAdd,
+	SaturatingSubtract,
+	ShiftLeft,
+	ShiftRight,
+	LessThan,
+	LessOrEqual,
+	GreaterThan,
+	GreaterOrEqual,
+	Equals,
+	NotEquals,
+	BitAnd,
+	BitXor,
+	BitOr,
+	Concatenate,
+	LogicalAnd,
+	LogicalOr,
+	/// `a &&& b` in keysets.
+	Mask,
+	/// `a .. b` in keysets.
+	Range,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Block {
+	pub statements: Vec<Statement>,
+	pub location: Location,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Statement {
+	pub kind: StatementKind,
+	pub annotations: Vec<Annotation>,
+	pub location: Location,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum StatementKind {
+	Assignment(Expression, Expression),
+	/// A method or function call used as a statement.
+	Call(Expression),
+	If(Expression, Box<Statement>, Option<Box<Statement>>),
+	Switch(Expression, Vec<SwitchCase>),
+	Block(Block),
+	Return(Option<Expression>),
+	Exit,
+	Empty,
+	Declaration(Box<Declaration>),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SwitchCase {
+	/// The case label, or [`ExpressionKind::Default`] for `default`.
+	pub label: Expression,
+	/// The body of the case, or `None` if it falls through to the next one.
+	pub body: Option<Block>,
+	pub location: Location,
+}
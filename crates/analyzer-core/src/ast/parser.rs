@@ -0,0 +1,1626 @@
+//! A recursive descent parser that builds a [`Program`] from a (preprocessed) token stream.
+//!
+//! The parser recovers from syntax errors by skipping to the end of the enclosing declaration or statement, so that
+//! a single error does not hide the rest of the program from later passes.
+
+use logos::Logos;
+
+use super::*;
+use crate::lexer::{Lextras, Token};
+
+/// Parses a program from a stream of tokens, returning the syntax tree alongside any syntax errors.
+///
+/// Whitespace, comments, preprocessor directives and error tokens (which the lexer has already reported) are
+/// skipped.
+pub fn parse(tokens: &[(FileId, Token, Span)]) -> (Program, Vec<Diagnostic>) {
+	let mut parser = P4Parser::new(tokens);
+	let program = parser.program();
+	(program, parser.diagnostics)
+}
+
+/// Parses a single expression, for example the body of a macro definition.
+pub fn parse_expression(tokens: &[(FileId, Token, Span)]) -> Option<Expression> {
+	let mut parser = P4Parser::new(tokens);
+	let expression = parser.expression()?;
+	parser.is_eof().then_some(expression)
+}
+
+struct P4Parser<'a> {
+	tokens: Vec<&'a (FileId, Token, Span)>,
+	pos: usize,
+	diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> P4Parser<'a> {
+	fn new(tokens: &'a [(FileId, Token, Span)]) -> Self {
+		let tokens = tokens
+			.iter()
+			.filter(|(_, tk, _)| {
+				!matches!(tk, Token::Whitespace | Token::Comment | Token::PreprocessorDirective(_) | Token::Error)
+			})
+			.collect();
+
+		Self { tokens, pos: 0, diagnostics: vec![] }
+	}
+
+	// token stream helpers
+
+	fn peek(&self) -> Option<&'a Token> { self.peek_nth(0) }
+
+	fn peek_nth(&self, n: usize) -> Option<&'a Token> { self.tokens.get(self.pos + n).map(|(_, tk, _)| tk) }
+
+	fn is_eof(&self) -> bool { self.pos >= self.tokens.len() }
+
+	fn at(&self, tk: &Token) -> bool { self.peek() == Some(tk) }
+
+	fn at_nth(&self, n: usize, tk: &Token) -> bool { self.peek_nth(n) == Some(tk) }
+
+	fn at_identifier(&self) -> bool { matches!(self.peek(), Some(Token::Identifier(_))) }
+
+	fn at_keyword(&self, name: &str) -> bool { matches!(self.peek(), Some(Token::Identifier(n)) if n == name) }
+
+	fn bump(&mut self) -> Option<&'a (FileId, Token, Span)> {
+		let tk = self.tokens.get(self.pos).copied();
+		if tk.is_some() {
+			self.pos += 1;
+		}
+		tk
+	}
+
+	fn eat(&mut self, tk: &Token) -> bool {
+		if self.at(tk) {
+			self.pos += 1;
+			true
+		} else {
+			false
+		}
+	}
+
+	fn expect(&mut self, tk: &Token) -> Option<()> {
+		if self.eat(tk) {
+			Some(())
+		} else {
+			self.error_expected(&describe(tk));
+			None
+		}
+	}
+
+	/// Returns `true` if the next two tokens are adjacent `>`s, i.e. a right shift.
+	fn at_shift_right(&self) -> bool {
+		match (self.tokens.get(self.pos), self.tokens.get(self.pos + 1)) {
+			(Some((f1, Token::CloseChevron, s1)), Some((f2, Token::CloseChevron, s2))) => {
+				f1 == f2 && s1.end == s2.start
+			}
+			_ => false,
+		}
+	}
+
+	/// The location of the token at `index`, or an empty location at the end of the input.
+	fn token_location(&self, index: usize) -> Option<Location> {
+		match self.tokens.get(index) {
+			Some((file, _, span)) => Some(Location::new(*file, span.clone())),
+			None => self.tokens.last().map(|(file, _, span)| Location::new(*file, span.end..span.end)),
+		}
+	}
+
+	/// The location spanning from the token at `start` to the last consumed token.
+	fn location_from(&self, start: usize) -> Location {
+		let (file, _, first) = match self.tokens.get(start).or(self.tokens.last()) {
+			Some(tk) => *tk,
+			None => unreachable!("locations are only computed after consuming tokens"),
+		};
+		match self.pos.checked_sub(1).and_then(|last| self.tokens.get(last)) {
+			Some((last_file, _, last)) if last_file == file && last.end >= first.start => {
+				Location::new(*file, first.start..last.end)
+			}
+			_ => Location::new(*file, first.clone()),
+		}
+	}
+
+	fn error_expected(&mut self, what: &str) {
+		let found = match self.peek() {
+			Some(tk) => describe(tk),
+			None => "end of file".to_string(),
+		};
+		self.error_at(self.pos, format!("expected {what}, found {found}"));
+	}
+
+	fn error_at(&mut self, index: usize, message: String) {
+		if let Some(Location { file, span }) = self.token_location(index) {
+			// avoid reporting a cascade of errors at the same position
+			if self.diagnostics.last().map_or(false, |d| d.file == file && d.location == span) {
+				return;
+			}
+			self.diagnostics.push(Diagnostic::new(file, span, Severity::Error, message));
+		}
+	}
+
+	/// Runs `f`, restoring the position and discarding any diagnostics if it fails.
+	fn speculate<T>(&mut self, f: impl FnOnce(&mut Self) -> Option<T>) -> Option<T> {
+		let (pos, diagnostics) = (self.pos, self.diagnostics.len());
+		let result = f(self);
+		if result.is_none() {
+			self.pos = pos;
+			self.diagnostics.truncate(diagnostics);
+		}
+		result
+	}
+
+	/// Skips tokens until just past the end of the current declaration or statement: a `;` or a balanced `}` at
+	/// the nesting level where the skipping started, or up to (excluding) an unmatched closing brace.
+	fn recover(&mut self, start: usize) {
+		if self.pos == start {
+			self.bump();
+			if matches!(self.tokens.get(start), Some((_, Token::Semicolon | Token::CloseBrace, _))) {
+				return;
+			}
+		}
+
+		let mut depth = 0usize;
+		while let Some(tk) = self.peek() {
+			match tk {
+				Token::OpenBrace | Token::OpenParen | Token::OpenBracket => depth += 1,
+				Token::CloseParen | Token::CloseBracket => depth = depth.saturating_sub(1),
+				Token::CloseBrace if depth == 0 => return,
+				Token::CloseBrace => {
+					depth -= 1;
+					if depth == 0 {
+						self.bump();
+						return;
+					}
+				}
+				Token::Semicolon if depth == 0 => {
+					self.bump();
+					return;
+				}
+				_ if depth == 0 && starts_declaration(tk) => return,
+				_ => (),
+			}
+			self.bump();
+		}
+	}
+
+	// declarations
+
+	fn program(&mut self) -> Program {
+		let mut declarations = vec![];
+		while !self.is_eof() {
+			let start = self.pos;
+			match self.declaration() {
+				Some(declaration) => declarations.push(declaration),
+				None => {
+					self.recover(start);
+					// a stray closing brace at the top level
+					if self.at(&Token::CloseBrace) {
+						self.error_expected("a declaration");
+						self.bump();
+					}
+				}
+			}
+		}
+		Program { declarations }
+	}
+
+	fn declaration(&mut self) -> Option<Declaration> {
+		// empty declarations are skipped
+		while self.at(&Token::Semicolon) {
+			self.bump();
+		}
+		let start = self.pos;
+		let annotations = self.annotations()?;
+		let mut declaration = match self.peek()? {
+			Token::KwConst => self.constant(start).map(Declaration::Constant),
+			Token::KwTypedef | Token::KwType => self.typedef(start).map(Declaration::Typedef),
+			Token::KwHeader | Token::KwHeaderUnion | Token::KwStruct => {
+				self.struct_like(start).map(Declaration::Struct)
+			}
+			Token::KwEnum => self.enumeration(start).map(Declaration::Enum),
+			Token::Identifier(name) if name == "error" && self.at_nth(1, &Token::OpenBrace) => {
+				self.members(start).map(Declaration::Error)
+			}
+			Token::Identifier(name) if name == "match_kind" && self.at_nth(1, &Token::OpenBrace) => {
+				self.members(start).map(Declaration::MatchKind)
+			}
+			Token::KwExtern => self.extern_declaration(start),
+			Token::KwParser => self.parser_declaration(start).map(Declaration::Parser),
+			Token::KwControl => self.control(start).map(Declaration::Control),
+			Token::KwPackage => self.package(start).map(Declaration::Package),
+			Token::KwAction => self.action(start).map(Declaration::Action),
+			Token::KwTable => self.table(start).map(Declaration::Table),
+			_ => self.typed_declaration(start, true),
+		}?;
+		declaration.annotations_mut().splice(0..0, annotations);
+		Some(declaration)
+	}
+
+	fn annotations(&mut self) -> Option<Vec<Annotation>> {
+		let mut annotations = vec![];
+		loop {
+			match self.tokens.get(self.pos) {
+				Some((_, Token::At, _)) => annotations.push(self.annotation()?),
+				Some((file, Token::Pragma(body), span)) => {
+					self.pos += 1;
+					annotations.push(pragma(*file, body, span));
+				}
+				_ => return Some(annotations),
+			}
+		}
+	}
+
+	fn annotation(&mut self) -> Option<Annotation> {
+		let start = self.pos;
+		self.expect(&Token::At)?;
+		let name = self.member_name()?;
+		let body = match self.peek() {
+			Some(Token::OpenParen) => {
+				self.bump();
+				// the tokens up to the matching parenthesis
+				let mut tokens = vec![];
+				let mut depth = 0usize;
+				loop {
+					let Some((file, tk, span)) = self.tokens.get(self.pos) else {
+						self.error_expected("`)`");
+						return None;
+					};
+					self.pos += 1;
+					match tk {
+						Token::OpenParen => depth += 1,
+						Token::CloseParen if depth == 0 => break,
+						Token::CloseParen => depth -= 1,
+						_ => (),
+					}
+					tokens.push((tk.clone(), Location::new(*file, span.clone())));
+				}
+				AnnotationBody::Unstructured(tokens)
+			}
+			Some(Token::OpenBracket) => {
+				self.bump();
+				if self.at_identifier() && self.at_nth(1, &Token::Equals) {
+					AnnotationBody::KeyValues(self.comma_separated(&Token::CloseBracket, |p| {
+						let key = p.identifier()?;
+						p.expect(&Token::Equals)?;
+						Some((key, p.expression()?))
+					})?)
+				} else {
+					AnnotationBody::Expressions(self.comma_separated(&Token::CloseBracket, |p| p.expression())?)
+				}
+			}
+			_ => AnnotationBody::Empty,
+		};
+		Some(Annotation { name, body, location: self.location_from(start) })
+	}
+
+	/// Returns `true` if the current token, after any annotations, is `tk`.
+	fn at_annotated(&mut self, tk: &Token) -> bool {
+		let (pos, diagnostics) = (self.pos, self.diagnostics.len());
+		let result = self.annotations().is_some() && self.at(tk);
+		self.pos = pos;
+		self.diagnostics.truncate(diagnostics);
+		result
+	}
+
+	fn identifier(&mut self) -> Option<Identifier> {
+		match self.tokens.get(self.pos) {
+			Some((file, Token::Identifier(name), span)) => {
+				self.pos += 1;
+				Some(Identifier { name: name.clone(), location: Location::new(*file, span.clone()) })
+			}
+			// a few keywords are also valid names of declarations, e.g. `CounterType type`
+			Some((file, tk @ (Token::KwApply | Token::KwState | Token::KwType), span)) => {
+				self.pos += 1;
+				Some(Identifier {
+					name: keyword_text(tk).unwrap().to_string(),
+					location: Location::new(*file, span.clone()),
+				})
+			}
+			_ => {
+				self.error_expected("an identifier");
+				None
+			}
+		}
+	}
+
+	/// Parses the name of a member, which may also be a keyword such as `apply`.
+	fn member_name(&mut self) -> Option<Identifier> {
+		match self.tokens.get(self.pos) {
+			Some((file, tk, span)) if keyword_text(tk).is_some() => {
+				self.pos += 1;
+				Some(Identifier {
+					name: keyword_text(tk).unwrap().to_string(),
+					location: Location::new(*file, span.clone()),
+				})
+			}
+			_ => self.identifier(),
+		}
+	}
+
+	fn constant(&mut self, start: usize) -> Option<Constant> {
+		self.expect(&Token::KwConst)?;
+		let ty = self.type_ref()?;
+		let name = self.identifier()?;
+		self.expect(&Token::Equals)?;
+		let value = self.expression()?;
+		self.expect(&Token::Semicolon)?;
+		Some(Constant { ty, name, value, annotations: vec![], location: self.location_from(start) })
+	}
+
+	fn typedef(&mut self, start: usize) -> Option<Typedef> {
+		let is_new_type = self.bump()?.1 == Token::KwType;
+		let ty = self.type_ref()?;
+		let name = self.identifier()?;
+		self.expect(&Token::Semicolon)?;
+		Some(Typedef { is_new_type, ty, name, annotations: vec![], location: self.location_from(start) })
+	}
+
+	fn struct_like(&mut self, start: usize) -> Option<Struct> {
+		let kind = match self.bump()?.1 {
+			Token::KwHeader => StructKind::Header,
+			Token::KwHeaderUnion => StructKind::HeaderUnion,
+			_ => StructKind::Struct,
+		};
+		let name = self.identifier()?;
+		let type_params = self.optional_type_params()?;
+		self.expect(&Token::OpenBrace)?;
+		let mut fields = vec![];
+		while !self.eat(&Token::CloseBrace) {
+			let field_start = self.pos;
+			let annotations = self.annotations()?;
+			let ty = self.type_ref()?;
+			let name = self.identifier()?;
+			self.expect(&Token::Semicolon)?;
+			fields.push(Field { ty, name, annotations, location: self.location_from(field_start) });
+		}
+		Some(Struct { kind, name, type_params, fields, annotations: vec![], location: self.location_from(start) })
+	}
+
+	fn enumeration(&mut self, start: usize) -> Option<Enum> {
+		self.expect(&Token::KwEnum)?;
+		let underlying_type =
+			if self.at_identifier() && self.at_nth(1, &Token::OpenBrace) { None } else { Some(self.type_ref()?) };
+		let name = self.identifier()?;
+		self.expect(&Token::OpenBrace)?;
+		let mut members = vec![];
+		while !self.eat(&Token::CloseBrace) {
+			let name = self.identifier()?;
+			let value = if self.eat(&Token::Equals) { Some(self.expression()?) } else { None };
+			members.push(EnumMember { name, value });
+			if !self.eat(&Token::Comma) {
+				self.expect(&Token::CloseBrace)?;
+				break;
+			}
+		}
+		Some(Enum { underlying_type, name, members, annotations: vec![], location: self.location_from(start) })
+	}
+
+	fn members(&mut self, start: usize) -> Option<Members> {
+		self.bump();
+		self.expect(&Token::OpenBrace)?;
+		let members = self.comma_separated(&Token::CloseBrace, |p| p.identifier())?;
+		Some(Members { members, annotations: vec![], location: self.location_from(start) })
+	}
+
+	fn extern_declaration(&mut self, start: usize) -> Option<Declaration> {
+		self.expect(&Token::KwExtern)?;
+		if self.at_identifier() && self.at_nth(1, &Token::OpenBrace) {
+			let name = self.identifier()?;
+			return self.extern_object(start, name, vec![]).map(Declaration::ExternObject);
+		}
+
+		let ty = self.type_ref()?;
+		if self.at(&Token::OpenBrace) {
+			// `extern Name<T> { ... }`: the "type" is the name of the extern along with its type parameters
+			let (name, type_params) = match ty.kind {
+				TypeRefKind::Named(name, args) => {
+					let type_params = args
+						.into_iter()
+						.map(|arg| match arg.kind {
+							TypeRefKind::Named(param, args) if args.is_empty() => Some(param),
+							_ => None,
+						})
+						.collect::<Option<Vec<_>>>();
+					match type_params {
+						Some(type_params) => (name, type_params),
+						None => {
+							self.error_at(start, "expected type parameters of an extern".to_string());
+							return None;
+						}
+					}
+				}
+				_ => {
+					self.error_at(start, "expected the name of an extern".to_string());
+					return None;
+				}
+			};
+			return self.extern_object(start, name, type_params).map(Declaration::ExternObject);
+		}
+
+		let prototype = self.function_prototype_after_type(start, Some(ty), false)?;
+		self.expect(&Token::Semicolon)?;
+		Some(Declaration::ExternFunction(FunctionPrototype { location: self.location_from(start), ..prototype }))
+	}
+
+	fn extern_object(&mut self, start: usize, name: Identifier, type_params: Vec<Identifier>) -> Option<ExternObject> {
+		self.expect(&Token::OpenBrace)?;
+		let mut methods = vec![];
+		while !self.eat(&Token::CloseBrace) {
+			let method_start = self.pos;
+			match self.method_prototype(&name) {
+				Some(method) => methods.push(method),
+				None => {
+					self.recover(method_start);
+					if self.is_eof() {
+						return None;
+					}
+				}
+			}
+		}
+		Some(ExternObject { name, type_params, methods, annotations: vec![], location: self.location_from(start) })
+	}
+
+	fn method_prototype(&mut self, extern_name: &Identifier) -> Option<FunctionPrototype> {
+		let start = self.pos;
+		let annotations = self.annotations()?;
+		let is_abstract = self.eat(&Token::KwAbstract);
+		let prototype = if self.at_keyword(&extern_name.name) && self.at_nth(1, &Token::OpenParen) {
+			// a constructor
+			self.function_prototype_after_type(start, None, is_abstract)?
+		} else {
+			let ty = self.type_ref()?;
+			self.function_prototype_after_type(start, Some(ty), is_abstract)?
+		};
+		self.expect(&Token::Semicolon)?;
+		Some(FunctionPrototype { annotations, location: self.location_from(start), ..prototype })
+	}
+
+	fn function_prototype_after_type(
+		&mut self,
+		start: usize,
+		return_type: Option<TypeRef>,
+		is_abstract: bool,
+	) -> Option<FunctionPrototype> {
+		let name = self.member_name()?;
+		let type_params = self.optional_type_params()?;
+		let params = self.parameters()?;
+		Some(FunctionPrototype {
+			return_type,
+			name,
+			type_params,
+			params,
+			is_abstract,
+			annotations: vec![],
+			location: self.location_from(start),
+		})
+	}
+
+	fn parser_declaration(&mut self, start: usize) -> Option<Parser> {
+		self.expect(&Token::KwParser)?;
+		let name = self.identifier()?;
+		let type_params = self.optional_type_params()?;
+		let params = self.parameters()?;
+		if self.eat(&Token::Semicolon) {
+			return Some(Parser {
+				name,
+				type_params,
+				params,
+				constructor_params: vec![],
+				is_type_declaration: true,
+				locals: vec![],
+				states: vec![],
+				annotations: vec![],
+				location: self.location_from(start),
+			});
+		}
+
+		let constructor_params = if self.at(&Token::OpenParen) { self.parameters()? } else { vec![] };
+		self.expect(&Token::OpenBrace)?;
+
+		let mut locals = vec![];
+		let mut states = vec![];
+		while !self.eat(&Token::CloseBrace) {
+			let item_start = self.pos;
+			let parsed = if self.at_annotated(&Token::KwState) {
+				self.parser_state().map(|state| states.push(state))
+			} else {
+				self.local_declaration().map(|local| locals.push(local))
+			};
+			if parsed.is_none() {
+				self.recover(item_start);
+				if self.is_eof() {
+					break;
+				}
+			}
+		}
+
+		Some(Parser {
+			name,
+			type_params,
+			params,
+			constructor_params,
+			is_type_declaration: false,
+			locals,
+			states,
+			annotations: vec![],
+			location: self.location_from(start),
+		})
+	}
+
+	/// Parses a declaration that is local to a parser or control.
+	fn local_declaration(&mut self) -> Option<Declaration> {
+		let start = self.pos;
+		let annotations = self.annotations()?;
+		let mut declaration = match self.peek()? {
+			Token::KwConst => self.constant(start).map(Declaration::Constant),
+			Token::KwAction => self.action(start).map(Declaration::Action),
+			Token::KwTable => self.table(start).map(Declaration::Table),
+			_ => self.typed_declaration(start, false),
+		}?;
+		declaration.annotations_mut().splice(0..0, annotations);
+		Some(declaration)
+	}
+
+	fn parser_state(&mut self) -> Option<ParserState> {
+		let start = self.pos;
+		let annotations = self.annotations()?;
+		self.expect(&Token::KwState)?;
+		let name = self.identifier()?;
+		self.expect(&Token::OpenBrace)?;
+
+		let mut statements = vec![];
+		let mut transition = None;
+		while !self.eat(&Token::CloseBrace) {
+			let item_start = self.pos;
+			if self.at(&Token::KwTransition) {
+				match self.transition() {
+					Some(t) => transition = Some(t),
+					None => self.recover(item_start),
+				}
+			} else {
+				match self.statement() {
+					Some(statement) => statements.push(statement),
+					None => self.recover(item_start),
+				}
+			}
+			if self.is_eof() {
+				self.expect(&Token::CloseBrace)?;
+			}
+		}
+
+		Some(ParserState { name, statements, transition, annotations, location: self.location_from(start) })
+	}
+
+	fn transition(&mut self) -> Option<Transition> {
+		let start = self.pos;
+		self.expect(&Token::KwTransition)?;
+		let kind = if self.eat(&Token::KwSelect) {
+			self.expect(&Token::OpenParen)?;
+			let selectors = self.comma_separated(&Token::CloseParen, |p| p.expression())?;
+			self.expect(&Token::OpenBrace)?;
+			let mut cases = vec![];
+			while !self.eat(&Token::CloseBrace) {
+				let case_start = self.pos;
+				let keyset = self.keyset()?;
+				self.expect(&Token::Colon)?;
+				let state = self.identifier()?;
+				self.expect(&Token::Semicolon)?;
+				cases.push(SelectCase { keyset, state, location: self.location_from(case_start) });
+			}
+			TransitionKind::Select(selectors, cases)
+		} else {
+			let state = self.identifier()?;
+			self.expect(&Token::Semicolon)?;
+			TransitionKind::Direct(state)
+		};
+		Some(Transition { kind, location: self.location_from(start) })
+	}
+
+	/// Parses a keyset, which is either a single keyset expression or a parenthesized tuple of them.
+	fn keyset(&mut self) -> Option<Vec<Expression>> {
+		if self.at(&Token::OpenParen) {
+			let start = self.pos;
+			let tuple = self.speculate(|p| {
+				p.bump();
+				let elements = p.comma_separated(&Token::CloseParen, |p| p.keyset_expression())?;
+				(p.at(&Token::Colon) || p.at(&Token::Semicolon) || p.at(&Token::CloseBrace)).then_some(elements)
+			});
+			if let Some(elements) = tuple {
+				if elements.is_empty() {
+					let Location { file, span } = self.location_from(start);
+					self.diagnostics.push(Diagnostic::new(
+						file,
+						span,
+						Severity::Error,
+						"a keyset cannot be empty".into(),
+					));
+				}
+				return Some(elements);
+			}
+		}
+		Some(vec![self.keyset_expression()?])
+	}
+
+	fn keyset_expression(&mut self) -> Option<Expression> {
+		let start = self.pos;
+		let lhs = self.expression()?;
+		let op = match self.peek() {
+			Some(Token::TripleAmpersand) => BinaryOp::Mask,
+			Some(Token::DoubleDot) => BinaryOp::Range,
+			_ => return Some(lhs),
+		};
+		self.bump();
+		let rhs = self.expression()?;
+		Some(Expression {
+			kind: ExpressionKind::Binary(op, lhs.into(), rhs.into()),
+			location: self.location_from(start),
+		})
+	}
+
+	fn control(&mut self, start: usize) -> Option<Control> {
+		self.expect(&Token::KwControl)?;
+		let name = self.identifier()?;
+		let type_params = self.optional_type_params()?;
+		let params = self.parameters()?;
+		if self.eat(&Token::Semicolon) {
+			return Some(Control {
+				name,
+				type_params,
+				params,
+				constructor_params: vec![],
+				is_type_declaration: true,
+				locals: vec![],
+				apply: None,
+				annotations: vec![],
+				location: self.location_from(start),
+			});
+		}
+
+		let constructor_params = if self.at(&Token::OpenParen) { self.parameters()? } else { vec![] };
+		self.expect(&Token::OpenBrace)?;
+
+		let mut locals = vec![];
+		let mut apply = None;
+		while !self.eat(&Token::CloseBrace) {
+			let item_start = self.pos;
+			let parsed = if self.eat(&Token::KwApply) {
+				self.block().map(|block| apply = Some(block))
+			} else {
+				self.local_declaration().map(|local| locals.push(local))
+			};
+			if parsed.is_none() {
+				self.recover(item_start);
+				if self.is_eof() {
+					break;
+				}
+			}
+		}
+
+		Some(Control {
+			name,
+			type_params,
+			params,
+			constructor_params,
+			is_type_declaration: false,
+			locals,
+			apply,
+			annotations: vec![],
+			location: self.location_from(start),
+		})
+	}
+
+	fn package(&mut self, start: usize) -> Option<Package> {
+		self.expect(&Token::KwPackage)?;
+		let name = self.identifier()?;
+		let type_params = self.optional_type_params()?;
+		let params = self.parameters()?;
+		self.expect(&Token::Semicolon)?;
+		Some(Package { name, type_params, params, annotations: vec![], location: self.location_from(start) })
+	}
+
+	fn action(&mut self, start: usize) -> Option<Action> {
+		self.expect(&Token::KwAction)?;
+		let name = self.identifier()?;
+		let params = self.parameters()?;
+		let body = self.block()?;
+		Some(Action { name, params, body, annotations: vec![], location: self.location_from(start) })
+	}
+
+	fn table(&mut self, start: usize) -> Option<Table> {
+		self.expect(&Token::KwTable)?;
+		let name = self.identifier()?;
+		self.expect(&Token::OpenBrace)?;
+		let mut properties = vec![];
+		while !self.eat(&Token::CloseBrace) {
+			let property_start = self.pos;
+			match self.table_property() {
+				Some(property) => properties.push(property),
+				None => {
+					self.recover(property_start);
+					if self.is_eof() {
+						break;
+					}
+				}
+			}
+		}
+		Some(Table { name, properties, annotations: vec![], location: self.location_from(start) })
+	}
+
+	fn table_property(&mut self) -> Option<TableProperty> {
+		let start = self.pos;
+		let annotations = self.annotations()?;
+		let is_const = self.eat(&Token::KwConst);
+		let name = self.identifier()?;
+		self.expect(&Token::Equals)?;
+
+		let property = match name.name.as_str() {
+			"key" | "actions" if !annotations.is_empty() => {
+				self.error_at(start, format!("the `{}` property of a table cannot be annotated", name.name));
+				return None;
+			}
+			"key" if !is_const => {
+				self.expect(&Token::OpenBrace)?;
+				let mut elements = vec![];
+				while !self.eat(&Token::CloseBrace) {
+					let element_start = self.pos;
+					let expression = self.expression()?;
+					self.expect(&Token::Colon)?;
+					let match_kind = self.identifier()?;
+					let annotations = self.annotations()?;
+					self.expect(&Token::Semicolon)?;
+					let location = self.location_from(element_start);
+					elements.push(KeyElement { expression, match_kind, annotations, location });
+				}
+				TableProperty::Key(elements, self.location_from(start))
+			}
+			"actions" if !is_const => {
+				self.expect(&Token::OpenBrace)?;
+				let mut actions = vec![];
+				while !self.eat(&Token::CloseBrace) {
+					let reference_start = self.pos;
+					let annotations = self.annotations()?;
+					let action = self.action_reference()?;
+					actions.push(ActionReference {
+						annotations,
+						location: self.location_from(reference_start),
+						..action
+					});
+					self.expect(&Token::Semicolon)?;
+				}
+				TableProperty::Actions(actions, self.location_from(start))
+			}
+			"default_action" => {
+				let action = self.action_reference()?;
+				self.expect(&Token::Semicolon)?;
+				TableProperty::DefaultAction { is_const, action, annotations, location: self.location_from(start) }
+			}
+			"entries" => {
+				self.expect(&Token::OpenBrace)?;
+				let mut entries = vec![];
+				while !self.eat(&Token::CloseBrace) {
+					let entry_start = self.pos;
+					let keyset = self.keyset()?;
+					self.expect(&Token::Colon)?;
+					let action = self.action_reference()?;
+					let annotations = self.annotations()?;
+					self.expect(&Token::Semicolon)?;
+					entries.push(Entry { keyset, action, annotations, location: self.location_from(entry_start) });
+				}
+				TableProperty::Entries { is_const, entries, annotations, location: self.location_from(start) }
+			}
+			_ => {
+				let value = self.expression()?;
+				self.expect(&Token::Semicolon)?;
+				TableProperty::Custom { is_const, name, value, annotations, location: self.location_from(start) }
+			}
+		};
+		Some(property)
+	}
+
+	fn action_reference(&mut self) -> Option<ActionReference> {
+		let start = self.pos;
+		let name = self.identifier()?;
+		let args = if self.at(&Token::OpenParen) { Some(self.arguments()?) } else { None };
+		Some(ActionReference { name, args, annotations: vec![], location: self.location_from(start) })
+	}
+
+	/// Parses a declaration that starts with a type: a variable, an instantiation or a function.
+	fn typed_declaration(&mut self, start: usize, allow_functions: bool) -> Option<Declaration> {
+		let ty = self.type_ref()?;
+
+		if self.at(&Token::OpenParen) {
+			let args = self.arguments()?;
+			let name = self.identifier()?;
+			let mut initializer = vec![];
+			if self.eat(&Token::Equals) {
+				self.expect(&Token::OpenBrace)?;
+				while !self.eat(&Token::CloseBrace) {
+					let item_start = self.pos;
+					// the implementations of abstract methods
+					self.eat(&Token::KwAbstract);
+					match self.typed_declaration(item_start, true) {
+						Some(declaration) => initializer.push(declaration),
+						None => self.recover(item_start),
+					}
+					if self.is_eof() {
+						return None;
+					}
+				}
+			}
+			self.expect(&Token::Semicolon)?;
+			return Some(Declaration::Instantiation(Instantiation {
+				ty,
+				args,
+				name,
+				initializer,
+				annotations: vec![],
+				location: self.location_from(start),
+			}));
+		}
+
+		let name = self.identifier()?;
+		if allow_functions && (self.at(&Token::OpenParen) || self.at(&Token::OpenChevron)) {
+			let type_params = self.optional_type_params()?;
+			let params = self.parameters()?;
+			let prototype = FunctionPrototype {
+				return_type: Some(ty),
+				name,
+				type_params,
+				params,
+				is_abstract: false,
+				annotations: vec![],
+				location: self.location_from(start),
+			};
+			let body = self.block()?;
+			return Some(Declaration::Function(Function { prototype, body, location: self.location_from(start) }));
+		}
+
+		let initializer = if self.eat(&Token::Equals) { Some(self.expression()?) } else { None };
+		self.expect(&Token::Semicolon)?;
+		let location = self.location_from(start);
+		Some(Declaration::Variable(Variable { ty, name, initializer, annotations: vec![], location }))
+	}
+
+	fn optional_type_params(&mut self) -> Option<Vec<Identifier>> {
+		if self.eat(&Token::OpenChevron) {
+			self.comma_separated(&Token::CloseChevron, |p| p.identifier())
+		} else {
+			Some(vec![])
+		}
+	}
+
+	fn parameters(&mut self) -> Option<Vec<Parameter>> {
+		self.expect(&Token::OpenParen)?;
+		self.comma_separated(&Token::CloseParen, |p| {
+			let start = p.pos;
+			let annotations = p.annotations()?;
+			let direction = match p.peek() {
+				Some(Token::KwIn) => Some(Direction::In),
+				Some(Token::KwOut) => Some(Direction::Out),
+				Some(Token::KwInout) => Some(Direction::InOut),
+				_ => None,
+			};
+			if direction.is_some() {
+				p.bump();
+			}
+			let ty = p.type_ref()?;
+			let name = p.identifier()?;
+			let default = if p.eat(&Token::Equals) { Some(p.expression()?) } else { None };
+			Some(Parameter { direction, ty, name, default, annotations, location: p.location_from(start) })
+		})
+	}
+
+	/// Parses a list of elements separated by commas (allowing a trailing one), up to and including `close`.
+	fn comma_separated<T>(&mut self, close: &Token, mut element: impl FnMut(&mut Self) -> Option<T>) -> Option<Vec<T>> {
+		let mut elements = vec![];
+		while !self.eat(close) {
+			elements.push(element(self)?);
+			if !self.eat(&Token::Comma) {
+				self.expect(close)?;
+				break;
+			}
+		}
+		Some(elements)
+	}
+
+	// types
+
+	fn type_ref(&mut self) -> Option<TypeRef> {
+		let start = self.pos;
+		// a leading dot refers to the top-level scope
+		if self.at(&Token::Dot) && matches!(self.peek_nth(1), Some(Token::Identifier(_))) {
+			self.bump();
+		}
+
+		let kind = match self.peek() {
+			Some(Token::Identifier(name)) => match name.as_str() {
+				"bit" | "int" => {
+					let is_bit = name == "bit";
+					self.bump();
+					let width = if self.at(&Token::OpenChevron) { Some(self.width()?.into()) } else { None };
+					if is_bit {
+						TypeRefKind::Bit(width)
+					} else {
+						TypeRefKind::Int(width)
+					}
+				}
+				"varbit" => {
+					self.bump();
+					TypeRefKind::Varbit(self.width()?.into())
+				}
+				"bool" => self.keyword_type(TypeRefKind::Bool),
+				"string" => self.keyword_type(TypeRefKind::String),
+				"void" => self.keyword_type(TypeRefKind::Void),
+				"error" => self.keyword_type(TypeRefKind::Error),
+				"match_kind" => self.keyword_type(TypeRefKind::MatchKind),
+				"_" => self.keyword_type(TypeRefKind::DontCare),
+				"tuple" => {
+					self.bump();
+					self.expect(&Token::OpenChevron)?;
+					TypeRefKind::Tuple(self.comma_separated(&Token::CloseChevron, |p| p.type_ref())?)
+				}
+				_ => {
+					let name = self.identifier()?;
+					let args = if self.eat(&Token::OpenChevron) {
+						self.comma_separated(&Token::CloseChevron, |p| p.type_ref())?
+					} else {
+						vec![]
+					};
+					TypeRefKind::Named(name, args)
+				}
+			},
+			_ => {
+				self.error_expected("a type");
+				return None;
+			}
+		};
+
+		let mut ty = TypeRef { kind, location: self.location_from(start) };
+		while self.at(&Token::OpenBracket) {
+			self.bump();
+			let size = self.expression()?;
+			self.expect(&Token::CloseBracket)?;
+			ty = TypeRef { kind: TypeRefKind::Stack(ty.into(), size.into()), location: self.location_from(start) };
+		}
+		Some(ty)
+	}
+
+	fn keyword_type(&mut self, kind: TypeRefKind) -> TypeRefKind {
+		self.bump();
+		kind
+	}
+
+	/// Parses the `<W>` of a `bit<W>`, `int<W>` or `varbit<W>`. The width is a literal, a name, or a parenthesized
+	/// expression.
+	fn width(&mut self) -> Option<Expression> {
+		self.expect(&Token::OpenChevron)?;
+		let width = match self.peek() {
+			Some(Token::OpenParen) => self.primary()?,
+			Some(Token::Integer(_) | Token::Identifier(_)) => self.primary()?,
+			_ => {
+				self.error_expected("a width");
+				return None;
+			}
+		};
+		self.expect(&Token::CloseChevron)?;
+		Some(width)
+	}
+
+	// statements
+
+	fn block(&mut self) -> Option<Block> {
+		let start = self.pos;
+		self.expect(&Token::OpenBrace)?;
+		let mut statements = vec![];
+		while !self.eat(&Token::CloseBrace) {
+			if self.is_eof() {
+				self.expect(&Token::CloseBrace)?;
+			}
+			let statement_start = self.pos;
+			match self.statement() {
+				Some(statement) => statements.push(statement),
+				None => self.recover(statement_start),
+			}
+		}
+		Some(Block { statements, location: self.location_from(start) })
+	}
+
+	fn statement(&mut self) -> Option<Statement> {
+		let start = self.pos;
+		let mut annotations = self.annotations()?;
+		let mut kind = match self.peek() {
+			Some(Token::OpenBrace) => StatementKind::Block(self.block()?),
+			Some(Token::Semicolon) => {
+				self.bump();
+				StatementKind::Empty
+			}
+			Some(Token::KwIf) => {
+				self.bump();
+				self.expect(&Token::OpenParen)?;
+				let condition = self.expression()?;
+				self.expect(&Token::CloseParen)?;
+				let then = self.statement()?;
+				let otherwise = if self.eat(&Token::KwElse) { Some(self.statement()?.into()) } else { None };
+				StatementKind::If(condition, then.into(), otherwise)
+			}
+			Some(Token::KwSwitch) => self.switch()?,
+			Some(Token::KwReturn) => {
+				self.bump();
+				let value = if self.at(&Token::Semicolon) { None } else { Some(self.expression()?) };
+				self.expect(&Token::Semicolon)?;
+				StatementKind::Return(value)
+			}
+			Some(Token::KwExit) => {
+				self.bump();
+				self.expect(&Token::Semicolon)?;
+				StatementKind::Exit
+			}
+			Some(Token::KwConst) => StatementKind::Declaration(Declaration::Constant(self.constant(start)?).into()),
+			_ => {
+				if let Some(declaration) = self.speculate(|p| p.variable_declaration()) {
+					StatementKind::Declaration(declaration.into())
+				} else {
+					let lhs = self.expression()?;
+					if self.eat(&Token::Equals) {
+						let rhs = self.expression()?;
+						self.expect(&Token::Semicolon)?;
+						StatementKind::Assignment(lhs, rhs)
+					} else if matches!(lhs.kind, ExpressionKind::Call(..)) {
+						self.expect(&Token::Semicolon)?;
+						StatementKind::Call(lhs)
+					} else {
+						self.error_expected("`=` or `(`");
+						return None;
+					}
+				}
+			}
+		};
+		// the annotations of a declaration belong to the declaration itself
+		if let StatementKind::Declaration(declaration) = &mut kind {
+			declaration.annotations_mut().splice(0..0, annotations.drain(..));
+		}
+		Some(Statement { kind, annotations, location: self.location_from(start) })
+	}
+
+	/// Parses `T name;`, `T name = value;` or an instantiation within a block. Fails without consuming any input
+	/// (when used with [`P4Parser::speculate`]) if the input doesn't start with a type followed by a name.
+	fn variable_declaration(&mut self) -> Option<Declaration> {
+		let start = self.pos;
+		let ty = self.type_ref()?;
+		if !self.at_identifier() && !self.at(&Token::OpenParen) {
+			return None;
+		}
+		if self.at(&Token::OpenParen) {
+			// only an instantiation if the arguments are followed by a name
+			let args = self.arguments()?;
+			let name = self.identifier()?;
+			self.expect(&Token::Semicolon)?;
+			return Some(Declaration::Instantiation(Instantiation {
+				ty,
+				args,
+				name,
+				initializer: vec![],
+				annotations: vec![],
+				location: self.location_from(start),
+			}));
+		}
+		let name = self.identifier()?;
+		if !self.at(&Token::Equals) && !self.at(&Token::Semicolon) {
+			return None;
+		}
+		let initializer = if self.eat(&Token::Equals) { Some(self.expression()?) } else { None };
+		self.expect(&Token::Semicolon)?;
+		let location = self.location_from(start);
+		Some(Declaration::Variable(Variable { ty, name, initializer, annotations: vec![], location }))
+	}
+
+	fn switch(&mut self) -> Option<StatementKind> {
+		self.expect(&Token::KwSwitch)?;
+		self.expect(&Token::OpenParen)?;
+		let scrutinee = self.expression()?;
+		self.expect(&Token::CloseParen)?;
+		self.expect(&Token::OpenBrace)?;
+		let mut cases = vec![];
+		while !self.eat(&Token::CloseBrace) {
+			let start = self.pos;
+			let label = self.expression()?;
+			self.expect(&Token::Colon)?;
+			let body = if self.at(&Token::OpenBrace) { Some(self.block()?) } else { None };
+			cases.push(SwitchCase { label, body, location: self.location_from(start) });
+		}
+		Some(StatementKind::Switch(scrutinee, cases))
+	}
+
+	// expressions
+
+	pub(crate) fn expression(&mut self) -> Option<Expression> {
+		let start = self.pos;
+		let condition = self.binary(0)?;
+		if !self.eat(&Token::QuestionMark) {
+			return Some(condition);
+		}
+		let then = self.expression()?;
+		self.expect(&Token::Colon)?;
+		let otherwise = self.expression()?;
+		Some(Expression {
+			kind: ExpressionKind::Ternary(condition.into(), then.into(), otherwise.into()),
+			location: self.location_from(start),
+		})
+	}
+
+	/// Returns the binary operator at the current position if it binds at `level`, along with the number of
+	/// tokens it spans.
+	fn binary_operator(&self, level: usize) -> Option<(BinaryOp, usize)> {
+		use BinaryOp::*;
+
+		let tk = self.peek()?;
+		let op = match (level, tk) {
+			(0, Token::DoublePipe) => LogicalOr,
+			(1, Token::DoubleAmpersand) => LogicalAnd,
+			(2, Token::DoubleEquals) => Equals,
+			(2, Token::NotEquals) => NotEquals,
+			(3, Token::OpenChevron) => LessThan,
+			(3, Token::LessOrEqual) => LessOrEqual,
+			(3, Token::CloseChevron) if !self.at_shift_right() => GreaterThan,
+			(3, Token::GreaterOrEqual) => GreaterOrEqual,
+			(4, Token::Pipe) => BitOr,
+			(5, Token::Caret) => BitXor,
+			(6, Token::Ampersand) => BitAnd,
+			(7, Token::ShiftLeft) => ShiftLeft,
+			(7, Token::CloseChevron) if self.at_shift_right() => return Some((ShiftRight, 2)),
+			(8, Token::DoublePlus) => Concatenate,
+			(8, Token::Plus) => Add,
+			(8, Token::Minus) => Subtract,
+			(8, Token::SaturatingPlus) => SaturatingAdd,
+			(8, Token::SaturatingMinus) => SaturatingSubtract,
+			(9, Token::Asterisk) => Multiply,
+			(9, Token::Slash) => Divide,
+			(9, Token::Percent) => Modulo,
+			_ => return None,
+		};
+		Some((op, 1))
+	}
+
+	/// Parses a left-associative chain of binary operators that bind at `level` or tighter (see
+	/// [the P4 specification](https://p4.org/p4-spec/docs/P4-16-v-1.2.3.html#sec-p4-grammar) for the precedences).
+	fn binary(&mut self, level: usize) -> Option<Expression> {
+		const TIGHTEST: usize = 9;
+
+		let start = self.pos;
+		let mut lhs = if level == TIGHTEST { self.unary()? } else { self.binary(level + 1)? };
+		while let Some((op, width)) = self.binary_operator(level) {
+			self.pos += width;
+			let rhs = if level == TIGHTEST { self.unary()? } else { self.binary(level + 1)? };
+			lhs = Expression {
+				kind: ExpressionKind::Binary(op, lhs.into(), rhs.into()),
+				location: self.location_from(start),
+			};
+		}
+		Some(lhs)
+	}
+
+	fn unary(&mut self) -> Option<Expression> {
+		let start = self.pos;
+		let op = match self.peek() {
+			Some(Token::ExclamationMark) => UnaryOp::Not,
+			Some(Token::Tilde) => UnaryOp::Complement,
+			Some(Token::Minus) => UnaryOp::Negate,
+			Some(Token::Plus) => UnaryOp::Plus,
+			Some(Token::OpenParen) => {
+				if let Some(cast) = self.speculate(|p| p.cast(start)) {
+					return Some(cast);
+				}
+				return self.postfix();
+			}
+			_ => return self.postfix(),
+		};
+		self.bump();
+		let operand = self.unary()?;
+		Some(Expression { kind: ExpressionKind::Unary(op, operand.into()), location: self.location_from(start) })
+	}
+
+	/// Parses `(T) e`. A parenthesized plain name is only taken as a type if an operand follows it directly, which
+	/// disambiguates, e.g., `(a) - b` from `(T) -b` in favour of the former.
+	fn cast(&mut self, start: usize) -> Option<Expression> {
+		self.expect(&Token::OpenParen)?;
+		let ty = self.type_ref()?;
+		self.expect(&Token::CloseParen)?;
+		let is_plain_name = matches!(&ty.kind, TypeRefKind::Named(_, args) if args.is_empty());
+		let operand_follows = matches!(
+			self.peek(),
+			Some(
+				Token::Identifier(_)
+					| Token::Integer(_) | Token::OpenParen
+					| Token::OpenBrace | Token::KwTrue
+					| Token::KwFalse | Token::ExclamationMark
+					| Token::Tilde
+			)
+		);
+		if !operand_follows && (is_plain_name || !matches!(self.peek(), Some(Token::Minus | Token::Plus))) {
+			return None;
+		}
+		let operand = self.unary()?;
+		Some(Expression { kind: ExpressionKind::Cast(ty, operand.into()), location: self.location_from(start) })
+	}
+
+	fn postfix(&mut self) -> Option<Expression> {
+		let start = self.pos;
+		let mut expression = self.primary()?;
+		loop {
+			let kind = match self.peek() {
+				Some(Token::Dot) => {
+					self.bump();
+					let member = self.member_name()?;
+					ExpressionKind::Member(expression.into(), member)
+				}
+				Some(Token::OpenBracket) => {
+					self.bump();
+					let index = self.expression()?;
+					if self.eat(&Token::Colon) {
+						let low = self.expression()?;
+						self.expect(&Token::CloseBracket)?;
+						ExpressionKind::Slice(expression.into(), index.into(), low.into())
+					} else {
+						self.expect(&Token::CloseBracket)?;
+						ExpressionKind::Index(expression.into(), index.into())
+					}
+				}
+				Some(Token::OpenParen) => ExpressionKind::Call(expression.into(), vec![], self.arguments()?),
+				Some(Token::OpenChevron)
+					if matches!(expression.kind, ExpressionKind::Name(_) | ExpressionKind::Member(..)) =>
+				{
+					// either the type arguments of a call, or a comparison
+					let type_args = self.speculate(|p| {
+						p.bump();
+						let type_args = p.comma_separated(&Token::CloseChevron, |p| p.type_ref())?;
+						p.at(&Token::OpenParen).then_some(type_args)
+					});
+					match type_args {
+						Some(type_args) => ExpressionKind::Call(expression.into(), type_args, self.arguments()?),
+						None => return Some(expression),
+					}
+				}
+				_ => return Some(expression),
+			};
+			expression = Expression { kind, location: self.location_from(start) };
+		}
+	}
+
+	fn arguments(&mut self) -> Option<Vec<Argument>> {
+		self.expect(&Token::OpenParen)?;
+		self.comma_separated(&Token::CloseParen, |p| {
+			let name = if p.at_identifier() && p.at_nth(1, &Token::Equals) { Some(p.identifier()?) } else { None };
+			if name.is_some() {
+				p.bump();
+			}
+			let value = p.expression()?;
+			Some(Argument { name, value })
+		})
+	}
+
+	fn primary(&mut self) -> Option<Expression> {
+		let start = self.pos;
+		let (file, tk, span) = match self.tokens.get(self.pos) {
+			Some(tk) => *tk,
+			None => {
+				self.error_expected("an expression");
+				return None;
+			}
+		};
+		let location = Location::new(*file, span.clone());
+
+		let kind = match tk {
+			Token::Integer(literal) => {
+				self.bump();
+				ExpressionKind::Integer(literal.clone())
+			}
+			Token::KwTrue | Token::KwFalse => {
+				self.bump();
+				ExpressionKind::Boolean(*tk == Token::KwTrue)
+			}
+			Token::StringLiteral(s) => {
+				self.bump();
+				ExpressionKind::String(s.clone())
+			}
+			Token::KwDefault => {
+				self.bump();
+				ExpressionKind::Default
+			}
+			Token::Identifier(name) if name == "_" => {
+				self.bump();
+				ExpressionKind::DontCare
+			}
+			Token::Identifier(name) if is_type_keyword(name) && name != "error" => {
+				ExpressionKind::Type(self.type_ref()?)
+			}
+			Token::Identifier(name) => {
+				self.bump();
+				ExpressionKind::Name(Identifier { name: name.clone(), location })
+			}
+			Token::Dot if matches!(self.peek_nth(1), Some(Token::Identifier(_))) => {
+				// a reference to the top-level scope
+				self.bump();
+				ExpressionKind::Name(self.identifier()?)
+			}
+			Token::OpenParen => {
+				self.bump();
+				let inner = self.expression()?;
+				self.expect(&Token::CloseParen)?;
+				return Some(Expression { kind: inner.kind, location: self.location_from(start) });
+			}
+			Token::OpenBrace => {
+				self.bump();
+				if self.at_identifier() && self.at_nth(1, &Token::Equals) {
+					let fields = self.comma_separated(&Token::CloseBrace, |p| {
+						let name = p.identifier()?;
+						p.expect(&Token::Equals)?;
+						Some((name, p.expression()?))
+					})?;
+					ExpressionKind::StructInitializer(fields)
+				} else {
+					ExpressionKind::List(self.comma_separated(&Token::CloseBrace, |p| p.expression())?)
+				}
+			}
+			_ => {
+				self.error_expected("an expression");
+				return None;
+			}
+		};
+		Some(Expression { kind, location: self.location_from(start) })
+	}
+}
+
+/// Returns `true` for the built-in type names, which the lexer treats as identifiers.
+pub fn is_type_keyword(name: &str) -> bool {
+	matches!(name, "bit" | "int" | "varbit" | "bool" | "string" | "void" | "error" | "match_kind" | "tuple")
+}
+
+/// Builds the annotation of a `@pragma`, lexing its body, which runs to the end of the line.
+fn pragma(file_id: FileId, body: &str, span: &Span) -> Annotation {
+	let offset = span.end - body.len();
+	let body = Token::lexer_with_extras(body, Lextras { db: None, file_id })
+		.spanned()
+		.filter(|(tk, _)| !matches!(tk, Token::Whitespace | Token::Comment))
+		.map(|(tk, body_span)| (tk, Location::new(file_id, body_span.start + offset..body_span.end + offset)))
+		.collect();
+	let name = Identifier {
+		name: "pragma".to_string(),
+		location: Location::new(file_id, span.start + 1..span.start + "@pragma".len()),
+	};
+	Annotation { name, body: AnnotationBody::Unstructured(body), location: Location::new(file_id, span.clone()) }
+}
+
+/// Returns `true` if the token can only appear at the start of a declaration.
+fn starts_declaration(tk: &Token) -> bool {
+	matches!(
+		tk,
+		Token::KwHeader
+			| Token::KwHeaderUnion
+			| Token::KwStruct
+			| Token::KwEnum
+			| Token::KwTypedef
+			| Token::KwType
+			| Token::KwExtern
+			| Token::KwParser
+			| Token::KwControl
+			| Token::KwPackage
+			| Token::KwAction
+			| Token::KwTable
+			| Token::KwState
+			| Token::KwApply
+	)
+}
+
+/// Returns the source text of a keyword token.
+pub fn keyword_text(tk: &Token) -> Option<&'static str> {
+	Some(match tk {
+		Token::KwAbstract => "abstract",
+		Token::KwAction => "action",
+		Token::KwApply => "apply",
+		Token::KwConst => "const",
+		Token::KwControl => "control",
+		Token::KwDefault => "default",
+		Token::KwElse => "else",
+		Token::KwEnum => "enum",
+		Token::KwExit => "exit",
+		Token::KwExtern => "extern",
+		Token::KwFalse => "false",
+		Token::KwHeader => "header",
+		Token::KwHeaderUnion => "header_union",
+		Token::KwIf => "if",
+		Token::KwIn => "in",
+		Token::KwInout => "inout",
+		Token::KwOut => "out",
+		Token::KwPackage => "package",
+		Token::KwParser => "parser",
+		Token::KwReturn => "return",
+		Token::KwSelect => "select",
+		Token::KwState => "state",
+		Token::KwStruct => "struct",
+		Token::KwSwitch => "switch",
+		Token::KwTable => "table",
+		Token::KwTransition => "transition",
+		Token::KwTrue => "true",
+		Token::KwType => "type",
+		Token::KwTypedef => "typedef",
+		_ => return None,
+	})
+}
+
+/// Describes a token for use in an error message.
+fn describe(tk: &Token) -> String {
+	let punctuation = match tk {
+		Token::CloseBrace => "}",
+		Token::CloseBracket => "]",
+		Token::CloseChevron => ">",
+		Token::CloseParen => ")",
+		Token::At => "@",
+		Token::Colon => ":",
+		Token::Comma => ",",
+		Token::Dot => ".",
+		Token::Equals => "=",
+		Token::Asterisk => "*",
+		Token::Slash => "/",
+		Token::Plus => "+",
+		Token::Minus => "-",
+		Token::Percent => "%",
+		Token::Ampersand => "&",
+		Token::Pipe => "|",
+		Token::Caret => "^",
+		Token::Tilde => "~",
+		Token::ExclamationMark => "!",
+		Token::QuestionMark => "?",
+		Token::DoubleAmpersand => "&&",
+		Token::TripleAmpersand => "&&&",
+		Token::DoublePipe => "||",
+		Token::DoubleDot => "..",
+		Token::DoubleEquals => "==",
+		Token::NotEquals => "!=",
+		Token::LessOrEqual => "<=",
+		Token::GreaterOrEqual => ">=",
+		Token::ShiftLeft => "<<",
+		Token::DoublePlus => "++",
+		Token::SaturatingPlus => "|+|",
+		Token::SaturatingMinus => "|-|",
+		Token::OpenBrace => "{",
+		Token::OpenBracket => "[",
+		Token::OpenChevron => "<",
+		Token::OpenParen => "(",
+		Token::Semicolon => ";",
+		Token::Identifier(name) => return format!("identifier `{name}`"),
+		Token::Integer(_) => return "an integer".to_string(),
+		Token::StringLiteral(_) => return "a string".to_string(),
+		Token::Pragma(_) => return "a `@pragma`".to_string(),
+		tk => match keyword_text(tk) {
+			Some(keyword) => return format!("keyword `{keyword}`"),
+			None => return format!("{tk:?}"),
+		},
+	};
+	format!("`{punctuation}`")
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::Database;
+	use pretty_assertions::assert_eq;
+
+	fn tokens(s: &str) -> Vec<(FileId, Token, Span)> {
+		let db = Database::new(|base, _| Ok(base.into()));
+		let file_id = FileId::new(&db, "foo.p4".to_string());
+		Token::lexer_with_extras(s, Lextras { db: None, file_id })
+			.spanned()
+			.map(|(tk, span)| (file_id, tk, span))
+			.collect()
+	}
+
+	/// Renders an expression with explicit parentheses.
+	fn render(e: &Expression) -> String {
+		match &e.kind {
+			ExpressionKind::Integer(l) => l.value.to_string(),
+			ExpressionKind::Name(n) => n.name.clone(),
+			ExpressionKind::Member(base, member) => format!("{}.{}", render(base), member.name),
+			ExpressionKind::Unary(op, operand) => format!("({op:?} {})", render(operand)),
+			ExpressionKind::Binary(op, lhs, rhs) => format!("({op:?} {} {})", render(lhs), render(rhs)),
+			ExpressionKind::Ternary(c, t, o) => format!("({} ? {} : {})", render(c), render(t), render(o)),
+			ExpressionKind::Slice(base, hi, lo) => format!("{}[{}:{}]", render(base), render(hi), render(lo)),
+			ExpressionKind::Cast(_, operand) => format!("(cast {})", render(operand)),
+			ExpressionKind::Call(callee, type_args, args) => {
+				let args: Vec<_> = args.iter().map(|a| render(&a.value)).collect();
+				format!("{}<{}>({})", render(callee), type_args.len(), args.join(", "))
+			}
+			kind => format!("{kind:?}"),
+		}
+	}
+
+	fn expression(s: &str) -> String { render(&parse_expression(&tokens(s)).expect("failed to parse")) }
+
+	fn errors(s: &str) -> Vec<String> { parse(&tokens(s)).1.into_iter().map(|d| d.message).collect() }
+
+	#[test]
+	fn precedence() {
+		assert_eq!(expression("a + b * c"), "(Add a (Multiply b c))");
+		assert_eq!(expression("a & b == c"), "(Equals (BitAnd a b) c)");
+		assert_eq!(expression("a < b || !c && d"), "(LogicalOr (LessThan a b) (LogicalAnd (Not c) d))");
+		assert_eq!(expression("a ++ b << 2"), "(ShiftLeft (Concatenate a b) 2)");
+		assert_eq!(expression("a ? b : c ? d : e"), "(a ? b : (c ? d : e))");
+	}
+
+	#[test]
+	fn shifts_and_comparisons() {
+		assert_eq!(expression("a >> 2"), "(ShiftRight a 2)");
+		assert_eq!(expression("a > b"), "(GreaterThan a b)");
+		assert_eq!(expression("(a) - b"), "(Subtract a b)");
+		assert_eq!(expression("(bit<8>) a >> 1"), "(ShiftRight (cast a) 1)");
+	}
+
+	#[test]
+	fn postfix() {
+		assert_eq!(expression("h.f[7:0]"), "h.f[7:0]");
+		assert_eq!(expression("p.lookahead<bit<8>>()"), "p.lookahead<1>()");
+		assert_eq!(expression("t.apply().hit"), "t.apply<0>().hit");
+	}
+
+	#[test]
+	fn declarations() {
+		let s = r"
+			header h_t { bit<8> f; }
+			parser P(packet_in p, out h_t h) {
+				state start { p.extract(h); transition select(h.f) { 1: accept; default: reject; } }
+			}
+			control C(inout h_t h) {
+				table t { key = { h.f: exact; } actions = { NoAction; } }
+				apply { if (h.isValid()) { t.apply(); } }
+			}
+			package Pkg(P p, C c);
+			Pkg(P(), C()) main;
+		";
+		let (program, diagnostics) = parse(&tokens(s));
+		assert!(diagnostics.is_empty());
+		let names: Vec<_> = program.declarations.iter().map(|d| d.name().unwrap().name.as_str()).collect();
+		assert_eq!(names, vec!["h_t", "P", "C", "Pkg", "main"]);
+	}
+
+	#[test]
+	fn recovery() {
+		let s = r"
+			const bit<8> x = ;
+			control C() { apply { y = ; z = 1; } }
+			struct s { bit<8> f; }
+		";
+		let (program, diagnostics) = parse(&tokens(s));
+		assert_eq!(diagnostics.len(), 2);
+		let names: Vec<_> = program.declarations.iter().map(|d| d.name().unwrap().name.as_str()).collect();
+		assert_eq!(names, vec!["C", "s"]);
+		assert_eq!(errors("header h { bit<8> f }"), vec!["expected `;`, found `}`"]);
+		assert_eq!(
+			errors("parser P() { state start { transition select (8w1) { (): accept; } } }"),
+			vec!["a keyset cannot be empty"]
+		);
+	}
+
+	#[test]
+	fn annotations() {
+		let s = r#"
+			@pragma dont_trim
+			@name("ingress.t") @vendor[level = 2, mode = "fast"] @flags[1, 2]
+			table t { actions = { @tableonly a; @defaultonly NoAction; } @id(1) size = 1024; }
+			struct s { @match(ternary) bit<8> f; }
+		"#;
+		let (program, diagnostics) = parse(&tokens(s));
+		assert!(diagnostics.is_empty());
+		let [Declaration::Table(t), Declaration::Struct(st)] = program.declarations.as_slice() else { panic!() };
+		let names: Vec<_> = t.annotations.iter().map(|a| a.name.name.as_str()).collect();
+		assert_eq!(names, vec!["pragma", "name", "vendor", "flags"]);
+		assert_eq!(annotation(&t.annotations, "name").and_then(Annotation::string), Some("ingress.t"));
+		assert!(matches!(&t.annotations[0].body, AnnotationBody::Unstructured(tokens) if tokens.len() == 1));
+		assert!(matches!(&t.annotations[2].body, AnnotationBody::KeyValues(pairs) if pairs.len() == 2));
+		assert!(matches!(&t.annotations[3].body, AnnotationBody::Expressions(values) if values.len() == 2));
+		let actions: Vec<_> = t.actions().map(|a| a.annotations[0].name.name.as_str()).collect();
+		assert_eq!(actions, vec!["tableonly", "defaultonly"]);
+		assert_eq!(t.properties[1].annotations()[0].integer(), Some(1));
+		assert_eq!(st.fields[0].annotations[0].name.name, "match");
+
+		assert_eq!(
+			errors("table t { @name(\"k\") key = { } }"),
+			vec!["the `key` property of a table cannot be annotated"]
+		);
+	}
+}
@@ -0,0 +1,279 @@
+//! A visitor over the abstract syntax tree.
+//!
+//! Each `visit_*` method of a [`Visitor`] defaults to the matching `walk_*` function, which visits the children of
+//! the node. An implementation overrides the methods for the nodes it is interested in, calling the `walk_*`
+//! function to continue the traversal below them.
+
+use super::*;
+
+pub trait Visitor<'a>: Sized {
+	fn visit_declaration(&mut self, declaration: &'a Declaration) { walk_declaration(self, declaration) }
+
+	fn visit_parameter(&mut self, parameter: &'a Parameter) { walk_parameter(self, parameter) }
+
+	fn visit_statement(&mut self, statement: &'a Statement) { walk_statement(self, statement) }
+
+	fn visit_expression(&mut self, expression: &'a Expression) { walk_expression(self, expression) }
+
+	fn visit_type_ref(&mut self, ty: &'a TypeRef) { walk_type_ref(self, ty) }
+}
+
+pub fn walk_program<'a>(visitor: &mut impl Visitor<'a>, program: &'a Program) {
+	for declaration in &program.declarations {
+		visitor.visit_declaration(declaration);
+	}
+}
+
+pub fn walk_declaration<'a>(visitor: &mut impl Visitor<'a>, declaration: &'a Declaration) {
+	match declaration {
+		Declaration::Constant(c) => {
+			visitor.visit_type_ref(&c.ty);
+			visitor.visit_expression(&c.value);
+		}
+		Declaration::Variable(v) => {
+			visitor.visit_type_ref(&v.ty);
+			if let Some(initializer) = &v.initializer {
+				visitor.visit_expression(initializer);
+			}
+		}
+		Declaration::Instantiation(i) => {
+			visitor.visit_type_ref(&i.ty);
+			walk_arguments(visitor, &i.args);
+			for declaration in &i.initializer {
+				visitor.visit_declaration(declaration);
+			}
+		}
+		Declaration::Typedef(t) => visitor.visit_type_ref(&t.ty),
+		Declaration::Struct(s) => {
+			for field in &s.fields {
+				visitor.visit_type_ref(&field.ty);
+			}
+		}
+		Declaration::Enum(e) => {
+			if let Some(ty) = &e.underlying_type {
+				visitor.visit_type_ref(ty);
+			}
+			for value in e.members.iter().filter_map(|m| m.value.as_ref()) {
+				visitor.visit_expression(value);
+			}
+		}
+		Declaration::Error(_) | Declaration::MatchKind(_) => (),
+		Declaration::ExternObject(e) => {
+			for method in &e.methods {
+				walk_prototype(visitor, method);
+			}
+		}
+		Declaration::ExternFunction(f) => walk_prototype(visitor, f),
+		Declaration::Parser(p) => {
+			walk_parameters(visitor, &p.params);
+			walk_parameters(visitor, &p.constructor_params);
+			for local in &p.locals {
+				visitor.visit_declaration(local);
+			}
+			for state in &p.states {
+				walk_statements(visitor, &state.statements);
+				if let Some(Transition { kind: TransitionKind::Select(selectors, cases), .. }) = &state.transition {
+					for selector in selectors {
+						visitor.visit_expression(selector);
+					}
+					for key in cases.iter().flat_map(|c| &c.keyset) {
+						visitor.visit_expression(key);
+					}
+				}
+			}
+		}
+		Declaration::Control(c) => {
+			walk_parameters(visitor, &c.params);
+			walk_parameters(visitor, &c.constructor_params);
+			for local in &c.locals {
+				visitor.visit_declaration(local);
+			}
+			if let Some(apply) = &c.apply {
+				walk_statements(visitor, &apply.statements);
+			}
+		}
+		Declaration::Package(p) => walk_parameters(visitor, &p.params),
+		Declaration::Action(a) => {
+			walk_parameters(visitor, &a.params);
+			walk_statements(visitor, &a.body.statements);
+		}
+		Declaration::Function(f) => {
+			walk_prototype(visitor, &f.prototype);
+			walk_statements(visitor, &f.body.statements);
+		}
+		Declaration::Table(t) => {
+			for property in &t.properties {
+				walk_table_property(visitor, property);
+			}
+		}
+	}
+}
+
+fn walk_prototype<'a>(visitor: &mut impl Visitor<'a>, prototype: &'a FunctionPrototype) {
+	if let Some(return_type) = &prototype.return_type {
+		visitor.visit_type_ref(return_type);
+	}
+	walk_parameters(visitor, &prototype.params);
+}
+
+fn walk_parameters<'a>(visitor: &mut impl Visitor<'a>, params: &'a [Parameter]) {
+	for param in params {
+		visitor.visit_parameter(param);
+	}
+}
+
+fn walk_arguments<'a>(visitor: &mut impl Visitor<'a>, args: &'a [Argument]) {
+	for arg in args {
+		visitor.visit_expression(&arg.value);
+	}
+}
+
+fn walk_statements<'a>(visitor: &mut impl Visitor<'a>, statements: &'a [Statement]) {
+	for statement in statements {
+		visitor.visit_statement(statement);
+	}
+}
+
+pub fn walk_table_property<'a>(visitor: &mut impl Visitor<'a>, property: &'a TableProperty) {
+	match property {
+		TableProperty::Key(elements, _) => {
+			for element in elements {
+				visitor.visit_expression(&element.expression);
+			}
+		}
+		TableProperty::Actions(actions, _) => {
+			for args in actions.iter().filter_map(|a| a.args.as_ref()) {
+				walk_arguments(visitor, args);
+			}
+		}
+		TableProperty::DefaultAction { action, .. } => {
+			if let Some(args) = &action.args {
+				walk_arguments(visitor, args);
+			}
+		}
+		TableProperty::Entries { entries, .. } => {
+			for entry in entries {
+				for key in &entry.keyset {
+					visitor.visit_expression(key);
+				}
+				if let Some(args) = &entry.action.args {
+					walk_arguments(visitor, args);
+				}
+			}
+		}
+		TableProperty::Custom { value, .. } => visitor.visit_expression(value),
+	}
+}
+
+pub fn walk_parameter<'a>(visitor: &mut impl Visitor<'a>, parameter: &'a Parameter) {
+	visitor.visit_type_ref(&parameter.ty);
+	if let Some(default) = &parameter.default {
+		visitor.visit_expression(default);
+	}
+}
+
+pub fn walk_statement<'a>(visitor: &mut impl Visitor<'a>, statement: &'a Statement) {
+	match &statement.kind {
+		StatementKind::Assignment(lhs, rhs) => {
+			visitor.visit_expression(lhs);
+			visitor.visit_expression(rhs);
+		}
+		StatementKind::Call(call) => visitor.visit_expression(call),
+		StatementKind::If(condition, then, otherwise) => {
+			visitor.visit_expression(condition);
+			visitor.visit_statement(then);
+			if let Some(otherwise) = otherwise {
+				visitor.visit_statement(otherwise);
+			}
+		}
+		StatementKind::Switch(scrutinee, cases) => {
+			visitor.visit_expression(scrutinee);
+			for case in cases {
+				visitor.visit_expression(&case.label);
+				if let Some(body) = &case.body {
+					walk_statements(visitor, &body.statements);
+				}
+			}
+		}
+		StatementKind::Block(block) => walk_statements(visitor, &block.statements),
+		StatementKind::Return(value) => {
+			if let Some(value) = value {
+				visitor.visit_expression(value);
+			}
+		}
+		StatementKind::Exit | StatementKind::Empty => (),
+		StatementKind::Declaration(declaration) => visitor.visit_declaration(declaration),
+	}
+}
+
+pub fn walk_expression<'a>(visitor: &mut impl Visitor<'a>, expression: &'a Expression) {
+	match &expression.kind {
+		ExpressionKind::Integer(_)
+		| ExpressionKind::Boolean(_)
+		| ExpressionKind::String(_)
+		| ExpressionKind::Name(_)
+		| ExpressionKind::DontCare
+		| ExpressionKind::Default
+		| ExpressionKind::Missing => (),
+		ExpressionKind::Member(base, _) => visitor.visit_expression(base),
+		ExpressionKind::Index(base, index) => {
+			visitor.visit_expression(base);
+			visitor.visit_expression(index);
+		}
+		ExpressionKind::Slice(base, high, low) => {
+			visitor.visit_expression(base);
+			visitor.visit_expression(high);
+			visitor.visit_expression(low);
+		}
+		ExpressionKind::Call(callee, type_args, args) => {
+			visitor.visit_expression(callee);
+			for ty in type_args {
+				visitor.visit_type_ref(ty);
+			}
+			walk_arguments(visitor, args);
+		}
+		ExpressionKind::Unary(_, operand) => visitor.visit_expression(operand),
+		ExpressionKind::Binary(_, lhs, rhs) => {
+			visitor.visit_expression(lhs);
+			visitor.visit_expression(rhs);
+		}
+		ExpressionKind::Ternary(condition, then, otherwise) => {
+			visitor.visit_expression(condition);
+			visitor.visit_expression(then);
+			visitor.visit_expression(otherwise);
+		}
+		ExpressionKind::Cast(ty, operand) => {
+			visitor.visit_type_ref(ty);
+			visitor.visit_expression(operand);
+		}
+		ExpressionKind::List(elements) => {
+			for element in elements {
+				visitor.visit_expression(element);
+			}
+		}
+		ExpressionKind::StructInitializer(fields) => {
+			for (_, value) in fields {
+				visitor.visit_expression(value);
+			}
+		}
+		ExpressionKind::Type(ty) => visitor.visit_type_ref(ty),
+	}
+}
+
+pub fn walk_type_ref<'a>(visitor: &mut impl Visitor<'a>, ty: &'a TypeRef) {
+	match &ty.kind {
+		TypeRefKind::Bit(Some(width)) | TypeRefKind::Int(Some(width)) | TypeRefKind::Varbit(width) => {
+			visitor.visit_expression(width)
+		}
+		TypeRefKind::Named(_, args) | TypeRefKind::Tuple(args) => {
+			for arg in args {
+				visitor.visit_type_ref(arg);
+			}
+		}
+		TypeRefKind::Stack(element, size) => {
+			visitor.visit_type_ref(element);
+			visitor.visit_expression(size);
+		}
+		_ => (),
+	}
+}
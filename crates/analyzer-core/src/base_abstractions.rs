@@ -21,6 +21,22 @@ pub struct FileId {
 	pub path: String,
 }
 
+/// A span of source text in a given file.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Location {
+	pub file: FileId,
+	pub span: Span,
+}
+
+impl Location {
+	pub fn new(file: FileId, span: Span) -> Self { Self { file, span } }
+
+	/// Returns `true` if the location is in `file` and covers `offset`, including the position just past its end.
+	pub fn contains(&self, file: FileId, offset: usize) -> bool {
+		self.file == file && self.span.start <= offset && offset <= self.span.end
+	}
+}
+
 #[salsa::accumulator]
 pub struct Diagnostics(Diagnostic);
 
@@ -28,7 +44,11 @@ pub struct Diagnostics(Diagnostic);
 #[salsa::accumulator]
 pub struct IncludedDependencies(IncludedDependency);
 
-#[derive(Clone, PartialEq, Eq)]
+/// An accumulated collection of the macros defined while preprocessing a translation unit.
+#[salsa::accumulator]
+pub struct MacroDefinitions(MacroDefinition);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Severity {
 	Info,
 	Hint,
@@ -36,12 +56,42 @@ pub enum Severity {
 	Error,
 }
 
-#[derive(Clone, PartialEq, Eq)]
+/// Additional metadata about a diagnostic, which clients may use to render the code it covers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DiagnosticTag {
+	/// The code is unused or unnecessary, and may be faded out.
+	Unnecessary,
+	/// The code is deprecated, and may be struck through.
+	Deprecated,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Diagnostic {
 	pub file: FileId,
 	pub location: std::ops::Range<usize>,
 	pub severity: Severity,
 	pub message: String,
+	/// The code of the lint reporting the diagnostic (e.g. `P4W0012`), or `None` for an error of the language.
+	pub code: Option<&'static str>,
+	/// The other locations involved in the diagnostic, such as a previous declaration, each with a message.
+	pub related: Vec<(Location, String)>,
+	pub tags: Vec<DiagnosticTag>,
+}
+
+impl Diagnostic {
+	pub fn new(file: FileId, location: std::ops::Range<usize>, severity: Severity, message: String) -> Self {
+		Diagnostic { file, location, severity, message, code: None, related: vec![], tags: vec![] }
+	}
+
+	pub fn with_related(mut self, location: Location, message: String) -> Self {
+		self.related.push((location, message));
+		self
+	}
+
+	pub fn with_tag(mut self, tag: DiagnosticTag) -> Self {
+		self.tags.push(tag);
+		self
+	}
 }
 
 /// Represents an included dependency.
@@ -53,3 +103,15 @@ pub struct IncludedDependency {
 	/// A flag indicating the resolved state of the dependency.
 	pub is_resolved: bool,
 }
+
+/// Represents a macro defined with `#define`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MacroDefinition {
+	pub name: String,
+
+	/// The unexpanded replacement text of the macro.
+	pub value: String,
+
+	/// The location of the `#define` directive.
+	pub location: Location,
+}
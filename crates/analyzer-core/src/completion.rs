@@ -0,0 +1,822 @@
+//! Completion.
+//!
+//! Like signature help, completion works on the tokens before the cursor, since the code being written rarely
+//! parses. The innermost block, parenthesis or type argument list left open gives the context: the declarations
+//! allowed at the top level or in the body of a parser or control, the statements of a block, the properties of a
+//! table, the fields and match kinds of its `key`, the parameters of a declaration, and so on. The names offered are
+//! those visible from the cursor, filtered by what the context accepts, along with the keywords it allows.
+//!
+//! After a `.`, the members of the value before it are offered instead: the fields and built-in methods of headers
+//! and structs, the members of header stacks, the methods of extern instances, the `apply` method of tables, parsers
+//! and controls, and the members of enums and of `error`.
+//!
+//! The declarations of the headers of the workspace which the translation unit does not include yet are offered along
+//! with the visible names. Resolving a completion adds the signature and documentation of its declaration and, for
+//! such a header, the `#include` making it visible.
+//!
+//! Where a declaration may start, templates of the common ones are offered as snippets: tables, parser states,
+//! controls, headers and the skeleton of a v1model program. Their placeholders are filled in from the program, with
+//! its actions, match kinds, states and the structs holding its headers and metadata.
+
+use std::collections::HashSet;
+
+use crate::{
+	base_abstractions::*,
+	file_lexemes, file_text,
+	hover::Describer,
+	lexer::Token,
+	outline, preprocess,
+	preprocessor::PreprocessorDirective,
+	symbols::{self, SymbolId, SymbolKind, SymbolTable},
+	types::{self, Type, TypeInfo},
+	Fs,
+};
+
+/// A completion item.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Completion {
+	pub label: String,
+	pub kind: CompletionKind,
+	/// The type of the completed value, or the signature of the completed method.
+	pub detail: Option<String>,
+	/// The text inserted, if not the label.
+	pub insert_text: Option<String>,
+	/// The location of the name of the completed declaration, if any.
+	pub symbol: Option<Location>,
+}
+
+/// The details of a completion, computed once it is selected.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Resolution {
+	/// The signature of the completed declaration.
+	pub detail: Option<String>,
+	/// The comments and annotations documenting the declaration, as Markdown.
+	pub documentation: Option<String>,
+	/// The `#include` of the header declaring it, if the file does not include it yet, as the offset where it is
+	/// inserted and the text inserted.
+	pub include: Option<(usize, String)>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CompletionKind {
+	/// A symbol, or a built-in member of the given kind.
+	Symbol(SymbolKind),
+	/// A keyword, a table property or a built-in type.
+	Keyword,
+	/// The template of a declaration, whose insert text has the syntax of LSP snippets.
+	Snippet,
+}
+
+/// The keywords starting the declarations of the top level.
+const TOP_LEVEL: &[&str] = &[
+	"action",
+	"const",
+	"control",
+	"enum",
+	"error",
+	"extern",
+	"header",
+	"header_union",
+	"match_kind",
+	"package",
+	"parser",
+	"struct",
+	"type",
+	"typedef",
+];
+/// The built-in types, which the lexer does not tell apart from names.
+const BUILTIN_TYPES: &[&str] = &["bit", "bool", "error", "int", "match_kind", "string", "varbit", "void"];
+const TABLE_PROPERTIES: &[&str] = &["actions", "const", "default_action", "entries", "key", "size"];
+
+/// Returns the completions at `offset` in the translation unit rooted at `file_id`.
+pub fn completions(db: &dyn crate::Db, fs: Fs, file_id: FileId, offset: usize) -> Vec<Completion> {
+	let (Some(lexemes), Some(text), Some(table)) =
+		(file_lexemes(db, fs, file_id), file_text(db, fs, file_id), symbols::symbols(db, fs, file_id).as_ref())
+	else {
+		return vec![];
+	};
+	let info = types::types(db, fs, file_id).as_ref();
+	// the tokens before the cursor, leaving out the directives and the word being completed
+	let tokens: Vec<_> = lexemes
+		.iter()
+		.filter(|(token, span)| {
+			!matches!(token, Token::Whitespace | Token::Comment | Token::PreprocessorDirective(_)) && span.end <= offset
+		})
+		.filter(|(_, span)| span.end < offset || !text[span.clone()].chars().all(|c| c.is_alphanumeric() || c == '_'))
+		.map(|(token, _)| token)
+		.collect();
+
+	let scope = table.scope_at(file_id, offset);
+	let completer = Completer { db, fs, table, info, scope, file_id, offset };
+	match tokens.last() {
+		Some(Token::Dot) => return completer.members(&tokens[..tokens.len() - 1]),
+		Some(Token::KwTransition) => return completer.states(true),
+		_ => (),
+	}
+	let context = context(&tokens, |name| completer.is_type(name));
+	// keywords only start statements and declarations, not expressions
+	let starts = matches!(tokens.last(), None | Some(Token::Semicolon | Token::OpenBrace | Token::CloseBrace));
+	let after_colon = matches!(tokens.last(), Some(Token::Colon));
+	let default_action =
+		matches!(tokens[..], [.., Token::Identifier(property), Token::Equals] if property == "default_action");
+
+	let mut completions = match context {
+		Context::TopLevel => completer.with_keywords(if starts { TOP_LEVEL } else { &[] }, |kind| kind.is_type()),
+		Context::Control => {
+			let keywords: &[_] = if starts { &["action", "apply", "const", "table"] } else { &[] };
+			completer.with_keywords(keywords, |kind| kind.is_type())
+		}
+		Context::Parser => {
+			let keywords: &[_] = if starts { &["const", "state"] } else { &[] };
+			completer.with_keywords(keywords, |kind| kind.is_type())
+		}
+		Context::Statements { parser } => {
+			let keywords: &[_] = match (starts, parser) {
+				(false, _) => &[],
+				(true, true) => &["const", "transition"],
+				(true, false) => &["const", "else", "exit", "if", "return", "switch"],
+			};
+			completer.with_keywords(keywords, |kind| *kind != SymbolKind::State)
+		}
+		Context::Table if default_action => completer.names(|kind| *kind == SymbolKind::Action),
+		Context::Table if starts => completer.with_keywords(TABLE_PROPERTIES, |_| false),
+		Context::Key if after_colon => completer.names(|kind| *kind == SymbolKind::MatchKind),
+		Context::Key => completer.names(is_value),
+		Context::Actions => completer.names(|kind| *kind == SymbolKind::Action),
+		Context::Select if after_colon => completer.states(false),
+		Context::Select => completer.with_keywords(&["default"], is_value),
+		Context::Switch if !after_colon => completer.with_keywords(&["default"], |kind| *kind == SymbolKind::Action),
+		Context::Fields | Context::TypeArguments => completer.with_keywords(&[], |kind| kind.is_type()),
+		Context::Extern => {
+			let keywords: &[_] = if starts { &["abstract"] } else { &[] };
+			completer.with_keywords(keywords, |kind| kind.is_type())
+		}
+		Context::Parameters => {
+			let keywords: &[_] = match tokens.last() {
+				Some(Token::OpenParen | Token::Comma) => &["in", "inout", "out"],
+				_ => &[],
+			};
+			completer.with_keywords(keywords, |kind| kind.is_type())
+		}
+		Context::Width => completer.names(|kind| matches!(kind, SymbolKind::Constant | SymbolKind::Macro)),
+		Context::Expression => completer.names(is_value),
+		Context::Table | Context::Switch | Context::Members => vec![],
+	};
+	if starts {
+		completions.extend(completer.snippets(context));
+	}
+	completions
+}
+
+/// Resolves the completion of the declaration whose name is at `symbol`, offered in the translation unit rooted at
+/// `file_id`.
+pub fn resolve(db: &dyn crate::Db, fs: Fs, file_id: FileId, symbol: &Location) -> Option<Resolution> {
+	// a header not included yet is described as the root of its own translation unit
+	let header = !unit_files(db, fs, file_id).contains(&symbol.file) && fs.fs(db).contains_key(&symbol.file);
+	let describer = Describer::new(db, fs, if header { symbol.file } else { file_id })?;
+	let id = describer.table.declaration_at(symbol.file, symbol.span.start)?;
+	let declaration = describer.table.symbol(id);
+	Some(Resolution {
+		detail: describer.signature(declaration),
+		documentation: describer.documentation(declaration),
+		include: if header { include(db, fs, file_id, symbol.file) } else { None },
+	})
+}
+
+/// Returns the files of the translation unit rooted at `file_id`: the root and the headers it includes.
+fn unit_files(db: &dyn crate::Db, fs: Fs, file_id: FileId) -> HashSet<FileId> {
+	let included = preprocess::accumulated::<IncludedDependencies>(db, fs, file_id);
+	included.into_iter().map(|dependency| dependency.file_id).chain([file_id]).collect()
+}
+
+/// Returns the `#include` of `header` in `file_id`, after its last `#include`, or at its start.
+fn include(db: &dyn crate::Db, fs: Fs, file_id: FileId, header: FileId) -> Option<(usize, String)> {
+	let text = file_text(db, fs, file_id)?;
+	let directive = format!("#include \"{}\"", relative_path(&file_id.path(db), &header.path(db)));
+	let last = file_lexemes(db, fs, file_id)?
+		.iter()
+		.rev()
+		.find(|(token, _)| matches!(token, Token::PreprocessorDirective(PreprocessorDirective::Include(..))));
+	Some(match last {
+		// a directive ends with its line
+		Some((_, span)) if text[..span.end].ends_with('\n') => (span.end, format!("{directive}\n")),
+		Some((_, span)) => (span.end, format!("\n{directive}")),
+		None => (0, format!("{directive}\n")),
+	})
+}
+
+/// Returns the path of `to` relative to the directory of `from`, both being paths or URLs separated by `/`.
+fn relative_path(from: &str, to: &str) -> String {
+	let from: Vec<_> = from.split('/').collect();
+	let to: Vec<_> = to.split('/').collect();
+	let (directory, file) = (&from[..from.len() - 1], &to[to.len() - 1]);
+	let common = directory.iter().zip(&to[..to.len() - 1]).take_while(|(a, b)| a == b).count();
+
+	let mut components = vec![".."; directory.len() - common];
+	components.extend(&to[common..to.len() - 1]);
+	components.push(file);
+	components.join("/")
+}
+
+/// Returns `true` for the symbols which may be used in an expression.
+fn is_value(kind: &SymbolKind) -> bool {
+	match kind {
+		SymbolKind::State => false,
+		// the members of enums are qualified by their type, and the instances of externs constructed from it
+		SymbolKind::Enum | SymbolKind::ExternObject => true,
+		kind => !kind.is_type(),
+	}
+}
+
+/// What may be written at the cursor.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Context {
+	TopLevel,
+	/// The declarations of the body of a control.
+	Control,
+	/// The declarations of the body of a parser.
+	Parser,
+	/// The statements of a block, or of a parser state.
+	Statements {
+		parser: bool,
+	},
+	Table,
+	Key,
+	Actions,
+	Select,
+	Switch,
+	/// The fields of a header or struct.
+	Fields,
+	/// The methods of an extern.
+	Extern,
+	/// The members of an enum, `error` or `match_kind`.
+	Members,
+	/// The parameter list of a declaration.
+	Parameters,
+	TypeArguments,
+	/// The width of `bit<W>`, `int<W>` or `varbit<W>`.
+	Width,
+	Expression,
+}
+
+/// Returns the context of the innermost delimiter left open by `tokens`, given a function telling whether a name
+/// names a type, and thus whether a `<` after it opens type arguments.
+fn context(tokens: &[&Token], is_type: impl Fn(&str) -> bool) -> Context {
+	let mut open: Vec<Context> = vec![];
+	for (i, token) in tokens.iter().enumerate() {
+		let enclosing = open.last().copied().unwrap_or(Context::TopLevel);
+		match token {
+			Token::OpenBrace => open.push(block(&tokens[..i], enclosing)),
+			Token::OpenParen => {
+				let declaration = match tokens[..i].iter().rev().nth(1) {
+					Some(Token::KwAction | Token::KwControl | Token::KwParser | Token::KwPackage) => true,
+					// the name of a function or method is preceded by its return type
+					Some(Token::Identifier(_) | Token::CloseChevron) => {
+						matches!(enclosing, Context::TopLevel | Context::Extern | Context::Control)
+					}
+					// and the constructors of an extern by the previous method
+					_ => enclosing == Context::Extern,
+				};
+				open.push(if declaration { Context::Parameters } else { Context::Expression });
+			}
+			Token::OpenBracket => open.push(Context::Expression),
+			Token::OpenChevron => match tokens[..i].last() {
+				Some(Token::Identifier(name)) if matches!(name.as_str(), "bit" | "int" | "varbit") => {
+					open.push(Context::Width)
+				}
+				Some(Token::Identifier(name)) if is_type(name) || matches!(name.as_str(), "tuple" | "list") => {
+					open.push(Context::TypeArguments)
+				}
+				_ => (),
+			},
+			Token::CloseChevron if matches!(enclosing, Context::TypeArguments | Context::Width) => {
+				open.pop();
+			}
+			Token::CloseParen | Token::CloseBracket | Token::CloseBrace => {
+				while matches!(open.last(), Some(Context::TypeArguments | Context::Width)) {
+					open.pop();
+				}
+				open.pop();
+			}
+			// the parameter lists of the declarations without a body end with them
+			Token::Semicolon => {
+				while matches!(open.last(), Some(Context::TypeArguments | Context::Width | Context::Parameters)) {
+					open.pop();
+				}
+			}
+			_ => (),
+		}
+	}
+	open.last().copied().unwrap_or(Context::TopLevel)
+}
+
+/// Returns the context of a block opened after `tokens`, from the keyword introducing it.
+fn block(tokens: &[&Token], enclosing: Context) -> Context {
+	let statements = Context::Statements { parser: matches!(enclosing, Context::Statements { parser: true }) };
+	if let Some(Token::Identifier(name)) = tokens.last() {
+		if matches!(name.as_str(), "error" | "match_kind") {
+			return Context::Members;
+		}
+	}
+
+	// skip the name, parameters and type parameters of the declaration
+	let mut i = tokens.len();
+	let mut depth = 0;
+	while i > 0 {
+		match tokens[i - 1] {
+			Token::CloseParen | Token::CloseChevron => depth += 1,
+			Token::OpenParen | Token::OpenChevron if depth > 0 => depth -= 1,
+			_ if depth > 0 => (),
+			Token::Identifier(_) => (),
+			_ => break,
+		}
+		i -= 1;
+	}
+	match tokens[..i].last() {
+		Some(Token::KwControl) => Context::Control,
+		Some(Token::KwParser) => Context::Parser,
+		Some(Token::KwState) => Context::Statements { parser: true },
+		Some(Token::KwTable) => Context::Table,
+		Some(Token::KwHeader | Token::KwHeaderUnion | Token::KwStruct) => Context::Fields,
+		Some(Token::KwExtern) => Context::Extern,
+		Some(Token::KwEnum) => Context::Members,
+		Some(Token::KwSelect) => Context::Select,
+		Some(Token::KwSwitch) => Context::Switch,
+		Some(Token::Equals) => match tokens[..i - 1].last() {
+			Some(Token::Identifier(name)) if name == "key" => Context::Key,
+			Some(Token::Identifier(name)) if name == "actions" => Context::Actions,
+			_ => Context::Expression,
+		},
+		_ => statements,
+	}
+}
+
+struct Completer<'a> {
+	db: &'a dyn crate::Db,
+	fs: Fs,
+	table: &'a SymbolTable,
+	info: Option<&'a TypeInfo>,
+	/// The innermost scope at the cursor.
+	scope: symbols::ScopeId,
+	file_id: FileId,
+	offset: usize,
+}
+
+/// A step from a value to one of its members.
+enum Step<'a> {
+	Member(&'a str),
+	Index,
+	/// A call of the `apply` method of a table.
+	Apply,
+}
+
+impl Completer<'_> {
+	/// Returns the symbols visible from the cursor, the innermost ones first, leaving out those shadowed by them and
+	/// those declared after the cursor.
+	fn visible(&self) -> Vec<SymbolId> {
+		let table = self.table;
+		// macros are looked up first, as by name resolution
+		let mut scopes = vec![table.macros];
+		let mut scope = Some(self.scope);
+		while let Some(current) = scope {
+			scopes.push(current);
+			scope = table.scope(current).parent;
+		}
+
+		let mut names = HashSet::new();
+		let mut symbols = vec![];
+		for scope in scopes {
+			for &id in &table.scope(scope).symbols {
+				let symbol = table.symbol(id);
+				// the states of a parser may be referenced before they are declared
+				let later = symbol.location.file == self.file_id
+					&& symbol.location.span.end >= self.offset
+					&& symbol.kind != SymbolKind::State;
+				if !later && names.insert(symbol.name.clone()) {
+					symbols.push(id);
+				}
+			}
+		}
+		symbols
+	}
+
+	/// Returns the visible names accepted by the filter, followed by the declarations of the headers not included yet.
+	fn names(&self, filter: impl Fn(&SymbolKind) -> bool) -> Vec<Completion> {
+		let visible = self.visible();
+		let names: HashSet<_> = visible.iter().map(|id| self.table.symbol(*id).name.as_str()).collect();
+		let mut completions: Vec<_> = visible
+			.iter()
+			.filter(|id| filter(&self.table.symbol(**id).kind))
+			.map(|id| self.symbol(*id))
+			.collect();
+		completions.extend(self.headers(&filter, &names));
+		completions
+	}
+
+	/// Returns the top-level declarations of the headers of the workspace which the translation unit neither includes
+	/// nor is included by, leaving out those named like a visible symbol.
+	fn headers(&self, filter: impl Fn(&SymbolKind) -> bool, visible: &HashSet<&str>) -> Vec<Completion> {
+		let unit = unit_files(self.db, self.fs, self.file_id);
+		let mut headers: Vec<_> = self
+			.fs
+			.fs(self.db)
+			.iter()
+			.filter(|(header, _)| !unit.contains(header))
+			.filter(|(header, _)| !unit_files(self.db, self.fs, **header).contains(&self.file_id))
+			.map(|(header, buf)| (header.path(self.db), *header, *buf))
+			.collect();
+		headers.sort_by(|a, b| a.0.cmp(&b.0));
+
+		let mut completions = vec![];
+		for (_, header, buf) in headers {
+			let declarations = outline::document_symbols(self.db, header, buf);
+			// a program instantiating its package is not a header
+			if declarations.iter().any(|d| d.kind == SymbolKind::Instance && d.name == "main") {
+				continue;
+			}
+			let declarations = declarations.iter().filter(|d| filter(&d.kind) && !visible.contains(d.name.as_str()));
+			completions.extend(declarations.map(|declaration| Completion {
+				label: declaration.name.clone(),
+				kind: CompletionKind::Symbol(declaration.kind.clone()),
+				detail: None,
+				insert_text: None,
+				symbol: Some(Location::new(header, declaration.selection_span.clone())),
+			}));
+		}
+		completions
+	}
+
+	/// Returns the keywords, followed by the built-in types if the filter accepts types, and the visible names.
+	fn with_keywords(&self, keywords: &[&str], filter: impl Fn(&SymbolKind) -> bool) -> Vec<Completion> {
+		let mut completions: Vec<_> = keywords.iter().map(|keyword| keyword_completion(keyword)).collect();
+		if filter(&SymbolKind::Typedef) {
+			let types = BUILTIN_TYPES.iter().filter(|ty| !keywords.contains(ty));
+			completions.extend(types.map(|ty| keyword_completion(ty)));
+		}
+		completions.extend(self.names(filter));
+		completions
+	}
+
+	/// Returns the states of the enclosing parser, with `accept` and `reject`, and `select` if `transition` precedes
+	/// the cursor.
+	fn states(&self, transition: bool) -> Vec<Completion> {
+		let mut completions = self.names(|kind| *kind == SymbolKind::State);
+		for state in ["accept", "reject"] {
+			completions
+				.push(Completion { kind: CompletionKind::Symbol(SymbolKind::State), ..keyword_completion(state) });
+		}
+		if transition {
+			completions.push(keyword_completion("select"));
+		}
+		completions
+	}
+
+	fn symbol(&self, id: SymbolId) -> Completion {
+		let symbol = self.table.symbol(id);
+		let detail = match symbol.kind {
+			SymbolKind::Constant
+			| SymbolKind::Variable
+			| SymbolKind::Parameter(_)
+			| SymbolKind::Field
+			| SymbolKind::Instance => self
+				.info
+				.and_then(|info| info.symbol_type(id))
+				.filter(|ty| !ty.is_unknown())
+				.map(|ty| ty.display(self.table).to_string()),
+			_ => None,
+		};
+		Completion {
+			label: symbol.name.clone(),
+			kind: CompletionKind::Symbol(symbol.kind.clone()),
+			detail,
+			insert_text: None,
+			symbol: Some(symbol.location.clone()),
+		}
+	}
+
+	fn is_type(&self, name: &str) -> bool {
+		let id = self.table.lookup(self.scope, name);
+		id.map_or(false, |id| self.table.symbol(id).kind.is_type())
+	}
+
+	/// Returns the members of the value whose expression ends `tokens`.
+	fn members(&self, tokens: &[&Token]) -> Vec<Completion> {
+		let table = self.table;
+		// the steps from the first name of the expression to the value, in reverse
+		let mut steps = vec![];
+		let mut i = tokens.len();
+		let first = loop {
+			match tokens[..i] {
+				[.., Token::Identifier(name)] if i > 1 && matches!(tokens[i - 2], Token::Dot) => {
+					steps.push(Step::Member(name.as_str()));
+					i -= 2;
+				}
+				[.., Token::Identifier(name)] => break name.as_str(),
+				[.., Token::KwApply, Token::OpenParen, Token::CloseParen] => {
+					steps.push(Step::Apply);
+					i -= 3;
+					if i > 0 && matches!(tokens[i - 1], Token::Dot) {
+						i -= 1;
+					}
+				}
+				[.., Token::CloseBracket] => {
+					// the index itself does not matter
+					let Some(open) = tokens[..i].iter().rposition(|t| matches!(t, Token::OpenBracket)) else {
+						return vec![];
+					};
+					steps.push(Step::Index);
+					i = open;
+				}
+				_ => return vec![],
+			}
+		};
+
+		if first == "error" && steps.is_empty() {
+			let members = table.symbols().filter(|(_, symbol)| symbol.kind == SymbolKind::ErrorMember);
+			return members.map(|(id, _)| self.symbol(id)).collect();
+		}
+		let Some(id) = table.lookup(self.scope, first) else { return vec![] };
+		let symbol = table.symbol(id);
+		if symbol.kind == SymbolKind::Enum && steps.is_empty() {
+			return symbol
+				.body
+				.map_or(vec![], |body| table.scope(body).symbols.iter().map(|id| self.symbol(*id)).collect());
+		}
+		let Some(info) = self.info else { return vec![] };
+		// tables are typed by their uses only
+		let ty = if symbol.kind == SymbolKind::Table { Some(Type::Table(id)) } else { info.symbol_type(id).cloned() };
+		let Some(mut ty) = ty else { return vec![] };
+		for step in steps.into_iter().rev() {
+			ty = match (step, ty) {
+				(Step::Member(name), Type::Header(id, _) | Type::HeaderUnion(id, _) | Type::Struct(id, _)) => {
+					let Some(field) = self.field(id, name) else { return vec![] };
+					let Some(ty) = info.symbol_type(field) else { return vec![] };
+					ty.clone()
+				}
+				(Step::Member("next" | "last") | Step::Index, Type::Stack(element, _)) => *element,
+				(Step::Apply, Type::Table(id)) => Type::ApplyResult(id),
+				_ => return vec![],
+			};
+		}
+
+		let builtin = |name: &str, kind: SymbolKind, detail: &str| Completion {
+			label: name.to_string(),
+			kind: CompletionKind::Symbol(kind.clone()),
+			detail: Some(detail.to_string()),
+			insert_text: (kind == SymbolKind::Method).then(|| format!("{name}()")),
+			symbol: None,
+		};
+		let mut completions = vec![];
+		match &ty {
+			&Type::Header(id, _) | &Type::HeaderUnion(id, _) | &Type::Struct(id, _) => {
+				completions.extend(info.fields(table, id).map(|(field, _)| self.symbol(field)));
+				if matches!(ty, Type::Header(..) | Type::HeaderUnion(..)) {
+					completions.push(builtin("isValid", SymbolKind::Method, "bool isValid()"));
+				}
+				if matches!(ty, Type::Header(..)) {
+					completions.push(builtin("setValid", SymbolKind::Method, "void setValid()"));
+					completions.push(builtin("setInvalid", SymbolKind::Method, "void setInvalid()"));
+				}
+				completions.push(builtin("minSizeInBits", SymbolKind::Method, "int minSizeInBits()"));
+				completions.push(builtin("minSizeInBytes", SymbolKind::Method, "int minSizeInBytes()"));
+			}
+			Type::Stack(element, _) => {
+				let element = element.display(table).to_string();
+				completions.push(builtin("next", SymbolKind::Field, &element));
+				completions.push(builtin("last", SymbolKind::Field, &element));
+				for name in ["size", "lastIndex", "nextIndex"] {
+					completions.push(builtin(name, SymbolKind::Field, "bit<32>"));
+				}
+				completions.push(Completion {
+					insert_text: Some("push_front(".to_string()),
+					..builtin("push_front", SymbolKind::Method, "void push_front(int count)")
+				});
+				completions.push(Completion {
+					insert_text: Some("pop_front(".to_string()),
+					..builtin("pop_front", SymbolKind::Method, "void pop_front(int count)")
+				});
+			}
+			&Type::Extern(id, _) => {
+				let name = &table.symbol(id).name;
+				let methods =
+					table.symbol(id).body.map(|body| table.scope(body).symbols.as_slice()).unwrap_or_default();
+				// the constructors are named after the extern
+				let methods = methods.iter().filter(|m| table.symbol(**m).kind == SymbolKind::Method);
+				let methods = methods.filter(|m| table.symbol(**m).name != *name);
+				let mut names = HashSet::new();
+				for &method in methods {
+					if names.insert(&table.symbol(method).name) {
+						completions.push(self.symbol(method));
+					}
+				}
+			}
+			Type::Table(_) | Type::Parser(_) | Type::Control(_) => {
+				completions.push(builtin("apply", SymbolKind::Method, "apply()"));
+			}
+			Type::ApplyResult(_) => {
+				completions.push(builtin("hit", SymbolKind::Field, "bool"));
+				completions.push(builtin("miss", SymbolKind::Field, "bool"));
+				completions.push(builtin("action_run", SymbolKind::Field, "action_run"));
+			}
+			_ => (),
+		}
+		completions
+	}
+
+	/// Returns the field `name` of a header, header union or struct.
+	fn field(&self, id: SymbolId, name: &str) -> Option<SymbolId> {
+		let body = self.table.symbol(id).body?;
+		self.table
+			.members(body, name)
+			.iter()
+			.copied()
+			.find(|m| self.table.symbol(*m).kind == SymbolKind::Field)
+	}
+}
+
+/// The templates of declarations.
+impl Completer<'_> {
+	/// Returns the templates of the declarations which may start at the cursor in `context`.
+	fn snippets(&self, context: Context) -> Vec<Completion> {
+		match context {
+			Context::TopLevel => {
+				let mut snippets = vec![self.control_snippet(), header_snippet()];
+				if self.table.lookup(self.scope, "main").is_none() {
+					snippets.push(self.main_snippet());
+				}
+				snippets
+			}
+			Context::Control => vec![self.table_snippet()],
+			Context::Parser => vec![self.state_snippet()],
+			_ => vec![],
+		}
+	}
+
+	fn table_snippet(&self) -> Completion {
+		let actions = self.visible_names(SymbolKind::Action);
+		// tables without a default action run `NoAction`
+		let default_actions = preferring(actions.clone(), "NoAction");
+		let match_kinds = preferring(self.visible_names(SymbolKind::MatchKind), "exact");
+		let mut lines = vec![
+			"table ${1:name} {".to_string(),
+			"\tkey = {".to_string(),
+			format!("\t\t${{2:field}}: {};", choice(3, &match_kinds, "exact")),
+			"\t}".to_string(),
+			"\tactions = {".to_string(),
+		];
+		lines.extend(actions.iter().map(|action| format!("\t\t{action};")));
+		lines.extend([
+			"\t\t$4".to_string(),
+			"\t}".to_string(),
+			"\tsize = ${5:1024};".to_string(),
+			format!("\tdefault_action = {};", choice(6, &default_actions, "NoAction")),
+			"}".to_string(),
+		]);
+		snippet("table", "table with a key, actions, size and default action", lines.join("\n"))
+	}
+
+	fn state_snippet(&self) -> Completion {
+		let mut states = self.visible_names(SymbolKind::State);
+		states.extend(["accept".to_string(), "reject".to_string()]);
+		// the packet and headers are the parameters of the enclosing parser
+		let parameter = |f: &dyn Fn(&Type) -> bool| {
+			let parameters = self.visible().into_iter().filter(|id| {
+				matches!(self.table.symbol(*id).kind, SymbolKind::Parameter(_))
+					&& self.info.and_then(|info| info.symbol_type(*id)).map_or(false, f)
+			});
+			parameters.map(|id| self.table.symbol(id).name.clone()).next()
+		};
+		let packet = parameter(&|ty| matches!(ty, Type::Extern(id, _) if self.table.symbol(*id).name == "packet_in"));
+		let headers = parameter(&|ty| matches!(ty, Type::Struct(..)));
+		let (packet, headers) = (packet.as_deref().unwrap_or("packet"), headers.as_deref().unwrap_or("hdr"));
+		let lines = [
+			"state ${1:name} {".to_string(),
+			format!("\t${{2:{packet}.extract({headers}.header);}}"),
+			"\ttransition select(${3:field}) {".to_string(),
+			format!("\t\t${{4:value}}: {};", choice(5, &states, "accept")),
+			format!("\t\tdefault: {};", choice(6, &states[states.len() - 2..], "accept")),
+			"\t}".to_string(),
+			"}".to_string(),
+		];
+		snippet("state", "parser state with a select transition", lines.join("\n"))
+	}
+
+	fn control_snippet(&self) -> Completion {
+		let (headers, metadata) = self.program_structs();
+		let mut parameters = format!(
+			"inout ${{2:{}}} hdr, inout ${{3:{}}} meta",
+			headers.as_deref().unwrap_or("headers_t"),
+			metadata.as_deref().unwrap_or("metadata_t")
+		);
+		if self.table.lookup(self.scope, "standard_metadata_t").is_some() {
+			parameters.push_str(", inout standard_metadata_t standard_metadata");
+		}
+		let body = format!("control ${{1:name}}({parameters}) {{\n\t$0\n\tapply {{\n\t}}\n}}");
+		snippet("control", "control with an apply block", body)
+	}
+
+	/// Returns the skeleton of a v1model program, declaring the structs of its headers and metadata if it has none,
+	/// and including the architecture if it does not yet.
+	fn main_snippet(&self) -> Completion {
+		let (headers, metadata) = self.program_structs();
+		let mut body = String::new();
+		if self.table.lookup(self.scope, "V1Switch").is_none() {
+			body.push_str("#include <core.p4>\n#include <v1model.p4>\n\n");
+		}
+		for (n, name, default) in [(1, &headers, "headers_t"), (2, &metadata, "metadata_t")] {
+			if name.is_none() {
+				body.push_str(&format!("struct ${{{n}:{default}}} {{\n}}\n\n"));
+			}
+		}
+		let (h, m) = (headers.as_deref().unwrap_or("headers_t"), metadata.as_deref().unwrap_or("metadata_t"));
+		let (h, m) = (format!("${{1:{h}}}"), format!("${{2:{m}}}"));
+		let standard = "inout standard_metadata_t standard_metadata";
+		let (start, apply) = ("\tstate start {\n\t\ttransition accept;\n\t}", "\tapply {\n\t}");
+		let blocks = [
+			("parser", "MyParser", format!("packet_in packet, out {h} hdr, inout {m} meta, {standard}"), start),
+			("control", "MyVerifyChecksum", format!("inout {h} hdr, inout {m} meta"), apply),
+			(
+				"control",
+				"MyIngress",
+				format!("inout {h} hdr, inout {m} meta, {standard}"),
+				"\tapply {\n\t\t$0\n\t}",
+			),
+			("control", "MyEgress", format!("inout {h} hdr, inout {m} meta, {standard}"), apply),
+			("control", "MyComputeChecksum", format!("inout {h} hdr, inout {m} meta"), apply),
+			("control", "MyDeparser", format!("packet_out packet, in {h} hdr"), apply),
+		];
+		for (keyword, name, parameters, block) in &blocks {
+			body.push_str(&format!("{keyword} {name}({parameters}) {{\n{block}\n}}\n\n"));
+		}
+		let instances: Vec<_> = blocks.iter().map(|(_, name, _, _)| format!("{name}()")).collect();
+		body.push_str(&format!("V1Switch({}) main;", instances.join(", ")));
+		snippet("main", "v1model program skeleton", body)
+	}
+
+	/// Returns the names of the visible symbols of a kind, the innermost ones first.
+	fn visible_names(&self, kind: SymbolKind) -> Vec<String> {
+		let visible = self.visible().into_iter().filter(|id| self.table.symbol(*id).kind == kind);
+		visible.map(|id| self.table.symbol(id).name.clone()).collect()
+	}
+
+	/// Returns the names of the structs of the program holding its headers, and its metadata.
+	fn program_structs(&self) -> (Option<String>, Option<String>) {
+		let (table, files) = (self.table, self.fs.fs(self.db));
+		let Some(info) = self.info else { return (None, None) };
+		// the structs of the program, as opposed to those of the architecture
+		let structs: Vec<_> = self
+			.visible()
+			.into_iter()
+			.filter(|id| table.symbol(*id).kind == SymbolKind::Struct)
+			.filter(|id| files.contains_key(&table.symbol(*id).location.file))
+			.collect();
+		let headers = structs.iter().copied().find(|id| {
+			let mut fields = info.fields(table, *id).peekable();
+			fields.peek().is_some()
+				&& fields.all(|(_, ty)| matches!(ty, Type::Header(..) | Type::HeaderUnion(..) | Type::Stack(..)))
+		});
+		let metadata = structs.iter().copied().find(|id| Some(*id) != headers);
+		let name = |id: SymbolId| table.symbol(id).name.clone();
+		(headers.map(name), metadata.map(name))
+	}
+}
+
+/// Moves `name` to the front of `names`, if it is one of them.
+fn preferring(mut names: Vec<String>, name: &str) -> Vec<String> {
+	if let Some(i) = names.iter().position(|n| n == name) {
+		names[..=i].rotate_right(1);
+	}
+	names
+}
+
+/// Returns a choice between `options` at the tab stop `n`, or a placeholder holding `default` if there are none.
+fn choice(n: usize, options: &[String], default: &str) -> String {
+	match options {
+		[] => format!("${{{n}:{default}}}"),
+		options => format!("${{{n}|{}|}}", options.join(",")),
+	}
+}
+
+fn header_snippet() -> Completion {
+	snippet("header", "header declaration", "header ${1:name_t} {\n\t${2:bit<8>} ${3:field};\n}".to_string())
+}
+
+fn snippet(label: &str, detail: &str, body: String) -> Completion {
+	Completion {
+		label: label.to_string(),
+		kind: CompletionKind::Snippet,
+		detail: Some(detail.to_string()),
+		insert_text: Some(body),
+		symbol: None,
+	}
+}
+
+fn keyword_completion(keyword: &str) -> Completion {
+	Completion {
+		label: keyword.to_string(),
+		kind: CompletionKind::Keyword,
+		detail: None,
+		insert_text: None,
+		symbol: None,
+	}
+}
@@ -0,0 +1,649 @@
+//! Compile-time evaluation of constant expressions.
+//!
+//! Integers of a fixed width are evaluated with the wrap-around semantics of P4 over any width, so values are
+//! arbitrary-precision integers that are reduced to the range of their type after every operation. Unlike the
+//! evaluation of `#if` conditions by the preprocessor, this works on the syntax tree and knows about the types of
+//! constants, casts, slices and concatenation.
+
+use std::{collections::HashMap, fmt};
+
+use logos::Logos;
+use num_bigint::BigInt;
+use num_traits::{One, Signed, ToPrimitive, Zero};
+
+use crate::{
+	ast::{
+		visit::{self, Visitor},
+		*,
+	},
+	base_abstractions::*,
+	lexer::{Lextras, Token},
+	parse,
+	symbols::{self, SymbolId, SymbolKind, SymbolTable},
+	types::{self, Type, TypeInfo},
+	Fs,
+};
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Value {
+	/// A `bit<W>`, whose value is in `0..2^W`.
+	Bit(u32, BigInt),
+	/// An `int<W>`, whose value is in `-2^(W-1)..2^(W-1)`.
+	Int(u32, BigInt),
+	/// An integer of arbitrary precision.
+	InfInt(BigInt),
+	Bool(bool),
+	String(String),
+	/// A member of an `error`, a `match_kind` or an enum without an underlying type. The members of serializable
+	/// enums evaluate to their value.
+	Member(SymbolId),
+	List(Vec<Value>),
+	Struct(Vec<(String, Value)>),
+}
+
+impl Value {
+	/// Returns the value of an integer.
+	pub fn integer(&self) -> Option<&BigInt> {
+		match self {
+			Value::Bit(_, value) | Value::Int(_, value) | Value::InfInt(value) => Some(value),
+			_ => None,
+		}
+	}
+
+	pub fn to_i128(&self) -> Option<i128> { self.integer()?.to_i128() }
+
+	/// Returns the type of the value, for those whose type doesn't depend on where they are declared.
+	pub fn ty(&self) -> Option<Type> {
+		match self {
+			Value::Bit(width, _) => Some(Type::Bit(*width)),
+			Value::Int(width, _) => Some(Type::Int(*width)),
+			Value::InfInt(_) => Some(Type::InfInt),
+			Value::Bool(_) => Some(Type::Bool),
+			Value::String(_) => Some(Type::String),
+			Value::List(elements) => {
+				Some(Type::List(elements.iter().map(|e| e.ty().unwrap_or(Type::Unknown)).collect()))
+			}
+			_ => None,
+		}
+	}
+
+	/// Returns an object that displays the value as a P4 literal.
+	pub fn display<'a>(&'a self, table: &'a SymbolTable) -> impl fmt::Display + 'a { DisplayValue(self, table) }
+}
+
+struct DisplayValue<'a>(&'a Value, &'a SymbolTable);
+
+impl fmt::Display for DisplayValue<'_> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let DisplayValue(value, table) = *self;
+		match value {
+			Value::Bit(width, value) => write!(f, "{width}w{value}"),
+			Value::Int(width, value) if value.is_negative() => write!(f, "-{width}s{}", value.abs()),
+			Value::Int(width, value) => write!(f, "{width}s{value}"),
+			Value::InfInt(value) => write!(f, "{value}"),
+			Value::Bool(value) => write!(f, "{value}"),
+			Value::String(value) => write!(f, "{value:?}"),
+			Value::Member(id) => {
+				let symbol = table.symbol(*id);
+				match symbol.kind {
+					SymbolKind::ErrorMember => write!(f, "error.{}", symbol.name),
+					SymbolKind::EnumMember => match table.scope(symbol.scope).owner {
+						Some(owner) => write!(f, "{}.{}", table.symbol(owner).name, symbol.name),
+						None => write!(f, "{}", symbol.name),
+					},
+					_ => write!(f, "{}", symbol.name),
+				}
+			}
+			Value::List(elements) => {
+				write!(f, "{{ ")?;
+				for (i, element) in elements.iter().enumerate() {
+					if i > 0 {
+						write!(f, ", ")?;
+					}
+					write!(f, "{}", element.display(table))?;
+				}
+				write!(f, " }}")
+			}
+			Value::Struct(fields) => {
+				write!(f, "{{ ")?;
+				for (i, (name, value)) in fields.iter().enumerate() {
+					if i > 0 {
+						write!(f, ", ")?;
+					}
+					write!(f, "{name} = {}", value.display(table))?;
+				}
+				write!(f, " }}")
+			}
+		}
+	}
+}
+
+/// Wraps `value` around to the range of a `bit<width>` or, if `signed`, of an `int<width>`.
+pub fn wrap(value: &BigInt, width: u32, signed: bool) -> BigInt {
+	if width == 0 {
+		return BigInt::zero();
+	}
+	let modulus = BigInt::one() << width;
+	let mut value = value % &modulus;
+	if value.is_negative() {
+		value += &modulus;
+	}
+	if signed && value >= BigInt::one() << (width - 1) {
+		value -= modulus;
+	}
+	value
+}
+
+fn fixed(width: u32, signed: bool, value: &BigInt) -> Value {
+	match signed {
+		true => Value::Int(width, wrap(value, width, true)),
+		false => Value::Bit(width, wrap(value, width, false)),
+	}
+}
+
+/// The result of evaluating an expression: either its value, or an error message if the expression is constant but
+/// invalid (such as a division by zero), or nothing if it isn't a compile-time constant.
+type Evaluation = Result<Value, Option<String>>;
+
+/// The context an expression is evaluated in.
+pub trait Environment {
+	/// Returns the value of the constant `name` refers to.
+	fn value_of(&mut self, name: &Identifier) -> Option<Value>;
+
+	/// Resolves the target type of a cast.
+	fn resolve_type(&mut self, ty: &TypeRef) -> Type;
+
+	/// Returns the type a `type` declaration or a serializable enum is based on.
+	fn underlying_type(&mut self, id: SymbolId) -> Option<Type>;
+
+	/// Returns the outcome of an earlier evaluation of `expression`, if it has been evaluated already.
+	fn evaluated(&mut self, _expression: &Expression) -> Option<Option<Value>> { None }
+
+	/// Records the outcome of evaluating `expression`.
+	fn record(&mut self, _expression: &Expression, _value: Option<&Value>) {}
+
+	/// Reports an invalid constant expression.
+	fn error(&mut self, _location: &Location, _message: String) {}
+}
+
+/// Evaluates `expression` if its value is known at compile time. Every subexpression is evaluated, even if the
+/// expression as a whole isn't constant.
+pub fn evaluate(env: &mut impl Environment, expression: &Expression) -> Option<Value> {
+	if let Some(value) = env.evaluated(expression) {
+		return value;
+	}
+	let value = match evaluate_kind(env, expression) {
+		Ok(value) => Some(value),
+		Err(Some(message)) => {
+			env.error(&expression.location, message);
+			None
+		}
+		Err(None) => None,
+	};
+	env.record(expression, value.as_ref());
+	value
+}
+
+fn evaluate_kind(env: &mut impl Environment, expression: &Expression) -> Evaluation {
+	match &expression.kind {
+		ExpressionKind::Integer(literal) => Ok(match literal.width {
+			Some(width) => fixed(width, literal.signed, &literal.value),
+			None => Value::InfInt(literal.value.clone()),
+		}),
+		ExpressionKind::Boolean(value) => Ok(Value::Bool(*value)),
+		ExpressionKind::String(value) => Ok(Value::String(value.clone())),
+		ExpressionKind::Name(name) => env.value_of(name).ok_or(None),
+		ExpressionKind::Member(base, member) => match env.value_of(member) {
+			Some(value) => Ok(value),
+			None => {
+				evaluate(env, base);
+				Err(None)
+			}
+		},
+		ExpressionKind::Unary(op, operand) => unary(*op, evaluate(env, operand).ok_or(None)?),
+		ExpressionKind::Binary(op, lhs, rhs) => {
+			let lhs = evaluate(env, lhs);
+			let rhs = evaluate(env, rhs);
+			binary(*op, lhs.ok_or(None)?, rhs.ok_or(None)?)
+		}
+		ExpressionKind::Ternary(condition, then, otherwise) => {
+			let condition = evaluate(env, condition);
+			let then = evaluate(env, then);
+			let otherwise = evaluate(env, otherwise);
+			match condition {
+				Some(Value::Bool(true)) => then.ok_or(None),
+				Some(Value::Bool(false)) => otherwise.ok_or(None),
+				_ => Err(None),
+			}
+		}
+		ExpressionKind::Cast(ty, operand) => {
+			let value = evaluate(env, operand);
+			let ty = env.resolve_type(ty);
+			cast(env, value.ok_or(None)?, &ty)
+		}
+		ExpressionKind::Slice(base, high, low) => {
+			let base = evaluate(env, base);
+			let high = evaluate(env, high);
+			let low = evaluate(env, low);
+			slice(&base.ok_or(None)?, &high.ok_or(None)?, &low.ok_or(None)?)
+		}
+		ExpressionKind::List(elements) => {
+			let values: Vec<_> = elements.iter().map(|e| evaluate(env, e)).collect();
+			values.into_iter().collect::<Option<_>>().map(Value::List).ok_or(None)
+		}
+		ExpressionKind::StructInitializer(fields) => {
+			let values: Vec<_> = fields.iter().map(|(name, e)| Some((name.name.clone(), evaluate(env, e)?))).collect();
+			values.into_iter().collect::<Option<_>>().map(Value::Struct).ok_or(None)
+		}
+		ExpressionKind::Index(base, index) => {
+			evaluate(env, base);
+			evaluate(env, index);
+			Err(None)
+		}
+		ExpressionKind::Call(callee, _, args) => {
+			evaluate(env, callee);
+			for arg in args {
+				evaluate(env, &arg.value);
+			}
+			Err(None)
+		}
+		ExpressionKind::Type(_) | ExpressionKind::DontCare | ExpressionKind::Default | ExpressionKind::Missing => {
+			Err(None)
+		}
+	}
+}
+
+fn unary(op: UnaryOp, value: Value) -> Evaluation {
+	match (op, value) {
+		(UnaryOp::Not, Value::Bool(value)) => Ok(Value::Bool(!value)),
+		(UnaryOp::Plus, value @ (Value::Bit(..) | Value::Int(..) | Value::InfInt(_))) => Ok(value),
+		(UnaryOp::Negate, Value::Bit(width, value)) => Ok(fixed(width, false, &-value)),
+		(UnaryOp::Negate, Value::Int(width, value)) => Ok(fixed(width, true, &-value)),
+		(UnaryOp::Negate, Value::InfInt(value)) => Ok(Value::InfInt(-value)),
+		(UnaryOp::Complement, Value::Bit(width, value)) => Ok(fixed(width, false, &!value)),
+		(UnaryOp::Complement, Value::Int(width, value)) => Ok(fixed(width, true, &!value)),
+		_ => Err(None),
+	}
+}
+
+/// The type of the operands of a binary operation on integers, after implicitly casting integers of arbitrary
+/// precision to the type of the other operand.
+#[derive(Clone, Copy)]
+enum Operands {
+	Fixed(u32, bool),
+	InfInt,
+}
+
+fn operands(lhs: &Value, rhs: &Value) -> Option<(Operands, BigInt, BigInt)> {
+	let ty = match (lhs, rhs) {
+		(Value::Bit(w, _), Value::Bit(v, _)) if w == v => Operands::Fixed(*w, false),
+		(Value::Bit(w, _), Value::InfInt(_)) | (Value::InfInt(_), Value::Bit(w, _)) => Operands::Fixed(*w, false),
+		(Value::Int(w, _), Value::Int(v, _)) if w == v => Operands::Fixed(*w, true),
+		(Value::Int(w, _), Value::InfInt(_)) | (Value::InfInt(_), Value::Int(w, _)) => Operands::Fixed(*w, true),
+		(Value::InfInt(_), Value::InfInt(_)) => Operands::InfInt,
+		_ => return None,
+	};
+	let (lhs, rhs) = (lhs.integer()?, rhs.integer()?);
+	match ty {
+		Operands::Fixed(width, signed) => Some((ty, wrap(lhs, width, signed), wrap(rhs, width, signed))),
+		Operands::InfInt => Some((ty, lhs.clone(), rhs.clone())),
+	}
+}
+
+fn integer(ty: Operands, value: BigInt) -> Value {
+	match ty {
+		Operands::Fixed(width, signed) => fixed(width, signed, &value),
+		Operands::InfInt => Value::InfInt(value),
+	}
+}
+
+/// The largest shift of an integer of arbitrary precision that is evaluated.
+const MAX_SHIFT: u32 = 1 << 16;
+
+fn binary(op: BinaryOp, lhs: Value, rhs: Value) -> Evaluation {
+	use BinaryOp::*;
+
+	match op {
+		LogicalAnd | LogicalOr => match (lhs, rhs) {
+			(Value::Bool(lhs), Value::Bool(rhs)) => {
+				Ok(Value::Bool(if op == LogicalAnd { lhs && rhs } else { lhs || rhs }))
+			}
+			_ => Err(None),
+		},
+		Equals | NotEquals => {
+			let equal = match operands(&lhs, &rhs) {
+				Some((_, lhs, rhs)) => lhs == rhs,
+				None if lhs.integer().is_some() || rhs.integer().is_some() => return Err(None),
+				None => lhs == rhs,
+			};
+			Ok(Value::Bool(equal == (op == Equals)))
+		}
+		LessThan | LessOrEqual | GreaterThan | GreaterOrEqual => {
+			let (_, lhs, rhs) = operands(&lhs, &rhs).ok_or(None)?;
+			Ok(Value::Bool(match op {
+				LessThan => lhs < rhs,
+				LessOrEqual => lhs <= rhs,
+				GreaterThan => lhs > rhs,
+				_ => lhs >= rhs,
+			}))
+		}
+		Concatenate => match (&lhs, &rhs) {
+			(Value::Bit(a, x) | Value::Int(a, x), Value::Bit(b, y) | Value::Int(b, y)) => {
+				let value = (wrap(x, *a, false) << *b) | wrap(y, *b, false);
+				Ok(fixed(a + b, matches!(lhs, Value::Int(..)), &value))
+			}
+			_ => Err(None),
+		},
+		ShiftLeft | ShiftRight => {
+			let amount = rhs.integer().ok_or(None)?;
+			if amount.is_negative() {
+				return Err(Some(format!("cannot shift by a negative amount ({amount})")));
+			}
+			let (ty, value) = match lhs {
+				Value::Bit(width, value) => (Operands::Fixed(width, false), value),
+				Value::Int(width, value) => (Operands::Fixed(width, true), value),
+				Value::InfInt(value) => (Operands::InfInt, value),
+				_ => return Err(None),
+			};
+			let amount = match (ty, amount.to_u32()) {
+				(Operands::Fixed(width, _), amount) => amount.unwrap_or(u32::MAX).min(width),
+				(Operands::InfInt, Some(amount)) if amount <= MAX_SHIFT => amount,
+				(Operands::InfInt, _) => return Err(Some(format!("the shift amount {amount} is too large"))),
+			};
+			Ok(integer(ty, if op == ShiftLeft { value << amount } else { value >> amount }))
+		}
+		Mask | Range => Err(None),
+		_ => {
+			let (ty, lhs, rhs) = operands(&lhs, &rhs).ok_or(None)?;
+			let value = match op {
+				Add => lhs + rhs,
+				Subtract => lhs - rhs,
+				Multiply => lhs * rhs,
+				Divide | Modulo => {
+					if rhs.is_zero() {
+						return Err(Some(if op == Divide { "division by zero" } else { "modulo by zero" }.to_string()));
+					}
+					if lhs.is_negative() || rhs.is_negative() {
+						return Err(Some("the operands of `/` and `%` must not be negative".to_string()));
+					}
+					if op == Divide {
+						lhs / rhs
+					} else {
+						lhs % rhs
+					}
+				}
+				SaturatingAdd | SaturatingSubtract => {
+					let value = if op == SaturatingAdd { lhs + rhs } else { lhs - rhs };
+					match ty {
+						Operands::Fixed(width, signed) => {
+							let (min, max) = match signed {
+								true => (-(BigInt::one() << (width - 1)), (BigInt::one() << (width - 1)) - 1),
+								false => (BigInt::zero(), (BigInt::one() << width) - 1),
+							};
+							value.clamp(min, max)
+						}
+						Operands::InfInt => value,
+					}
+				}
+				BitAnd => lhs & rhs,
+				BitOr => lhs | rhs,
+				BitXor => lhs ^ rhs,
+				_ => return Err(None),
+			};
+			Ok(integer(ty, value))
+		}
+	}
+}
+
+/// Converts a value to `ty`, as an explicit or implicit cast would.
+pub fn cast_value(env: &mut impl Environment, value: Value, ty: &Type) -> Option<Value> { cast(env, value, ty).ok() }
+
+fn cast(env: &mut impl Environment, value: Value, ty: &Type) -> Evaluation {
+	match (ty, value) {
+		(Type::Bit(width), Value::Bit(_, value) | Value::Int(_, value) | Value::InfInt(value)) => {
+			Ok(fixed(*width, false, &value))
+		}
+		(Type::Int(width), Value::Bit(_, value) | Value::Int(_, value) | Value::InfInt(value)) => {
+			Ok(fixed(*width, true, &value))
+		}
+		(Type::Bit(width), Value::Bool(value)) => Ok(Value::Bit(*width, u8::from(value).into())),
+		(Type::Int(width), Value::Bool(value)) => Ok(fixed(*width, true, &u8::from(value).into())),
+		(Type::Bool, Value::Bit(_, value) | Value::InfInt(value)) => match value.to_u8() {
+			Some(value @ (0 | 1)) => Ok(Value::Bool(value == 1)),
+			_ => Err(Some(format!("only 0 and 1 can be cast to `bool`, found {value}"))),
+		},
+		(Type::InfInt, value @ (Value::Bit(..) | Value::Int(..) | Value::InfInt(_))) => {
+			Ok(Value::InfInt(value.integer().unwrap().clone()))
+		}
+		(Type::Enum(id) | Type::NewType(id), value) => match env.underlying_type(*id) {
+			Some(underlying) => cast(env, value, &underlying),
+			None => Ok(value),
+		},
+		(Type::Bool, value @ Value::Bool(_))
+		| (Type::String, value @ Value::String(_))
+		| (Type::Error | Type::MatchKind, value @ Value::Member(_))
+		| (_, value @ (Value::List(_) | Value::Struct(_))) => Ok(value),
+		(Type::Unknown | Type::TypeParameter(_) | Type::DontCare, value) => Ok(value),
+		_ => Err(None),
+	}
+}
+
+fn slice(value: &Value, high: &Value, low: &Value) -> Evaluation {
+	let (Some(value), Some(high), Some(low)) = (value.integer(), high.to_i128(), low.to_i128()) else {
+		return Err(None);
+	};
+	// out-of-range slices are reported by the type checker
+	let (Ok(high), Ok(low)) = (u32::try_from(high), u32::try_from(low)) else {
+		return Err(None);
+	};
+	if high < low {
+		return Err(None);
+	}
+	let width = high - low + 1;
+	Ok(Value::Bit(width, wrap(&(value >> low), width, false)))
+}
+
+/// Parses the replacement text of a macro as an expression.
+pub(crate) fn macro_expression(table: &SymbolTable, id: SymbolId) -> Option<Expression> {
+	let definition = table.macro_definition(id)?;
+	let file_id = definition.location.file;
+	let tokens: Vec<_> = Token::lexer_with_extras(&definition.value, Lextras { db: None, file_id })
+		.spanned()
+		.map(|(tk, span)| (file_id, tk, span))
+		.collect();
+	crate::ast::parser::parse_expression(&tokens)
+}
+
+/// Evaluates a macro whose replacement text is a constant expression without names, e.g. `#define SIZE (1 << 10)`.
+pub fn macro_value(table: &SymbolTable, id: SymbolId) -> Option<Value> {
+	struct Literals;
+
+	impl Environment for Literals {
+		fn value_of(&mut self, _name: &Identifier) -> Option<Value> { None }
+
+		fn resolve_type(&mut self, _ty: &TypeRef) -> Type { Type::Unknown }
+
+		fn underlying_type(&mut self, _id: SymbolId) -> Option<Type> { None }
+	}
+
+	evaluate(&mut Literals, &macro_expression(table, id)?)
+}
+
+/// The values of the compile-time constant expressions of a program.
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct Constants {
+	expressions: HashMap<Location, Value>,
+	symbols: HashMap<SymbolId, Value>,
+}
+
+impl Constants {
+	/// Returns the value of the expression at `location`, if it is a compile-time constant.
+	pub fn value(&self, location: &Location) -> Option<&Value> { self.expressions.get(location) }
+
+	/// Returns the innermost constant expression covering `offset` in `file`, along with its value.
+	pub fn value_at(&self, file: FileId, offset: usize) -> Option<(&Location, &Value)> {
+		self.expressions
+			.iter()
+			.filter(|(location, _)| location.contains(file, offset))
+			.min_by_key(|(l, _)| l.span.len())
+	}
+
+	/// Returns the value of a constant, an enum member or a macro.
+	pub fn symbol_value(&self, id: SymbolId) -> Option<&Value> { self.symbols.get(&id) }
+}
+
+/// Evaluates the compile-time constant expressions of the translation unit rooted at `file_id`.
+#[salsa::tracked(return_ref)]
+pub fn constants(db: &dyn crate::Db, fs: Fs, file_id: FileId) -> Option<Constants> {
+	let program = parse(db, fs, file_id).as_ref()?;
+	let table = symbols::symbols(db, fs, file_id).as_ref()?;
+	let info = types::types(db, fs, file_id).as_ref()?;
+	let prelude = symbols::prelude(db);
+
+	let mut folder = Folder::new(table, info);
+	visit::walk_program(&mut IndexDeclarations(&mut folder), prelude);
+	visit::walk_program(&mut IndexDeclarations(&mut folder), program);
+	visit::walk_program(&mut folder, program);
+
+	for (location, message) in folder.errors {
+		Diagnostics::push(db, Diagnostic::new(location.file, location.span, Severity::Error, message));
+	}
+	Some(folder.constants)
+}
+
+/// Evaluates every expression of a program, using the types inferred by the type checker.
+struct Folder<'a> {
+	table: &'a SymbolTable,
+	info: &'a TypeInfo,
+	/// The symbols declared by the names at a given position.
+	declared: HashMap<(FileId, usize), SymbolId>,
+	/// The symbols referred to by the names at a given position.
+	targets: HashMap<(FileId, usize), SymbolId>,
+	/// The expressions giving the values of constants and enum members.
+	declarations: HashMap<SymbolId, &'a Expression>,
+	evaluated: HashMap<Location, Option<Value>>,
+	in_progress: Vec<SymbolId>,
+	constants: Constants,
+	errors: Vec<(Location, String)>,
+}
+
+impl<'a> Folder<'a> {
+	fn new(table: &'a SymbolTable, info: &'a TypeInfo) -> Self {
+		let declared = table.symbols().map(|(id, s)| ((s.location.file, s.location.span.start), id)).collect();
+		let targets = table
+			.references()
+			.iter()
+			.filter_map(|r| Some(((r.location.file, r.location.span.start), r.target?)))
+			.collect();
+		Self {
+			table,
+			info,
+			declared,
+			targets,
+			declarations: HashMap::new(),
+			evaluated: HashMap::new(),
+			in_progress: vec![],
+			constants: Constants::default(),
+			errors: vec![],
+		}
+	}
+
+	fn declared_at(&self, name: &Identifier) -> Option<SymbolId> {
+		self.declared.get(&(name.location.file, name.location.span.start)).copied()
+	}
+
+	fn symbol_value(&mut self, id: SymbolId) -> Option<Value> {
+		if let Some(value) = self.constants.symbols.get(&id) {
+			return Some(value.clone());
+		}
+		if self.in_progress.contains(&id) {
+			return None;
+		}
+
+		self.in_progress.push(id);
+		let value = match self.table.symbol(id).kind {
+			SymbolKind::Constant | SymbolKind::EnumMember => match self.declarations.get(&id).copied() {
+				Some(expression) => evaluate(self, expression).and_then(|value| {
+					let ty = self.info.symbol_type(id).cloned().unwrap_or(Type::Unknown);
+					cast_value(self, value, &ty)
+				}),
+				None => Some(Value::Member(id)),
+			},
+			SymbolKind::ErrorMember | SymbolKind::MatchKind => Some(Value::Member(id)),
+			SymbolKind::Macro => macro_value(self.table, id),
+			_ => None,
+		};
+		self.in_progress.pop();
+
+		if let Some(value) = &value {
+			self.constants.symbols.insert(id, value.clone());
+		}
+		value
+	}
+}
+
+impl Environment for Folder<'_> {
+	fn value_of(&mut self, name: &Identifier) -> Option<Value> {
+		let id = *self.targets.get(&(name.location.file, name.location.span.start))?;
+		self.symbol_value(id)
+	}
+
+	fn resolve_type(&mut self, ty: &TypeRef) -> Type {
+		self.info.type_ref(&ty.location).cloned().unwrap_or(Type::Unknown)
+	}
+
+	fn underlying_type(&mut self, id: SymbolId) -> Option<Type> { self.info.underlying_type(id).cloned() }
+
+	fn evaluated(&mut self, expression: &Expression) -> Option<Option<Value>> {
+		self.evaluated.get(&expression.location).cloned()
+	}
+
+	fn record(&mut self, expression: &Expression, value: Option<&Value>) {
+		self.evaluated.insert(expression.location.clone(), value.cloned());
+		if let Some(value) = value {
+			self.constants.expressions.insert(expression.location.clone(), value.clone());
+		}
+	}
+
+	fn error(&mut self, location: &Location, message: String) { self.errors.push((location.clone(), message)) }
+}
+
+impl<'a> Visitor<'a> for Folder<'a> {
+	fn visit_expression(&mut self, expression: &'a Expression) {
+		// the subexpressions are evaluated first, so that type references within them are visited too
+		visit::walk_expression(self, expression);
+		evaluate(self, expression);
+	}
+
+	fn visit_declaration(&mut self, declaration: &'a Declaration) {
+		visit::walk_declaration(self, declaration);
+		let names: Vec<_> = match declaration {
+			Declaration::Constant(c) => vec![&c.name],
+			Declaration::Enum(e) => e.members.iter().map(|m| &m.name).collect(),
+			_ => vec![],
+		};
+		let ids: Vec<_> = names.into_iter().filter_map(|name| self.declared_at(name)).collect();
+		for id in ids {
+			self.symbol_value(id);
+		}
+	}
+}
+
+/// Collects the declarations of constants and enum members.
+struct IndexDeclarations<'f, 'a>(&'f mut Folder<'a>);
+
+impl<'a> Visitor<'a> for IndexDeclarations<'_, 'a> {
+	fn visit_declaration(&mut self, declaration: &'a Declaration) {
+		let values: Vec<_> = match declaration {
+			Declaration::Constant(c) => vec![(&c.name, &c.value)],
+			Declaration::Enum(e) => e.members.iter().filter_map(|m| Some((&m.name, m.value.as_ref()?))).collect(),
+			_ => vec![],
+		};
+		for (name, value) in values {
+			if let Some(id) = self.0.declared_at(name) {
+				self.0.declarations.insert(id, value);
+			}
+		}
+		visit::walk_declaration(self, declaration);
+	}
+}
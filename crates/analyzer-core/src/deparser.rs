@@ -0,0 +1,308 @@
+//! Consistency of the deparser with the parser.
+//!
+//! A deparser emits the valid headers in the order of its `emit` calls, which should be the order in which the parser
+//! of the same pipeline extracts them from the wire. For each package instantiated by `main`, the headers extracted
+//! along the paths of the parser's state graph are compared with those emitted by the deparser: this warns about the
+//! headers extracted but never emitted, which are dropped from the packet, the headers emitted but neither extracted
+//! nor set valid anywhere, which are never emitted, and the headers emitted after a header the parser extracts after
+//! them.
+//!
+//! Headers are identified by the type of the root of their path and the fields leading to them, so that the parser and
+//! the deparser may name their parameters differently. The elements of a header stack and the members of a header
+//! union stand for the stack or the union, and emitting a struct emits its fields in order.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+	arch,
+	ast::{
+		visit::{self, Visitor},
+		*,
+	},
+	base_abstractions::*,
+	lints, parse,
+	parser_states::{self, StateGraph},
+	symbols::{self, SymbolKind, SymbolTable},
+	types::{self, Builtin, Type, TypeInfo},
+	Fs,
+};
+
+/// A header, header stack or header union: the type of the root of its path, and the fields leading to it.
+type Header = (Type, String);
+
+/// Checks the deparsers of the translation unit rooted at `file_id` against the parsers of the same pipelines.
+#[salsa::tracked]
+pub fn emit_order(db: &dyn crate::Db, fs: Fs, file_id: FileId) {
+	let Some(program) = parse(db, fs, file_id).as_ref() else { return };
+	let Some(table) = symbols::symbols(db, fs, file_id).as_ref() else { return };
+	let Some(info) = types::types(db, fs, file_id).as_ref() else { return };
+	let Some(model) = arch::model(db, fs, file_id).as_ref() else { return };
+	let Some(graphs) = parser_states::state_graphs(db, fs, file_id).as_ref() else { return };
+
+	let symbols: HashMap<_, _> = table.symbols().map(|(id, symbol)| (symbol.location.clone(), id)).collect();
+	let declarations: HashMap<_, _> = program
+		.declarations
+		.iter()
+		.filter_map(|declaration| Some((*symbols.get(&declaration.name()?.location)?, declaration)))
+		.collect();
+
+	let mut checker = Checker { table, info, validated: HashSet::new(), diagnostics: vec![] };
+	let mut effects = Effects::new(table, info);
+	visit::walk_program(&mut effects, program);
+	checker.validated = effects.validated.iter().filter_map(|header| Some(checker.header(header)?.0)).collect();
+
+	for blocks in &model.main_blocks {
+		let blocks: Vec<_> = blocks.iter().filter_map(|id| declarations.get(id)).collect();
+		let parser = blocks.iter().find_map(|declaration| match declaration {
+			Declaration::Parser(parser) if checker.takes(&parser.params, "packet_in") => Some(parser),
+			_ => None,
+		});
+		let deparser = blocks.iter().find_map(|declaration| match declaration {
+			Declaration::Control(control) if checker.takes(&control.params, "packet_out") => Some(control),
+			_ => None,
+		});
+		let (Some(parser), Some(deparser)) = (parser, deparser) else { continue };
+		let Some(graph) = graphs.iter().find(|graph| symbols.get(&parser.name.location) == Some(&graph.parser)) else {
+			continue;
+		};
+		checker.pipeline(parser, graph, deparser);
+	}
+
+	for diagnostic in checker.diagnostics {
+		Diagnostics::push(db, diagnostic);
+	}
+}
+
+/// The headers extracted, emitted and made valid by some code, in order.
+struct Effects<'a> {
+	table: &'a SymbolTable,
+	info: &'a TypeInfo,
+	extracted: Vec<&'a Expression>,
+	emitted: Vec<&'a Expression>,
+	/// The headers on which `setValid()` is called, or which are assigned.
+	validated: Vec<&'a Expression>,
+}
+
+impl<'a> Effects<'a> {
+	fn new(table: &'a SymbolTable, info: &'a TypeInfo) -> Self {
+		Effects { table, info, extracted: vec![], emitted: vec![], validated: vec![] }
+	}
+}
+
+impl<'a> Visitor<'a> for Effects<'a> {
+	fn visit_expression(&mut self, expression: &'a Expression) {
+		if let ExpressionKind::Call(callee, _, args) = &expression.kind {
+			let table = self.table;
+			let headers = match self.info.expression_type(&callee.location) {
+				Some(Type::Method(base, method)) => match (base.as_ref(), table.symbol(*method).name.as_str()) {
+					(Type::Extern(object, _), "extract") if table.symbol(*object).name == "packet_in" => {
+						Some(&mut self.extracted)
+					}
+					(Type::Extern(object, _), "emit") if table.symbol(*object).name == "packet_out" => {
+						Some(&mut self.emitted)
+					}
+					_ => None,
+				},
+				_ => None,
+			};
+			if let (Some(headers), Some(arg)) = (headers, args.first()) {
+				headers.push(&arg.value);
+			}
+			if let (Some(Type::Builtin(Builtin::SetValid)), ExpressionKind::Member(base, _)) =
+				(self.info.expression_type(&callee.location), &callee.kind)
+			{
+				self.validated.push(base);
+			}
+		}
+		visit::walk_expression(self, expression)
+	}
+
+	fn visit_statement(&mut self, statement: &'a Statement) {
+		if let StatementKind::Assignment(lhs, _) = &statement.kind {
+			if let Some(Type::Header(..)) = self.info.expression_type(&lhs.location) {
+				self.validated.push(lhs);
+			}
+		}
+		visit::walk_statement(self, statement)
+	}
+}
+
+/// A header extracted or emitted at a given location, as it is written there.
+struct Occurrence<'a> {
+	header: Header,
+	text: String,
+	location: &'a Location,
+}
+
+struct Checker<'a> {
+	table: &'a SymbolTable,
+	info: &'a TypeInfo,
+	/// The headers set valid anywhere in the program.
+	validated: HashSet<Header>,
+	diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> Checker<'a> {
+	/// Returns `true` if one of the parameters has the extern type `name`, such as `packet_in`.
+	fn takes(&self, params: &[Parameter], name: &str) -> bool {
+		params.iter().any(|param| match self.info.type_ref(&param.ty.location) {
+			Some(Type::Extern(id, _)) => self.table.symbol(*id).name == name,
+			_ => false,
+		})
+	}
+
+	fn pipeline(&mut self, parser: &'a Parser, graph: &StateGraph, deparser: &'a Control) {
+		let Some(start) = graph.state("start") else { return };
+		let reachable = graph.reachable(&[start]);
+		let table = self.table;
+		let extracted: Vec<Vec<_>> = parser
+			.states
+			.iter()
+			.map(|state| {
+				let mut effects = Effects::new(table, self.info);
+				for statement in &state.statements {
+					effects.visit_statement(statement);
+				}
+				effects.extracted.into_iter().flat_map(|header| self.occurrences(header)).collect()
+			})
+			.collect();
+		let precedes = precedes(graph, &reachable, &extracted);
+
+		let mut effects = Effects::new(table, self.info);
+		if let Some(apply) = &deparser.apply {
+			for statement in &apply.statements {
+				effects.visit_statement(statement);
+			}
+		}
+		let emitted: Vec<_> = effects.emitted.into_iter().flat_map(|header| self.occurrences(header)).collect();
+
+		let (parser_name, deparser_name) = (&parser.name.name, &deparser.name.name);
+		let mut reported = HashSet::new();
+		for occurrence in extracted.iter().zip(&reachable).filter(|(_, reachable)| **reachable).flat_map(|(e, _)| e) {
+			let header = &occurrence.header;
+			if emitted.iter().any(|emitted| emitted.header == *header) || !reported.insert(header.clone()) {
+				continue;
+			}
+			let message = format!(
+				"the header `{}` is extracted by the parser `{parser_name}`, but never emitted by the deparser \
+				 `{deparser_name}`",
+				occurrence.text
+			);
+			self.diagnostics.push(lints::UNEMITTED_HEADER.diagnostic(occurrence.location, message));
+		}
+
+		for (i, occurrence) in emitted.iter().enumerate() {
+			let header = &occurrence.header;
+			if !extracted.iter().flatten().any(|extracted| extracted.header == *header)
+				&& !self.validated.contains(header)
+			{
+				let message = format!(
+					"the header `{}` is emitted by the deparser `{deparser_name}`, but never extracted by the parser \
+					 `{parser_name}` or set valid",
+					occurrence.text
+				);
+				self.diagnostics.push(lints::NEVER_VALID_HEADER.diagnostic(occurrence.location, message));
+			}
+
+			let earlier = emitted[..i].iter().find(|earlier| {
+				precedes.contains(&(header, &earlier.header)) && !precedes.contains(&(&earlier.header, header))
+			});
+			if let Some(earlier) = earlier {
+				let message = format!(
+					"the header `{}` is emitted after `{}`, but the parser `{parser_name}` extracts it before",
+					occurrence.text, earlier.text
+				);
+				let diagnostic = lints::EMIT_ORDER
+					.diagnostic(occurrence.location, message)
+					.with_related(earlier.location.clone(), format!("`{}` is emitted here", earlier.text));
+				self.diagnostics.push(diagnostic);
+			}
+		}
+	}
+
+	/// Returns the headers extracted or emitted by passing `expression` to `extract` or `emit`, which are the fields of
+	/// a struct, in order.
+	fn occurrences(&self, expression: &'a Expression) -> Vec<Occurrence<'a>> {
+		let Some((header, text)) = self.header(expression) else { return vec![] };
+		let mut occurrences = vec![];
+		if let Some(ty) = self.info.expression_type(&expression.location) {
+			self.expand(ty, header, text, &expression.location, &mut occurrences);
+		}
+		occurrences
+	}
+
+	fn expand(&self, ty: &Type, header: Header, text: String, location: &'a Location, out: &mut Vec<Occurrence<'a>>) {
+		match ty {
+			Type::Header(..) | Type::HeaderUnion(..) | Type::Stack(..) => {
+				out.push(Occurrence { header, text, location })
+			}
+			Type::Struct(id, _) => {
+				let table = self.table;
+				let Some(body) = table.symbol(*id).body else { return };
+				for &field in &table.scope(body).symbols {
+					let symbol = table.symbol(field);
+					let (SymbolKind::Field, Some(ty)) = (&symbol.kind, self.info.symbol_type(field)) else { continue };
+					let header = (header.0.clone(), format!("{}.{}", header.1, symbol.name));
+					self.expand(ty, header, format!("{text}.{}", symbol.name), location, out);
+				}
+			}
+			_ => (),
+		}
+	}
+
+	/// Returns the header a header, or a field of it, belongs to, and the text of its path. The elements of stacks and
+	/// the members of unions belong to the stack or the union.
+	fn header(&self, expression: &Expression) -> Option<(Header, String)> {
+		let base_type = |base: &Expression| self.info.expression_type(&base.location);
+		match &expression.kind {
+			ExpressionKind::Name(name) => {
+				let ty = self.info.expression_type(&expression.location)?.clone();
+				Some(((ty, String::new()), name.name.clone()))
+			}
+			ExpressionKind::Member(base, _) | ExpressionKind::Index(base, _)
+				if matches!(base_type(base), Some(Type::Stack(..) | Type::HeaderUnion(..))) =>
+			{
+				self.header(base)
+			}
+			ExpressionKind::Member(base, member) => {
+				let ((root, path), text) = self.header(base)?;
+				Some(((root, format!("{path}.{}", member.name)), format!("{text}.{}", member.name)))
+			}
+			_ => None,
+		}
+	}
+}
+
+/// Returns the pairs of headers `(a, b)` such that `a` is extracted before `b` along some path of the state graph.
+fn precedes<'o>(
+	graph: &StateGraph,
+	reachable: &[bool],
+	extracted: &'o [Vec<Occurrence>],
+) -> HashSet<(&'o Header, &'o Header)> {
+	// the headers extracted along some path from `start` to each state
+	let mut before: Vec<HashSet<&Header>> = vec![HashSet::new(); graph.states.len()];
+	let mut changed = true;
+	while changed {
+		changed = false;
+		for (from, to) in &graph.transitions {
+			let headers: Vec<_> = before[*from]
+				.iter()
+				.copied()
+				.chain(extracted.get(*from).into_iter().flatten().map(|occurrence| &occurrence.header))
+				.collect();
+			for header in headers {
+				changed |= before[*to].insert(header);
+			}
+		}
+	}
+
+	let mut pairs = HashSet::new();
+	for (state, occurrences) in extracted.iter().enumerate().filter(|(state, _)| reachable[*state]) {
+		let mut seen = before[state].clone();
+		for occurrence in occurrences {
+			pairs.extend(seen.iter().filter(|header| ***header != occurrence.header).map(|h| (*h, &occurrence.header)));
+			seen.insert(&occurrence.header);
+		}
+	}
+	pairs
+}
@@ -0,0 +1,226 @@
+//! Folding ranges.
+//!
+//! The blocks of a file are folded from its syntax tree: declarations with a body, parser states and their `select`,
+//! `apply` blocks, table properties, and block and `switch` statements. While the file does not parse, they are
+//! folded from the pairs of braces of its lexemes instead. Comments, runs of `#include`s and the branches of
+//! conditional directives, which the syntax tree leaves out, are always folded from the lexemes.
+
+use crate::{
+	ast::{visit::*, *},
+	base_abstractions::*,
+	lex,
+	lexer::Token,
+	parse_file, parses,
+	preprocessor::PreprocessorDirective,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FoldingKind {
+	Block,
+	Comment,
+	/// A run of `#include`s.
+	Includes,
+	/// A branch of an `#if`, up to the next `#elif`, `#else` or `#endif`.
+	Region,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FoldingRange {
+	/// The span of the folded code, which stops before the closing brace of a block or the directive ending a region,
+	/// so that they stay visible.
+	pub span: Span,
+	pub kind: FoldingKind,
+}
+
+/// Returns the folding ranges of a single file, ordered by their start.
+pub fn folding_ranges(db: &dyn crate::Db, file_id: FileId, buf: Buffer) -> Vec<FoldingRange> {
+	let lexemes: Vec<_> = lex(db, file_id, buf).lexemes(db).iter().filter(|(tk, _)| *tk != Token::Whitespace).collect();
+	let text = buf.file(db).get_file_content();
+	let mut folder = Folder { text, lexemes, ranges: vec![] };
+
+	if parses(db, file_id, buf) {
+		walk_program(&mut folder, parse_file(db, file_id, buf));
+	} else {
+		folder.braces();
+	}
+	// a declaration and its body end with the same brace, and are folded from the start of the declaration
+	folder.ranges.sort_by_key(|range| (range.span.end, range.span.start));
+	folder.ranges.dedup_by_key(|range| range.span.end);
+
+	folder.comments();
+	folder.includes();
+	folder.regions();
+	folder.ranges.sort_by_key(|range| (range.span.start, range.span.end));
+	folder.ranges
+}
+
+struct Folder<'a> {
+	text: &'a str,
+	/// The lexemes of the file, but for whitespace.
+	lexemes: Vec<&'a (Token, Span)>,
+	ranges: Vec<FoldingRange>,
+}
+
+impl Folder<'_> {
+	fn fold(&mut self, span: Span, kind: FoldingKind) {
+		if self.text[span.clone()].trim_end().contains('\n') {
+			self.ranges.push(FoldingRange { span, kind });
+		}
+	}
+
+	/// Folds the node spanning `span` up to its closing brace, if it ends with one.
+	fn block(&mut self, span: &Span) {
+		let Ok(close) = self.lexemes.binary_search_by_key(&span.end, |(_, span)| span.end) else { return };
+		if self.lexemes[close].0 == Token::CloseBrace && close > 0 {
+			self.fold(span.start..self.end(close - 1), FoldingKind::Block);
+		}
+	}
+
+	/// Returns the end of the lexeme `i`, but for the line break ending a line comment or a directive.
+	fn end(&self, i: usize) -> usize {
+		let span = &self.lexemes[i].1;
+		span.start + self.text[span.clone()].trim_end_matches('\n').len()
+	}
+
+	/// Folds the pairs of braces.
+	fn braces(&mut self) {
+		let mut open = vec![];
+		for i in 0..self.lexemes.len() {
+			match self.lexemes[i].0 {
+				Token::OpenBrace => open.push(i),
+				Token::CloseBrace => {
+					if let Some(start) = open.pop() {
+						let span = self.lexemes[start].1.start..self.end(i - 1);
+						self.fold(span, FoldingKind::Block);
+					}
+				}
+				_ => (),
+			}
+		}
+	}
+
+	/// Folds the comments spanning several lines, and the runs of line comments.
+	fn comments(&mut self) {
+		let mut run: Option<(usize, usize)> = None;
+		for i in 0..=self.lexemes.len() {
+			let line_comment = self.lexemes.get(i).map_or(false, |(token, span)| {
+				*token == Token::Comment && self.text[span.clone()].starts_with("//") && self.starts_line(i)
+			});
+			match (&mut run, line_comment) {
+				(Some((_, last)), true) if self.adjacent(*last, i) => *last = i,
+				(_, true) => {
+					self.fold_run(run.take(), FoldingKind::Comment);
+					run = Some((i, i));
+				}
+				(_, false) => {
+					self.fold_run(run.take(), FoldingKind::Comment);
+					if let Some((Token::Comment, span)) = self.lexemes.get(i) {
+						self.fold(span.clone(), FoldingKind::Comment);
+					}
+				}
+			}
+		}
+	}
+
+	/// Folds the runs of `#include`s, which may be separated by blank lines.
+	fn includes(&mut self) {
+		let mut run: Option<(usize, usize)> = None;
+		for i in 0..=self.lexemes.len() {
+			let include = matches!(
+				self.lexemes.get(i),
+				Some((Token::PreprocessorDirective(PreprocessorDirective::Include(..)), _))
+			);
+			match (&mut run, include) {
+				(Some((_, last)), true) if *last + 1 == i => *last = i,
+				(_, true) => {
+					self.fold_run(run.take(), FoldingKind::Includes);
+					run = Some((i, i));
+				}
+				(_, false) => self.fold_run(run.take(), FoldingKind::Includes),
+			}
+		}
+	}
+
+	fn fold_run(&mut self, run: Option<(usize, usize)>, kind: FoldingKind) {
+		if let Some((first, last)) = run.filter(|(first, last)| first < last) {
+			self.fold(self.lexemes[first].1.start..self.end(last), kind);
+		}
+	}
+
+	/// Folds the branches of the conditional directives, each up to the directive ending it.
+	fn regions(&mut self) {
+		let mut open = vec![];
+		for i in 0..self.lexemes.len() {
+			let Token::PreprocessorDirective(directive) = &self.lexemes[i].0 else { continue };
+			let start = self.lexemes[i].1.start;
+			match directive {
+				PreprocessorDirective::If(_) => open.push(start),
+				PreprocessorDirective::ElseIf(_) | PreprocessorDirective::Else | PreprocessorDirective::EndIf => {
+					let Some(branch) = open.pop() else { continue };
+					self.fold(branch..self.end(i - 1), FoldingKind::Region);
+					if *directive != PreprocessorDirective::EndIf {
+						open.push(start);
+					}
+				}
+				_ => (),
+			}
+		}
+	}
+
+	/// Returns `true` if the lexeme `i` is the first of its line.
+	fn starts_line(&self, i: usize) -> bool {
+		if i == 0 {
+			return true;
+		}
+		let start = self.lexemes[i].1.start;
+		let previous = &self.lexemes[i - 1].1;
+		self.text[previous.clone()].ends_with('\n') || self.text[previous.end..start].contains('\n')
+	}
+
+	/// Returns `true` if the line comment `i` is on the line following the one `previous` ends.
+	fn adjacent(&self, previous: usize, i: usize) -> bool {
+		previous + 1 == i && !self.text[self.lexemes[previous].1.end..self.lexemes[i].1.start].contains('\n')
+	}
+}
+
+impl<'a> Visitor<'a> for Folder<'_> {
+	fn visit_declaration(&mut self, declaration: &'a Declaration) {
+		self.block(&declaration.location().span);
+		match declaration {
+			Declaration::Parser(parser) => {
+				for state in &parser.states {
+					self.block(&state.location.span);
+					if let Some(transition) = &state.transition {
+						self.block(&transition.location.span);
+					}
+				}
+			}
+			Declaration::Control(control) => {
+				if let Some(apply) = &control.apply {
+					self.block(&apply.location.span);
+				}
+			}
+			Declaration::Table(table) => {
+				for property in &table.properties {
+					self.block(&property.location().span);
+				}
+			}
+			_ => (),
+		}
+		walk_declaration(self, declaration);
+	}
+
+	fn visit_statement(&mut self, statement: &'a Statement) {
+		match &statement.kind {
+			StatementKind::Block(_) => self.block(&statement.location.span),
+			StatementKind::Switch(_, cases) => {
+				self.block(&statement.location.span);
+				for body in cases.iter().filter_map(|case| case.body.as_ref()) {
+					self.block(&body.location.span);
+				}
+			}
+			_ => (),
+		}
+		walk_statement(self, statement);
+	}
+}
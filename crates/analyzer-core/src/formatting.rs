@@ -0,0 +1,507 @@
+//! Formatting.
+//!
+//! The formatter works on the lexemes of a file rather than on its syntax tree, so that the code after a syntax error
+//! is formatted like the rest, and it only changes the whitespace between lexemes: comments, directives and literals
+//! are kept as written. The layout of each gap is decided from the lexemes around it. The original gap is only
+//! looked at to keep comments and annotations on their own lines, and to keep a blank line where there was at least
+//! one, which makes formatting idempotent.
+//!
+//! Lines longer than the maximum width are broken after the opening parenthesis of their first parenthesized list,
+//! with one element per line, and so on for the lines of the elements.
+
+use std::collections::HashSet;
+
+use crate::{base_abstractions::*, lexer::Token};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BraceStyle {
+	/// The opening brace of a block ends the line of its declaration or statement.
+	SameLine,
+	/// The opening brace of a block is on a line of its own.
+	NextLine,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FormatOptions {
+	/// The width of an indentation level, and of a tab.
+	pub tab_size: usize,
+	/// Whether to indent with spaces rather than tabs.
+	pub insert_spaces: bool,
+	pub brace_style: BraceStyle,
+	/// The width beyond which lines are broken, where possible.
+	pub max_width: usize,
+}
+
+impl Default for FormatOptions {
+	fn default() -> Self {
+		Self { tab_size: 4, insert_spaces: true, brace_style: BraceStyle::SameLine, max_width: 100 }
+	}
+}
+
+/// A replacement of the whitespace between two lexemes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TextEdit {
+	pub span: Span,
+	pub text: String,
+}
+
+/// Returns the edits formatting a whole file, whose source is `text`.
+pub fn format(text: &str, lexemes: &[(Token, Span)], options: &FormatOptions) -> Vec<TextEdit> {
+	Formatter::new(text, lexemes, options).edits(|_| true)
+}
+
+/// Returns the edits formatting the lexemes starting within `range`, along with the whitespace before them.
+pub fn format_range(text: &str, lexemes: &[(Token, Span)], options: &FormatOptions, range: Span) -> Vec<TextEdit> {
+	Formatter::new(text, lexemes, options).edits(|gap| range.start <= gap.end && gap.end <= range.end)
+}
+
+/// Returns the edits formatting the code completed by typing `typed` before `offset`: the block closed by a `}`, the
+/// line ended by a `;`, or the line ended by a new line.
+pub fn format_on_type(
+	text: &str,
+	lexemes: &[(Token, Span)],
+	options: &FormatOptions,
+	offset: usize,
+	typed: char,
+) -> Vec<TextEdit> {
+	let formatter = Formatter::new(text, lexemes, options);
+	let end = match typed {
+		'\n' => offset.saturating_sub(1),
+		_ => offset,
+	};
+	let mut start = end;
+	if typed == '}' {
+		// the block starts on the line of its opening brace
+		let items = &formatter.items;
+		let Some(close) = items.iter().position(|(token, span)| **token == Token::CloseBrace && span.end == offset)
+		else {
+			return vec![];
+		};
+		let mut depth = 0;
+		for (token, span) in items[..=close].iter().rev() {
+			match token {
+				Token::CloseBrace => depth += 1,
+				Token::OpenBrace => depth -= 1,
+				_ => (),
+			}
+			if depth == 0 {
+				start = span.start;
+				break;
+			}
+		}
+	}
+	let start = text[..start].rfind('\n').map_or(0, |i| i + 1);
+	formatter.edits(|gap| start <= gap.end && gap.end < end)
+}
+
+/// The whitespace between two lexemes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Gap {
+	None,
+	Space,
+	/// A line break, followed by a blank line if `blank`, and by the indentation of `depth` levels.
+	Newline {
+		blank: bool,
+		depth: usize,
+	},
+}
+
+/// A delimiter left open before a lexeme.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Frame {
+	/// The braces of a declaration or statement, whose contents are indented on their own lines.
+	Block,
+	/// The braces of a list expression, kept on the line.
+	Inline,
+	Paren,
+	Bracket,
+}
+
+struct Formatter<'a> {
+	text: &'a str,
+	options: &'a FormatOptions,
+	/// The lexemes, but for whitespace.
+	items: Vec<(&'a Token, Span)>,
+	/// The gap before each lexeme, and the one after the last.
+	gaps: Vec<Gap>,
+	/// The chevrons delimiting type arguments or parameters, rather than comparing.
+	generic: HashSet<usize>,
+	/// The last lexemes of annotations.
+	annotation_ends: HashSet<usize>,
+}
+
+impl<'a> Formatter<'a> {
+	fn new(text: &'a str, lexemes: &'a [(Token, Span)], options: &'a FormatOptions) -> Self {
+		let items: Vec<_> = lexemes
+			.iter()
+			.filter(|(token, _)| *token != Token::Whitespace)
+			.map(|(t, s)| (t, s.clone()))
+			.collect();
+		let mut formatter =
+			Formatter { text, options, items, gaps: vec![], generic: HashSet::new(), annotation_ends: HashSet::new() };
+		formatter.find_generics();
+		formatter.find_annotations();
+		formatter.lay_out();
+		let mut line = 0;
+		while line < formatter.items.len() {
+			line = formatter.wrap(line);
+		}
+		formatter
+	}
+
+	fn token(&self, i: usize) -> &'a Token { self.items[i].0 }
+
+	fn source(&self, i: usize) -> &'a str { &self.text[self.items[i].1.clone()] }
+
+	/// Returns the original whitespace before the lexeme `i`, or after the last lexeme.
+	fn original(&self, i: usize) -> Span {
+		let start = if i == 0 { 0 } else { self.items[i - 1].1.end };
+		let end = self.items.get(i).map_or(self.text.len(), |(_, span)| span.start);
+		start..end
+	}
+
+	/// Returns the edits of the gaps whose original span is accepted by `filter`.
+	fn edits(&self, filter: impl Fn(&Span) -> bool) -> Vec<TextEdit> {
+		// a file without code is left as it is
+		if self.items.is_empty() {
+			return vec![];
+		}
+		let mut edits = vec![];
+		for (i, gap) in self.gaps.iter().enumerate() {
+			let span = self.original(i);
+			let text = self.render(i, *gap);
+			if filter(&span) && self.text[span.clone()] != text {
+				edits.push(TextEdit { span, text });
+			}
+		}
+		edits
+	}
+
+	fn render(&self, i: usize, gap: Gap) -> String {
+		// line comments and directives end with their line
+		let after_line = i > 0 && self.source(i - 1).ends_with('\n');
+		match gap {
+			Gap::None | Gap::Space if after_line => String::new(),
+			Gap::None => String::new(),
+			Gap::Space => " ".to_string(),
+			Gap::Newline { blank, depth } => {
+				let mut text = "\n".repeat(usize::from(!after_line) + usize::from(blank));
+				if i < self.items.len() {
+					text.push_str(&self.indentation(depth));
+				}
+				text
+			}
+		}
+	}
+
+	fn indentation(&self, depth: usize) -> String {
+		match self.options.insert_spaces {
+			true => " ".repeat(depth * self.options.tab_size),
+			false => "\t".repeat(depth),
+		}
+	}
+
+	/// Finds the chevrons of type arguments and parameters: those after a name enclosing only names, integers, commas,
+	/// dots, brackets and other such chevrons.
+	fn find_generics(&mut self) {
+		for open in 1..self.items.len() {
+			if *self.token(open) != Token::OpenChevron || !matches!(self.token(open - 1), Token::Identifier(_)) {
+				continue;
+			}
+			let mut depth = 0;
+			for i in open..self.items.len() {
+				match self.token(i) {
+					Token::OpenChevron => depth += 1,
+					Token::CloseChevron => {
+						depth -= 1;
+						if depth == 0 {
+							self.generic.extend([open, i]);
+							break;
+						}
+					}
+					Token::Identifier(_)
+					| Token::Integer(_)
+					| Token::Comma
+					| Token::Dot
+					| Token::OpenBracket
+					| Token::CloseBracket
+					| Token::Comment => (),
+					_ => break,
+				}
+			}
+		}
+	}
+
+	/// Finds the ends of the annotations: the name following an `@`, or the parenthesis closing its arguments.
+	fn find_annotations(&mut self) {
+		for at in 0..self.items.len().saturating_sub(1) {
+			if *self.token(at) != Token::At {
+				continue;
+			}
+			let mut end = at + 1;
+			if self.items.get(end + 1).map_or(false, |(token, _)| **token == Token::OpenParen) {
+				let mut depth = 0;
+				for i in end + 1..self.items.len() {
+					match self.token(i) {
+						Token::OpenParen => depth += 1,
+						Token::CloseParen => depth -= 1,
+						_ => (),
+					}
+					end = i;
+					if depth == 0 {
+						break;
+					}
+				}
+			}
+			self.annotation_ends.insert(end);
+		}
+	}
+
+	/// Decides the gap before each lexeme, from the lexemes around it and the delimiters left open before it.
+	fn lay_out(&mut self) {
+		let mut frames: Vec<Frame> = vec![];
+		// the number of `?` of the conditional expressions whose `:` is yet to come
+		let mut conditions = 0;
+		// the number of type argument lists left open
+		let mut arguments = 0;
+		let mut closed_block = false;
+		self.gaps.push(Gap::None);
+
+		// the last lexeme but for comments, which are left out of the layout of the code around them
+		let mut last = 0;
+
+		for i in 1..self.items.len() {
+			let (prev, cur) = (self.token(last), self.token(i));
+			// the closing delimiters are indented as the code around their block
+			let closed = match cur {
+				Token::CloseBrace => pop(&mut frames, |f| matches!(f, Frame::Block | Frame::Inline)),
+				Token::CloseParen => pop(&mut frames, |f| f == Frame::Paren),
+				Token::CloseBracket => pop(&mut frames, |f| f == Frame::Bracket),
+				_ => None,
+			};
+			let blocks = frames.iter().filter(|f| **f == Frame::Block).count();
+			let continued = frames.last().map_or(false, |f| *f != Frame::Block);
+			let depth = blocks + usize::from(continued);
+
+			let original = &self.text[self.original(i)];
+			let newlines = original.matches('\n').count() + usize::from(self.source(i - 1).ends_with('\n'));
+			let blank = newlines > 1 && *self.token(i - 1) != Token::OpenBrace && *cur != Token::CloseBrace;
+			let newline = Gap::Newline { blank, depth };
+			let keep_newline = if newlines > 0 { newline } else { Gap::Space };
+			let after_comment = *self.token(i - 1) == Token::Comment;
+			let opens_block = *cur == Token::OpenBrace && self.opens_block(last, &frames);
+			let ternary = *cur == Token::Colon && conditions > 0;
+
+			let gap = match (prev, cur) {
+				(_, Token::PreprocessorDirective(_)) => Gap::Newline { blank, depth: 0 },
+				_ if self.source(i - 1).ends_with('\n') => newline,
+				(Token::Pragma(_), _) | (_, Token::Pragma(_)) => newline,
+				(_, Token::Comment) => keep_newline,
+				_ if after_comment && newlines > 0 => newline,
+				_ if self.annotation_ends.contains(&last) => keep_newline,
+				(Token::OpenBrace, Token::CloseBrace) => Gap::None,
+				(_, Token::CloseBrace) if closed == Some(Frame::Block) => newline,
+				(Token::OpenBrace, _) if frames.last() == Some(&Frame::Block) => newline,
+				(Token::CloseBrace, Token::KwElse) if closed_block => match self.options.brace_style {
+					BraceStyle::SameLine => Gap::Space,
+					BraceStyle::NextLine => newline,
+				},
+				(Token::CloseBrace, Token::Semicolon | Token::Comma) if closed_block => Gap::None,
+				(Token::CloseBrace, _) if closed_block => newline,
+				(Token::Semicolon, _) => newline,
+				(Token::Comma, _) if frames.last() == Some(&Frame::Block) && arguments == 0 => newline,
+				(_, Token::OpenBrace) if opens_block => match self.options.brace_style {
+					BraceStyle::SameLine => Gap::Space,
+					BraceStyle::NextLine => Gap::Newline { blank: false, depth },
+				},
+				_ if after_comment || self.spaced(last, i, ternary) => Gap::Space,
+				_ => Gap::None,
+			};
+			self.gaps.push(gap);
+
+			if *cur == Token::Comment {
+				continue;
+			}
+			last = i;
+			closed_block = closed == Some(Frame::Block);
+			match cur {
+				Token::OpenBrace if opens_block => {
+					// the parentheses left open before a block are errors
+					while frames.last().map_or(false, |f| *f != Frame::Block) {
+						frames.pop();
+					}
+					frames.push(Frame::Block);
+				}
+				Token::OpenBrace => frames.push(Frame::Inline),
+				Token::OpenParen => frames.push(Frame::Paren),
+				Token::OpenBracket => frames.push(Frame::Bracket),
+				Token::QuestionMark => conditions += 1,
+				Token::Colon if ternary => conditions -= 1,
+				Token::OpenChevron if self.generic.contains(&i) => arguments += 1,
+				Token::CloseChevron if self.generic.contains(&i) => arguments -= 1,
+				// and so are those left open before the end of a statement
+				Token::Semicolon => {
+					while frames.last().map_or(false, |f| *f != Frame::Block) {
+						frames.pop();
+					}
+					conditions = 0;
+					arguments = 0;
+				}
+				_ => (),
+			}
+		}
+		self.gaps.push(Gap::Newline { blank: false, depth: 0 });
+	}
+
+	/// Returns `true` if a brace following the lexeme `prev` opens a block rather than a list expression.
+	fn opens_block(&self, prev: usize, frames: &[Frame]) -> bool {
+		if matches!(frames.last(), Some(Frame::Paren | Frame::Bracket | Frame::Inline)) {
+			return false;
+		}
+		match self.token(prev) {
+			// the properties of tables are blocks
+			Token::Equals => match prev.checked_sub(1).map(|i| self.token(i)) {
+				Some(Token::Identifier(name)) => ["key", "actions", "entries"].contains(&name.as_str()),
+				_ => false,
+			},
+			Token::Comma | Token::OpenParen | Token::OpenBracket | Token::OpenBrace | Token::KwReturn => false,
+			_ => true,
+		}
+	}
+
+	/// Returns `true` if the lexeme `i` is separated from the lexeme `prev` by a space, on a line.
+	fn spaced(&self, prev: usize, i: usize, ternary: bool) -> bool {
+		let (prev_index, prev, cur) = (prev, self.token(prev), self.token(i));
+		let attached_before = match cur {
+			Token::Comma | Token::Semicolon | Token::CloseParen | Token::CloseBracket | Token::OpenBracket => true,
+			Token::Dot | Token::DoubleDot => true,
+			Token::Colon => !ternary,
+			Token::OpenChevron => self.generic.contains(&i),
+			Token::CloseChevron => self.generic.contains(&i) || self.shift(i),
+			Token::OpenParen => {
+				matches!(prev, Token::Identifier(_) | Token::KwSelect | Token::KwApply | Token::CloseParen)
+					|| matches!(prev, Token::CloseBracket)
+					|| self.generic.contains(&prev_index)
+			}
+			Token::CloseBrace => *prev == Token::OpenBrace,
+			_ => false,
+		};
+		let attached_after = match prev {
+			Token::OpenParen | Token::OpenBracket | Token::Dot | Token::DoubleDot | Token::At => true,
+			Token::ExclamationMark | Token::Tilde => true,
+			Token::OpenChevron => self.generic.contains(&prev_index),
+			Token::Minus | Token::Plus => self.unary(prev_index),
+			Token::OpenBrace => *cur == Token::CloseBrace,
+			_ => false,
+		};
+		!attached_before && !attached_after
+	}
+
+	/// Returns `true` if the chevron `i` is the second of a right shift.
+	fn shift(&self, i: usize) -> bool {
+		*self.token(i - 1) == Token::CloseChevron
+			&& !self.generic.contains(&(i - 1))
+			&& !self.generic.contains(&i)
+			&& self.items[i - 1].1.end == self.items[i].1.start
+	}
+
+	/// Returns `true` if the operator `i` is unary, not following an operand.
+	fn unary(&self, i: usize) -> bool {
+		let Some(prev) = i.checked_sub(1) else { return true };
+		let operand = match self.token(prev) {
+			Token::Identifier(_) | Token::Integer(_) | Token::StringLiteral(_) => true,
+			Token::KwTrue | Token::KwFalse | Token::CloseParen | Token::CloseBracket => true,
+			// keywords may also name members
+			_ => prev > 0 && *self.token(prev - 1) == Token::Dot,
+		};
+		!operand
+	}
+
+	/// Breaks the line starting at the lexeme `start` if it is too long, returning the start of the next line.
+	fn wrap(&mut self, start: usize) -> usize {
+		let end = (start + 1..self.items.len()).find(|i| matches!(self.gaps[*i], Gap::Newline { .. }));
+		let end = end.unwrap_or(self.items.len());
+		let depth = match self.gaps[start] {
+			Gap::Newline { depth, .. } => depth,
+			_ => 0,
+		};
+		if self.width(start, end, depth) <= self.options.max_width {
+			return end;
+		}
+
+		// the first list in parentheses closed on the line, with at least one element
+		let mut open = None;
+		let mut nesting = 0;
+		for i in start..end {
+			match self.token(i) {
+				Token::OpenParen | Token::OpenBracket | Token::OpenBrace => {
+					// the line may end the file with an opening parenthesis
+					let elements = matches!(self.items.get(i + 1), Some((token, _)) if **token != Token::CloseParen);
+					if nesting == 0 && *self.token(i) == Token::OpenParen && elements {
+						open = Some(i);
+					}
+					nesting += 1;
+				}
+				Token::CloseParen | Token::CloseBracket | Token::CloseBrace => {
+					nesting -= 1;
+					if nesting == 0 {
+						if let (Some(open), Token::CloseParen) = (open, self.token(i)) {
+							self.break_list(open, i, depth);
+							return start;
+						}
+						open = None;
+					}
+				}
+				_ => (),
+			}
+		}
+		end
+	}
+
+	/// Puts the elements of the list between the parentheses `open` and `close` on lines of their own.
+	fn break_list(&mut self, open: usize, close: usize, depth: usize) {
+		let inner = Gap::Newline { blank: false, depth: depth + 1 };
+		self.gaps[open + 1] = inner;
+		let mut nesting = 0;
+		for i in open + 1..close {
+			match self.token(i) {
+				Token::OpenParen | Token::OpenBracket | Token::OpenBrace => nesting += 1,
+				Token::CloseParen | Token::CloseBracket | Token::CloseBrace => nesting -= 1,
+				Token::OpenChevron if self.generic.contains(&i) => nesting += 1,
+				Token::CloseChevron if self.generic.contains(&i) => nesting -= 1,
+				Token::Comma if nesting == 0 && !matches!(self.gaps[i + 1], Gap::Newline { .. }) => {
+					self.gaps[i + 1] = inner
+				}
+				_ => (),
+			}
+		}
+		self.gaps[close] = Gap::Newline { blank: false, depth };
+	}
+
+	/// Returns the width of the line of the lexemes from `start` to `end`, but for a trailing comment, or 0 for a line
+	/// which cannot be broken as it holds several lines.
+	fn width(&self, start: usize, end: usize, depth: usize) -> usize {
+		let end = if *self.token(end - 1) == Token::Comment { end - 1 } else { end };
+		let mut width = depth * self.options.tab_size;
+		for i in start..end {
+			let source = self.source(i).trim_end_matches('\n');
+			if source.contains('\n') {
+				return 0;
+			}
+			width += source.chars().count() + usize::from(i > start && self.gaps[i] == Gap::Space);
+		}
+		width
+	}
+}
+
+/// Pops the frames up to the innermost one matching `closes`, returning it, or returns `None` without popping
+/// anything if no frame up to the innermost block matches.
+fn pop(frames: &mut Vec<Frame>, closes: impl Fn(Frame) -> bool) -> Option<Frame> {
+	let position = frames.iter().rposition(|f| closes(*f) || *f == Frame::Block)?;
+	let frame = frames[position];
+	if !closes(frame) {
+		return None;
+	}
+	frames.truncate(position);
+	Some(frame)
+}
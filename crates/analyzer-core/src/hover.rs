@@ -0,0 +1,358 @@
+//! Hover information.
+//!
+//! Hovering over a name shows the declaration it resolves to: its signature as written in the source, its type, the
+//! layout of a header or struct, its control-plane name and the comments documenting it. Hovering over the use of a
+//! macro shows its expansion, and hovering over an integer literal shows its value in several bases.
+
+use std::collections::HashSet;
+
+use logos::Logos;
+use num_traits::Signed;
+
+use crate::{
+	annotations::{self, Annotations},
+	ast::annotation,
+	base_abstractions::*,
+	const_eval::{self, Constants, Value},
+	file_text,
+	lexer::{Lextras, Literal, Token},
+	symbols::{self, Symbol, SymbolId, SymbolKind, SymbolTable},
+	token_at,
+	types::{self, Type, TypeInfo},
+	Fs,
+};
+
+/// The information shown when hovering over a token.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Hover {
+	/// The information, as Markdown.
+	pub contents: String,
+	/// The span of the hovered token.
+	pub span: Span,
+}
+
+/// Returns the information shown when hovering over `offset` in the translation unit rooted at `file_id`.
+pub fn hover(db: &dyn crate::Db, fs: Fs, file_id: FileId, offset: usize) -> Option<Hover> {
+	let (token, span) = token_at(db, fs, file_id, offset)?;
+
+	let contents = match token {
+		Token::Integer(literal) => integer(&file_text(db, fs, file_id)?[span.clone()], literal),
+		// keywords such as `type` may also name members
+		_ => {
+			let describer = Describer::new(db, fs, file_id)?;
+			let (table, info) = (describer.table, describer.info);
+			// macros are expanded before names are resolved, so their uses are only known by name
+			let macro_id = match token {
+				Token::Identifier(name) => table.members(table.macros, name).first(),
+				_ => None,
+			};
+			match macro_id {
+				Some(&id) => describer.macro_use(id),
+				None => {
+					let target = table.symbol_at(file_id, span.start);
+					let id = target.or_else(|| info.member_at(file_id, span.start)?.target)?;
+					describer.symbol(id)
+				}
+			}
+		}
+	};
+	Some(Hover { contents, span: span.clone() })
+}
+
+/// Describes an integer literal, whose source text is `text`.
+fn integer(text: &str, literal: &Literal) -> String {
+	let ty = match (literal.width, literal.signed) {
+		(Some(width), false) => format!("`bit<{width}>`, {width} bits"),
+		(Some(width), true) => format!("`int<{width}>`, {width} bits"),
+		(None, _) => "`int`, of arbitrary precision".to_string(),
+	};
+	let value = &literal.value;
+	format!("{}\n\nType: {ty}\n\nValue: `{value}` = `{value:#x}` = `{value:#b}`", code_block(text))
+}
+
+fn code_block(code: &str) -> String { format!("```p4\n{code}\n```") }
+
+/// Describes the symbols of a translation unit.
+pub(crate) struct Describer<'a> {
+	db: &'a dyn crate::Db,
+	fs: Fs,
+	pub(crate) table: &'a SymbolTable,
+	pub(crate) info: &'a TypeInfo,
+	constants: Option<&'a Constants>,
+	annotations: Option<&'a Annotations>,
+}
+
+impl<'a> Describer<'a> {
+	/// Returns a describer of the symbols of the translation unit rooted at `file_id`, if it can be analyzed.
+	pub(crate) fn new(db: &'a dyn crate::Db, fs: Fs, file_id: FileId) -> Option<Self> {
+		Some(Describer {
+			db,
+			fs,
+			table: symbols::symbols(db, fs, file_id).as_ref()?,
+			info: types::types(db, fs, file_id).as_ref()?,
+			constants: const_eval::constants(db, fs, file_id).as_ref(),
+			annotations: annotations::annotations(db, fs, file_id).as_ref(),
+		})
+	}
+}
+
+impl Describer<'_> {
+	fn symbol(&self, id: SymbolId) -> String {
+		let symbol = self.table.symbol(id);
+		let signature = match symbol.kind {
+			SymbolKind::Header | SymbolKind::HeaderUnion | SymbolKind::Struct => self.layout(id),
+			SymbolKind::EnumMember => match self.table.scope(symbol.scope).owner {
+				Some(owner) => format!("{}.{}", self.table.symbol(owner).name, symbol.name),
+				None => symbol.name.clone(),
+			},
+			SymbolKind::ErrorMember => format!("error.{}", symbol.name),
+			SymbolKind::MatchKind => format!("match_kind.{}", symbol.name),
+			_ => self.signature(symbol).unwrap_or_else(|| symbol.name.clone()),
+		};
+
+		let mut sections = vec![code_block(&signature)];
+		sections.extend(self.details(id));
+		self.finish(sections, symbol)
+	}
+
+	fn macro_use(&self, id: SymbolId) -> String {
+		let symbol = self.table.symbol(id);
+		let Some(definition) = self.table.macro_definition(id) else { return self.symbol(id) };
+
+		let mut sections = vec![code_block(&format!("#define {} {}", definition.name, definition.value))];
+		let file_id = definition.location.file;
+		let expansion = self.expand(file_id, &definition.value, &mut HashSet::from([definition.name.as_str()]));
+		if expansion.trim() != definition.value.trim() {
+			sections.push(format!("Expands to: `{}`", expansion.trim()));
+		}
+		if let Some(value) = const_eval::macro_value(self.table, id) {
+			sections.push(self.value(&value));
+		}
+		self.finish(sections, symbol)
+	}
+
+	/// Joins the sections describing a symbol, followed by its documentation.
+	fn finish(&self, sections: Vec<String>, symbol: &Symbol) -> String {
+		let mut contents = sections.join("\n\n");
+		if let Some(documentation) = self.documentation(symbol) {
+			contents.push_str("\n\n---\n\n");
+			contents.push_str(&documentation);
+		}
+		contents
+	}
+
+	/// Returns the source text of a declaration up to its body, on a single line and without comments.
+	pub(crate) fn signature(&self, symbol: &Symbol) -> Option<String> {
+		let text = &file_text(self.db, self.fs, symbol.declaration.file)?[symbol.declaration.span.clone()];
+		// the members of an enum are short enough to be shown with it
+		let keeps_body = matches!(symbol.kind, SymbolKind::Enum);
+
+		let mut signature = String::new();
+		let mut depth = 0usize;
+		for (token, span) in
+			Token::lexer_with_extras(text, Lextras { db: None, file_id: symbol.declaration.file }).spanned()
+		{
+			match token {
+				Token::Comment => continue,
+				Token::Whitespace => {
+					if !signature.is_empty() && !signature.ends_with(' ') {
+						signature.push(' ');
+					}
+					continue;
+				}
+				Token::OpenParen | Token::OpenBracket => depth += 1,
+				Token::CloseParen | Token::CloseBracket => depth = depth.saturating_sub(1),
+				Token::Semicolon if depth == 0 => break,
+				Token::OpenBrace if depth == 0 && !keeps_body => break,
+				_ => (),
+			}
+			signature.push_str(&text[span]);
+		}
+		Some(signature.trim_end().to_string())
+	}
+
+	/// Renders a header, header union or struct with its fields, each with its offset and size.
+	fn layout(&self, id: SymbolId) -> String {
+		let symbol = self.table.symbol(id);
+		let keyword = match symbol.kind {
+			SymbolKind::Header => "header",
+			SymbolKind::HeaderUnion => "header_union",
+			_ => "struct",
+		};
+
+		let mut layout = format!("{keyword} {} {{\n", symbol.name);
+		let mut offset = Some(0);
+		for (field, ty) in self.info.fields(self.table, id) {
+			let size = self.info.size_in_bits(self.table, ty);
+			let position = match (symbol.kind == SymbolKind::HeaderUnion, offset, size) {
+				(true, _, Some(size)) => format!(" // {size} bits"),
+				(false, Some(offset), Some(size)) => format!(" // offset {offset}, {size} bits"),
+				_ => String::new(),
+			};
+			layout.push_str(&format!("    {} {};{position}\n", ty.display(self.table), self.table.symbol(field).name));
+			offset = offset.zip(size).map(|(offset, size)| offset + size);
+		}
+		layout.push('}');
+		layout
+	}
+
+	/// Describes the type, value, size and control-plane name of a symbol, as applicable.
+	fn details(&self, id: SymbolId) -> Vec<String> {
+		let symbol = self.table.symbol(id);
+		let ty = self.info.symbol_type(id).filter(|ty| !ty.is_unknown());
+		let mut details = vec![];
+
+		match (&symbol.kind, ty) {
+			(
+				SymbolKind::Constant
+				| SymbolKind::Variable
+				| SymbolKind::Parameter(_)
+				| SymbolKind::Field
+				| SymbolKind::Instance,
+				Some(ty),
+			) => details.push(format!("Type: `{}`", ty.display(self.table))),
+			(SymbolKind::Typedef, Some(Type::Meta(ty))) => details.push(format!("Type: `{}`", ty.display(self.table))),
+			(SymbolKind::Type | SymbolKind::Enum, _) => {
+				if let Some(underlying) = self.info.underlying_type(id) {
+					details.push(format!("Underlying type: `{}`", underlying.display(self.table)));
+				}
+			}
+			_ => (),
+		}
+
+		if let Some(value) = self.constants.and_then(|constants| constants.symbol_value(id)) {
+			details.push(self.value(value));
+		}
+
+		match symbol.kind {
+			SymbolKind::Header | SymbolKind::HeaderUnion | SymbolKind::Struct => {
+				let ty = match symbol.kind {
+					SymbolKind::Header => Type::Header(id, vec![]),
+					SymbolKind::HeaderUnion => Type::HeaderUnion(id, vec![]),
+					_ => Type::Struct(id, vec![]),
+				};
+				if let Some(size) = self.info.size_in_bits(self.table, &ty) {
+					details.push(size_in_bits(size));
+				}
+			}
+			SymbolKind::Field => {
+				if let Some((owner, offset)) = self.offset(id) {
+					details.push(format!("Offset: {offset} bits into `{}`", self.table.symbol(owner).name));
+				}
+				if let Some(size) = ty.and_then(|ty| self.info.size_in_bits(self.table, ty)) {
+					details.push(size_in_bits(size));
+				}
+			}
+			SymbolKind::Action | SymbolKind::Table | SymbolKind::Instance => {
+				let annotations = self.annotations;
+				if let Some(name) = annotations.and_then(|a| a.control_plane_name(&symbol.location)) {
+					details.push(format!("Control-plane name: `{name}`"));
+				}
+			}
+			_ => (),
+		}
+		details
+	}
+
+	fn value(&self, value: &Value) -> String {
+		match value.integer() {
+			Some(integer) if !integer.is_negative() => {
+				format!("Value: `{}` = `{integer:#x}`", value.display(self.table))
+			}
+			_ => format!("Value: `{}`", value.display(self.table)),
+		}
+	}
+
+	/// Returns the header or struct declaring a field, with the offset of the field in bits, if its fields before
+	/// it have a known size.
+	fn offset(&self, field: SymbolId) -> Option<(SymbolId, u32)> {
+		let owner = self.table.scope(self.table.symbol(field).scope).owner?;
+		if !matches!(self.table.symbol(owner).kind, SymbolKind::Header | SymbolKind::Struct) {
+			return None;
+		}
+		let mut offset = 0;
+		for (id, ty) in self.info.fields(self.table, owner) {
+			if id == field {
+				return Some((owner, offset));
+			}
+			offset += self.info.size_in_bits(self.table, ty)?;
+		}
+		None
+	}
+
+	/// Expands the macros used in `text`, except those in `expanding`.
+	fn expand<'a>(&'a self, file_id: FileId, text: &str, expanding: &mut HashSet<&'a str>) -> String {
+		let mut expansion = String::new();
+		for (token, span) in Token::lexer_with_extras(text, Lextras { db: None, file_id }).spanned() {
+			let definition = match &token {
+				Token::Identifier(name) => self.table.members(self.table.macros, name).first(),
+				_ => None,
+			}
+			.and_then(|&id| self.table.macro_definition(id));
+			match definition {
+				Some(definition) if expanding.insert(&definition.name) => {
+					expansion.push_str(&self.expand(file_id, &definition.value, expanding));
+					expanding.remove(definition.name.as_str());
+				}
+				_ => expansion.push_str(&text[span]),
+			}
+		}
+		expansion
+	}
+
+	/// Returns the comments right before a declaration, along with its `@brief`, `@description` and `@deprecated`
+	/// annotations.
+	pub(crate) fn documentation(&self, symbol: &Symbol) -> Option<String> {
+		let mut paragraphs = vec![];
+		let annotations = self.annotations.map(|a| a.get(&symbol.location)).unwrap_or_default();
+		if let Some(reason) = annotation(annotations, "deprecated").and_then(|a| a.string()) {
+			paragraphs.push(format!("**Deprecated**: {reason}"));
+		}
+		let text = file_text(self.db, self.fs, symbol.declaration.file)?;
+		paragraphs.extend(comments(&text[..symbol.declaration.span.start]));
+		for name in ["brief", "description"] {
+			paragraphs.extend(annotation(annotations, name).and_then(|a| a.string()).map(str::to_string));
+		}
+		Some(paragraphs.join("\n\n")).filter(|documentation| !documentation.is_empty())
+	}
+}
+
+fn size_in_bits(size: u32) -> String {
+	match size % 8 {
+		0 => format!("Size: {size} bits ({} bytes)", size / 8),
+		_ => format!("Size: {size} bits"),
+	}
+}
+
+/// Returns the text of the comments at the end of `text`, which document what follows it unless a blank line
+/// separates them. The comments configuring lints are left out.
+fn comments(mut text: &str) -> Option<String> {
+	let mut comments = vec![];
+	loop {
+		let trimmed = text.trim_end();
+		if text[trimmed.len()..].matches('\n').count() > 1 {
+			break;
+		}
+
+		let comment = if let Some(body) = trimmed.strip_suffix("*/") {
+			let Some(start) = body.rfind("/*") else { break };
+			text = &trimmed[..start];
+			let lines = body[start + 2..].lines().map(|line| {
+				let line = line.trim();
+				line.strip_prefix('*').unwrap_or(line).trim()
+			});
+			lines.collect::<Vec<_>>().join("\n").trim().to_string()
+		} else {
+			let start = trimmed.rfind('\n').map_or(0, |i| i + 1);
+			let Some(comment) = trimmed[start..].trim_start().strip_prefix("//") else { break };
+			text = &trimmed[..start];
+			comment.trim_start_matches('/').trim().to_string()
+		};
+		if !comment.starts_with("p4analyzer:") {
+			comments.push(comment);
+		}
+	}
+
+	comments.reverse();
+	Some(comments.join("\n")).filter(|comments| !comments.trim().is_empty())
+}
@@ -0,0 +1,469 @@
+//! The definite assignment analysis.
+//!
+//! Local variables declared without an initializer and `out` parameters hold unspecified values until they are
+//! assigned. This analysis tracks the variables (and the fields of variables) definitely assigned at each point of
+//! the actions, functions, controls and parsers of a program, and warns about the reads of the others, and about the
+//! `out` parameters left unassigned on some path when the body returns (or when a parser accepts the packet).
+//!
+//! Variables are assigned by assignments, and by being passed to `out` or `inout` parameters. Invoking an action,
+//! directly or by applying a table, assigns the variables the action may assign. Headers, header unions and header
+//! stacks are invalid until they are assigned, rather than unspecified, so variables of these types aren't tracked.
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use crate::{
+	ast::*,
+	base_abstractions::*,
+	lints, parse,
+	symbols::{self, SymbolId, SymbolKind, SymbolTable},
+	types::{self, Type, TypeInfo},
+	Fs,
+};
+
+/// A variable, or a field of it, such as `(meta, ".ttl")`.
+type Path = (SymbolId, String);
+
+/// The paths definitely assigned at a point of a program, or `None` if the point is unreachable.
+type State = Option<BTreeSet<Path>>;
+
+/// Checks the reads of unassigned variables and the unassigned `out` parameters in the translation unit rooted at
+/// `file_id`.
+#[salsa::tracked]
+pub fn definite_assignment(db: &dyn crate::Db, fs: Fs, file_id: FileId) {
+	let Some(program) = parse(db, fs, file_id).as_ref() else { return };
+	let Some(table) = symbols::symbols(db, fs, file_id).as_ref() else { return };
+	let Some(info) = types::types(db, fs, file_id).as_ref() else { return };
+
+	let mut analysis = Analysis {
+		table,
+		info,
+		symbols: table.symbols().map(|(id, symbol)| (symbol.location.clone(), id)).collect(),
+		references: table.references().iter().filter_map(|r| Some((r.location.clone(), r.target?))).collect(),
+		actions: HashMap::new(),
+		tables: HashMap::new(),
+		tracked: HashSet::new(),
+		returned: None,
+		report: true,
+		reported: HashSet::new(),
+		diagnostics: vec![],
+	};
+	let locals = program.declarations.iter().flat_map(|declaration| match declaration {
+		Declaration::Control(control) => control.locals.as_slice(),
+		_ => &[],
+	});
+	for declaration in program.declarations.iter().chain(locals.clone()) {
+		let Some(&id) = declaration.name().and_then(|name| analysis.symbols.get(&name.location)) else { continue };
+		match declaration {
+			Declaration::Action(action) => {
+				analysis.actions.insert(id, action);
+			}
+			Declaration::Table(t) => {
+				analysis.tables.insert(id, t);
+			}
+			_ => (),
+		}
+	}
+
+	for declaration in program.declarations.iter().chain(locals) {
+		match declaration {
+			Declaration::Action(action) => analysis.body(&action.params, &action.body),
+			Declaration::Function(function) => analysis.body(&function.prototype.params, &function.body),
+			Declaration::Control(control) => {
+				if let Some(apply) = &control.apply {
+					analysis.body(&control.params, apply);
+				}
+			}
+			Declaration::Parser(parser) if !parser.is_type_declaration => analysis.parser(parser),
+			_ => (),
+		}
+	}
+
+	for diagnostic in analysis.diagnostics {
+		Diagnostics::push(db, diagnostic);
+	}
+}
+
+struct Analysis<'a> {
+	table: &'a SymbolTable,
+	info: &'a TypeInfo,
+	/// The symbols declared by the names at a given location.
+	symbols: HashMap<Location, SymbolId>,
+	/// The symbols the names at a given location refer to.
+	references: HashMap<Location, SymbolId>,
+	actions: HashMap<SymbolId, &'a Action>,
+	tables: HashMap<SymbolId, &'a Table>,
+	/// The variables whose reads are checked: the `out` parameters and the local variables of the body being
+	/// analyzed.
+	tracked: HashSet<SymbolId>,
+	/// The paths assigned at the `return` statements of the body being analyzed.
+	returned: State,
+	/// `false` while analyzing code whose entry state isn't final yet.
+	report: bool,
+	reported: HashSet<Location>,
+	diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> Analysis<'a> {
+	/// Analyzes the body of an action, a function or a control.
+	fn body(&mut self, params: &[Parameter], body: &'a Block) {
+		let outs = self.out_params(params);
+		self.tracked = outs.iter().map(|(id, _)| *id).collect();
+		self.returned = None;
+		let end = self.statements(&body.statements, Some(BTreeSet::new()));
+		let exit = meet(end, self.returned.take());
+		self.check_out_params(&outs, exit.as_ref());
+	}
+
+	fn parser(&mut self, parser: &'a Parser) {
+		let outs = self.out_params(&parser.params);
+		self.tracked = outs.iter().map(|(id, _)| *id).collect();
+		let mut initial = BTreeSet::new();
+		for local in &parser.locals {
+			if let Declaration::Variable(variable) = local {
+				self.variable(variable, &mut initial);
+			}
+		}
+
+		let index: HashMap<_, _> =
+			parser.states.iter().enumerate().map(|(i, state)| (state.name.name.as_str(), i)).collect();
+		let Some(&start) = index.get("start") else { return };
+		let mut entries: Vec<State> = vec![None; parser.states.len()];
+		entries[start] = Some(initial);
+
+		self.report = false;
+		let mut worklist = vec![start];
+		while let Some(i) = worklist.pop() {
+			let state = &parser.states[i];
+			let exit = self.parser_state(state, entries[i].clone());
+			for target in state.targets() {
+				let Some(&j) = index.get(target.name.as_str()) else { continue };
+				let entry = meet(entries[j].clone(), exit.clone());
+				if entry != entries[j] {
+					entries[j] = entry;
+					worklist.push(j);
+				}
+			}
+		}
+
+		self.report = true;
+		let mut accepted = None;
+		for (state, entry) in parser.states.iter().zip(entries) {
+			let exit = self.parser_state(state, entry);
+			if state.targets().iter().any(|target| target.name == "accept") {
+				accepted = meet(accepted, exit);
+			}
+		}
+		self.check_out_params(&outs, accepted.as_ref());
+	}
+
+	fn parser_state(&mut self, state: &'a ParserState, entry: State) -> State {
+		let mut assigned = self.statements(&state.statements, entry)?;
+		if let Some(Transition { kind: TransitionKind::Select(selectors, _), .. }) = &state.transition {
+			for selector in selectors {
+				self.expression(selector, &mut assigned);
+			}
+		}
+		Some(assigned)
+	}
+
+	/// Returns the `out` parameters which have to be assigned, with their names.
+	fn out_params<'p>(&self, params: &'p [Parameter]) -> Vec<(SymbolId, &'p Identifier)> {
+		let outs = params.iter().filter(|param| param.direction == Some(Direction::Out));
+		outs.filter_map(|param| Some((*self.symbols.get(&param.name.location)?, &param.name)))
+			.filter(|(id, _)| self.info.symbol_type(*id).map_or(false, |ty| self.needs_initialization(ty)))
+			.collect()
+	}
+
+	fn check_out_params(&mut self, outs: &[(SymbolId, &Identifier)], exit: Option<&BTreeSet<Path>>) {
+		let Some(exit) = exit else { return };
+		for (id, name) in outs {
+			if !exit.iter().any(|(assigned, _)| assigned == id) {
+				let message = format!("the `out` parameter `{}` may be left unassigned", name.name);
+				self.diagnostics.push(lints::UNASSIGNED_OUT_PARAMETER.diagnostic(&name.location, message));
+			}
+		}
+	}
+
+	/// Returns `true` if a value of type `ty` is unspecified until it is assigned.
+	fn needs_initialization(&self, ty: &Type) -> bool {
+		match ty {
+			Type::Bit(_)
+			| Type::Int(_)
+			| Type::Varbit(_)
+			| Type::InfInt
+			| Type::Bool
+			| Type::String
+			| Type::Error
+			| Type::Enum(_)
+			| Type::NewType(_) => true,
+			Type::Struct(id, _) => {
+				let Some(body) = self.table.symbol(*id).body else { return false };
+				let mut fields =
+					self.table.scope(body).symbols.iter().filter_map(|field| self.info.symbol_type(*field));
+				fields.any(|ty| self.needs_initialization(ty))
+			}
+			Type::Tuple(elements) => elements.iter().any(|ty| self.needs_initialization(ty)),
+			_ => false,
+		}
+	}
+
+	fn statements(&mut self, statements: &'a [Statement], mut state: State) -> State {
+		for statement in statements {
+			state = self.statement(statement, state);
+		}
+		state
+	}
+
+	fn statement(&mut self, statement: &'a Statement, state: State) -> State {
+		let mut assigned = state?;
+		match &statement.kind {
+			StatementKind::Assignment(lhs, rhs) => {
+				self.expression(rhs, &mut assigned);
+				self.target(lhs, &mut assigned);
+			}
+			StatementKind::Call(call) => self.expression(call, &mut assigned),
+			StatementKind::If(condition, then, otherwise) => {
+				self.expression(condition, &mut assigned);
+				let then = self.statement(then, Some(assigned.clone()));
+				let otherwise = match otherwise {
+					Some(otherwise) => self.statement(otherwise, Some(assigned)),
+					None => Some(assigned),
+				};
+				return meet(then, otherwise);
+			}
+			StatementKind::Switch(scrutinee, cases) => {
+				self.expression(scrutinee, &mut assigned);
+				let mut exit = None;
+				for body in cases.iter().filter_map(|case| case.body.as_ref()) {
+					exit = meet(exit, self.statements(&body.statements, Some(assigned.clone())));
+				}
+				if !cases.iter().any(|case| matches!(case.label.kind, ExpressionKind::Default)) {
+					exit = meet(exit, Some(assigned));
+				}
+				return exit;
+			}
+			StatementKind::Block(block) => return self.statements(&block.statements, Some(assigned)),
+			StatementKind::Return(value) => {
+				if let Some(value) = value {
+					self.expression(value, &mut assigned);
+				}
+				self.returned = meet(self.returned.take(), Some(assigned));
+				return None;
+			}
+			StatementKind::Exit => return None,
+			StatementKind::Empty => (),
+			StatementKind::Declaration(declaration) => match declaration.as_ref() {
+				Declaration::Variable(variable) => self.variable(variable, &mut assigned),
+				Declaration::Constant(constant) => self.expression(&constant.value, &mut assigned),
+				_ => (),
+			},
+		}
+		Some(assigned)
+	}
+
+	fn variable(&mut self, variable: &'a Variable, assigned: &mut BTreeSet<Path>) {
+		let Some(&id) = self.symbols.get(&variable.name.location) else { return };
+		match &variable.initializer {
+			Some(initializer) => {
+				self.expression(initializer, assigned);
+				assigned.insert((id, String::new()));
+			}
+			None if self.info.symbol_type(id).map_or(false, |ty| self.needs_initialization(ty)) => {
+				self.tracked.insert(id);
+			}
+			None => (),
+		}
+	}
+
+	/// Checks the reads in an expression, and applies the assignments made by the calls in it.
+	fn expression(&mut self, expression: &'a Expression, assigned: &mut BTreeSet<Path>) {
+		match &expression.kind {
+			ExpressionKind::Integer(_)
+			| ExpressionKind::Boolean(_)
+			| ExpressionKind::String(_)
+			| ExpressionKind::DontCare
+			| ExpressionKind::Default
+			| ExpressionKind::Type(_)
+			| ExpressionKind::Missing => (),
+			ExpressionKind::Name(_) | ExpressionKind::Member(..) => match self.path(expression) {
+				Some(path) => self.read(&path, &expression.location, assigned),
+				None => {
+					if let ExpressionKind::Member(base, _) = &expression.kind {
+						self.expression(base, assigned);
+					}
+				}
+			},
+			ExpressionKind::Index(base, index) => {
+				self.expression(base, assigned);
+				self.expression(index, assigned);
+			}
+			ExpressionKind::Slice(base, high, low) => {
+				self.expression(base, assigned);
+				self.expression(high, assigned);
+				self.expression(low, assigned);
+			}
+			ExpressionKind::Call(callee, _, args) => self.call(callee, args, assigned),
+			ExpressionKind::Unary(_, operand) | ExpressionKind::Cast(_, operand) => self.expression(operand, assigned),
+			ExpressionKind::Binary(_, lhs, rhs) => {
+				self.expression(lhs, assigned);
+				self.expression(rhs, assigned);
+			}
+			ExpressionKind::Ternary(condition, then, otherwise) => {
+				self.expression(condition, assigned);
+				let mut when_false = assigned.clone();
+				self.expression(then, assigned);
+				self.expression(otherwise, &mut when_false);
+				assigned.retain(|path| when_false.contains(path));
+			}
+			ExpressionKind::List(elements) => {
+				for element in elements {
+					self.expression(element, assigned);
+				}
+			}
+			ExpressionKind::StructInitializer(fields) => {
+				for (_, value) in fields {
+					self.expression(value, assigned);
+				}
+			}
+		}
+	}
+
+	/// Checks the reads in the left-hand side of an assignment, and assigns the path it names.
+	fn target(&mut self, target: &'a Expression, assigned: &mut BTreeSet<Path>) {
+		match &target.kind {
+			ExpressionKind::Index(base, index) => {
+				self.expression(index, assigned);
+				self.target(base, assigned);
+			}
+			ExpressionKind::Slice(base, high, low) => {
+				self.expression(high, assigned);
+				self.expression(low, assigned);
+				self.target(base, assigned);
+			}
+			_ => match self.path(target) {
+				Some(path) => {
+					assigned.insert(path);
+				}
+				None => self.expression(target, assigned),
+			},
+		}
+	}
+
+	fn read(&mut self, (id, suffix): &Path, location: &Location, assigned: &BTreeSet<Path>) {
+		if !self.report || !self.tracked.contains(id) {
+			return;
+		}
+		// a path is assigned if it is within an assigned path, or if it contains one (to keep the reads of partially
+		// assigned structs quiet)
+		let is_assigned = assigned
+			.iter()
+			.any(|(other, assigned)| other == id && (is_within(suffix, assigned) || is_within(assigned, suffix)));
+		if is_assigned || !self.reported.insert(location.clone()) {
+			return;
+		}
+
+		let symbol = self.table.symbol(*id);
+		let message = match symbol.kind {
+			SymbolKind::Parameter(_) => {
+				format!("the `out` parameter `{}` may be read before it is assigned", symbol.name)
+			}
+			_ => format!("the variable `{}` may be read before it is initialized", symbol.name),
+		};
+		self.diagnostics.push(lints::UNINITIALIZED_READ.diagnostic(location, message));
+	}
+
+	fn call(&mut self, callee: &'a Expression, args: &'a [Argument], assigned: &mut BTreeSet<Path>) {
+		if let ExpressionKind::Member(base, _) = &callee.kind {
+			if !matches!(self.info.expression_type(&base.location), Some(Type::Header(..) | Type::Stack(..))) {
+				self.expression(base, assigned);
+			}
+		}
+
+		let params = self.params(callee);
+		let mut outs = vec![];
+		for (position, arg) in args.iter().enumerate() {
+			let param = match &arg.name {
+				Some(name) => params.iter().find(|param| param.name == name.name),
+				None => params.get(position),
+			};
+			match param.map(|param| &param.kind) {
+				Some(SymbolKind::Parameter(Some(Direction::Out))) => outs.push(&arg.value),
+				Some(SymbolKind::Parameter(Some(Direction::InOut))) => {
+					self.expression(&arg.value, assigned);
+					outs.push(&arg.value);
+				}
+				_ => self.expression(&arg.value, assigned),
+			}
+		}
+		for out in outs {
+			self.target(out, assigned);
+		}
+
+		match self.info.expression_type(&callee.location) {
+			Some(Type::Apply(id)) if self.tables.contains_key(id) => {
+				let t = self.tables[id];
+				for property in &t.properties {
+					if let TableProperty::Key(elements, _) = property {
+						for element in elements {
+							self.expression(&element.expression, assigned);
+						}
+					}
+				}
+				for action in t.actions() {
+					for arg in action.args.iter().flatten() {
+						self.expression(&arg.value, assigned);
+					}
+					if let Some(&id) = self.references.get(&action.name.location) {
+						self.invoke(id, assigned);
+					}
+				}
+			}
+			Some(Type::Function(id)) => self.invoke(*id, assigned),
+			_ => (),
+		}
+	}
+
+	/// Assigns the variables an action may assign.
+	fn invoke(&mut self, id: SymbolId, assigned: &mut BTreeSet<Path>) {
+		let Some(action) = self.actions.get(&id).copied() else { return };
+		let (report, tracked, returned) = (self.report, self.tracked.clone(), self.returned.take());
+		self.report = false;
+		let end = self.statements(&action.body.statements, Some(assigned.clone()));
+		for exit in [end, std::mem::replace(&mut self.returned, returned)].into_iter().flatten() {
+			assigned.extend(exit);
+		}
+		(self.report, self.tracked) = (report, tracked);
+	}
+
+	/// Returns the parameters of the function, method, action, parser or control called by `callee`.
+	fn params(&self, callee: &Expression) -> Vec<&'a symbols::Symbol> {
+		match self.info.expression_type(&callee.location) {
+			Some(Type::Method(_, id) | Type::Function(id) | Type::Apply(id)) => self.table.parameters(*id).collect(),
+			_ => vec![],
+		}
+	}
+
+	/// Returns the variable an expression such as `meta.ttl` names, and the path of the field within it.
+	fn path(&self, expression: &Expression) -> Option<Path> {
+		match &expression.kind {
+			ExpressionKind::Name(_) => Some((*self.references.get(&expression.location)?, String::new())),
+			ExpressionKind::Member(base, member) => {
+				let (id, suffix) = self.path(base)?;
+				Some((id, format!("{suffix}.{}", member.name)))
+			}
+			_ => None,
+		}
+	}
+}
+
+/// Returns `true` if the field path `path` is `prefix`, or a field within it.
+fn is_within(path: &str, prefix: &str) -> bool {
+	path.strip_prefix(prefix).map_or(false, |rest| rest.is_empty() || rest.starts_with('.'))
+}
+
+fn meet(a: State, b: State) -> State {
+	match (a, b) {
+		(Some(a), Some(b)) => Some(&a & &b),
+		(a, None) | (None, a) => a,
+	}
+}
@@ -1,6 +1,7 @@
 #![allow(clippy::tabs_in_doc_comments)]
 use lazy_static::lazy_static;
 use logos::{Logos, Span};
+use num_bigint::BigInt;
 use regex::Regex;
 
 use super::{base_abstractions::*, preprocessor::*};
@@ -21,7 +22,8 @@ pub struct Literal {
 	pub base: u8,
 	pub signed: bool,
 	pub width: Option<u32>,
-	pub value: i64,
+	/// The value, of arbitrary precision so that literals as wide as their type fit.
+	pub value: BigInt,
 }
 
 #[derive(Logos, PartialOrd, Ord, PartialEq, Eq, Debug, Clone)]
@@ -49,6 +51,10 @@ pub enum Token {
 	#[regex("/\\*", |lex| Lexer(lex).read_comment())]
 	Comment,
 
+	/// The `@` starting an annotation.
+	#[token("@")]
+	At,
+
 	#[token(".")]
 	Dot,
 
@@ -67,6 +73,66 @@ pub enum Token {
 	#[token("-")]
 	Minus,
 
+	#[token("%")]
+	Percent,
+
+	#[token("&")]
+	Ampersand,
+
+	#[token("|")]
+	Pipe,
+
+	#[token("^")]
+	Caret,
+
+	#[token("~")]
+	Tilde,
+
+	#[token("!")]
+	ExclamationMark,
+
+	#[token("?")]
+	QuestionMark,
+
+	#[token("&&")]
+	DoubleAmpersand,
+
+	#[token("&&&")]
+	TripleAmpersand,
+
+	#[token("||")]
+	DoublePipe,
+
+	#[token("..")]
+	DoubleDot,
+
+	#[token("==")]
+	DoubleEquals,
+
+	#[token("!=")]
+	NotEquals,
+
+	#[token("<=")]
+	LessOrEqual,
+
+	#[token(">=")]
+	GreaterOrEqual,
+
+	/// A left shift. There is no corresponding right shift token, since `>>` would be ambiguous with the closing of
+	/// nested type arguments (e.g., `tuple<bit<8>>`). The parser recognizes two adjacent [`Token::CloseChevron`]s
+	/// instead.
+	#[token("<<")]
+	ShiftLeft,
+
+	#[token("++")]
+	DoublePlus,
+
+	#[token("|+|")]
+	SaturatingPlus,
+
+	#[token("|-|")]
+	SaturatingMinus,
+
 	#[error]
 	Error,
 
@@ -81,36 +147,94 @@ pub enum Token {
 	#[regex("[0-9][0-9wsbBdDoOxXa-fA-F_]*", |lex| Lexer(lex).read_int())]
 	Integer(Literal),
 
+	/// A string literal, holding its contents without the enclosing double quotes.
+	#[regex(r#""([^"\\\n]|\\.)*""#, |lex| Lexer(lex).read_string())]
+	StringLiteral(String),
+
+	#[token("abstract")]
+	KwAbstract,
+
 	#[token("action")]
 	KwAction,
 
 	#[token("apply")]
 	KwApply,
 
+	#[token("const")]
+	KwConst,
+
 	#[token("control")]
 	KwControl,
 
+	#[token("default")]
+	KwDefault,
+
 	#[token("else")]
 	KwElse,
 
+	#[token("enum")]
+	KwEnum,
+
+	#[token("exit")]
+	KwExit,
+
+	#[token("extern")]
+	KwExtern,
+
+	#[token("false")]
+	KwFalse,
+
 	#[token("header")]
 	KwHeader,
 
+	#[token("header_union")]
+	KwHeaderUnion,
+
 	#[token("if")]
 	KwIf,
 
+	#[token("in")]
+	KwIn,
+
+	#[token("inout")]
+	KwInout,
+
+	#[token("out")]
+	KwOut,
+
+	#[token("package")]
+	KwPackage,
+
+	#[token("parser")]
+	KwParser,
+
 	#[token("return")]
 	KwReturn,
 
+	#[token("select")]
+	KwSelect,
+
 	#[token("state")]
 	KwState,
 
+	#[token("struct")]
+	KwStruct,
+
+	#[token("switch")]
+	KwSwitch,
+
 	#[token("table")]
 	KwTable,
 
 	#[token("transition")]
 	KwTransition,
 
+	#[token("true")]
+	KwTrue,
+
+	#[token("type")]
+	KwType,
+
 	#[token("typedef")]
 	KwTypedef,
 
@@ -129,6 +253,10 @@ pub enum Token {
 	#[regex(r"#\s*\w+", |lex| Lexer(lex).read_directive())]
 	PreprocessorDirective(PreprocessorDirective),
 
+	/// A `@pragma` annotation, holding the rest of the line, which is its body.
+	#[regex("@pragma[ \t][^\n]*", |lex| lex.slice()["@pragma".len()..].to_string())]
+	Pragma(String),
+
 	#[token(";")]
 	Semicolon,
 
@@ -141,7 +269,7 @@ struct Lexer<'a, 'b>(&'b mut logos::Lexer<'a, Token>);
 impl<'a, 'b> Lexer<'a, 'b> {
 	// TODO: report nice errors
 	fn read_int(mut self) -> Option<Literal> {
-		let mut lit = Literal { base: 10, signed: false, width: None, value: 0 };
+		let mut lit = Literal { base: 10, signed: false, width: None, value: BigInt::default() };
 
 		let str = self.0.slice();
 		// the stitching here is a little ugly, fn(&str) -> Option<(T, &str)> here
@@ -160,7 +288,7 @@ impl<'a, 'b> Lexer<'a, 'b> {
 			})
 			.unwrap_or(str);
 
-		lit.value = match i64::from_str_radix(str, lit.base as u32).ok() {
+		lit.value = match BigInt::parse_bytes(str.as_bytes(), lit.base as u32) {
 			Some(i) => i,
 			None => {
 				self.report(Severity::Error, "malformed integer literal");
@@ -170,6 +298,11 @@ impl<'a, 'b> Lexer<'a, 'b> {
 		Some(lit)
 	}
 
+	fn read_string(self) -> String {
+		let str = self.0.slice();
+		str[1..str.len() - 1].to_string()
+	}
+
 	fn read_directive(mut self) -> Option<PreprocessorDirective> {
 		lazy_static! {
 			static ref DIRECTIVE: Regex = Regex::new(r"#\s*(\w+)").unwrap();
@@ -192,6 +325,19 @@ impl<'a, 'b> Lexer<'a, 'b> {
 				let expr = parse_pp_expression(arg)?;
 				PreprocessorDirective::ElseIf(expr)
 			}
+			directive @ ("ifdef" | "ifndef") => {
+				// the name may be followed by a comment, which is ignored
+				let name: String =
+					arg.trim_start().chars().take_while(|ch| ch.is_ascii_alphanumeric() || *ch == '_').collect();
+				if name.is_empty() {
+					return None;
+				}
+				let defined = PreprocessorExpression::Defined(name);
+				match directive {
+					"ifdef" => PreprocessorDirective::If(defined),
+					_ => PreprocessorDirective::If(PreprocessorExpression::Not(defined.into())),
+				}
+			}
 			"else" => PreprocessorDirective::Else,
 			"endif" => PreprocessorDirective::EndIf,
 			"define" => {
@@ -347,10 +493,7 @@ impl<'a, 'b> Lexer<'a, 'b> {
 
 	fn report(&mut self, severity: Severity, msg: &str) {
 		if let Some(db) = self.0.extras.db.map(|db| unsafe { &*db }) {
-			Diagnostics::push(
-				db,
-				Diagnostic { file: self.0.extras.file_id, location: self.0.span(), severity, message: msg.to_string() },
-			);
+			Diagnostics::push(db, Diagnostic::new(self.0.extras.file_id, self.0.span(), severity, msg.to_string()));
 		}
 	}
 }
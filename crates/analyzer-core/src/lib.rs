@@ -1,17 +1,57 @@
+pub mod annotations;
+pub mod arch;
+pub mod ast;
 pub mod base_abstractions;
+pub mod completion;
+pub mod const_eval;
+pub mod deparser;
+pub mod folding;
+pub mod formatting;
+pub mod hover;
+pub mod initialization;
 pub mod lexer;
+pub mod lints;
 pub mod lsp_file;
+pub mod navigation;
+pub mod outline;
 pub mod parser;
+pub mod parser_states;
 pub mod preprocessor;
-
-use std::collections::HashMap;
+pub mod references;
+pub mod rename;
+pub mod selection;
+pub mod semantic_tokens;
+pub mod signature_help;
+pub mod symbols;
+pub mod tables;
+pub mod types;
+pub mod unused;
+pub mod validity;
+
+use std::collections::{HashMap, HashSet};
 
 use logos::Logos;
 
+use annotations::Annotations;
+use arch::{ArchModel, Architecture};
+use ast::Program;
 use base_abstractions::*;
+use completion::{Completion, Resolution};
+use const_eval::Constants;
+use folding::FoldingRange;
+use formatting::{FormatOptions, TextEdit};
+use hover::Hover;
 use lexer::*;
+use lints::LintConfig;
 use lsp_file::{ChangeEvent, LspFile};
+use outline::{DocumentSymbol, WorkspaceSymbol};
+use parser_states::StateGraph;
 use preprocessor::*;
+use references::Occurrence;
+use semantic_tokens::SemanticToken;
+use signature_help::SignatureHelp;
+use symbols::SymbolTable;
+use types::TypeInfo;
 
 // #[derive(Default)]
 #[salsa::db(crate::Jar)]
@@ -37,9 +77,31 @@ pub struct Jar(
 	Fs,
 	LexedFs,
 	IncludedDependencies,
+	MacroDefinitions,
 	// gotta include salsa functions as well
 	lex,
 	preprocess,
+	parse,
+	parse_file,
+	analyze,
+	symbols::parse_prelude,
+	symbols::file_symbols,
+	symbols::symbols,
+	types::types,
+	const_eval::constants,
+	arch::embedded_lexemes,
+	arch::include,
+	arch::model,
+	parser_states::state_graphs,
+	validity::header_validity,
+	initialization::definite_assignment,
+	tables::table_declarations,
+	deparser::emit_order,
+	lints::suppressions,
+	unused::uses,
+	unused::unused_declarations,
+	annotations::annotations,
+	outline::document_symbols,
 );
 
 pub trait Db: salsa::DbWithJar<Jar> {
@@ -57,6 +119,7 @@ pub struct Analyzer {
 	db: Database,
 	fs: Option<Fs>,
 	require_fn: Box<dyn Fn(&str) -> () + 'static>,
+	lints: LintConfig,
 }
 
 #[salsa::tracked]
@@ -74,7 +137,12 @@ impl Analyzer {
 		resolver_fn: impl Fn(&str, &str) -> Result<String, String> + 'static,
 		require_fn: impl Fn(&str) -> () + 'static,
 	) -> Self {
-		Self { db: Database::new(resolver_fn), fs: Default::default(), require_fn: Box::new(require_fn) }
+		Self {
+			db: Database::new(resolver_fn),
+			fs: Default::default(),
+			require_fn: Box::new(require_fn),
+			lints: Default::default(),
+		}
 	}
 
 	fn filesystem(&self) -> HashMap<FileId, Buffer> { self.fs.map(|fs| fs.fs(&self.db)).unwrap_or_default() }
@@ -121,14 +189,264 @@ impl Analyzer {
 		result
 	}
 
+	/// Retrieves the syntax tree of the translation unit rooted at a given source [`FileId`].
+	pub fn parsed(&self, file_id: FileId) -> Option<&Program> { parse(&self.db, self.fs?, file_id).as_ref() }
+
+	/// Retrieves the symbol table of the translation unit rooted at a given source [`FileId`].
+	pub fn symbols(&self, file_id: FileId) -> Option<&SymbolTable> {
+		symbols::symbols(&self.db, self.fs?, file_id).as_ref()
+	}
+
+	/// Retrieves the types of the translation unit rooted at a given source [`FileId`].
+	pub fn types(&self, file_id: FileId) -> Option<&TypeInfo> { types::types(&self.db, self.fs?, file_id).as_ref() }
+
+	/// Retrieves the values of the compile-time constants of the translation unit rooted at a given source [`FileId`].
+	pub fn constants(&self, file_id: FileId) -> Option<&Constants> {
+		const_eval::constants(&self.db, self.fs?, file_id).as_ref()
+	}
+
+	/// Retrieves the model of the architecture targeted by the translation unit rooted at a given source [`FileId`].
+	pub fn architecture(&self, file_id: FileId) -> Option<&ArchModel> {
+		arch::model(&self.db, self.fs?, file_id).as_ref()
+	}
+
+	/// Retrieves the state graphs of the parsers of the translation unit rooted at a given source [`FileId`].
+	pub fn state_graphs(&self, file_id: FileId) -> Option<&[StateGraph]> {
+		parser_states::state_graphs(&self.db, self.fs?, file_id).as_deref()
+	}
+
+	/// Retrieves the annotations of the translation unit rooted at a given source [`FileId`].
+	pub fn annotations(&self, file_id: FileId) -> Option<&Annotations> {
+		annotations::annotations(&self.db, self.fs?, file_id).as_ref()
+	}
+
+	/// Retrieves the information shown when hovering over `offset` in a given source [`FileId`].
+	pub fn hover(&self, file_id: FileId, offset: usize) -> Option<Hover> {
+		hover::hover(&self.db, self.fs?, file_id, offset)
+	}
+
+	/// Retrieves the definition of the name, macro or included file at `offset` in a given source [`FileId`].
+	pub fn definition(&self, file_id: FileId, offset: usize) -> Vec<Location> {
+		self.fs.map(|fs| navigation::definition(&self.db, fs, file_id, offset)).unwrap_or_default()
+	}
+
+	/// Retrieves the declaration of the name at `offset` in a given source [`FileId`].
+	pub fn declaration(&self, file_id: FileId, offset: usize) -> Vec<Location> {
+		self.fs.map(|fs| navigation::declaration(&self.db, fs, file_id, offset)).unwrap_or_default()
+	}
+
+	/// Retrieves the definition of the type of the name at `offset` in a given source [`FileId`].
+	pub fn type_definition(&self, file_id: FileId, offset: usize) -> Vec<Location> {
+		self.fs.map(|fs| navigation::type_definition(&self.db, fs, file_id, offset)).unwrap_or_default()
+	}
+
+	/// Retrieves the implementations of the parser type, control type or abstract method at `offset` in a given
+	/// source [`FileId`].
+	pub fn implementations(&self, file_id: FileId, offset: usize) -> Vec<Location> {
+		self.fs.map(|fs| navigation::implementations(&self.db, fs, file_id, offset)).unwrap_or_default()
+	}
+
+	/// Retrieves the references to the symbol declared or referenced at `offset` in a given source [`FileId`], across
+	/// all the translation units of the workspace, optionally including its declaration and the identifiers of the
+	/// inactive preprocessor regions named like it.
+	pub fn references(
+		&self,
+		file_id: FileId,
+		offset: usize,
+		include_declaration: bool,
+		include_inactive: bool,
+	) -> Vec<Location> {
+		let Some(fs) = self.fs else { return vec![] };
+		let Some(target) = references::target(&self.db, fs, file_id, offset) else { return vec![] };
+
+		// the same declaration may be seen by several translation units
+		let mut roots: Vec<_> = self.filesystem().keys().copied().filter(|&root| root != file_id).collect();
+		roots.insert(0, file_id);
+		let mut seen = HashSet::new();
+		let mut locations: Vec<_> = roots
+			.into_iter()
+			.flat_map(|root| references::occurrences(&self.db, fs, root, &target, include_inactive))
+			.filter(|occurrence| include_declaration || occurrence.access != references::Access::Declaration)
+			.map(|occurrence| occurrence.location)
+			.filter(|location| seen.insert(location.clone()))
+			.collect();
+		locations.sort_by_cached_key(|location| (location.file.path(&self.db), location.span.start));
+		locations
+	}
+
+	/// Retrieves the occurrences in a given source [`FileId`] of the symbol declared or referenced at `offset`, with
+	/// how they access it.
+	pub fn highlights(&self, file_id: FileId, offset: usize) -> Vec<Occurrence> {
+		let Some(fs) = self.fs else { return vec![] };
+		let Some(target) = references::target(&self.db, fs, file_id, offset) else { return vec![] };
+		let occurrences = references::occurrences(&self.db, fs, file_id, &target, false);
+		occurrences.into_iter().filter(|occurrence| occurrence.location.file == file_id).collect()
+	}
+
+	/// Retrieves the span of the name to rename at `offset` in a given source [`FileId`], or the reason why it cannot be
+	/// renamed.
+	pub fn prepare_rename(&self, file_id: FileId, offset: usize) -> Result<Span, String> {
+		let fs = self.fs.ok_or("the workspace is not loaded")?;
+		rename::prepare_rename(&self.db, fs, file_id, offset)
+	}
+
+	/// Retrieves the names to replace with `new_name` across the workspace to rename the symbol at `offset` in a
+	/// given source [`FileId`], or the reason why it cannot be renamed. The identifiers of the inactive preprocessor
+	/// regions named like the symbol are renamed too, so that the program still builds once they are enabled.
+	pub fn rename(&self, file_id: FileId, offset: usize, new_name: &str) -> Result<Vec<Location>, String> {
+		let fs = self.fs.ok_or("the workspace is not loaded")?;
+		let span = rename::prepare_rename(&self.db, fs, file_id, offset)?;
+		rename::check_new_name(&self.db, fs, file_id, offset, new_name)?;
+
+		let locations = self.references(file_id, offset, true, true);
+		let name = &self.source(file_id).unwrap_or_default()[span];
+		rename::check_occurrences(&self.db, fs, name, &locations)?;
+		Ok(locations)
+	}
+
+	/// Retrieves the outline of a given source [`FileId`].
+	pub fn document_symbols(&self, file_id: FileId) -> &[DocumentSymbol] {
+		let Some(buf) = self.buffer(file_id) else { return &[] };
+		outline::document_symbols(&self.db, file_id, buf)
+	}
+
+	/// Searches the declarations of the files of the workspace whose names fuzzily match `query`.
+	pub fn workspace_symbols(&self, query: &str) -> Vec<WorkspaceSymbol> {
+		self.fs.map(|fs| outline::workspace_symbols(&self.db, fs, query)).unwrap_or_default()
+	}
+
+	/// Retrieves the completions at `offset` in a given source [`FileId`].
+	pub fn completions(&self, file_id: FileId, offset: usize) -> Vec<Completion> {
+		self.fs.map(|fs| completion::completions(&self.db, fs, file_id, offset)).unwrap_or_default()
+	}
+
+	/// Resolves the details of a completion of the declaration named at `symbol`, offered in a given source [`FileId`].
+	pub fn resolve_completion(&self, file_id: FileId, symbol: &Location) -> Option<Resolution> {
+		completion::resolve(&self.db, self.fs?, file_id, symbol)
+	}
+
+	/// Retrieves the signatures of the call or type argument list around `offset` in a given source [`FileId`].
+	pub fn signature_help(&self, file_id: FileId, offset: usize) -> Option<SignatureHelp> {
+		signature_help::signature_help(&self.db, self.fs?, file_id, offset)
+	}
+
+	/// Retrieves the semantic tokens of a given source [`FileId`], in order.
+	pub fn semantic_tokens(&self, file_id: FileId) -> Vec<SemanticToken> {
+		self.fs.map(|fs| semantic_tokens::semantic_tokens(&self.db, fs, file_id)).unwrap_or_default()
+	}
+
+	/// Formats a given source [`FileId`], or the lexemes starting within `range` of it.
+	pub fn format(&self, file_id: FileId, options: &FormatOptions, range: Option<Span>) -> Vec<TextEdit> {
+		let (Some(text), Some(lexemes)) = (self.input(file_id), self.lexed(file_id)) else { return vec![] };
+		match range {
+			Some(range) => formatting::format_range(text, lexemes, options, range),
+			None => formatting::format(text, lexemes, options),
+		}
+	}
+
+	/// Formats the code of a given source [`FileId`] completed by typing `typed` before `offset`.
+	pub fn format_on_type(
+		&self,
+		file_id: FileId,
+		options: &FormatOptions,
+		offset: usize,
+		typed: char,
+	) -> Vec<TextEdit> {
+		let (Some(text), Some(lexemes)) = (self.input(file_id), self.lexed(file_id)) else { return vec![] };
+		formatting::format_on_type(text, lexemes, options, offset, typed)
+	}
+
+	/// Retrieves the folding ranges of a given source [`FileId`], ordered by their start.
+	pub fn folding_ranges(&self, file_id: FileId) -> Vec<FoldingRange> {
+		let Some(buf) = self.buffer(file_id) else { return vec![] };
+		folding::folding_ranges(&self.db, file_id, buf)
+	}
+
+	/// Retrieves the spans to select in turn when expanding the selection at `offset` in a given source [`FileId`].
+	pub fn selection_ranges(&self, file_id: FileId, offset: usize) -> Vec<Span> {
+		let Some(buf) = self.buffer(file_id) else { return vec![] };
+		selection::selection_ranges(&self.db, file_id, buf, offset)
+	}
+
+	/// Retrieves the source text of a file of the workspace, of an embedded architecture header or of the core
+	/// library.
+	pub fn source(&self, file_id: FileId) -> Option<&str> { file_text(&self.db, self.fs?, file_id) }
+
+	/// Sets the levels of the lints, overriding their default levels.
+	pub fn configure_lints(&mut self, config: LintConfig) { self.lints = config; }
+
+	/// Retrieves the diagnostics of the translation unit rooted at a given source [`FileId`], leaving out the lints
+	/// which are allowed by the configuration or by a comment.
 	pub fn diagnostics(&self, id: FileId) -> Vec<Diagnostic> {
-		if let Some(buf) = self.filesystem().get(&id) {
-			let mut d = lex::accumulated::<Diagnostics>(&self.db, id, *buf);
-			d.append(&mut preprocess::accumulated::<Diagnostics>(&self.db, self.fs.unwrap(), id));
-			d
-		} else {
-			vec![]
+		let Some(fs) = self.fs.filter(|_| self.filesystem().contains_key(&id)) else { return vec![] };
+
+		// the diagnostics of a query include those of the queries it depends on
+		let diagnostics = analyze::accumulated::<Diagnostics>(&self.db, fs, id);
+		let suppressions = lints::suppressions(&self.db, fs, id).as_deref().unwrap_or_default();
+
+		// a declaration is only unused if no other translation unit including its file uses it
+		let includers: Vec<_> = self
+			.roots_including(id)
+			.into_iter()
+			.filter_map(|root| unused::uses(&self.db, fs, root).as_ref())
+			.collect();
+		let unused = unused::unused_declarations(&self.db, fs, id).as_deref().unwrap_or_default();
+		let unused = unused.iter().filter(|unused| !includers.iter().any(|uses| unused.is_used(uses)));
+
+		diagnostics
+			.into_iter()
+			.chain(unused.map(|unused| unused.diagnostic.clone()))
+			.filter_map(|diagnostic| {
+				let Some(lint) = diagnostic.code.and_then(lints::lint) else { return Some(diagnostic) };
+				if suppressions.iter().any(|suppression| suppression.allows(&diagnostic)) {
+					return None;
+				}
+				let severity = self.lints.level(lint).severity()?;
+				Some(Diagnostic { severity, ..diagnostic })
+			})
+			.collect()
+	}
+
+	/// Retrieves the diagnostics of the files affected by a change to a given source [`FileId`], grouped by the file
+	/// they are located in. The file comes first, followed by the other translation units including it and the files
+	/// of the workspace included by any of them, each listed even if it has no diagnostics so that those previously
+	/// reported in it can be cleared. A file included by several translation units gets the diagnostics of all of them.
+	/// The diagnostics located in the embedded headers are left out.
+	pub fn diagnostics_by_file(&self, id: FileId) -> Vec<(FileId, Vec<Diagnostic>)> {
+		let filesystem = self.filesystem();
+		let mut files = vec![];
+		for root in std::iter::once(id).chain(self.roots_including(id)) {
+			for file in std::iter::once(root).chain(self.include_dependencies(root).into_iter().map(|i| i.file_id)) {
+				if filesystem.contains_key(&file) && !files.contains(&file) {
+					files.push(file);
+				}
+			}
+		}
+
+		let mut by_root = HashMap::new();
+		let mut groups = vec![];
+		for file in files {
+			let mut group: Vec<Diagnostic> = vec![];
+			for root in std::iter::once(file).chain(self.roots_including(file)) {
+				let diagnostics = by_root.entry(root).or_insert_with(|| self.diagnostics(root));
+				for diagnostic in diagnostics.iter().filter(|diagnostic| diagnostic.file == file) {
+					if !group.contains(diagnostic) {
+						group.push(diagnostic.clone());
+					}
+				}
+			}
+			groups.push((file, group));
 		}
+		groups
+	}
+
+	/// Retrieves the files of the workspace, other than a given source [`FileId`], whose translation unit includes it.
+	pub fn roots_including(&self, id: FileId) -> Vec<FileId> {
+		self.filesystem()
+			.keys()
+			.copied()
+			.filter(|&root| root != id && self.include_dependencies(root).iter().any(|i| i.file_id == id))
+			.collect()
 	}
 
 	/// Retrieves the included dependencies for a given source [`FileId`].
@@ -195,12 +513,8 @@ where
 					// terminate the error range and emit a diagnostic
 					let location = err_span.clone();
 					*state = None;
-					let diagnostic = Diagnostic {
-						file: file_id,
-						location,
-						severity: Severity::Error,
-						message: "unexpected token".to_string(),
-					};
+					let diagnostic =
+						Diagnostic::new(file_id, location, Severity::Error, "unexpected token".to_string());
 
 					Diagnostics::push(db, diagnostic);
 				}
@@ -243,18 +557,19 @@ pub fn lex(db: &dyn crate::Db, file_id: FileId, buf: Buffer) -> LexedBuffer {
 pub fn preprocess(db: &dyn crate::Db, fs: Fs, file_id: FileId) -> Option<Vec<(FileId, Token, Span)>> {
 	let mut pp = PreprocessorState::new(
 		|path: &str| {
-			// Return a `FileId` with an absolute path resolved relative to the file being preprocessed. If the path
-			// is already absolute then `resolve_path` will return it as is.
-			FileId::new(db, db.resolve_path(file_id, path))
+			let included = resolve_include(db, fs, file_id, path);
+			let resolved = FileId::new(db, db.resolve_path(file_id, path));
+			if included != resolved {
+				// the missing header is still a dependency, which may be added later
+				IncludedDependencies::push(db, IncludedDependency { file_id: resolved, is_resolved: false });
+			}
+			included
 		},
 		|file_id| {
-			let lexemes = fs.fs(db).get(&file_id).map(|&buf| {
-				let lexed = lex(db, file_id, buf);
-				lexed.lexemes(db)
-			});
-
-			IncludedDependencies::push(db, IncludedDependency { file_id, is_resolved: lexemes.is_some() });
-
+			let lexemes = file_lexemes(db, fs, file_id);
+			if arch::embedded_lexemes(db, file_id).is_none() {
+				IncludedDependencies::push(db, IncludedDependency { file_id, is_resolved: lexemes.is_some() });
+			}
 			lexemes
 		},
 	);
@@ -268,8 +583,113 @@ pub fn preprocess(db: &dyn crate::Db, fs: Fs, file_id: FileId) -> Option<Vec<(Fi
 	dbg!(&pp.errors);
 
 	for ((file, location), msg) in pp.errors {
-		Diagnostics::push(db, Diagnostic { file, location, severity: Severity::Error, message: msg });
+		Diagnostics::push(db, Diagnostic::new(file, location, Severity::Error, msg));
+	}
+
+	for definition in pp.macros {
+		MacroDefinitions::push(db, definition);
 	}
 
 	Some(result)
 }
+
+/// Resolves the path of an `#include` in the translation unit rooted at `file_id`.
+pub(crate) fn resolve_include(db: &dyn crate::Db, fs: Fs, file_id: FileId, path: &str) -> FileId {
+	// Return a `FileId` with an absolute path resolved relative to the file being preprocessed. If the path is
+	// already absolute then `resolve_path` will return it as is.
+	let resolved = FileId::new(db, db.resolve_path(file_id, path));
+
+	// The core library or an architecture header that cannot be found is replaced by the embedded one.
+	match embedded_path(path) {
+		Some(embedded) if !fs.fs(db).contains_key(&resolved) => FileId::new(db, embedded.to_string()),
+		_ => resolved,
+	}
+}
+
+/// Returns the path of the embedded file standing in for an included file that cannot be found, if it is the core
+/// library or an architecture header.
+pub(crate) fn embedded_path(path: &str) -> Option<&'static str> {
+	match path.rsplit(['/', '\\']).next()? {
+		"core.p4" => Some(symbols::PRELUDE_PATH),
+		_ => Architecture::from_path(path).map(|architecture| architecture.path()),
+	}
+}
+
+/// Returns the lexemes of a file of the file system, or of an embedded file.
+pub(crate) fn file_lexemes(db: &dyn crate::Db, fs: Fs, file_id: FileId) -> Option<&Vec<(Token, Span)>> {
+	if let Some(lexemes) = arch::embedded_lexemes(db, file_id) {
+		return Some(lexemes);
+	}
+	fs.fs(db).get(&file_id).map(|&buf| lex(db, file_id, buf).lexemes(db))
+}
+
+/// Returns the token at `offset` in a file, ignoring whitespace and comments. Between two tokens, the one starting at
+/// the offset wins over the one ending there.
+pub(crate) fn token_at(db: &dyn crate::Db, fs: Fs, file_id: FileId, offset: usize) -> Option<&(Token, Span)> {
+	let lexemes = file_lexemes(db, fs, file_id)?;
+	let tokens = || lexemes.iter().filter(|(tk, _)| !matches!(tk, Token::Whitespace | Token::Comment));
+	tokens()
+		.find(|(_, span)| span.contains(&offset))
+		.or_else(|| tokens().find(|(_, span)| span.end == offset))
+}
+
+/// Returns the source of a file embedded in the analyzer, an architecture header or the core library, from its URI such
+/// as `builtin:///core.p4`. Clients may also shorten the URI to `builtin:/core.p4`.
+pub fn builtin_source(uri: &str) -> Option<&'static str> {
+	let name = uri.strip_prefix("builtin:")?.trim_start_matches('/');
+	let path = format!("builtin:///{name}");
+	if path == symbols::PRELUDE_PATH {
+		return Some(symbols::PRELUDE);
+	}
+	let architecture = Architecture::ALL.into_iter().find(|architecture| architecture.path() == path)?;
+	Some(architecture.source())
+}
+
+/// Returns the source text of a file of the file system, of an embedded architecture header or of the core library.
+pub(crate) fn file_text(db: &dyn crate::Db, fs: Fs, file_id: FileId) -> Option<&str> {
+	let path = file_id.path(db);
+	builtin_source(&path).or_else(|| fs.fs(db).get(&file_id).map(|&buf| buf.file(db).get_file_content().as_str()))
+}
+
+/// Parses the translation unit rooted at `file_id`.
+#[salsa::tracked(return_ref)]
+pub fn parse(db: &dyn crate::Db, fs: Fs, file_id: FileId) -> Option<Program> {
+	let tokens = preprocess(db, fs, file_id).as_ref()?;
+	Some(parse_tokens(db, tokens))
+}
+
+/// Runs every analysis of the translation unit rooted at `file_id`, so that their diagnostics accumulate here.
+#[salsa::tracked]
+pub fn analyze(db: &dyn crate::Db, fs: Fs, file_id: FileId) {
+	const_eval::constants(db, fs, file_id);
+	arch::model(db, fs, file_id);
+	parser_states::state_graphs(db, fs, file_id);
+	validity::header_validity(db, fs, file_id);
+	initialization::definite_assignment(db, fs, file_id);
+	tables::table_declarations(db, fs, file_id);
+	deparser::emit_order(db, fs, file_id);
+	lints::suppressions(db, fs, file_id);
+	annotations::annotations(db, fs, file_id);
+}
+
+/// Parses a single file, without following its `#include`s.
+#[salsa::tracked(return_ref)]
+pub fn parse_file(db: &dyn crate::Db, file_id: FileId, buf: Buffer) -> Program {
+	let tokens: Vec<_> =
+		lex(db, file_id, buf).lexemes(db).iter().cloned().map(|(tk, span)| (file_id, tk, span)).collect();
+	parse_tokens(db, &tokens)
+}
+
+/// Returns `true` if a single file lexes and parses without errors.
+pub(crate) fn parses(db: &dyn crate::Db, file_id: FileId, buf: Buffer) -> bool {
+	let diagnostics = parse_file::accumulated::<Diagnostics>(db, file_id, buf);
+	diagnostics.iter().all(|diagnostic| diagnostic.severity != Severity::Error)
+}
+
+fn parse_tokens(db: &dyn crate::Db, tokens: &[(FileId, Token, Span)]) -> Program {
+	let (program, diagnostics) = ast::parse(tokens);
+	for diagnostic in diagnostics {
+		Diagnostics::push(db, diagnostic);
+	}
+	program
+}
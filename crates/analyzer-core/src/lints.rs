@@ -0,0 +1,346 @@
+//! The lints of the analyzer.
+//!
+//! A lint is a check reporting code that is valid P4 but likely to be wrong, as opposed to the errors of the language
+//! itself. Each lint has a stable code (e.g. `P4W0012`), a name (e.g. `unused-action`) and a default level, which the
+//! configuration of a workspace can override with a [`LintConfig`].
+//!
+//! Lints can also be allowed in the source code with a comment, which applies to the declaration following it:
+//!
+//! ```p4
+//! // p4analyzer: allow(unused-action)
+//! action unused() { }
+//! ```
+//!
+//! or, with `allow-file`, to the whole file containing it. The lints are named by their names or their codes, separated
+//! by commas.
+
+use std::collections::HashMap;
+
+use crate::{
+	ast::{
+		visit::{self, Visitor},
+		Declaration,
+	},
+	base_abstractions::*,
+	lexer::Token,
+	parse, preprocess, Fs,
+};
+
+/// The level at which a lint is reported.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Level {
+	Allow,
+	Hint,
+	Info,
+	Warning,
+	Error,
+}
+
+impl Level {
+	/// Parses a level as it is written in the configuration, accepting `warn` and `deny` as the levels `warning` and
+	/// `error`.
+	pub fn parse(level: &str) -> Option<Level> {
+		match level {
+			"allow" => Some(Level::Allow),
+			"hint" => Some(Level::Hint),
+			"info" => Some(Level::Info),
+			"warn" | "warning" => Some(Level::Warning),
+			"deny" | "error" => Some(Level::Error),
+			_ => None,
+		}
+	}
+
+	/// Returns the severity of the diagnostics reported at this level, or `None` if they are not reported.
+	pub fn severity(self) -> Option<Severity> {
+		match self {
+			Level::Allow => None,
+			Level::Hint => Some(Severity::Hint),
+			Level::Info => Some(Severity::Info),
+			Level::Warning => Some(Severity::Warning),
+			Level::Error => Some(Severity::Error),
+		}
+	}
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Lint {
+	pub code: &'static str,
+	pub name: &'static str,
+	pub level: Level,
+	/// A short description of what the lint reports.
+	pub description: &'static str,
+}
+
+impl Lint {
+	/// Creates a diagnostic reported by the lint, at its default level.
+	pub fn diagnostic(&'static self, location: &Location, message: String) -> Diagnostic {
+		let Location { file, span } = location.clone();
+		let severity = self.level.severity().unwrap_or(Severity::Hint);
+		Diagnostic { code: Some(self.code), ..Diagnostic::new(file, span, severity, message) }
+	}
+}
+
+pub static UNREACHABLE_STATE: Lint = Lint {
+	code: "P4W0001",
+	name: "unreachable-state",
+	level: Level::Warning,
+	description: "a parser state that cannot be reached from `start`",
+};
+
+pub static NONTERMINATING_STATE: Lint = Lint {
+	code: "P4W0002",
+	name: "nonterminating-state",
+	level: Level::Warning,
+	description: "a parser state from which neither `accept` nor `reject` can be reached",
+};
+
+pub static UNBOUNDED_PARSER_LOOP: Lint = Lint {
+	code: "P4W0003",
+	name: "unbounded-parser-loop",
+	level: Level::Warning,
+	description: "a loop of parser states that neither advances a header stack nor extracts from the packet",
+};
+
+pub static INVALID_HEADER_ACCESS: Lint = Lint {
+	code: "P4W0004",
+	name: "invalid-header-access",
+	level: Level::Warning,
+	description: "an access to a field of a header that may be invalid",
+};
+
+pub static UNINITIALIZED_READ: Lint = Lint {
+	code: "P4W0005",
+	name: "uninitialized-read",
+	level: Level::Warning,
+	description: "a read of a variable or an `out` parameter that may not be initialized",
+};
+
+pub static UNASSIGNED_OUT_PARAMETER: Lint = Lint {
+	code: "P4W0006",
+	name: "unassigned-out-parameter",
+	level: Level::Warning,
+	description: "an `out` parameter that may be left unassigned",
+};
+
+pub static UNEMITTED_HEADER: Lint = Lint {
+	code: "P4W0007",
+	name: "unemitted-header",
+	level: Level::Warning,
+	description: "a header extracted by a parser, but never emitted by the deparser of the same pipeline",
+};
+
+pub static NEVER_VALID_HEADER: Lint = Lint {
+	code: "P4W0008",
+	name: "never-valid-header",
+	level: Level::Warning,
+	description: "a header emitted by a deparser, but never extracted or set valid",
+};
+
+pub static EMIT_ORDER: Lint = Lint {
+	code: "P4W0009",
+	name: "emit-order",
+	level: Level::Warning,
+	description: "a header emitted after a header the parser extracts after it",
+};
+
+pub static UNUSED_VARIABLE: Lint = Lint {
+	code: "P4W0010",
+	name: "unused-variable",
+	level: Level::Warning,
+	description: "a local variable that is never used",
+};
+
+pub static UNUSED_CONSTANT: Lint = Lint {
+	code: "P4W0011",
+	name: "unused-constant",
+	level: Level::Warning,
+	description: "a constant that is never used",
+};
+
+pub static UNUSED_ACTION: Lint = Lint {
+	code: "P4W0012",
+	name: "unused-action",
+	level: Level::Warning,
+	description: "an action that is neither called nor listed by a table",
+};
+
+pub static UNUSED_TABLE: Lint =
+	Lint { code: "P4W0013", name: "unused-table", level: Level::Warning, description: "a table that is never applied" };
+
+pub static UNUSED_FIELD: Lint = Lint {
+	code: "P4W0014",
+	name: "unused-field",
+	level: Level::Hint,
+	description: "a field of a header that is never accessed by name, although it may still be extracted or emitted",
+};
+
+pub static UNUSED_TYPE: Lint = Lint {
+	code: "P4W0015",
+	name: "unused-type",
+	level: Level::Warning,
+	description: "a `typedef` or a `type` that is never used",
+};
+
+pub static UNUSED_INCLUDE: Lint = Lint {
+	code: "P4W0016",
+	name: "unused-include",
+	level: Level::Warning,
+	description: "an `#include` of a file none of whose declarations are used",
+};
+
+pub static MISPLACED_ANNOTATION: Lint = Lint {
+	code: "P4W0017",
+	name: "misplaced-annotation",
+	level: Level::Warning,
+	description: "a well-known annotation on an element it has no effect on",
+};
+
+pub static UNKNOWN_LINT: Lint = Lint {
+	code: "P4W0100",
+	name: "unknown-lint",
+	level: Level::Warning,
+	description: "a lint named in a suppression comment that doesn't exist",
+};
+
+/// All the lints, ordered by code.
+pub static LINTS: &[&Lint] = &[
+	&UNREACHABLE_STATE,
+	&NONTERMINATING_STATE,
+	&UNBOUNDED_PARSER_LOOP,
+	&INVALID_HEADER_ACCESS,
+	&UNINITIALIZED_READ,
+	&UNASSIGNED_OUT_PARAMETER,
+	&UNEMITTED_HEADER,
+	&NEVER_VALID_HEADER,
+	&EMIT_ORDER,
+	&UNUSED_VARIABLE,
+	&UNUSED_CONSTANT,
+	&UNUSED_ACTION,
+	&UNUSED_TABLE,
+	&UNUSED_FIELD,
+	&UNUSED_TYPE,
+	&UNUSED_INCLUDE,
+	&MISPLACED_ANNOTATION,
+	&UNKNOWN_LINT,
+];
+
+/// Returns the lint with the given name or code.
+pub fn lint(name: &str) -> Option<&'static Lint> {
+	LINTS.iter().copied().find(|lint| lint.name == name || lint.code == name)
+}
+
+/// The levels of the lints configured for a workspace, overriding their default levels.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LintConfig {
+	levels: HashMap<&'static str, Level>,
+}
+
+impl LintConfig {
+	/// Sets the level of the lint with the given name or code, returning `false` if there is no such lint.
+	pub fn set(&mut self, name: &str, level: Level) -> bool {
+		let Some(lint) = lint(name) else { return false };
+		self.levels.insert(lint.code, level);
+		true
+	}
+
+	pub fn level(&self, lint: &Lint) -> Level { self.levels.get(lint.code).copied().unwrap_or(lint.level) }
+}
+
+/// A comment allowing some lints in the code following it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Suppression {
+	pub lints: Vec<&'static Lint>,
+	/// The code in which the lints are allowed: the declaration following the comment, or the whole file.
+	pub scope: Location,
+}
+
+impl Suppression {
+	/// Returns `true` if the suppression allows the lint reporting `diagnostic`.
+	pub fn allows(&self, diagnostic: &Diagnostic) -> bool {
+		let (scope, location) = (&self.scope.span, &diagnostic.location);
+		self.scope.file == diagnostic.file
+			&& scope.start <= location.start
+			&& location.end <= scope.end
+			&& self.lints.iter().any(|lint| Some(lint.code) == diagnostic.code)
+	}
+}
+
+/// Finds the comments allowing lints in the translation unit rooted at `file_id`.
+#[salsa::tracked(return_ref)]
+pub fn suppressions(db: &dyn crate::Db, fs: Fs, file_id: FileId) -> Option<Vec<Suppression>> {
+	let tokens = preprocess(db, fs, file_id).as_ref()?;
+	let program = parse(db, fs, file_id).as_ref()?;
+	let mut declarations = Declarations(vec![]);
+	visit::walk_program(&mut declarations, program);
+
+	let files = fs.fs(db);
+	let mut suppressions = vec![];
+	for (file, _, span) in tokens.iter().filter(|(_, token, _)| matches!(token, Token::Comment)) {
+		let Some(buffer) = files.get(file) else { continue };
+		let source = buffer.file(db).get_file_content();
+		let Some((is_file, names)) = source.get(span.clone()).and_then(parse_comment) else { continue };
+
+		let mut lints = vec![];
+		for (name, offset) in names {
+			match lint(name) {
+				Some(lint) => lints.push(lint),
+				None => {
+					let start = span.start + offset;
+					let location = Location::new(*file, start..start + name.len());
+					Diagnostics::push(db, UNKNOWN_LINT.diagnostic(&location, format!("there is no lint `{name}`")));
+				}
+			}
+		}
+
+		let scope = match is_file {
+			true => Some(0..source.len()),
+			// the outermost declaration starting first after the comment
+			false => declarations
+				.0
+				.iter()
+				.filter(|location| location.file == *file && location.span.start >= span.end)
+				.min_by_key(|location| (location.span.start, usize::MAX - location.span.end))
+				.map(|location| location.span.clone()),
+		};
+		if let Some(scope) = scope {
+			suppressions.push(Suppression { lints, scope: Location::new(*file, scope) });
+		}
+	}
+	Some(suppressions)
+}
+
+/// Parses a comment such as `// p4analyzer: allow(unused-action, P4W0001)`, returning whether it applies to the whole
+/// file, and the names of the lints with their offsets in the comment.
+fn parse_comment(comment: &str) -> Option<(bool, Vec<(&str, usize)>)> {
+	let directive = comment.strip_prefix("//")?.trim_start().strip_prefix("p4analyzer:")?.trim();
+	let (is_file, list) = match directive.strip_prefix("allow-file") {
+		Some(list) => (true, list),
+		None => (false, directive.strip_prefix("allow")?),
+	};
+	let list = list.trim_start().strip_prefix('(')?.strip_suffix(')')?;
+
+	let start = list.as_ptr() as usize - comment.as_ptr() as usize;
+	let mut names = vec![];
+	let mut offset = start;
+	for name in list.split(',') {
+		let trimmed = name.trim();
+		if !trimmed.is_empty() {
+			names.push((trimmed, offset + (name.len() - name.trim_start().len())));
+		}
+		offset += name.len() + 1;
+	}
+	Some((is_file, names))
+}
+
+/// Collects the locations of all the declarations of a program, including the states of parsers.
+struct Declarations(Vec<Location>);
+
+impl<'a> Visitor<'a> for Declarations {
+	fn visit_declaration(&mut self, declaration: &'a Declaration) {
+		self.0.push(declaration.location().clone());
+		if let Declaration::Parser(parser) = declaration {
+			self.0.extend(parser.states.iter().map(|state| state.location.clone()));
+		}
+		visit::walk_declaration(self, declaration);
+	}
+}
@@ -0,0 +1,213 @@
+//! Navigation to definitions, declarations, type definitions and implementations.
+//!
+//! P4 has no forward declarations, so the definition and the declaration of a name are usually the same. They only
+//! differ for the functions of an instance's initializer, whose declaration is the abstract method of the extern they
+//! implement. The implementations of a parser or control type are the parsers and controls passed for it to a
+//! package, and those of an abstract method are the functions implementing it in the initializers of the instances.
+//!
+//! Navigation follows names into included files, architecture headers and the core library, from the use of a macro
+//! to its `#define`, and from an `#include` directive to the included file.
+
+use crate::{
+	ast::{
+		visit::{self, Visitor},
+		*,
+	},
+	base_abstractions::*,
+	file_text,
+	lexer::Token,
+	parse,
+	preprocessor::PreprocessorDirective,
+	resolve_include,
+	symbols::{self, SymbolId, SymbolKind, SymbolTable},
+	token_at,
+	types::{self, Type, TypeInfo},
+	Fs,
+};
+
+/// Returns the definition of the name, macro or included file at `offset` in the translation unit rooted at
+/// `file_id`.
+pub fn definition(db: &dyn crate::Db, fs: Fs, file_id: FileId, offset: usize) -> Vec<Location> {
+	let Some((token, _)) = token_at(db, fs, file_id, offset) else { return vec![] };
+	match token {
+		Token::PreprocessorDirective(PreprocessorDirective::Include(_, path)) => {
+			let target = resolve_include(db, fs, file_id, path);
+			match file_text(db, fs, target) {
+				Some(_) => vec![Location { file: target, span: 0..0 }],
+				None => vec![],
+			}
+		}
+		_ => {
+			let Some(table) = symbols::symbols(db, fs, file_id).as_ref() else { return vec![] };
+			symbol_at(db, fs, table, file_id, offset)
+				.map(|id| vec![table.symbol(id).location.clone()])
+				.unwrap_or_default()
+		}
+	}
+}
+
+/// Returns the declaration of the name at `offset` in the translation unit rooted at `file_id`, which is its
+/// definition unless it names a function implementing an abstract method.
+pub fn declaration(db: &dyn crate::Db, fs: Fs, file_id: FileId, offset: usize) -> Vec<Location> {
+	let method = (|| {
+		let table = symbols::symbols(db, fs, file_id).as_ref()?;
+		let info = types::types(db, fs, file_id).as_ref()?;
+		let method = implemented_method(table, info, symbol_at(db, fs, table, file_id, offset)?)?;
+		Some(vec![table.symbol(method).location.clone()])
+	})();
+	method.unwrap_or_else(|| definition(db, fs, file_id, offset))
+}
+
+/// Returns the definition of the type of the name at `offset` in the translation unit rooted at `file_id`, e.g. the
+/// header declaration for the name of a header variable.
+pub fn type_definition(db: &dyn crate::Db, fs: Fs, file_id: FileId, offset: usize) -> Vec<Location> {
+	(|| {
+		let table = symbols::symbols(db, fs, file_id).as_ref()?;
+		let info = types::types(db, fs, file_id).as_ref()?;
+		let id = symbol_at(db, fs, table, file_id, offset)?;
+		let ty = info.symbol_type(id)?;
+		let id = named_type(ty)?;
+		Some(vec![table.symbol(id).location.clone()])
+	})()
+	.unwrap_or_default()
+}
+
+/// Returns the implementations of the parser type, control type or abstract method at `offset` in the translation
+/// unit rooted at `file_id`.
+pub fn implementations(db: &dyn crate::Db, fs: Fs, file_id: FileId, offset: usize) -> Vec<Location> {
+	(|| {
+		let program = parse(db, fs, file_id).as_ref()?;
+		let table = symbols::symbols(db, fs, file_id).as_ref()?;
+		let info = types::types(db, fs, file_id).as_ref()?;
+		let id = symbol_at(db, fs, table, file_id, offset)?;
+
+		let implementations = match table.symbol(id).kind {
+			SymbolKind::ParserType | SymbolKind::ControlType => {
+				let mut finder = Blocks { table, info, block: id, implementations: vec![] };
+				visit::walk_program(&mut finder, program);
+				finder.implementations
+			}
+			SymbolKind::Method => table
+				.symbols()
+				.filter(|(function, _)| implemented_method(table, info, *function) == Some(id))
+				.map(|(function, _)| function)
+				.collect(),
+			_ => vec![],
+		};
+		Some(implementations.into_iter().map(|id| table.symbol(id).location.clone()).collect())
+	})()
+	.unwrap_or_default()
+}
+
+/// Returns the symbol declared or referenced at `offset`, including the members resolved by the type checker and
+/// the macros, whose uses are expanded before names are resolved.
+pub(crate) fn symbol_at(
+	db: &dyn crate::Db,
+	fs: Fs,
+	table: &SymbolTable,
+	file_id: FileId,
+	offset: usize,
+) -> Option<SymbolId> {
+	let (token, span) = token_at(db, fs, file_id, offset)?;
+	if let Token::Identifier(name) = token {
+		if let Some(&id) = table.members(table.macros, name).first() {
+			return Some(id);
+		}
+	}
+	table.symbol_at(file_id, span.start).or_else(|| {
+		let info = types::types(db, fs, file_id).as_ref()?;
+		info.member_at(file_id, span.start)?.target
+	})
+}
+
+/// Returns the abstract method implemented by a function of an instance's initializer.
+fn implemented_method(table: &SymbolTable, info: &TypeInfo, function: SymbolId) -> Option<SymbolId> {
+	let symbol = table.symbol(function);
+	if symbol.kind != SymbolKind::Function {
+		return None;
+	}
+	let instance = table.scope(symbol.scope).owner.filter(|id| table.symbol(*id).kind == SymbolKind::Instance)?;
+	let Some(Type::Extern(extern_id, _)) = info.symbol_type(instance) else { return None };
+	let body = table.symbol(*extern_id).body?;
+	table
+		.members(body, &symbol.name)
+		.iter()
+		.copied()
+		.find(|id| table.symbol(*id).kind == SymbolKind::Method)
+}
+
+/// Returns the declaration of a named type, looking through type values and the elements of header stacks.
+fn named_type(ty: &Type) -> Option<SymbolId> {
+	match ty {
+		Type::Meta(ty) | Type::Stack(ty, _) => named_type(ty),
+		Type::Header(id, _)
+		| Type::HeaderUnion(id, _)
+		| Type::Struct(id, _)
+		| Type::Enum(id)
+		| Type::NewType(id)
+		| Type::Extern(id, _)
+		| Type::Parser(id)
+		| Type::Control(id)
+		| Type::Package(id, _)
+		| Type::Table(id)
+		| Type::TypeParameter(id) => Some(*id),
+		_ => None,
+	}
+}
+
+/// Finds the parsers and controls passed for a parser or control type to the packages instantiated by a program.
+struct Blocks<'a> {
+	table: &'a SymbolTable,
+	info: &'a TypeInfo,
+	block: SymbolId,
+	implementations: Vec<SymbolId>,
+}
+
+impl Blocks<'_> {
+	fn arguments(&mut self, callee: SymbolId, args: &[Argument]) {
+		let table = self.table;
+		let params: Vec<_> = match table.symbol(callee).body {
+			Some(body) => table
+				.scope(body)
+				.symbols
+				.iter()
+				.copied()
+				.filter(|id| matches!(table.symbol(*id).kind, SymbolKind::Parameter(_)))
+				.collect(),
+			None => vec![],
+		};
+		for (i, arg) in args.iter().enumerate() {
+			let param = match &arg.name {
+				Some(name) => params.iter().copied().find(|id| table.symbol(*id).name == name.name),
+				None => params.get(i).copied(),
+			};
+			let Some(param) = param else { continue };
+
+			match (self.info.symbol_type(param), self.info.expression_type(&arg.value.location)) {
+				(Some(Type::Parser(block)), Some(Type::Parser(user)))
+				| (Some(Type::Control(block)), Some(Type::Control(user)))
+					if *block == self.block && !self.implementations.contains(user) =>
+				{
+					self.implementations.push(*user);
+				}
+				(Some(Type::Package(inner, _)), Some(Type::Package(..))) => {
+					if let ExpressionKind::Call(_, _, args) = &arg.value.kind {
+						self.arguments(*inner, args);
+					}
+				}
+				_ => (),
+			}
+		}
+	}
+}
+
+impl<'a> Visitor<'a> for Blocks<'a> {
+	fn visit_declaration(&mut self, declaration: &'a Declaration) {
+		if let Declaration::Instantiation(instance) = declaration {
+			if let Some(Type::Package(package, _)) = self.info.type_ref(&instance.ty.location) {
+				self.arguments(*package, &instance.args);
+			}
+		}
+		visit::walk_declaration(self, declaration);
+	}
+}
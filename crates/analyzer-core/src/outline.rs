@@ -0,0 +1,196 @@
+//! Document outlines and workspace symbol search.
+//!
+//! The outline of a file is a tree of its declarations: parsers contain their states and local declarations,
+//! controls their tables, actions and `apply` block, and headers and structs their fields. It only depends on the
+//! file itself, so that the outlines of the whole workspace can be searched without analyzing every translation unit.
+
+use crate::{ast::*, base_abstractions::*, parse_file, symbols::SymbolKind, Fs};
+
+/// A declaration of the outline of a file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DocumentSymbol {
+	pub name: String,
+	pub kind: SymbolKind,
+	/// The type of a field, constant, variable or instance, as written in the source.
+	pub detail: Option<String>,
+	/// The span of the whole declaration.
+	pub span: Span,
+	/// The span of the declaration's name.
+	pub selection_span: Span,
+	pub children: Vec<DocumentSymbol>,
+}
+
+/// A declaration matching a workspace symbol search.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WorkspaceSymbol {
+	pub name: String,
+	pub kind: SymbolKind,
+	/// The location of the declaration's name.
+	pub location: Location,
+	/// The name of the declaration containing it, if any.
+	pub container: Option<String>,
+}
+
+/// Returns the outline of a single file.
+#[salsa::tracked(return_ref)]
+pub fn document_symbols(db: &dyn crate::Db, file_id: FileId, buf: Buffer) -> Vec<DocumentSymbol> {
+	let text = buf.file(db).get_file_content();
+	let outline = Outline { text };
+	parse_file(db, file_id, buf).declarations.iter().filter_map(|d| outline.declaration(d)).collect()
+}
+
+/// Returns the declarations of the files of the workspace whose names fuzzily match `query`, best matches first.
+pub fn workspace_symbols(db: &dyn crate::Db, fs: Fs, query: &str) -> Vec<WorkspaceSymbol> {
+	let mut matches = vec![];
+	for (&file_id, &buf) in fs.fs(db).iter() {
+		let mut pending: Vec<_> = document_symbols(db, file_id, buf).iter().map(|symbol| (symbol, None)).collect();
+		while let Some((symbol, container)) = pending.pop() {
+			pending.extend(symbol.children.iter().map(|child| (child, Some(&symbol.name))));
+			let Some(score) = fuzzy_score(query, &symbol.name) else { continue };
+			let location = Location { file: file_id, span: symbol.selection_span.clone() };
+			let container = container.cloned();
+			matches.push((
+				score,
+				WorkspaceSymbol { name: symbol.name.clone(), kind: symbol.kind.clone(), location, container },
+			));
+		}
+	}
+	matches.sort_by_cached_key(|(score, symbol)| {
+		(*score, symbol.name.clone(), symbol.location.file.path(db), symbol.location.span.start)
+	});
+	matches.into_iter().map(|(_, symbol)| symbol).collect()
+}
+
+/// Scores how well `candidate` matches `query`, lower being better, or returns `None` if the characters of the query
+/// do not all appear in order in the candidate. The case is ignored, and consecutive characters and matches at the
+/// start of the candidate are preferred.
+pub fn fuzzy_score(query: &str, candidate: &str) -> Option<usize> {
+	let mut score = 0;
+	let mut position = 0;
+	let candidate: Vec<_> = candidate.chars().flat_map(char::to_lowercase).collect();
+	for (i, expected) in query.chars().flat_map(char::to_lowercase).enumerate() {
+		let found = position + candidate[position..].iter().position(|&c| c == expected)?;
+		// the distance to the start counts for the first character, and the gaps between matches for the others
+		score += if i == 0 { found } else { (found - position) * 2 };
+		position = found + 1;
+	}
+	// shorter candidates are closer to the query
+	Some(score * 4 + candidate.len() - position.min(candidate.len()))
+}
+
+struct Outline<'a> {
+	text: &'a str,
+}
+
+impl Outline<'_> {
+	fn declaration(&self, declaration: &Declaration) -> Option<DocumentSymbol> {
+		let span = declaration.location().span.clone();
+		let symbol = match declaration {
+			Declaration::Constant(c) => self.symbol(&c.name, SymbolKind::Constant, span, Some(&c.ty), vec![]),
+			Declaration::Variable(v) => self.symbol(&v.name, SymbolKind::Variable, span, Some(&v.ty), vec![]),
+			Declaration::Instantiation(i) => {
+				let children = i.initializer.iter().filter_map(|d| self.declaration(d)).collect();
+				self.symbol(&i.name, SymbolKind::Instance, span, Some(&i.ty), children)
+			}
+			Declaration::Typedef(t) => {
+				let kind = if t.is_new_type { SymbolKind::Type } else { SymbolKind::Typedef };
+				self.symbol(&t.name, kind, span, Some(&t.ty), vec![])
+			}
+			Declaration::Struct(s) => {
+				let kind = match s.kind {
+					StructKind::Header => SymbolKind::Header,
+					StructKind::HeaderUnion => SymbolKind::HeaderUnion,
+					StructKind::Struct => SymbolKind::Struct,
+				};
+				let fields = s
+					.fields
+					.iter()
+					.map(|f| self.symbol(&f.name, SymbolKind::Field, f.location.span.clone(), Some(&f.ty), vec![]));
+				self.symbol(&s.name, kind, span, None, fields.collect())
+			}
+			Declaration::Enum(e) => {
+				let members = e
+					.members
+					.iter()
+					.map(|m| self.symbol(&m.name, SymbolKind::EnumMember, m.name.location.span.clone(), None, vec![]));
+				self.symbol(&e.name, SymbolKind::Enum, span, None, members.collect())
+			}
+			// `error` and `match_kind` declarations are named after the namespace they add members to
+			Declaration::Error(members) | Declaration::MatchKind(members) => {
+				let (name, kind) = match declaration {
+					Declaration::Error(_) => ("error", SymbolKind::ErrorMember),
+					_ => ("match_kind", SymbolKind::MatchKind),
+				};
+				let members =
+					members.members.iter().map(|m| self.symbol(m, kind.clone(), m.location.span.clone(), None, vec![]));
+				DocumentSymbol {
+					name: name.to_string(),
+					kind: SymbolKind::Enum,
+					detail: None,
+					selection_span: span.start..span.start + name.len(),
+					span,
+					children: members.collect(),
+				}
+			}
+			Declaration::ExternObject(e) => {
+				let methods = e.methods.iter().map(|m| {
+					// constructors are named after the extern
+					let kind = if m.return_type.is_some() { SymbolKind::Method } else { SymbolKind::Function };
+					self.symbol(&m.name, kind, m.location.span.clone(), None, vec![])
+				});
+				self.symbol(&e.name, SymbolKind::ExternObject, span, None, methods.collect())
+			}
+			Declaration::ExternFunction(f) => self.symbol(&f.name, SymbolKind::ExternFunction, span, None, vec![]),
+			Declaration::Parser(p) => {
+				let kind = if p.is_type_declaration { SymbolKind::ParserType } else { SymbolKind::Parser };
+				let locals = p.locals.iter().filter_map(|d| self.declaration(d));
+				let states = p
+					.states
+					.iter()
+					.map(|s| self.symbol(&s.name, SymbolKind::State, s.location.span.clone(), None, vec![]));
+				self.symbol(&p.name, kind, span, None, locals.chain(states).collect())
+			}
+			Declaration::Control(c) => {
+				let kind = if c.is_type_declaration { SymbolKind::ControlType } else { SymbolKind::Control };
+				let mut children: Vec<_> = c.locals.iter().filter_map(|d| self.declaration(d)).collect();
+				if let Some(apply) = &c.apply {
+					// the block starts at its opening brace, right after the `apply` keyword
+					let start = self.text[..apply.location.span.start].trim_end().len().saturating_sub("apply".len());
+					children.push(DocumentSymbol {
+						name: "apply".to_string(),
+						kind: SymbolKind::Method,
+						detail: None,
+						span: start..apply.location.span.end,
+						selection_span: start..start + "apply".len(),
+						children: vec![],
+					});
+				}
+				self.symbol(&c.name, kind, span, None, children)
+			}
+			Declaration::Package(p) => self.symbol(&p.name, SymbolKind::Package, span, None, vec![]),
+			Declaration::Action(a) => self.symbol(&a.name, SymbolKind::Action, span, None, vec![]),
+			Declaration::Function(f) => self.symbol(&f.prototype.name, SymbolKind::Function, span, None, vec![]),
+			Declaration::Table(t) => self.symbol(&t.name, SymbolKind::Table, span, None, vec![]),
+		};
+		// names that could not be parsed are left out
+		Some(symbol).filter(|symbol| !symbol.name.is_empty())
+	}
+
+	fn symbol(
+		&self,
+		name: &Identifier,
+		kind: SymbolKind,
+		span: Span,
+		ty: Option<&TypeRef>,
+		children: Vec<DocumentSymbol>,
+	) -> DocumentSymbol {
+		DocumentSymbol {
+			name: name.name.clone(),
+			kind,
+			detail: ty.and_then(|ty| self.text.get(ty.location.span.clone())).map(str::to_string),
+			span,
+			selection_span: name.location.span.clone(),
+			children,
+		}
+	}
+}
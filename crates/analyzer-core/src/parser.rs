@@ -1,19 +1,19 @@
 use anyhow::{anyhow, Result};
-use parking_lot::{RwLock, RwLockReadGuard};
-use std::{collections::HashMap, rc::Rc};
+use parking_lot::RwLock;
+use std::{collections::HashMap, sync::Arc};
 
 #[derive(Debug, Default)]
 pub struct Parser<Token: Clone> {
-	rules: Rc<HashMap<RuleName, Rule<Token>>>,
+	rules: Arc<HashMap<RuleName, Rule<Token>>>,
 	buffer: RwLock<Vec<Token>>,
 	memo_table: Vec<Column<Token>>,
 }
 
 #[derive(Debug)]
 pub struct Matcher<'a, Token: Clone> {
-	rules: Rc<HashMap<RuleName, Rule<Token>>>,
+	rules: Arc<HashMap<RuleName, Rule<Token>>>,
 	memo_table: &'a mut Vec<Column<Token>>,
-	input: RwLockReadGuard<'a, Vec<Token>>,
+	input: &'a [Token],
 	pos: usize,
 	max_examined_pos: isize,
 }
@@ -38,18 +38,20 @@ struct MemoTableEntry<Token: Clone> {
 
 #[derive(Debug, Clone)]
 struct ExistingMatch<Token: Clone> {
-	cst: Rc<Cst<Token>>,
+	cst: Arc<Cst<Token>>,
 	match_length: usize,
 }
 
 /// The concrete syntax tree type exactly mirrors the structure of the grammar.
+///
+/// Subtrees are shared through [`Arc`], so that memoized matches can be reused and whole trees can be handed
+/// between threads without copying.
 #[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Debug)]
 pub enum Cst<Token: Clone> {
-	Terminal(Rc<Vec<Token>>),
-	// TODO: switch to refcounting to share memoized stuff
-	Choice(RuleName, Rc<Cst<Token>>),
-	Sequence(Vec<Rc<Cst<Token>>>),
-	Repetition(Vec<Rc<Cst<Token>>>),
+	Terminal(Arc<Vec<Token>>),
+	Choice(RuleName, Arc<Cst<Token>>),
+	Sequence(Vec<Arc<Cst<Token>>>),
+	Repetition(Vec<Arc<Cst<Token>>>),
 	Not(RuleName),
 }
 
@@ -80,10 +82,12 @@ impl<Token: Clone + PartialEq> Parser<Token> {
 	}
 
 	pub fn _match(&mut self) -> Option<Cst<Token>> {
+		// the guard stays here rather than in the matcher, so that the matcher itself can be sent across threads
+		let input = self.buffer.read();
 		let mut matcher = Matcher {
 			rules: self.rules.clone(),
 			memo_table: &mut self.memo_table,
-			input: self.buffer.read(),
+			input: &input,
 			pos: 0,
 			max_examined_pos: -1,
 		};
@@ -135,7 +139,7 @@ impl<Token: Clone + PartialEq> Parser<Token> {
 
 impl<'a, Token: Clone + PartialEq> Matcher<'a, Token> {
 	// originally under the (weird?) RuleApplication abstraction
-	fn memoized_eval_rule(&mut self, rule_name: RuleName) -> Option<Rc<Cst<Token>>> {
+	fn memoized_eval_rule(&mut self, rule_name: RuleName) -> Option<Arc<Cst<Token>>> {
 		if let Some(cst) = self.use_memoized_result(rule_name) {
 			Some(cst)
 		} else {
@@ -152,7 +156,7 @@ impl<'a, Token: Clone + PartialEq> Matcher<'a, Token> {
 	}
 
 	// originally a Rule method
-	fn eval_rule(&mut self, rule_name: RuleName) -> Option<Rc<Cst<Token>>> {
+	fn eval_rule(&mut self, rule_name: RuleName) -> Option<Arc<Cst<Token>>> {
 		let rules = self.rules.clone();
 		match &rules[rule_name] {
 			Rule::Terminal(vec) => {
@@ -214,7 +218,7 @@ impl<'a, Token: Clone + PartialEq> Matcher<'a, Token> {
 		}
 	}
 
-	fn memoize_result(&mut self, pos: usize, rule_name: RuleName, cst: Option<Rc<Cst<Token>>>) {
+	fn memoize_result(&mut self, pos: usize, rule_name: RuleName, cst: Option<Arc<Cst<Token>>>) {
 		while self.memo_table.len() <= pos {
 			self.memo_table.push(Default::default());
 		}
@@ -229,7 +233,7 @@ impl<'a, Token: Clone + PartialEq> Matcher<'a, Token> {
 		col.max_examined_length = col.max_examined_length.max(examined_length as isize)
 	}
 
-	fn use_memoized_result(&mut self, rule_name: RuleName) -> Option<Rc<Cst<Token>>> {
+	fn use_memoized_result(&mut self, rule_name: RuleName) -> Option<Arc<Cst<Token>>> {
 		self.memo_table.get(self.pos).and_then(|col| {
 			col.memo.get(rule_name).and_then(|entry| {
 				self.max_examined_pos = self.max_examined_pos.max((self.pos + entry.examined_length - 1) as isize);
@@ -256,7 +260,7 @@ impl<'a, Token: Clone + PartialEq> Matcher<'a, Token> {
 
 #[derive(Debug, Clone)]
 pub enum Rule<Token: Clone> {
-	Terminal(Rc<Vec<Token>>),
+	Terminal(Arc<Vec<Token>>),
 	Choice(Vec<RuleName>),
 	Sequence(Vec<RuleName>),
 	Repetition(RuleName),
@@ -302,6 +306,15 @@ mod test {
 	use super::*;
 	use pretty_assertions::{assert_eq, assert_ne};
 
+	#[test]
+	fn thread_safety() {
+		fn assert_send_sync<T: Send + Sync>() {}
+
+		assert_send_sync::<Parser<char>>();
+		assert_send_sync::<Matcher<char>>();
+		assert_send_sync::<Cst<char>>();
+	}
+
 	#[test]
 	fn terminal() {
 		let matcher =
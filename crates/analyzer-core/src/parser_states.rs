@@ -0,0 +1,401 @@
+//! Analysis of the state machines of parsers.
+//!
+//! The states of a parser and the `transition` statements between them form a graph, which starts at `start` and
+//! ends in the final states `accept` and `reject`. Besides the explicit transitions, a state moves to `reject` when
+//! it has no `transition` statement, when none of the cases of its `select` matches, and when one of its statements
+//! fails, e.g. an `extract` beyond the end of the packet or a `verify`.
+//!
+//! The graph is checked for states that cannot be reached from `start`, transitions to states that don't exist,
+//! states from which neither `accept` nor `reject` can be reached, and loops that are not bounded by advancing a
+//! header stack. The keysets of `select` statements are checked against the types of their selectors.
+
+use std::collections::HashMap;
+
+use crate::{
+	ast::{
+		visit::{self, Visitor},
+		*,
+	},
+	base_abstractions::*,
+	const_eval::{self, Constants},
+	lints, parse,
+	symbols::{self, SymbolId, SymbolTable},
+	types::{self, Type, TypeInfo},
+	Fs,
+};
+
+/// The state graph of a parser.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StateGraph {
+	pub parser: SymbolId,
+	/// The names of the states in the order they are declared, followed by `accept` and `reject`.
+	pub states: Vec<String>,
+	/// The transitions between states, as indices into `states`, including the implicit transitions to `reject`.
+	pub transitions: Vec<(usize, usize)>,
+}
+
+impl StateGraph {
+	/// Returns the index of `accept`.
+	pub fn accept(&self) -> usize { self.states.len() - 2 }
+
+	/// Returns the index of `reject`.
+	pub fn reject(&self) -> usize { self.states.len() - 1 }
+
+	/// Returns the index of the state called `name`.
+	pub fn state(&self, name: &str) -> Option<usize> { self.states.iter().position(|state| state == name) }
+
+	/// Returns the states a transition from `state` leads to.
+	pub fn successors(&self, state: usize) -> impl Iterator<Item = usize> + '_ {
+		self.transitions.iter().filter(move |(from, _)| *from == state).map(|(_, to)| *to)
+	}
+
+	/// Returns, for every state, whether it can be reached from the given states (which are reachable themselves).
+	pub fn reachable(&self, from: &[usize]) -> Vec<bool> { self.search(from, |(from, to)| (*from, *to)) }
+
+	/// Returns, for every state, whether one of the given states can be reached from it.
+	pub fn reaching(&self, to: &[usize]) -> Vec<bool> { self.search(to, |(from, to)| (*to, *from)) }
+
+	fn search(&self, roots: &[usize], edge: impl Fn(&(usize, usize)) -> (usize, usize)) -> Vec<bool> {
+		let mut visited = vec![false; self.states.len()];
+		let mut stack = roots.to_vec();
+		while let Some(state) = stack.pop() {
+			if !std::mem::replace(&mut visited[state], true) {
+				let edges = self.transitions.iter().map(&edge);
+				stack.extend(edges.filter(|(from, _)| *from == state).map(|(_, to)| to));
+			}
+		}
+		visited
+	}
+}
+
+/// Builds and checks the state graphs of the parsers of the translation unit rooted at `file_id`.
+#[salsa::tracked(return_ref)]
+pub fn state_graphs(db: &dyn crate::Db, fs: Fs, file_id: FileId) -> Option<Vec<StateGraph>> {
+	let program = parse(db, fs, file_id).as_ref()?;
+	let table = symbols::symbols(db, fs, file_id).as_ref()?;
+	let info = types::types(db, fs, file_id).as_ref()?;
+	let constants = const_eval::constants(db, fs, file_id).as_ref()?;
+
+	let symbols: HashMap<_, _> = table.symbols().map(|(id, symbol)| (symbol.location.clone(), id)).collect();
+	let mut checker = Checker { table, info, constants, diagnostics: vec![] };
+	let mut graphs = vec![];
+	for declaration in &program.declarations {
+		let Declaration::Parser(parser) = declaration else { continue };
+		if parser.is_type_declaration {
+			continue;
+		}
+		if let Some(id) = symbols.get(&parser.name.location) {
+			graphs.push(checker.parser(*id, parser));
+		}
+	}
+
+	for diagnostic in checker.diagnostics {
+		Diagnostics::push(db, diagnostic);
+	}
+	Some(graphs)
+}
+
+/// What the statements (and the `select`) of a state do.
+struct StateEffects<'a> {
+	table: &'a SymbolTable,
+	info: &'a TypeInfo,
+	/// The state extracts from, or advances, the packet.
+	consumes: bool,
+	/// One of the statements may fail, moving to `reject`.
+	may_reject: bool,
+	/// The header stacks whose `next` element is accessed.
+	advanced_stacks: Vec<String>,
+	/// The header stacks accessed otherwise, through `last`, `lastIndex` or an index.
+	accessed_stacks: Vec<String>,
+}
+
+impl<'a> StateEffects<'a> {
+	fn new(table: &'a SymbolTable, info: &'a TypeInfo) -> Self {
+		StateEffects {
+			table,
+			info,
+			consumes: false,
+			may_reject: false,
+			advanced_stacks: vec![],
+			accessed_stacks: vec![],
+		}
+	}
+
+	fn is_stack(&self, expression: &Expression) -> bool {
+		matches!(self.info.expression_type(&expression.location), Some(Type::Stack(..)))
+	}
+
+	fn call(&mut self, callee: &Expression) {
+		let table = self.table;
+		match self.info.expression_type(&callee.location) {
+			Some(Type::Method(base, method)) => {
+				let Type::Extern(object, _) = base.as_ref() else { return };
+				if table.symbol(*object).name != "packet_in" {
+					return;
+				}
+				match table.symbol(*method).name.as_str() {
+					"extract" | "advance" => (self.consumes, self.may_reject) = (true, true),
+					"lookahead" => self.may_reject = true,
+					_ => (),
+				}
+			}
+			Some(Type::Function(function)) if table.symbol(*function).name == "verify" => self.may_reject = true,
+			_ => (),
+		}
+	}
+}
+
+impl<'a> Visitor<'a> for StateEffects<'a> {
+	fn visit_expression(&mut self, expression: &'a Expression) {
+		match &expression.kind {
+			ExpressionKind::Call(callee, _, _) => self.call(callee),
+			ExpressionKind::Member(base, member) if self.is_stack(base) => {
+				let stacks = match member.name.as_str() {
+					"next" => &mut self.advanced_stacks,
+					_ => &mut self.accessed_stacks,
+				};
+				if let Some(path) = path(base).filter(|path| !stacks.contains(path)) {
+					stacks.push(path);
+				}
+			}
+			ExpressionKind::Index(base, _) if self.is_stack(base) => {
+				if let Some(path) = path(base).filter(|path| !self.accessed_stacks.contains(path)) {
+					self.accessed_stacks.push(path);
+				}
+			}
+			_ => (),
+		}
+		visit::walk_expression(self, expression)
+	}
+}
+
+/// Returns the text of a (possibly qualified) name, such as `hdr.mpls`.
+fn path(expression: &Expression) -> Option<String> {
+	match &expression.kind {
+		ExpressionKind::Name(name) => Some(name.name.clone()),
+		ExpressionKind::Member(base, member) => Some(format!("{}.{}", path(base)?, member.name)),
+		_ => None,
+	}
+}
+
+struct Checker<'a> {
+	table: &'a SymbolTable,
+	info: &'a TypeInfo,
+	constants: &'a Constants,
+	diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> Checker<'a> {
+	fn parser(&mut self, id: SymbolId, parser: &'a Parser) -> StateGraph {
+		let mut states: Vec<_> = parser.states.iter().map(|state| state.name.name.clone()).collect();
+		states.extend(["accept".to_string(), "reject".to_string()]);
+		let mut graph = StateGraph { parser: id, states, transitions: vec![] };
+		let reject = graph.reject();
+
+		let mut effects = vec![];
+		for (index, state) in parser.states.iter().enumerate() {
+			let mut state_effects = StateEffects::new(self.table, self.info);
+			for statement in &state.statements {
+				state_effects.visit_statement(statement);
+			}
+
+			let mut targets = vec![];
+			match state.transition.as_ref().map(|transition| &transition.kind) {
+				None => targets.push(reject),
+				Some(TransitionKind::Direct(target)) => targets.extend(self.target(&graph, parser, target)),
+				Some(TransitionKind::Select(selectors, cases)) => {
+					for expression in selectors.iter().chain(cases.iter().flat_map(|case| &case.keyset)) {
+						state_effects.visit_expression(expression);
+					}
+					for case in cases {
+						self.keyset(selectors, case);
+						targets.extend(self.target(&graph, parser, &case.state));
+					}
+					// a `select` without a default case rejects the packets matching none of its cases
+					if !cases.iter().any(|case| !case.keyset.is_empty() && case.keyset.iter().all(is_wildcard)) {
+						targets.push(reject);
+					}
+				}
+			}
+			if state_effects.may_reject {
+				targets.push(reject);
+			}
+			for target in targets {
+				if !graph.transitions.contains(&(index, target)) {
+					graph.transitions.push((index, target));
+				}
+			}
+			effects.push(state_effects);
+		}
+
+		let Some(start) = graph.state("start") else {
+			let message = format!("the parser `{}` has no `start` state", parser.name.name);
+			self.diagnostic(&parser.name.location, Severity::Error, message);
+			return graph;
+		};
+
+		let reachable = graph.reachable(&[start]);
+		let terminating = graph.reaching(&[graph.accept(), reject]);
+		for (index, state) in parser.states.iter().enumerate() {
+			if !reachable[index] {
+				let message = format!("the state `{}` is unreachable from `start`", state.name.name);
+				let diagnostic = lints::UNREACHABLE_STATE.diagnostic(&state.location, message);
+				self.diagnostics.push(diagnostic.with_tag(DiagnosticTag::Unnecessary));
+			} else if !terminating[index] {
+				let message = format!("the state `{}` can never reach `accept` or `reject`", state.name.name);
+				self.diagnostics.push(lints::NONTERMINATING_STATE.diagnostic(&state.location, message));
+			}
+		}
+
+		self.loops(&graph, parser, &effects, &reachable, &terminating);
+		graph
+	}
+
+	/// Resolves the target of a transition, reporting the states that don't exist.
+	fn target(&mut self, graph: &StateGraph, parser: &Parser, target: &Identifier) -> Option<usize> {
+		let index = graph.state(&target.name);
+		if index.is_none() {
+			let message = format!("the parser `{}` has no state `{}`", parser.name.name, target.name);
+			self.diagnostic(&target.location, Severity::Error, message);
+		}
+		index
+	}
+
+	/// Reports the loops of reachable states which are not bounded by the size of a header stack.
+	fn loops(
+		&mut self,
+		graph: &StateGraph,
+		parser: &Parser,
+		effects: &[StateEffects],
+		reachable: &[bool],
+		terminating: &[bool],
+	) {
+		let count = parser.states.len();
+		let reachable_from: Vec<_> = (0..count).map(|index| graph.reachable(&[index])).collect();
+		let mut visited = vec![false; count];
+		for index in 0..count {
+			if visited[index] || !reachable[index] {
+				continue;
+			}
+			// the strongly connected component of the state
+			let component: Vec<_> = (index..count)
+				.filter(|other| reachable_from[index][*other] && reachable_from[*other][index])
+				.collect();
+			for other in &component {
+				visited[*other] = true;
+			}
+			let is_loop = component.len() > 1 || graph.transitions.contains(&(index, index));
+			// the states that never terminate have been reported already
+			if !is_loop || component.iter().any(|state| !terminating[*state]) {
+				continue;
+			}
+
+			let effects: Vec<_> = component.iter().map(|state| &effects[*state]).collect();
+			if effects.iter().any(|effects| !effects.advanced_stacks.is_empty()) {
+				continue;
+			}
+			let names = state_list(component.iter().map(|state| parser.states[*state].name.name.as_str()));
+			let message = match effects.iter().flat_map(|effects| &effects.accessed_stacks).next() {
+				Some(stack) => format!(
+					"the loop through {names} accesses the header stack `{stack}` without extracting into \
+					 `{stack}.next`, so it is not bounded by the size of the stack"
+				),
+				None if !effects.iter().any(|effects| effects.consumes) => {
+					format!("the loop through {names} never extracts from the packet, so it may never end")
+				}
+				None => continue,
+			};
+			self.diagnostics
+				.push(lints::UNBOUNDED_PARSER_LOOP.diagnostic(&parser.states[index].location, message));
+		}
+	}
+
+	/// Checks that a keyset has as many elements as there are selectors, and that they are as wide as the selectors.
+	fn keyset(&mut self, selectors: &[Expression], case: &SelectCase) {
+		if let [key] = case.keyset.as_slice() {
+			if is_wildcard(key) {
+				return;
+			}
+		}
+		// the parser reports empty keysets
+		let (Some(first), Some(last)) = (case.keyset.first(), case.keyset.last()) else { return };
+		if case.keyset.len() != selectors.len() {
+			let location =
+				Location { file: first.location.file, span: first.location.span.start..last.location.span.end };
+			let message = format!(
+				"expected a keyset of {} values to match the selector, found {}",
+				selectors.len(),
+				case.keyset.len()
+			);
+			return self.diagnostic(&location, Severity::Error, message);
+		}
+
+		for (selector, key) in selectors.iter().zip(&case.keyset) {
+			if let Some(selector_type @ (Type::Bit(_) | Type::Int(_))) = self.info.expression_type(&selector.location) {
+				self.key(selector_type, key);
+			}
+		}
+	}
+
+	fn key(&mut self, selector_type: &Type, key: &Expression) {
+		let ExpressionKind::Binary(BinaryOp::Mask | BinaryOp::Range, left, right) = &key.kind else {
+			return self.key_value(selector_type, key);
+		};
+		self.key(selector_type, left);
+		self.key(selector_type, right);
+	}
+
+	fn key_value(&mut self, selector_type: &Type, key: &Expression) {
+		let table = self.table;
+		match self.info.expression_type(&key.location) {
+			Some(key_type @ (Type::Bit(width) | Type::Int(width))) if Some(*width) != selector_type.width() => {
+				let message = format!(
+					"expected a keyset value of type `{}` to match the width of the selector, found `{}`",
+					selector_type.display(table),
+					key_type.display(table)
+				);
+				self.diagnostic(&key.location, Severity::Error, message);
+			}
+			Some(Type::InfInt) => {
+				let Some(value) = self.constants.value(&key.location).and_then(|value| value.to_i128()) else { return };
+				if !fits(value, selector_type) {
+					let message = format!(
+						"the value {value} doesn't fit in the selector's type `{}`",
+						selector_type.display(table)
+					);
+					self.diagnostic(&key.location, Severity::Error, message);
+				}
+			}
+			_ => (),
+		}
+	}
+
+	fn diagnostic(&mut self, location: &Location, severity: Severity, message: String) {
+		let Location { file, span } = location.clone();
+		self.diagnostics.push(Diagnostic::new(file, span, severity, message));
+	}
+}
+
+/// Returns `true` for the keyset expressions matching any value, `default` and `_`.
+pub(crate) fn is_wildcard(key: &Expression) -> bool {
+	matches!(key.kind, ExpressionKind::Default | ExpressionKind::DontCare)
+}
+
+/// Returns `true` if `value` can be represented in the fixed-width integer type `ty`.
+pub(crate) fn fits(value: i128, ty: &Type) -> bool {
+	match *ty {
+		Type::Bit(width) if width < 127 => (0..1 << width).contains(&value),
+		Type::Int(width) if (1..128).contains(&width) => (-(1 << (width - 1))..1 << (width - 1)).contains(&value),
+		_ => true,
+	}
+}
+
+/// Lists the names of states, e.g. "the states `a` and `b`".
+fn state_list<'a>(names: impl Iterator<Item = &'a str>) -> String {
+	let names: Vec<_> = names.map(|name| format!("`{name}`")).collect();
+	match names.as_slice() {
+		[name] => format!("the state {name}"),
+		[init @ .., last] => format!("the states {} and {last}", init.join(", ")),
+		[] => String::new(),
+	}
+}
@@ -240,6 +240,8 @@ pub type ResolvedToken = (FileId, PreprocessorDirective, Span);
 pub struct PreprocessorState<'a> {
 	definitions: HashMap<String, String>,
 	pub errors: Vec<((FileId, Span), String)>, // TODO: we should do better than strings here
+	/// The macros defined in the active regions of the input, in order of definition.
+	pub macros: Vec<MacroDefinition>,
 	state: HashMap<FileId, VertexState>,
 	/// A stack of branches for conditional compilation.
 	/// This will contain the opening #if's and #ifdef's, popped when reaching #endif.
@@ -264,6 +266,7 @@ impl<'a> PreprocessorState<'a> {
 		PreprocessorState {
 			definitions: Default::default(),
 			errors: vec![],
+			macros: vec![],
 			state: Default::default(),
 			conditional_stack: vec![],
 			lex: Box::new(lex),
@@ -276,13 +279,21 @@ impl<'a> PreprocessorState<'a> {
 
 	pub fn preprocess(&mut self, input: &mut VecDeque<(FileId, Token, Span)>) -> Vec<(FileId, Token, Span)> {
 		let mut result: Vec<(FileId, Token, Span)> = vec![];
-		let mut previous_file = None;
+		// the files whose tokens are being processed, each included by the previous one
+		let mut open_files: Vec<FileId> = vec![];
 
 		while let Some((id, tk, span)) = input.pop_front() {
-			if let Some(file) = previous_file {
-				if file != id {
-					self.state.insert(file, VertexState::Closed);
+			// the tokens of an included file are followed by the rest of the file including it
+			while let Some(&file) = open_files.last() {
+				if file == id {
+					break;
 				}
+				self.state.insert(file, VertexState::Closed);
+				open_files.pop();
+			}
+			if open_files.is_empty() {
+				self.state.insert(id, VertexState::Open);
+				open_files.push(id);
 			}
 
 			match tk {
@@ -291,14 +302,18 @@ impl<'a> PreprocessorState<'a> {
 						let recursive_err = format!("Recursive import of {path}");
 						let not_found_err = format!("Could not find {path}");
 
+						// a file included again once closed is processed again, and left to its include guard
 						let file_id = (self.to_id)(&path); // TODO: path resolution here or in to_id
-						if self.state.get(&file_id).is_some() {
+						if self.state.get(&file_id) == Some(&VertexState::Open) {
 							self.error(id, span, recursive_err);
 						} else if let Some(tokens) = (self.lex)(file_id) as Option<&Vec<(Token, Span)>> {
 							self.state.insert(file_id, VertexState::Open);
+							open_files.push(file_id);
 							input.reserve(input.len() + tokens.len());
+							// pushed in reverse so that the included tokens come out in order
 							tokens
 								.iter()
+								.rev()
 								.cloned()
 								.map(|(tk, span)| (file_id, tk, span))
 								.for_each(|x| input.push_front(x));
@@ -349,6 +364,8 @@ impl<'a> PreprocessorState<'a> {
 						}
 					}
 					PreprocessorDirective::Define(k, rhs) => {
+						let location = Location::new(id, span.clone());
+						self.macros.push(MacroDefinition { name: k.clone(), value: rhs.clone(), location });
 						self.definitions.insert(k, rhs);
 					}
 					PreprocessorDirective::Undef(k) => {
@@ -370,8 +387,6 @@ impl<'a> PreprocessorState<'a> {
 				},
 				_ => result.push((id, tk, span)),
 			}
-
-			previous_file = Some(id);
 		}
 
 		self.check_empty_cond_stack();
@@ -741,6 +756,47 @@ mod test {
 		);
 	}
 
+	#[test]
+	fn includes_files_again_once_closed() {
+		let db = Database::new(|base, _| Ok(base.into()));
+		let lexemes = |path: &str, source: &str| {
+			let file_id = FileId::new(&db, path.into());
+			(file_id, lex(&db, file_id, Buffer::from_string(&db, &source.to_string())).lexemes(&db))
+		};
+		let files = [
+			lexemes("actions.p4", "a"),
+			lexemes("guarded.p4", "#ifndef GUARDED\n#define GUARDED\ng\n#endif\n"),
+			lexemes("recursive.p4", "#include \"recursive.p4\"\n"),
+		];
+		let preprocess = |source: &str| {
+			let mut pp = PreprocessorState::new(
+				|path| FileId::new(&db, path.into()),
+				|file_id| files.iter().find(|(id, _)| *id == file_id).map(|(_, lexemes)| *lexemes),
+			);
+			let (main, lexemes) = lexemes("main.p4", source);
+			let mut input = lexemes.iter().cloned().map(|(tk, span)| (main, tk, span)).collect();
+			let tokens: Vec<_> = pp
+				.preprocess(&mut input)
+				.into_iter()
+				.filter_map(|(_, tk, _)| match tk {
+					Token::Identifier(name) => Some(name),
+					_ => None,
+				})
+				.collect();
+			(tokens, pp.errors.into_iter().map(|(_, msg)| msg).collect::<Vec<_>>())
+		};
+
+		// a file without an include guard is included as many times as it is named
+		let (tokens, errors) = preprocess("#include \"actions.p4\"\n#include \"actions.p4\"\n");
+		assert_eq!((tokens, errors), (vec!["a".to_string(), "a".to_string()], vec![]));
+
+		let (tokens, errors) = preprocess("#include \"guarded.p4\"\n#include \"guarded.p4\"\n");
+		assert_eq!((tokens, errors), (vec!["g".to_string()], vec![]));
+
+		let (tokens, errors) = preprocess("#include \"recursive.p4\"\n");
+		assert_eq!((tokens, errors), (vec![], vec!["Recursive import of recursive.p4".to_string()]));
+	}
+
 	#[test]
 	fn defines() {
 		test_pp!(
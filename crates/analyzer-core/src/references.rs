@@ -0,0 +1,173 @@
+//! References and highlights.
+//!
+//! The occurrences of a symbol are its declaration, the names resolved to it by name resolution and by the type
+//! checker, and, for a macro, the identifiers naming it in the preprocessed program. Each occurrence records whether
+//! it reads or writes the symbol: the left-hand side of an assignment and the arguments of `out` and `inout`
+//! parameters are writes.
+//!
+//! Symbols are identified across translation units by the location of their name, so that the references to a
+//! declaration of a header can be searched in every program including it. The code of inactive preprocessor regions
+//! is never resolved, so the identifiers there can only be matched by name.
+
+use std::collections::HashSet;
+
+use crate::{
+	ast::{
+		visit::{self, Visitor},
+		*,
+	},
+	base_abstractions::*,
+	file_lexemes,
+	lexer::Token,
+	parse, preprocess,
+	symbols::{self, SymbolKind, SymbolTable},
+	types::{self, Type, TypeInfo},
+	Fs,
+};
+
+/// How an occurrence of a symbol accesses it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Access {
+	/// The declaration of the symbol.
+	Declaration,
+	Read,
+	Write,
+	/// An identifier named like the symbol in an inactive preprocessor region, which may or may not refer to it.
+	Inactive,
+}
+
+/// An occurrence of a symbol.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Occurrence {
+	pub location: Location,
+	pub access: Access,
+}
+
+/// Returns the occurrences of the symbol whose name is at `target` in the translation unit rooted at `file_id`, in
+/// order of appearance, optionally including the identifiers of the inactive preprocessor regions named like it.
+pub fn occurrences(db: &dyn crate::Db, fs: Fs, file_id: FileId, target: &Location, inactive: bool) -> Vec<Occurrence> {
+	let (Some(program), Some(table), Some(info)) = (
+		parse(db, fs, file_id).as_ref(),
+		symbols::symbols(db, fs, file_id).as_ref(),
+		types::types(db, fs, file_id).as_ref(),
+	) else {
+		return vec![];
+	};
+	let Some((id, symbol)) = table.symbols().find(|(_, symbol)| symbol.location == *target) else { return vec![] };
+
+	let mut occurrences = vec![Occurrence { location: symbol.location.clone(), access: Access::Declaration }];
+	let uses = match symbol.kind {
+		// macros are expanded before names are resolved, so their uses are only known by name
+		SymbolKind::Macro => identifiers(db, fs, file_id, &symbol.name, true),
+		_ => table
+			.references_to(id)
+			.chain(info.member_references().iter().filter(|r| r.target == Some(id)))
+			.map(|r| r.location.clone())
+			.collect(),
+	};
+	let mut writes = Writes { table, info, locations: HashSet::new() };
+	visit::walk_program(&mut writes, program);
+	occurrences.extend(uses.into_iter().map(|location| {
+		let access = if writes.locations.contains(&location) { Access::Write } else { Access::Read };
+		Occurrence { location, access }
+	}));
+
+	if inactive {
+		let inactive = identifiers(db, fs, file_id, &symbol.name, false);
+		occurrences.extend(inactive.into_iter().map(|location| Occurrence { location, access: Access::Inactive }));
+	}
+	occurrences
+}
+
+/// Returns the location of the name of the symbol declared or referenced at `offset` in the translation unit rooted
+/// at `file_id`, which identifies it in the other translation units including its declaration.
+pub fn target(db: &dyn crate::Db, fs: Fs, file_id: FileId, offset: usize) -> Option<Location> {
+	let table = symbols::symbols(db, fs, file_id).as_ref()?;
+	let id = crate::navigation::symbol_at(db, fs, table, file_id, offset)?;
+	Some(table.symbol(id).location.clone())
+}
+
+/// Returns the locations of the identifiers named `name` in the active regions of the translation unit rooted at
+/// `file_id`, or in its inactive regions.
+fn identifiers(db: &dyn crate::Db, fs: Fs, file_id: FileId, name: &str, active: bool) -> Vec<Location> {
+	let Some(tokens) = preprocess(db, fs, file_id).as_ref() else { return vec![] };
+	let named = |token: &Token| matches!(token, Token::Identifier(identifier) if identifier == name);
+	if active {
+		return tokens
+			.iter()
+			.filter(|(_, token, _)| named(token))
+			.map(|(file, _, span)| Location { file: *file, span: span.clone() })
+			.collect();
+	}
+
+	// the tokens of the files of the translation unit which did not make it through the preprocessor
+	let preprocessed: HashSet<_> = tokens.iter().map(|(file, _, span)| (*file, span.start)).collect();
+	let mut files = vec![file_id];
+	for (file, ..) in tokens {
+		if !files.contains(file) {
+			files.push(*file);
+		}
+	}
+	let mut locations = vec![];
+	for file in files {
+		let Some(lexemes) = file_lexemes(db, fs, file) else { continue };
+		locations.extend(
+			lexemes
+				.iter()
+				.filter(|(token, span)| named(token) && !preprocessed.contains(&(file, span.start)))
+				.map(|(_, span)| Location { file, span: span.clone() }),
+		);
+	}
+	locations
+}
+
+/// Collects the locations of the names written by assignments and by the arguments of `out` and `inout` parameters.
+struct Writes<'a> {
+	table: &'a SymbolTable,
+	info: &'a TypeInfo,
+	locations: HashSet<Location>,
+}
+
+impl Writes<'_> {
+	/// Records the name written by an assignment to `target`: the last member of a member access, or the name of
+	/// the header stack or bit string an element or slice of which is written.
+	fn write(&mut self, target: &Expression) {
+		match &target.kind {
+			ExpressionKind::Name(name) | ExpressionKind::Member(_, name) => {
+				self.locations.insert(name.location.clone());
+			}
+			ExpressionKind::Index(base, _) | ExpressionKind::Slice(base, ..) => self.write(base),
+			_ => (),
+		}
+	}
+}
+
+impl<'a> Visitor<'a> for Writes<'a> {
+	fn visit_statement(&mut self, statement: &'a Statement) {
+		if let StatementKind::Assignment(target, _) = &statement.kind {
+			self.write(target);
+		}
+		visit::walk_statement(self, statement);
+	}
+
+	fn visit_expression(&mut self, expression: &'a Expression) {
+		if let ExpressionKind::Call(callee, _, args) = &expression.kind {
+			let params: Vec<_> = match self.info.expression_type(&callee.location) {
+				Some(Type::Method(_, id) | Type::Function(id) | Type::Apply(id)) => {
+					self.table.parameters(*id).collect()
+				}
+				_ => vec![],
+			};
+			for (position, arg) in args.iter().enumerate() {
+				let param = match &arg.name {
+					Some(name) => params.iter().find(|param| param.name == name.name),
+					None => params.get(position),
+				};
+				if let Some(SymbolKind::Parameter(Some(Direction::Out | Direction::InOut))) = param.map(|p| &p.kind) {
+					self.write(&arg.value);
+				}
+			}
+		}
+		visit::walk_expression(self, expression);
+	}
+}
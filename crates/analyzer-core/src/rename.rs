@@ -0,0 +1,93 @@
+//! Renaming.
+//!
+//! A symbol is renamed by replacing its declaration and every reference to it across the workspace (see
+//! [`crate::references`]). Renames which cannot be carried out by editing these names are refused: those of the
+//! symbols declared in the core library or in an architecture header, which are read-only, and those of the macros
+//! and of the names used in the body of a macro, whose uses are only known by name.
+
+use logos::Logos;
+
+use crate::{
+	arch::Architecture,
+	base_abstractions::*,
+	file_text,
+	lexer::{Lextras, Token},
+	navigation,
+	symbols::{self, SymbolKind, PRELUDE_PATH},
+	Fs,
+};
+
+/// Returns the span of the name to rename at `offset` in the translation unit rooted at `file_id`, or the reason why
+/// it cannot be renamed.
+pub fn prepare_rename(db: &dyn crate::Db, fs: Fs, file_id: FileId, offset: usize) -> Result<Span, String> {
+	let table = symbols::symbols(db, fs, file_id).as_ref().ok_or("the file cannot be analyzed")?;
+	let Some(id) = navigation::symbol_at(db, fs, table, file_id, offset) else {
+		return Err("there is no symbol to rename here".to_string());
+	};
+	let symbol = table.symbol(id);
+	let name = &symbol.name;
+
+	let path = symbol.location.file.path(db);
+	if path == PRELUDE_PATH {
+		return Err(format!("`{name}` is declared in the core library, which cannot be edited"));
+	}
+	if let Some(architecture) = Architecture::from_path(&path) {
+		return Err(format!("`{name}` is declared in the read-only architecture header `{}`", architecture.header()));
+	}
+	if symbol.kind == SymbolKind::Macro {
+		return Err(format!("the macro `{name}` cannot be renamed, since its uses are only known by name"));
+	}
+	let in_macro = table.symbols().filter(|(_, symbol)| symbol.kind == SymbolKind::Macro).find(|(id, _)| {
+		let Some(definition) = table.macro_definition(*id) else { return false };
+		let file_id = definition.location.file;
+		let mut tokens = Token::lexer_with_extras(&definition.value, Lextras { db: None, file_id });
+		tokens.any(|token| matches!(&token, Token::Identifier(identifier) if identifier == name))
+	});
+	if let Some((_, definition)) = in_macro {
+		return Err(format!(
+			"`{name}` is used in the expansion of the macro `{}`, which cannot be renamed",
+			definition.name
+		));
+	}
+
+	let (_, span) = crate::token_at(db, fs, file_id, offset).ok_or("there is no symbol to rename here")?;
+	Ok(span.clone())
+}
+
+/// Checks that `new_name` is a valid name for the symbol at `offset` in the translation unit rooted at `file_id`,
+/// which is not already declared in the same scope.
+pub fn check_new_name(
+	db: &dyn crate::Db,
+	fs: Fs,
+	file_id: FileId,
+	offset: usize,
+	new_name: &str,
+) -> Result<(), String> {
+	let mut tokens = Token::lexer_with_extras(new_name, Lextras { db: None, file_id });
+	let (Some(Token::Identifier(_)), None) = (tokens.next(), tokens.next()) else {
+		return Err(format!("`{new_name}` is not a valid name"));
+	};
+
+	let table = symbols::symbols(db, fs, file_id).as_ref().ok_or("the file cannot be analyzed")?;
+	let id = navigation::symbol_at(db, fs, table, file_id, offset).ok_or("there is no symbol to rename here")?;
+	let symbol = table.symbol(id);
+	if symbol.name != new_name && !table.members(symbol.scope, new_name).is_empty() {
+		return Err(format!("`{new_name}` is already declared in the scope of `{}`", symbol.name));
+	}
+	Ok(())
+}
+
+/// Checks that every occurrence of a symbol named `name` can be edited, i.e. that it is in a file of the workspace
+/// and spells out the name rather than coming from a macro expansion.
+pub fn check_occurrences(db: &dyn crate::Db, fs: Fs, name: &str, locations: &[Location]) -> Result<(), String> {
+	for location in locations {
+		if !fs.fs(db).contains_key(&location.file) {
+			return Err(format!("`{name}` is used in `{}`, which cannot be edited", location.file.path(db)));
+		}
+		let text = file_text(db, fs, location.file).and_then(|text| text.get(location.span.clone()));
+		if text != Some(name) {
+			return Err(format!("a use of `{name}` comes from a macro expansion, which cannot be renamed"));
+		}
+	}
+	Ok(())
+}
@@ -0,0 +1,204 @@
+//! Selection ranges.
+//!
+//! Expanding the selection at a position walks outward from the lexeme there through the nodes of the syntax tree
+//! containing it: a member expression, the call it is an argument of, the statement, the enclosing block, and so on
+//! up to the top-level declaration. While the file does not parse, it walks outward through the pairs of brackets
+//! around the position instead, selecting the contents of each pair before the pair itself.
+
+use crate::{
+	ast::{visit::*, *},
+	base_abstractions::*,
+	lex,
+	lexer::Token,
+	parse_file, parses,
+};
+
+/// Returns the spans to select in turn when expanding the selection at `offset` of a single file, from the innermost
+/// out, each containing the previous one.
+pub fn selection_ranges(db: &dyn crate::Db, file_id: FileId, buf: Buffer, offset: usize) -> Vec<Span> {
+	let text = buf.file(db).get_file_content();
+	let lexemes: Vec<_> = lex(db, file_id, buf)
+		.lexemes(db)
+		.iter()
+		.filter(|(token, _)| *token != Token::Whitespace)
+		.map(|(token, span)| (token, span.start..span.start + text[span.clone()].trim_end_matches('\n').len()))
+		.collect();
+
+	// the lexeme at the offset, or the one ending there
+	let lexeme = lexemes.iter().find(|(_, span)| span.contains(&offset)).or_else(|| {
+		lexemes
+			.iter()
+			.find(|(token, span)| span.end == offset && !matches!(token, Token::Comment | Token::Semicolon))
+	});
+	let mut enclosing = Enclosing { offset, spans: lexeme.iter().map(|(_, span)| span.clone()).collect() };
+
+	if parses(db, file_id, buf) {
+		walk_program(&mut enclosing, parse_file(db, file_id, buf));
+	} else {
+		enclosing.brackets(&lexemes);
+	}
+
+	let mut spans = enclosing.spans;
+	spans.sort_by_key(|span| (span.len(), span.start));
+	let mut selections: Vec<Span> = vec![];
+	for span in spans {
+		let contains_last = selections
+			.last()
+			.map_or(true, |last| span.start <= last.start && last.end <= span.end && span.len() > last.len());
+		if contains_last {
+			selections.push(span);
+		}
+	}
+	selections
+}
+
+struct Enclosing {
+	offset: usize,
+	/// The spans containing the offset, in no particular order.
+	spans: Vec<Span>,
+}
+
+impl Enclosing {
+	fn add(&mut self, location: &Location) {
+		if location.span.start <= self.offset && self.offset <= location.span.end {
+			self.spans.push(location.span.clone());
+		}
+	}
+
+	/// Adds the contents and the whole of the pairs of brackets containing the offset, found in `lexemes`.
+	fn brackets(&mut self, lexemes: &[(&Token, Span)]) {
+		let mut open = vec![];
+		for (i, (token, span)) in lexemes.iter().enumerate() {
+			match token {
+				Token::OpenBrace | Token::OpenParen | Token::OpenBracket => open.push(i),
+				Token::CloseBrace | Token::CloseParen | Token::CloseBracket => {
+					// the brackets left open inside a pair are errors
+					let opening = match token {
+						Token::CloseBrace => Token::OpenBrace,
+						Token::CloseParen => Token::OpenParen,
+						_ => Token::OpenBracket,
+					};
+					let Some(position) = open.iter().rposition(|start| *lexemes[*start].0 == opening) else { continue };
+					let start = open[position];
+					open.truncate(position);
+					let start_span = &lexemes[start].1;
+					if start_span.end <= self.offset && self.offset <= span.start && start + 1 < i {
+						self.spans.push(lexemes[start + 1].1.start..lexemes[i - 1].1.end);
+					}
+					if start_span.start <= self.offset && self.offset <= span.end {
+						self.spans.push(start_span.start..span.end);
+					}
+				}
+				_ => (),
+			}
+		}
+	}
+}
+
+impl<'a> Visitor<'a> for Enclosing {
+	fn visit_declaration(&mut self, declaration: &'a Declaration) {
+		self.add(declaration.location());
+		if let Some(name) = declaration.name() {
+			self.add(&name.location);
+		}
+		for annotation in declaration.annotations() {
+			self.add(&annotation.location);
+		}
+		match declaration {
+			Declaration::Struct(s) => {
+				for field in &s.fields {
+					self.add(&field.location);
+					self.add(&field.name.location);
+				}
+			}
+			Declaration::ExternObject(extern_object) => {
+				for method in &extern_object.methods {
+					self.add(&method.location);
+					self.add(&method.name.location);
+				}
+			}
+			Declaration::Parser(parser) => {
+				for state in &parser.states {
+					self.add(&state.location);
+					self.add(&state.name.location);
+					let Some(transition) = &state.transition else { continue };
+					self.add(&transition.location);
+					match &transition.kind {
+						TransitionKind::Direct(target) => self.add(&target.location),
+						TransitionKind::Select(_, cases) => {
+							for case in cases {
+								self.add(&case.location);
+								self.add(&case.state.location);
+							}
+						}
+					}
+				}
+			}
+			Declaration::Control(control) => {
+				if let Some(apply) = &control.apply {
+					self.add(&apply.location);
+				}
+			}
+			Declaration::Table(table) => {
+				for property in &table.properties {
+					self.add(property.location());
+					match property {
+						TableProperty::Key(elements, _) => {
+							for element in elements {
+								self.add(&element.location);
+								self.add(&element.match_kind.location);
+							}
+						}
+						TableProperty::Actions(actions, _) => {
+							for action in actions {
+								self.add(&action.location);
+								self.add(&action.name.location);
+							}
+						}
+						TableProperty::DefaultAction { action, .. } => self.add(&action.location),
+						TableProperty::Entries { entries, .. } => {
+							for entry in entries {
+								self.add(&entry.location);
+								self.add(&entry.action.location);
+							}
+						}
+						TableProperty::Custom { name, .. } => self.add(&name.location),
+					}
+				}
+			}
+			Declaration::Action(action) => self.add(&action.body.location),
+			Declaration::Function(function) => self.add(&function.body.location),
+			_ => (),
+		}
+		walk_declaration(self, declaration);
+	}
+
+	fn visit_parameter(&mut self, parameter: &'a Parameter) {
+		self.add(&parameter.location);
+		self.add(&parameter.name.location);
+		walk_parameter(self, parameter);
+	}
+
+	fn visit_statement(&mut self, statement: &'a Statement) {
+		self.add(&statement.location);
+		if let StatementKind::Switch(_, cases) = &statement.kind {
+			for case in cases {
+				self.add(&case.location);
+			}
+		}
+		walk_statement(self, statement);
+	}
+
+	fn visit_expression(&mut self, expression: &'a Expression) {
+		self.add(&expression.location);
+		if let ExpressionKind::Member(_, member) = &expression.kind {
+			self.add(&member.location);
+		}
+		walk_expression(self, expression);
+	}
+
+	fn visit_type_ref(&mut self, ty: &'a TypeRef) {
+		self.add(&ty.location);
+		walk_type_ref(self, ty);
+	}
+}
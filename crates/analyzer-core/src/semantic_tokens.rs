@@ -0,0 +1,120 @@
+//! Semantic tokens.
+//!
+//! Unlike the TextMate grammar of the editors, which only sees the text, semantic tokens classify each name by the
+//! symbol it resolves to, so that e.g. the name of a table and that of an action are told apart. The names resolved
+//! by the type checker, such as fields and methods, and the uses of macros are classified too, as is the code of the
+//! inactive preprocessor regions, which is never resolved.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+	arch::Architecture,
+	base_abstractions::*,
+	file_lexemes, file_text,
+	lexer::Token,
+	preprocess,
+	preprocessor::PreprocessorDirective,
+	symbols::{self, SymbolId, SymbolKind, PRELUDE_PATH},
+	types, Fs,
+};
+
+/// The classification of a semantic token.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SemanticTokenKind {
+	/// A name of a symbol of the given kind, parameters including their direction.
+	Symbol(SymbolKind),
+	/// A token of an inactive preprocessor region.
+	Inactive,
+}
+
+/// A classified token of a file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SemanticToken {
+	pub span: Span,
+	pub kind: SemanticTokenKind,
+	/// Whether the token is the name of a declaration rather than a reference to it.
+	pub declaration: bool,
+	/// Whether the symbol is declared in the core library or in an architecture header.
+	pub library: bool,
+}
+
+/// Returns the semantic tokens of the file `file_id`, analyzed as the root of its translation unit, in order.
+pub fn semantic_tokens(db: &dyn crate::Db, fs: Fs, file_id: FileId) -> Vec<SemanticToken> {
+	let (Some(lexemes), Some(preprocessed), Some(table)) = (
+		file_lexemes(db, fs, file_id),
+		preprocess(db, fs, file_id).as_ref(),
+		symbols::symbols(db, fs, file_id).as_ref(),
+	) else {
+		return vec![];
+	};
+	let info = types::types(db, fs, file_id).as_ref();
+	let text = file_text(db, fs, file_id).unwrap_or_default();
+
+	let active: HashSet<_> =
+		preprocessed.iter().filter(|(file, ..)| *file == file_id).map(|(_, _, span)| span.start).collect();
+	// the names of the file by their start, macros being declared by their whole `#define` directive
+	let mut declarations = HashMap::new();
+	for (id, symbol) in table.symbols().filter(|(_, symbol)| symbol.location.file == file_id) {
+		declarations.insert(symbol.location.span.start, id);
+	}
+	let mut references: HashMap<_, _> = table
+		.references()
+		.iter()
+		.filter(|r| r.location.file == file_id)
+		.filter_map(|r| Some((r.location.span.start, r.target?)))
+		.collect();
+	for r in info.map(|info| info.member_references()).unwrap_or_default() {
+		if let (Some(target), true) = (r.target, r.location.file == file_id) {
+			references.entry(r.location.span.start).or_insert(target);
+		}
+	}
+
+	let token = |span: Span, id: SymbolId, declaration: bool| {
+		let symbol = table.symbol(id);
+		let path = symbol.location.file.path(db);
+		SemanticToken {
+			span,
+			kind: SemanticTokenKind::Symbol(symbol.kind.clone()),
+			declaration,
+			library: path == PRELUDE_PATH || Architecture::from_path(&path).is_some(),
+		}
+	};
+	let mut tokens = vec![];
+	for (lexeme, span) in lexemes {
+		match lexeme {
+			Token::Whitespace | Token::Comment => (),
+			// the directives never make it through the preprocessor, active or not
+			Token::PreprocessorDirective(PreprocessorDirective::Define(name, _)) => {
+				let Some(&id) = declarations.get(&span.start) else { continue };
+				let directive = text.get(span.clone()).unwrap_or_default();
+				let Some(start) = directive.find("define").and_then(|i| Some(i + directive[i..].find(name.as_str())?))
+				else {
+					continue;
+				};
+				let start = span.start + start;
+				tokens.push(token(start..start + name.len(), id, true));
+			}
+			Token::PreprocessorDirective(_) => (),
+			_ if !active.contains(&span.start) => tokens.push(SemanticToken {
+				span: span.clone(),
+				kind: SemanticTokenKind::Inactive,
+				declaration: false,
+				library: false,
+			}),
+			Token::Identifier(name) => {
+				let (id, declaration) = match (declarations.get(&span.start), references.get(&span.start)) {
+					(Some(&id), _) => (id, true),
+					(None, Some(&id)) => (id, false),
+					// the uses of macros are expanded before names are resolved
+					(None, None) => match table.members(table.macros, name).first() {
+						Some(&id) => (id, false),
+						None => continue,
+					},
+				};
+				tokens.push(token(span.clone(), id, declaration));
+			}
+			_ => (),
+		}
+	}
+	tokens
+}
@@ -0,0 +1,216 @@
+//! Signature help.
+//!
+//! The call being written is usually not well-formed yet, so it is found from the tokens before the cursor rather
+//! than from the syntax tree: the innermost parenthesis or type argument list left open, the name before it, and the
+//! commas since, which select the active parameter. The name is resolved as seen from the cursor, giving the
+//! overloads of an action, function or extern method, the constructors of an extern, the parameters of a package,
+//! or the type parameters of a generic type.
+
+use std::ops::Range;
+
+use crate::{
+	base_abstractions::*,
+	file_lexemes,
+	hover::Describer,
+	lexer::Token,
+	symbols::{SymbolId, SymbolKind},
+	types::Type,
+	Fs,
+};
+
+/// The signatures of the call at the cursor.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SignatureHelp {
+	pub signatures: Vec<Signature>,
+	/// The index of the signature matching the number of arguments written so far.
+	pub active_signature: usize,
+	pub active_parameter: usize,
+}
+
+/// A signature of a callable symbol or of a generic type.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Signature {
+	/// The signature on a single line, with the direction, type and name of each parameter.
+	pub label: String,
+	/// The comments and annotations documenting the declaration, as Markdown.
+	pub documentation: Option<String>,
+	/// The spans of the parameters within the label.
+	pub parameters: Vec<Range<usize>>,
+}
+
+/// Returns the signatures of the call or type argument list around `offset` in the translation unit rooted at
+/// `file_id`.
+pub fn signature_help(db: &dyn crate::Db, fs: Fs, file_id: FileId, offset: usize) -> Option<SignatureHelp> {
+	let describer = Describer::new(db, fs, file_id)?;
+	let table = describer.table;
+	let scope = table.scope_at(file_id, offset);
+	let lexemes = file_lexemes(db, fs, file_id)?;
+	let tokens: Vec<_> = lexemes
+		.iter()
+		.filter(|(token, span)| !matches!(token, Token::Whitespace | Token::Comment) && span.end <= offset)
+		.map(|(token, _)| token)
+		.collect();
+
+	// a `<` opens a list of type arguments after the name of a type, and is a comparison otherwise
+	let generic = |name: &Token| match name {
+		Token::Identifier(name) if matches!(name.as_str(), "bit" | "int" | "varbit" | "tuple" | "list") => true,
+		Token::Identifier(name) => table.lookup(scope, name).map_or(false, |id| {
+			let kind = &table.symbol(id).kind;
+			kind.is_type() || matches!(kind, SymbolKind::ExternFunction | SymbolKind::Function | SymbolKind::Method)
+		}),
+		_ => false,
+	};
+	let mut open: Vec<Open> = vec![];
+	for (i, token) in tokens.iter().enumerate() {
+		match token {
+			Token::OpenParen => open.push(Open { delimiter: Delimiter::Paren, position: i, commas: 0 }),
+			Token::OpenBracket | Token::OpenBrace => {
+				open.push(Open { delimiter: Delimiter::Other, position: i, commas: 0 })
+			}
+			Token::OpenChevron if i > 0 && generic(tokens[i - 1]) => {
+				open.push(Open { delimiter: Delimiter::Angle, position: i, commas: 0 })
+			}
+			Token::CloseChevron if open.last().map(|o| o.delimiter) == Some(Delimiter::Angle) => {
+				open.pop();
+			}
+			// the type argument lists left open are closed along with their enclosing parentheses
+			Token::CloseParen | Token::CloseBracket | Token::CloseBrace => {
+				while open.last().map(|o| o.delimiter) == Some(Delimiter::Angle) {
+					open.pop();
+				}
+				open.pop();
+			}
+			// an unclosed call ends with its statement
+			Token::Semicolon => {
+				while matches!(open.last().map(|o| o.delimiter), Some(Delimiter::Paren | Delimiter::Angle)) {
+					open.pop();
+				}
+			}
+			Token::Comma => {
+				if let Some(open) = open.last_mut() {
+					open.commas += 1;
+				}
+			}
+			_ => (),
+		}
+	}
+
+	let open = open.pop().filter(|open| open.delimiter != Delimiter::Other)?;
+	let mut callee = open.position.checked_sub(1)?;
+	// the type arguments of a constructor or of a generic function come before its arguments
+	if open.delimiter == Delimiter::Paren && matches!(tokens[callee], Token::CloseChevron) {
+		let mut depth = 0;
+		loop {
+			match tokens[callee] {
+				Token::CloseChevron => depth += 1,
+				Token::OpenChevron => depth -= 1,
+				_ => (),
+			}
+			callee = callee.checked_sub(1)?;
+			if depth == 0 {
+				break;
+			}
+		}
+	}
+	let Token::Identifier(name) = tokens[callee] else { return None };
+
+	let candidates: Vec<SymbolId> = match (callee.checked_sub(2).map(|i| (tokens[i], tokens[i + 1])), open.delimiter) {
+		// the methods of an extern instance
+		(Some((Token::Identifier(receiver), Token::Dot)), Delimiter::Paren) => {
+			let receiver = table.lookup(scope, receiver)?;
+			let Some(Type::Extern(extern_id, _)) = describer.info.symbol_type(receiver) else { return None };
+			table.members(table.symbol(*extern_id).body?, name).to_vec()
+		}
+		_ => table.lookup_all(scope, name).to_vec(),
+	};
+	let signatures: Vec<_> = match open.delimiter {
+		Delimiter::Paren => candidates
+			.into_iter()
+			.flat_map(|id| match table.symbol(id).kind {
+				// the constructors of an extern are named after it
+				SymbolKind::ExternObject => {
+					table.symbol(id).body.map_or(vec![], |body| table.members(body, name).to_vec())
+				}
+				SymbolKind::Action
+				| SymbolKind::Function
+				| SymbolKind::ExternFunction
+				| SymbolKind::Method
+				| SymbolKind::Package => vec![id],
+				_ => vec![],
+			})
+			.map(|id| call_signature(&describer, id))
+			.collect(),
+		_ => candidates.into_iter().filter_map(|id| type_signature(&describer, id)).collect(),
+	};
+	if signatures.is_empty() {
+		return None;
+	}
+
+	let active_parameter = open.commas;
+	let active_signature = signatures.iter().position(|s| s.parameters.len() > active_parameter).unwrap_or(0);
+	Some(SignatureHelp { signatures, active_signature, active_parameter })
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Delimiter {
+	Paren,
+	Angle,
+	Other,
+}
+
+/// A delimiter left open before the cursor.
+struct Open {
+	delimiter: Delimiter,
+	/// The index of the delimiter among the tokens.
+	position: usize,
+	/// The number of commas after the delimiter, at its level.
+	commas: usize,
+}
+
+/// Returns the signature of an action, function, method or package, with its parameters.
+fn call_signature(describer: &Describer, id: SymbolId) -> Signature {
+	let symbol = describer.table.symbol(id);
+	let signature = describer.signature(symbol).unwrap_or_else(|| symbol.name.clone());
+	let prefix = signature.find('(').map_or(signature.as_str(), |i| &signature[..i]).trim_end();
+
+	let mut label = format!("{prefix}(");
+	let mut parameters = vec![];
+	for (i, param) in describer.table.parameters(id).enumerate() {
+		if i > 0 {
+			label.push_str(", ");
+		}
+		let text = describer.signature(param).unwrap_or_else(|| param.name.clone());
+		parameters.push(label.len()..label.len() + text.len());
+		label.push_str(&text);
+	}
+	label.push(')');
+	Signature { label, documentation: describer.documentation(symbol), parameters }
+}
+
+/// Returns the signature of a generic type or function, with its type parameters.
+fn type_signature(describer: &Describer, id: SymbolId) -> Option<Signature> {
+	let table = describer.table;
+	let symbol = table.symbol(id);
+	let type_params: Vec<_> = table
+		.scope(symbol.body?)
+		.symbols
+		.iter()
+		.map(|id| table.symbol(*id))
+		.filter(|symbol| symbol.kind == SymbolKind::TypeParameter)
+		.collect();
+	if type_params.is_empty() {
+		return None;
+	}
+
+	let mut label = format!("{}<", symbol.name);
+	let mut parameters = vec![];
+	for (i, param) in type_params.into_iter().enumerate() {
+		if i > 0 {
+			label.push_str(", ");
+		}
+		parameters.push(label.len()..label.len() + param.name.len());
+		label.push_str(&param.name);
+	}
+	label.push('>');
+	Some(Signature { label, documentation: describer.documentation(symbol), parameters })
+}
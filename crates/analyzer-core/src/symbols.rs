@@ -0,0 +1,806 @@
+//! Name resolution.
+//!
+//! A [`SymbolTable`] records every named declaration of a program as a [`Symbol`] in a tree of [`Scope`]s, along
+//! with every use of a name as a [`Reference`] to the symbol it resolves to (if any).
+//!
+//! Names declared at the top level of a program, or directly within a parser, control or extern, may be used
+//! before their declaration (e.g. a parser state can transition to a state declared further down). Names declared
+//! in a block of statements are only visible after their declaration.
+
+use std::collections::HashMap;
+
+use logos::Logos;
+
+use crate::{
+	ast::{self, *},
+	base_abstractions::*,
+	embedded_path,
+	lexer::{Lextras, Token},
+	parse, parse_file, preprocess, Fs,
+};
+
+/// The path of the embedded P4 core library (see [`PRELUDE`]).
+pub const PRELUDE_PATH: &str = "builtin:///core.p4";
+
+/// The source of the P4 core library, whose declarations are visible in every program.
+pub const PRELUDE: &str = include_str!("../resources/core.p4");
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SymbolId(usize);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ScopeId(usize);
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SymbolKind {
+	Constant,
+	Variable,
+	Parameter(Option<Direction>),
+	Typedef,
+	/// A new type introduced with `type`.
+	Type,
+	Header,
+	HeaderUnion,
+	Struct,
+	Field,
+	Enum,
+	EnumMember,
+	ErrorMember,
+	MatchKind,
+	ExternObject,
+	ExternFunction,
+	Method,
+	Parser,
+	ParserType,
+	Control,
+	ControlType,
+	Package,
+	Action,
+	Function,
+	Table,
+	State,
+	Instance,
+	TypeParameter,
+	Macro,
+}
+
+impl SymbolKind {
+	/// Returns `true` if the symbol names a type.
+	pub fn is_type(&self) -> bool {
+		use SymbolKind::*;
+		matches!(
+			self,
+			Typedef
+				| Type | Header | HeaderUnion
+				| Struct | Enum | ExternObject
+				| ParserType | ControlType
+				| Package | TypeParameter
+		)
+	}
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Symbol {
+	pub name: String,
+	pub kind: SymbolKind,
+	/// The location of the symbol's name.
+	pub location: Location,
+	/// The location of the symbol's whole declaration.
+	pub declaration: Location,
+	/// The scope the symbol is declared in.
+	pub scope: ScopeId,
+	/// The scope opened by the symbol's declaration (e.g. containing the fields of a struct), if any.
+	pub body: Option<ScopeId>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Scope {
+	pub parent: Option<ScopeId>,
+	/// The symbol whose declaration opened this scope, if any.
+	pub owner: Option<SymbolId>,
+	/// The source text the scope covers, if any.
+	pub location: Option<Location>,
+	/// The symbols declared in this scope, in order of declaration.
+	pub symbols: Vec<SymbolId>,
+	names: HashMap<String, Vec<SymbolId>>,
+}
+
+/// A use of a name.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Reference {
+	pub name: String,
+	pub location: Location,
+	/// The scope the name is used in.
+	pub scope: ScopeId,
+	/// The name qualifying a member reference, e.g. `error` for `error.NoMatch`.
+	pub qualifier: Option<String>,
+	/// The symbol the name resolves to, or `None` if it could not be resolved.
+	pub target: Option<SymbolId>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SymbolTable {
+	symbols: Vec<Symbol>,
+	scopes: Vec<Scope>,
+	references: Vec<Reference>,
+	macro_definitions: HashMap<SymbolId, MacroDefinition>,
+	/// The scope containing the declarations of the core library.
+	pub prelude: ScopeId,
+	/// The scope containing the top-level declarations of the program.
+	pub root: ScopeId,
+	/// The scope containing the members of `error`, which are declared across all `error` declarations.
+	pub errors: ScopeId,
+	/// The scope containing macro definitions, which take precedence over all other names.
+	pub macros: ScopeId,
+}
+
+impl SymbolTable {
+	pub fn symbol(&self, id: SymbolId) -> &Symbol { &self.symbols[id.0] }
+
+	pub fn scope(&self, id: ScopeId) -> &Scope { &self.scopes[id.0] }
+
+	pub fn symbols(&self) -> impl Iterator<Item = (SymbolId, &Symbol)> {
+		self.symbols.iter().enumerate().map(|(i, symbol)| (SymbolId(i), symbol))
+	}
+
+	pub fn references(&self) -> &[Reference] { &self.references }
+
+	/// Returns the definition of a macro symbol.
+	pub fn macro_definition(&self, id: SymbolId) -> Option<&MacroDefinition> { self.macro_definitions.get(&id) }
+
+	/// Returns the references that could not be resolved.
+	pub fn unresolved(&self) -> impl Iterator<Item = &Reference> {
+		self.references.iter().filter(|r| r.target.is_none())
+	}
+
+	/// Returns the references to a given symbol.
+	pub fn references_to(&self, id: SymbolId) -> impl Iterator<Item = &Reference> {
+		self.references.iter().filter(move |r| r.target == Some(id))
+	}
+
+	/// Resolves a name as seen from `scope`, searching macros first and then the enclosing scopes from the innermost
+	/// outwards.
+	pub fn lookup(&self, scope: ScopeId, name: &str) -> Option<SymbolId> {
+		self.lookup_all(scope, name).first().copied()
+	}
+
+	/// Like [`SymbolTable::lookup`], but returns all the (overloaded) symbols of the innermost scope declaring `name`.
+	pub fn lookup_all(&self, scope: ScopeId, name: &str) -> &[SymbolId] {
+		if let Some(symbols) = self.scope(self.macros).names.get(name) {
+			return symbols;
+		}
+
+		let mut current = Some(scope);
+		while let Some(id) = current {
+			let scope = self.scope(id);
+			if let Some(symbols) = scope.names.get(name) {
+				return symbols;
+			}
+			current = scope.parent;
+		}
+		&[]
+	}
+
+	/// Returns the symbols named `name` declared directly in `scope` (e.g. the fields of a struct).
+	pub fn members(&self, scope: ScopeId, name: &str) -> &[SymbolId] {
+		self.scope(scope).names.get(name).map(Vec::as_slice).unwrap_or_default()
+	}
+
+	/// Returns the parameters of a function, method, action, parser or control, in order.
+	pub fn parameters(&self, id: SymbolId) -> impl Iterator<Item = &Symbol> {
+		let symbols = self.symbol(id).body.map(|body| self.scope(body).symbols.as_slice()).unwrap_or_default();
+		symbols
+			.iter()
+			.map(|id| self.symbol(*id))
+			.filter(|symbol| matches!(symbol.kind, SymbolKind::Parameter(_)))
+	}
+
+	/// Returns the innermost scope covering `offset` in `file`.
+	pub fn scope_at(&self, file: FileId, offset: usize) -> ScopeId {
+		self.scopes
+			.iter()
+			.enumerate()
+			.filter_map(|(i, scope)| match &scope.location {
+				Some(location) if location.contains(file, offset) => Some((ScopeId(i), location.span.len())),
+				_ => None,
+			})
+			.min_by_key(|(_, len)| *len)
+			.map_or(self.root, |(id, _)| id)
+	}
+
+	/// Returns the reference whose name covers `offset` in `file`.
+	pub fn reference_at(&self, file: FileId, offset: usize) -> Option<&Reference> {
+		self.references.iter().find(|r| r.location.contains(file, offset))
+	}
+
+	/// Returns the symbol whose declared name covers `offset` in `file`.
+	pub fn declaration_at(&self, file: FileId, offset: usize) -> Option<SymbolId> {
+		self.symbols().find(|(_, symbol)| symbol.location.contains(file, offset)).map(|(id, _)| id)
+	}
+
+	/// Returns the symbol declared or referenced at `offset` in `file`.
+	pub fn symbol_at(&self, file: FileId, offset: usize) -> Option<SymbolId> {
+		self.declaration_at(file, offset).or_else(|| self.reference_at(file, offset).and_then(|r| r.target))
+	}
+}
+
+/// Returns the syntax tree of the embedded core library.
+pub fn prelude(db: &dyn crate::Db) -> &Program { parse_prelude(db, FileId::new(db, PRELUDE_PATH.to_string())) }
+
+/// Parses the embedded core library, identified by `file_id`, once for all the translation units.
+#[salsa::tracked(return_ref)]
+pub fn parse_prelude(db: &dyn crate::Db, file_id: FileId) -> Program {
+	let tokens: Vec<_> = Token::lexer_with_extras(PRELUDE, Lextras { db: None, file_id })
+		.spanned()
+		.map(|(tk, span)| (file_id, tk, span))
+		.collect();
+	ast::parse(&tokens).0
+}
+
+/// Builds the symbol table of a single file, without following its `#include`s.
+#[salsa::tracked(return_ref)]
+pub fn file_symbols(db: &dyn crate::Db, file_id: FileId, buf: Buffer) -> SymbolTable {
+	let macros = lex_macros(db, file_id, buf);
+	build(prelude(db), parse_file(db, file_id, buf), macros)
+}
+
+/// Builds the symbol table of the translation unit rooted at `file_id`, and reports the names that cannot be
+/// resolved.
+#[salsa::tracked(return_ref)]
+pub fn symbols(db: &dyn crate::Db, fs: Fs, file_id: FileId) -> Option<SymbolTable> {
+	let program = parse(db, fs, file_id).as_ref()?;
+	let macros = preprocess::accumulated::<MacroDefinitions>(db, fs, file_id);
+	let table = build(prelude(db), program, macros);
+
+	// Names declared in a missing file cannot be told apart from undeclared ones, so only report them once every
+	// include has been found (the core library and the architecture headers are always available).
+	let includes = preprocess::accumulated::<IncludedDependencies>(db, fs, file_id);
+	let is_complete = includes.iter().all(|i| i.is_resolved || embedded_path(&i.file_id.path(db)).is_some());
+
+	if is_complete {
+		for reference in table.unresolved() {
+			let message = match &reference.qualifier {
+				Some(qualifier) => format!("no member `{}` in `{qualifier}`", reference.name),
+				None => format!("cannot find `{}` in this scope", reference.name),
+			};
+			let Location { file, span } = reference.location.clone();
+			Diagnostics::push(db, Diagnostic::new(file, span, Severity::Error, message));
+		}
+	}
+
+	Some(table)
+}
+
+/// Collects the macros defined anywhere in a file.
+fn lex_macros(db: &dyn crate::Db, file_id: FileId, buf: Buffer) -> Vec<MacroDefinition> {
+	use crate::preprocessor::PreprocessorDirective;
+
+	crate::lex(db, file_id, buf)
+		.lexemes(db)
+		.iter()
+		.filter_map(|(tk, span)| match tk {
+			Token::PreprocessorDirective(PreprocessorDirective::Define(name, value)) => Some(MacroDefinition {
+				name: name.clone(),
+				value: value.clone(),
+				location: Location::new(file_id, span.clone()),
+			}),
+			_ => None,
+		})
+		.collect()
+}
+
+/// Builds the symbol table of `program`, nested in the scope of `prelude`.
+pub fn build(prelude: &Program, program: &Program, macros: Vec<MacroDefinition>) -> SymbolTable {
+	let mut builder = Builder {
+		table: SymbolTable {
+			symbols: vec![],
+			scopes: vec![],
+			references: vec![],
+			macro_definitions: HashMap::new(),
+			prelude: ScopeId(0),
+			root: ScopeId(1),
+			errors: ScopeId(2),
+			macros: ScopeId(3),
+		},
+	};
+
+	let prelude_scope = builder.new_scope(None, None, None);
+	let root = builder.new_scope(Some(prelude_scope), None, None);
+	let errors = builder.new_scope(None, None, None);
+	let macro_scope = builder.new_scope(None, None, None);
+	debug_assert_eq!((prelude_scope, root, errors, macro_scope), (ScopeId(0), ScopeId(1), ScopeId(2), ScopeId(3)));
+
+	for definition in macros {
+		let name = Identifier { name: definition.name.clone(), location: definition.location.clone() };
+		let id = builder.declare(macro_scope, &name, SymbolKind::Macro, &definition.location);
+		builder.table.macro_definitions.insert(id, definition);
+	}
+
+	builder.declarations(prelude_scope, &prelude.declarations);
+	builder.declarations(root, &program.declarations);
+	builder.table
+}
+
+struct Builder {
+	table: SymbolTable,
+}
+
+impl Builder {
+	fn new_scope(&mut self, parent: Option<ScopeId>, owner: Option<SymbolId>, location: Option<Location>) -> ScopeId {
+		self.table.scopes.push(Scope { parent, owner, location, symbols: vec![], names: HashMap::new() });
+		ScopeId(self.table.scopes.len() - 1)
+	}
+
+	fn declare(&mut self, scope: ScopeId, name: &Identifier, kind: SymbolKind, declaration: &Location) -> SymbolId {
+		let id = SymbolId(self.table.symbols.len());
+		self.table.symbols.push(Symbol {
+			name: name.name.clone(),
+			kind,
+			location: name.location.clone(),
+			declaration: declaration.clone(),
+			scope,
+			body: None,
+		});
+		let scope = &mut self.table.scopes[scope.0];
+		scope.symbols.push(id);
+		scope.names.entry(name.name.clone()).or_default().push(id);
+		id
+	}
+
+	/// Opens the body scope of a symbol.
+	fn open(&mut self, symbol: SymbolId) -> ScopeId {
+		let Symbol { scope, declaration, .. } = self.table.symbol(symbol).clone();
+		let body = self.new_scope(Some(scope), Some(symbol), Some(declaration));
+		self.table.symbols[symbol.0].body = Some(body);
+		body
+	}
+
+	fn reference(&mut self, scope: ScopeId, name: &Identifier) -> Option<SymbolId> {
+		let target = self.table.lookup(scope, &name.name);
+		self.table.references.push(Reference {
+			name: name.name.clone(),
+			location: name.location.clone(),
+			scope,
+			qualifier: None,
+			target,
+		});
+		target
+	}
+
+	fn member_reference(&mut self, scope: ScopeId, container: ScopeId, qualifier: &str, name: &Identifier) {
+		let target = self.table.members(container, &name.name).first().copied();
+		self.table.references.push(Reference {
+			name: name.name.clone(),
+			location: name.location.clone(),
+			scope,
+			qualifier: Some(qualifier.to_string()),
+			target,
+		});
+	}
+
+	/// Declares a list of declarations before resolving any of them, so that they can refer to each other
+	/// regardless of their order.
+	fn declarations(&mut self, scope: ScopeId, declarations: &[Declaration]) {
+		let symbols: Vec<_> = declarations.iter().map(|d| self.declare_declaration(scope, d)).collect();
+		for (declaration, symbol) in declarations.iter().zip(symbols) {
+			self.resolve_declaration(scope, declaration, symbol);
+		}
+	}
+
+	/// Declares the name(s) introduced by a declaration, along with the members that are visible from outside of
+	/// it (the fields of a struct or the members of an enum).
+	fn declare_declaration(&mut self, scope: ScopeId, declaration: &Declaration) -> Option<SymbolId> {
+		let kind = match declaration {
+			Declaration::Constant(_) => SymbolKind::Constant,
+			Declaration::Variable(_) => SymbolKind::Variable,
+			Declaration::Instantiation(_) => SymbolKind::Instance,
+			Declaration::Typedef(t) if t.is_new_type => SymbolKind::Type,
+			Declaration::Typedef(_) => SymbolKind::Typedef,
+			Declaration::Struct(s) => match s.kind {
+				StructKind::Header => SymbolKind::Header,
+				StructKind::HeaderUnion => SymbolKind::HeaderUnion,
+				StructKind::Struct => SymbolKind::Struct,
+			},
+			Declaration::Enum(_) => SymbolKind::Enum,
+			Declaration::Error(members) => {
+				for member in &members.members {
+					self.declare(self.table.errors, member, SymbolKind::ErrorMember, &member.location);
+				}
+				return None;
+			}
+			Declaration::MatchKind(members) => {
+				for member in &members.members {
+					self.declare(scope, member, SymbolKind::MatchKind, &member.location);
+				}
+				return None;
+			}
+			Declaration::ExternObject(_) => SymbolKind::ExternObject,
+			Declaration::ExternFunction(_) => SymbolKind::ExternFunction,
+			Declaration::Parser(p) if p.is_type_declaration => SymbolKind::ParserType,
+			Declaration::Parser(_) => SymbolKind::Parser,
+			Declaration::Control(c) if c.is_type_declaration => SymbolKind::ControlType,
+			Declaration::Control(_) => SymbolKind::Control,
+			Declaration::Package(_) => SymbolKind::Package,
+			Declaration::Action(_) => SymbolKind::Action,
+			Declaration::Function(_) => SymbolKind::Function,
+			Declaration::Table(_) => SymbolKind::Table,
+		};
+
+		let symbol = self.declare(scope, declaration.name()?, kind, declaration.location());
+		match declaration {
+			Declaration::Struct(s) => {
+				let body = self.open(symbol);
+				self.type_params(body, &s.type_params);
+				for field in &s.fields {
+					self.declare(body, &field.name, SymbolKind::Field, &field.location);
+				}
+			}
+			Declaration::Enum(e) => {
+				let body = self.open(symbol);
+				for member in &e.members {
+					self.declare(body, &member.name, SymbolKind::EnumMember, &member.name.location);
+				}
+			}
+			_ => (),
+		}
+		Some(symbol)
+	}
+
+	fn resolve_declaration(&mut self, scope: ScopeId, declaration: &Declaration, symbol: Option<SymbolId>) {
+		match declaration {
+			Declaration::Constant(c) => {
+				self.type_ref(scope, &c.ty);
+				self.expression(scope, &c.value);
+			}
+			Declaration::Variable(v) => {
+				self.type_ref(scope, &v.ty);
+				if let Some(initializer) = &v.initializer {
+					self.expression(scope, initializer);
+				}
+			}
+			Declaration::Instantiation(i) => {
+				self.type_ref(scope, &i.ty);
+				self.arguments(scope, &i.args);
+				if !i.initializer.is_empty() {
+					let body = match symbol {
+						Some(symbol) => self.open(symbol),
+						None => self.new_scope(Some(scope), None, Some(i.location.clone())),
+					};
+					self.declarations(body, &i.initializer);
+				}
+			}
+			Declaration::Typedef(t) => self.type_ref(scope, &t.ty),
+			Declaration::Struct(s) => {
+				let body = self.body(symbol, scope);
+				for field in &s.fields {
+					self.type_ref(body, &field.ty);
+				}
+			}
+			Declaration::Enum(e) => {
+				if let Some(ty) = &e.underlying_type {
+					self.type_ref(scope, ty);
+				}
+				for value in e.members.iter().filter_map(|m| m.value.as_ref()) {
+					self.expression(scope, value);
+				}
+			}
+			Declaration::Error(_) | Declaration::MatchKind(_) => (),
+			Declaration::ExternObject(e) => {
+				let body = self.open_or_new(symbol, scope, &e.location);
+				self.type_params(body, &e.type_params);
+				for method in &e.methods {
+					let id = self.declare(body, &method.name, SymbolKind::Method, &method.location);
+					let method_scope = self.open(id);
+					self.prototype(method_scope, method);
+				}
+			}
+			Declaration::ExternFunction(f) => {
+				let body = self.open_or_new(symbol, scope, &f.location);
+				self.prototype(body, f);
+			}
+			Declaration::Parser(p) => {
+				let body = self.open_or_new(symbol, scope, &p.location);
+				self.type_params(body, &p.type_params);
+				self.parameters(body, &p.params);
+				self.parameters(body, &p.constructor_params);
+
+				let locals: Vec<_> = p.locals.iter().map(|d| self.declare_declaration(body, d)).collect();
+				let states: Vec<_> =
+					p.states.iter().map(|s| self.declare(body, &s.name, SymbolKind::State, &s.location)).collect();
+				for (local, symbol) in p.locals.iter().zip(locals) {
+					self.resolve_declaration(body, local, symbol);
+				}
+				for (state, symbol) in p.states.iter().zip(states) {
+					let state_scope = self.open(symbol);
+					self.statements(state_scope, &state.statements);
+					if let Some(transition) = &state.transition {
+						self.transition(state_scope, body, transition);
+					}
+				}
+			}
+			Declaration::Control(c) => {
+				let body = self.open_or_new(symbol, scope, &c.location);
+				self.type_params(body, &c.type_params);
+				self.parameters(body, &c.params);
+				self.parameters(body, &c.constructor_params);
+				self.declarations(body, &c.locals);
+				if let Some(apply) = &c.apply {
+					self.block(body, apply);
+				}
+			}
+			Declaration::Package(p) => {
+				let body = self.open_or_new(symbol, scope, &p.location);
+				self.type_params(body, &p.type_params);
+				self.parameters(body, &p.params);
+			}
+			Declaration::Action(a) => {
+				let body = self.open_or_new(symbol, scope, &a.location);
+				self.parameters(body, &a.params);
+				self.statements(body, &a.body.statements);
+			}
+			Declaration::Function(f) => {
+				let body = self.open_or_new(symbol, scope, &f.location);
+				self.prototype(body, &f.prototype);
+				self.statements(body, &f.body.statements);
+			}
+			Declaration::Table(t) => {
+				for property in &t.properties {
+					self.table_property(scope, property);
+				}
+			}
+		}
+	}
+
+	/// Returns the body scope of a symbol, which has already been opened.
+	fn body(&mut self, symbol: Option<SymbolId>, scope: ScopeId) -> ScopeId {
+		symbol.and_then(|s| self.table.symbol(s).body).unwrap_or(scope)
+	}
+
+	fn open_or_new(&mut self, symbol: Option<SymbolId>, scope: ScopeId, location: &Location) -> ScopeId {
+		match symbol {
+			Some(symbol) => self.open(symbol),
+			None => self.new_scope(Some(scope), None, Some(location.clone())),
+		}
+	}
+
+	fn type_params(&mut self, scope: ScopeId, type_params: &[Identifier]) {
+		for param in type_params {
+			self.declare(scope, param, SymbolKind::TypeParameter, &param.location);
+		}
+	}
+
+	fn parameters(&mut self, scope: ScopeId, params: &[Parameter]) {
+		for param in params {
+			self.type_ref(scope, &param.ty);
+			if let Some(default) = &param.default {
+				self.expression(scope, default);
+			}
+			self.declare(scope, &param.name, SymbolKind::Parameter(param.direction.clone()), &param.location);
+		}
+	}
+
+	fn prototype(&mut self, scope: ScopeId, prototype: &FunctionPrototype) {
+		self.type_params(scope, &prototype.type_params);
+		if let Some(return_type) = &prototype.return_type {
+			self.type_ref(scope, return_type);
+		}
+		self.parameters(scope, &prototype.params);
+	}
+
+	fn transition(&mut self, scope: ScopeId, parser: ScopeId, transition: &Transition) {
+		match &transition.kind {
+			TransitionKind::Direct(state) => self.state_reference(scope, parser, state),
+			TransitionKind::Select(selectors, cases) => {
+				for selector in selectors {
+					self.expression(scope, selector);
+				}
+				for case in cases {
+					for key in &case.keyset {
+						self.expression(scope, key);
+					}
+					self.state_reference(scope, parser, &case.state);
+				}
+			}
+		}
+	}
+
+	fn state_reference(&mut self, scope: ScopeId, parser: ScopeId, state: &Identifier) {
+		// the built-in final states
+		if state.name == "accept" || state.name == "reject" {
+			return;
+		}
+		// transitions to undefined states are reported by the analysis of the parser's state graph
+		let Some(&target) = self.table.members(parser, &state.name).first() else { return };
+		self.table.references.push(Reference {
+			name: state.name.clone(),
+			location: state.location.clone(),
+			scope,
+			qualifier: None,
+			target: Some(target),
+		});
+	}
+
+	fn table_property(&mut self, scope: ScopeId, property: &TableProperty) {
+		match property {
+			TableProperty::Key(elements, _) => {
+				for element in elements {
+					self.expression(scope, &element.expression);
+					self.reference(scope, &element.match_kind);
+				}
+			}
+			TableProperty::Actions(actions, _) => {
+				for action in actions {
+					self.action_reference(scope, action);
+				}
+			}
+			TableProperty::DefaultAction { action, .. } => self.action_reference(scope, action),
+			TableProperty::Entries { entries, .. } => {
+				for entry in entries {
+					for key in &entry.keyset {
+						self.expression(scope, key);
+					}
+					self.action_reference(scope, &entry.action);
+				}
+			}
+			TableProperty::Custom { value, .. } => self.expression(scope, value),
+		}
+	}
+
+	fn action_reference(&mut self, scope: ScopeId, action: &ActionReference) {
+		self.reference(scope, &action.name);
+		if let Some(args) = &action.args {
+			self.arguments(scope, args);
+		}
+	}
+
+	fn block(&mut self, scope: ScopeId, block: &Block) {
+		let scope = self.new_scope(Some(scope), None, Some(block.location.clone()));
+		self.statements(scope, &block.statements);
+	}
+
+	fn statements(&mut self, scope: ScopeId, statements: &[Statement]) {
+		for statement in statements {
+			self.statement(scope, statement);
+		}
+	}
+
+	fn statement(&mut self, scope: ScopeId, statement: &Statement) {
+		match &statement.kind {
+			StatementKind::Assignment(lhs, rhs) => {
+				self.expression(scope, lhs);
+				self.expression(scope, rhs);
+			}
+			StatementKind::Call(call) => self.expression(scope, call),
+			StatementKind::If(condition, then, otherwise) => {
+				self.expression(scope, condition);
+				self.statement(scope, then);
+				if let Some(otherwise) = otherwise {
+					self.statement(scope, otherwise);
+				}
+			}
+			StatementKind::Switch(scrutinee, cases) => {
+				self.expression(scope, scrutinee);
+				for case in cases {
+					self.expression(scope, &case.label);
+					if let Some(body) = &case.body {
+						self.block(scope, body);
+					}
+				}
+			}
+			StatementKind::Block(block) => self.block(scope, block),
+			StatementKind::Return(value) => {
+				if let Some(value) = value {
+					self.expression(scope, value);
+				}
+			}
+			StatementKind::Exit | StatementKind::Empty => (),
+			StatementKind::Declaration(declaration) => match declaration.as_ref() {
+				// local variables are only visible after their declaration
+				Declaration::Constant(_) | Declaration::Variable(_) => {
+					self.resolve_declaration(scope, declaration, None);
+					self.declare_declaration(scope, declaration);
+				}
+				_ => self.declarations(scope, std::slice::from_ref(declaration)),
+			},
+		}
+	}
+
+	fn arguments(&mut self, scope: ScopeId, args: &[Argument]) {
+		for arg in args {
+			self.expression(scope, &arg.value);
+		}
+	}
+
+	fn type_ref(&mut self, scope: ScopeId, ty: &TypeRef) {
+		match &ty.kind {
+			TypeRefKind::Bit(Some(width)) | TypeRefKind::Int(Some(width)) | TypeRefKind::Varbit(width) => {
+				self.expression(scope, width)
+			}
+			TypeRefKind::Named(name, args) => {
+				self.reference(scope, name);
+				for arg in args {
+					self.type_ref(scope, arg);
+				}
+			}
+			TypeRefKind::Stack(element, size) => {
+				self.type_ref(scope, element);
+				self.expression(scope, size);
+			}
+			TypeRefKind::Tuple(elements) => {
+				for element in elements {
+					self.type_ref(scope, element);
+				}
+			}
+			_ => (),
+		}
+	}
+
+	fn expression(&mut self, scope: ScopeId, expression: &Expression) {
+		match &expression.kind {
+			ExpressionKind::Name(name) if name.name == "this" => (),
+			ExpressionKind::Name(name) => {
+				self.reference(scope, name);
+			}
+			ExpressionKind::Member(base, member) => match &base.kind {
+				ExpressionKind::Name(name) if name.name == "error" && self.table.lookup(scope, "error").is_none() => {
+					self.member_reference(scope, self.table.errors, "error", member)
+				}
+				ExpressionKind::Name(name) => {
+					let target = self.reference(scope, name).map(|id| self.table.symbol(id).clone());
+					// members of anything but an enum can only be resolved once types are known
+					if let Some(Symbol { kind: SymbolKind::Enum, body: Some(body), .. }) = target {
+						self.member_reference(scope, body, &name.name, member);
+					}
+				}
+				_ => self.expression(scope, base),
+			},
+			ExpressionKind::Index(base, index) => {
+				self.expression(scope, base);
+				self.expression(scope, index);
+			}
+			ExpressionKind::Slice(base, high, low) => {
+				self.expression(scope, base);
+				self.expression(scope, high);
+				self.expression(scope, low);
+			}
+			ExpressionKind::Call(callee, type_args, args) => {
+				self.expression(scope, callee);
+				for ty in type_args {
+					self.type_ref(scope, ty);
+				}
+				self.arguments(scope, args);
+			}
+			ExpressionKind::Unary(_, operand) => self.expression(scope, operand),
+			ExpressionKind::Binary(_, lhs, rhs) => {
+				self.expression(scope, lhs);
+				self.expression(scope, rhs);
+			}
+			ExpressionKind::Ternary(condition, then, otherwise) => {
+				self.expression(scope, condition);
+				self.expression(scope, then);
+				self.expression(scope, otherwise);
+			}
+			ExpressionKind::Cast(ty, operand) => {
+				self.type_ref(scope, ty);
+				self.expression(scope, operand);
+			}
+			ExpressionKind::List(elements) => {
+				for element in elements {
+					self.expression(scope, element);
+				}
+			}
+			ExpressionKind::StructInitializer(fields) => {
+				for (_, value) in fields {
+					self.expression(scope, value);
+				}
+			}
+			ExpressionKind::Type(ty) => self.type_ref(scope, ty),
+			ExpressionKind::Integer(_)
+			| ExpressionKind::Boolean(_)
+			| ExpressionKind::String(_)
+			| ExpressionKind::DontCare
+			| ExpressionKind::Default
+			| ExpressionKind::Missing => (),
+		}
+	}
+}
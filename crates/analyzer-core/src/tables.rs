@@ -0,0 +1,311 @@
+//! Checks of table declarations.
+//!
+//! The types of the properties of a table are checked along with the rest of the program; this module checks what
+//! they mean together. The match kinds of the keys must be supported by the architecture, the actions must be
+//! declared actions whose control-plane parameters the control plane can supply, the default action and the actions
+//! of the entries must be among the actions of the table, and the entries must match the key. The `size` of a table
+//! must be known at compile time, and the labels of a `switch` on `action_run` must be actions of the table.
+
+use std::collections::HashMap;
+
+use crate::{
+	arch::{self, ArchModel},
+	ast::{
+		visit::{self, Visitor},
+		*,
+	},
+	base_abstractions::*,
+	const_eval::{self, Constants, Value},
+	parse,
+	parser_states::{fits, is_wildcard},
+	symbols::{self, SymbolId, SymbolKind, SymbolTable},
+	types::{self, Type, TypeInfo},
+	Fs,
+};
+
+/// Checks the table declarations of the translation unit rooted at `file_id`, and the `switch` statements on the
+/// actions they run.
+#[salsa::tracked]
+pub fn table_declarations(db: &dyn crate::Db, fs: Fs, file_id: FileId) {
+	let Some(program) = parse(db, fs, file_id).as_ref() else { return };
+	let Some(table) = symbols::symbols(db, fs, file_id).as_ref() else { return };
+	let Some(info) = types::types(db, fs, file_id).as_ref() else { return };
+	let Some(constants) = const_eval::constants(db, fs, file_id).as_ref() else { return };
+
+	let mut checker = Checker {
+		table,
+		info,
+		constants,
+		model: arch::model(db, fs, file_id).as_ref(),
+		symbols: table.symbols().map(|(id, symbol)| (symbol.location.clone(), id)).collect(),
+		references: table.references().iter().filter_map(|r| Some((r.location.clone(), r.target?))).collect(),
+		actions: HashMap::new(),
+		diagnostics: vec![],
+	};
+	visit::walk_program(&mut checker, program);
+
+	for diagnostic in checker.diagnostics {
+		Diagnostics::push(db, diagnostic);
+	}
+}
+
+struct Checker<'a> {
+	table: &'a SymbolTable,
+	info: &'a TypeInfo,
+	constants: &'a Constants,
+	model: Option<&'a ArchModel>,
+	/// The symbols declared by the names at a given location.
+	symbols: HashMap<Location, SymbolId>,
+	/// The symbols the names at a given location refer to.
+	references: HashMap<Location, SymbolId>,
+	/// The actions of the tables checked so far.
+	actions: HashMap<SymbolId, Vec<SymbolId>>,
+	diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> Checker<'a> {
+	fn table_declaration(&mut self, t: &'a Table) {
+		let list: Vec<_> = t
+			.properties
+			.iter()
+			.flat_map(|property| match property {
+				TableProperty::Actions(actions, _) => actions.as_slice(),
+				_ => &[],
+			})
+			.collect();
+		if !t.properties.iter().any(|property| matches!(property, TableProperty::Actions(..))) {
+			self.error(&t.name.location, format!("the table `{}` has no `actions` property", t.name.name));
+		}
+		for action in &list {
+			if let Some(id) = self.action(action) {
+				self.arguments(action, id, true);
+				self.control_plane_parameters(action, id);
+			}
+		}
+		let actions: Vec<_> = list.iter().filter_map(|action| self.action(action)).collect();
+
+		let key: Vec<_> = t
+			.properties
+			.iter()
+			.flat_map(|property| match property {
+				TableProperty::Key(elements, _) => elements.as_slice(),
+				_ => &[],
+			})
+			.collect();
+		for element in &key {
+			self.match_kind(&element.match_kind);
+		}
+
+		for property in &t.properties {
+			match property {
+				TableProperty::DefaultAction { action, .. } => {
+					self.listed(t, &actions, action, "default action");
+				}
+				TableProperty::Entries { entries, location, .. } => {
+					if key.is_empty() {
+						self.error(location, format!("the table `{}` has entries but no key", t.name.name));
+					}
+					for entry in entries {
+						if !key.is_empty() {
+							self.entry(&key, entry);
+						}
+						self.listed(t, &actions, &entry.action, "entry action");
+					}
+				}
+				TableProperty::Custom { name, value, .. }
+					if name.name == "size"
+						&& self.constants.value(&value.location).and_then(Value::integer).is_none() =>
+				{
+					let message = "the `size` of a table must be a compile-time constant integer".to_string();
+					self.error(&value.location, message);
+				}
+				_ => (),
+			}
+		}
+
+		if let Some(&id) = self.symbols.get(&t.name.location) {
+			self.actions.insert(id, actions);
+		}
+	}
+
+	/// Returns the action an action reference refers to, if it resolves to one.
+	fn action(&self, action: &ActionReference) -> Option<SymbolId> {
+		let id = *self.references.get(&action.name.location)?;
+		(self.table.symbol(id).kind == SymbolKind::Action).then_some(id)
+	}
+
+	/// Checks that the default action, or the action of an entry, is one of the actions of the table, and that it binds
+	/// all the parameters of the action.
+	fn listed(&mut self, t: &Table, actions: &[SymbolId], action: &ActionReference, what: &str) {
+		let Some(id) = self.action(action) else { return };
+		if !actions.contains(&id) {
+			let message =
+				format!("the {what} `{}` is not one of the actions of the table `{}`", action.name.name, t.name.name);
+			self.error(&action.name.location, message);
+		}
+		self.arguments(action, id, false);
+	}
+
+	/// Checks that a reference to an action without an argument list doesn't need arguments. In the `actions` list only
+	/// the parameters with a direction must be bound; the arguments given are checked with the types.
+	fn arguments(&mut self, action: &ActionReference, id: SymbolId, in_list: bool) {
+		if action.args.is_some() {
+			return;
+		}
+		let expected = self
+			.table
+			.parameters(id)
+			.filter(|param| !in_list || !matches!(param.kind, SymbolKind::Parameter(None)))
+			.count();
+		if expected > 0 {
+			let message = format!("the action `{}` expects {expected} arguments, found 0", action.name.name);
+			self.error(&action.location, message);
+		}
+	}
+
+	/// Checks that the parameters of an action supplied by the control plane have types it can represent.
+	fn control_plane_parameters(&mut self, action: &ActionReference, id: SymbolId) {
+		let table = self.table;
+		for param in table.parameters(id).filter(|param| param.kind == SymbolKind::Parameter(None)) {
+			let Some(ty) = self.symbols.get(&param.location).and_then(|id| self.info.symbol_type(*id)) else { continue };
+			if !self.is_control_plane_type(ty) {
+				let message = format!(
+					"the parameter `{}` of the action `{}` cannot be supplied by the control plane, because its type is \
+					 `{}`",
+					param.name,
+					action.name.name,
+					ty.display(table)
+				);
+				self.error(&action.name.location, message);
+			}
+		}
+	}
+
+	/// Returns `true` for the types the control plane can represent: fixed-width integers, booleans, and serializable
+	/// enums and new types of them.
+	fn is_control_plane_type(&self, ty: &Type) -> bool {
+		match ty {
+			Type::Bit(_) | Type::Int(_) | Type::Bool => true,
+			Type::Enum(id) | Type::NewType(id) => {
+				self.info.underlying_type(*id).map_or(false, |underlying| self.is_control_plane_type(underlying))
+			}
+			ty => ty.is_unknown(),
+		}
+	}
+
+	/// Checks that a match kind is one of the core library or of the architecture.
+	fn match_kind(&mut self, name: &Identifier) {
+		let Some(&id) = self.references.get(&name.location) else { return };
+		let symbol = self.table.symbol(id);
+		if symbol.kind != SymbolKind::MatchKind {
+			return self.error(&name.location, format!("`{}` is not a match kind", name.name));
+		}
+		let Some(model) = self.model else { return };
+		// the core library may also be included, declaring its match kinds again
+		let is_core = symbol.scope == self.table.prelude
+			|| self
+				.table
+				.lookup_all(self.table.prelude, &name.name)
+				.iter()
+				.any(|&id| self.table.symbol(id).kind == SymbolKind::MatchKind);
+		if !is_core && !model.declares(self.table, id) {
+			let message = format!(
+				"the architecture `{}` doesn't support the match kind `{}`",
+				model.architecture.name(),
+				name.name
+			);
+			self.error(&name.location, message);
+		}
+	}
+
+	/// Checks that the keyset of an entry has a value for each key element, which fits in its type.
+	fn entry(&mut self, key: &[&KeyElement], entry: &Entry) {
+		if let [value] = entry.keyset.as_slice() {
+			if is_wildcard(value) {
+				return;
+			}
+		}
+		// the parser reports empty keysets
+		let (Some(first), Some(last)) = (entry.keyset.first(), entry.keyset.last()) else { return };
+		if entry.keyset.len() != key.len() {
+			let location =
+				Location { file: first.location.file, span: first.location.span.start..last.location.span.end };
+			let message =
+				format!("expected an entry of {} key values to match the key, found {}", key.len(), entry.keyset.len());
+			return self.error(&location, message);
+		}
+
+		for (element, value) in key.iter().zip(&entry.keyset) {
+			if let Some(key_type @ (Type::Bit(_) | Type::Int(_))) =
+				self.info.expression_type(&element.expression.location)
+			{
+				self.key_value(key_type, value);
+			}
+		}
+	}
+
+	fn key_value(&mut self, key_type: &Type, value: &Expression) {
+		if let ExpressionKind::Binary(BinaryOp::Mask | BinaryOp::Range, left, right) = &value.kind {
+			self.key_value(key_type, left);
+			return self.key_value(key_type, right);
+		}
+		let table = self.table;
+		match self.info.expression_type(&value.location) {
+			Some(value_type @ (Type::Bit(width) | Type::Int(width))) if Some(*width) != key_type.width() => {
+				let message = format!(
+					"expected a key value of type `{}` to match the width of the key, found `{}`",
+					key_type.display(table),
+					value_type.display(table)
+				);
+				self.error(&value.location, message);
+			}
+			Some(Type::InfInt) => {
+				let Some(n) = self.constants.value(&value.location).and_then(|value| value.to_i128()) else { return };
+				if !fits(n, key_type) {
+					let message = format!("the value {n} doesn't fit in the key's type `{}`", key_type.display(table));
+					self.error(&value.location, message);
+				}
+			}
+			_ => (),
+		}
+	}
+
+	/// Checks that the labels of a `switch` on the action run by a table are actions of the table.
+	fn switch(&mut self, scrutinee: &Expression, cases: &[SwitchCase]) {
+		let Some(Type::ActionRun(id)) = self.info.expression_type(&scrutinee.location) else { return };
+		let Some(actions) = self.actions.get(id) else { return };
+		let mut errors = vec![];
+		for case in cases {
+			let ExpressionKind::Name(name) = &case.label.kind else { continue };
+			let Some(action) = self.references.get(&name.location) else { continue };
+			if !actions.contains(action) {
+				let message = format!("the table `{}` has no action `{}`", self.table.symbol(*id).name, name.name);
+				errors.push((case.label.location.clone(), message));
+			}
+		}
+		for (location, message) in errors {
+			self.error(&location, message);
+		}
+	}
+
+	fn error(&mut self, location: &Location, message: String) {
+		let Location { file, span } = location.clone();
+		self.diagnostics.push(Diagnostic::new(file, span, Severity::Error, message));
+	}
+}
+
+impl<'a> Visitor<'a> for Checker<'a> {
+	fn visit_declaration(&mut self, declaration: &'a Declaration) {
+		if let Declaration::Table(t) = declaration {
+			self.table_declaration(t);
+		}
+		visit::walk_declaration(self, declaration);
+	}
+
+	fn visit_statement(&mut self, statement: &'a Statement) {
+		if let StatementKind::Switch(scrutinee, cases) = &statement.kind {
+			self.switch(scrutinee, cases);
+		}
+		visit::walk_statement(self, statement);
+	}
+}
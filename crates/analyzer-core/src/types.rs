@@ -0,0 +1,1771 @@
+//! Type checking.
+//!
+//! The checker infers the [`Type`] of every expression of a program and checks it against the types expected by
+//! its context (e.g. the left-hand side of an assignment, or the parameters of a callee). It also resolves the
+//! member accesses that name resolution cannot resolve without types, such as the fields of a header.
+//!
+//! Expressions whose type cannot be inferred (for example because they refer to an undeclared name, which has
+//! already been reported) are given the [`Type::Unknown`] type, which is compatible with every other type so that
+//! a single mistake is only reported once.
+
+use std::{
+	collections::{HashMap, HashSet},
+	fmt,
+};
+
+use crate::{
+	ast::*,
+	base_abstractions::*,
+	const_eval::{self, Value},
+	parse,
+	symbols::{self, Reference, SymbolId, SymbolKind, SymbolTable},
+	Fs,
+};
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Type {
+	/// `bit<W>`
+	Bit(u32),
+	/// `int<W>`
+	Int(u32),
+	/// `varbit<W>`
+	Varbit(u32),
+	/// An integer of arbitrary precision, such as an integer literal without a width.
+	InfInt,
+	Bool,
+	String,
+	Void,
+	Error,
+	MatchKind,
+	/// The `_` type, which is also the type of `_` and `default` in keysets.
+	DontCare,
+	Header(SymbolId, Vec<Type>),
+	HeaderUnion(SymbolId, Vec<Type>),
+	Struct(SymbolId, Vec<Type>),
+	/// A header stack, `T[N]`.
+	Stack(Box<Type>, u32),
+	Tuple(Vec<Type>),
+	/// The type of a list expression, `{ a, b }`, which can initialize a tuple, a struct or a header.
+	List(Vec<Type>),
+	/// The type of a structure-valued expression, `{ a = x, b = y }`.
+	StructLiteral(Vec<(String, Type)>),
+	/// An enum, which is serializable if it declares an underlying type.
+	Enum(SymbolId),
+	/// A type introduced with `type`, which is distinct from the type it is defined as.
+	NewType(SymbolId),
+	Extern(SymbolId, Vec<Type>),
+	Parser(SymbolId),
+	Control(SymbolId),
+	Package(SymbolId, Vec<Type>),
+	Table(SymbolId),
+	TypeParameter(SymbolId),
+	/// An action, a function or an extern function.
+	Function(SymbolId),
+	/// A method of a value of the given type.
+	Method(Box<Type>, SymbolId),
+	/// A built-in method, such as `isValid`.
+	Builtin(Builtin),
+	/// The `apply` method of a table, parser or control.
+	Apply(SymbolId),
+	/// The result of applying a table.
+	ApplyResult(SymbolId),
+	/// The type of `action_run`, whose values are the actions of a table.
+	ActionRun(SymbolId),
+	/// A type used as a value, e.g. the callee of a constructor call.
+	Meta(Box<Type>),
+	/// The type of an expression that could not be typed.
+	Unknown,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Builtin {
+	IsValid,
+	SetValid,
+	SetInvalid,
+	PushFront,
+	PopFront,
+	MinSizeInBits,
+	MinSizeInBytes,
+}
+
+impl Builtin {
+	pub fn name(&self) -> &'static str {
+		match self {
+			Builtin::IsValid => "isValid",
+			Builtin::SetValid => "setValid",
+			Builtin::SetInvalid => "setInvalid",
+			Builtin::PushFront => "push_front",
+			Builtin::PopFront => "pop_front",
+			Builtin::MinSizeInBits => "minSizeInBits",
+			Builtin::MinSizeInBytes => "minSizeInBytes",
+		}
+	}
+}
+
+impl Type {
+	/// Returns `true` for `bit<W>`, `int<W>` and integers of arbitrary precision.
+	pub fn is_numeric(&self) -> bool { matches!(self, Type::Bit(_) | Type::Int(_) | Type::InfInt) }
+
+	/// Returns `true` for types that only arise from an error, or from an unbound type parameter, and therefore
+	/// shouldn't be reported.
+	pub fn is_unknown(&self) -> bool { matches!(self, Type::Unknown | Type::TypeParameter(_)) }
+
+	/// Returns the width in bits of a fixed-width integer type.
+	pub fn width(&self) -> Option<u32> {
+		match self {
+			Type::Bit(w) | Type::Int(w) => Some(*w),
+			Type::Bool => Some(1),
+			_ => None,
+		}
+	}
+
+	/// Returns an object that displays the type as it would be written in P4.
+	pub fn display<'a>(&'a self, table: &'a SymbolTable) -> impl fmt::Display + 'a { DisplayType(self, table) }
+}
+
+struct DisplayType<'a>(&'a Type, &'a SymbolTable);
+
+impl fmt::Display for DisplayType<'_> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let DisplayType(ty, table) = *self;
+		let name = |id: &SymbolId| table.symbol(*id).name.as_str();
+		let list = |f: &mut fmt::Formatter<'_>, types: &[Type]| -> fmt::Result {
+			for (i, ty) in types.iter().enumerate() {
+				if i > 0 {
+					write!(f, ", ")?;
+				}
+				write!(f, "{}", ty.display(table))?;
+			}
+			Ok(())
+		};
+		let generic = |f: &mut fmt::Formatter<'_>, id: &SymbolId, args: &[Type]| -> fmt::Result {
+			write!(f, "{}", name(id))?;
+			if !args.is_empty() {
+				write!(f, "<")?;
+				list(f, args)?;
+				write!(f, ">")?;
+			}
+			Ok(())
+		};
+
+		match ty {
+			Type::Bit(w) => write!(f, "bit<{w}>"),
+			Type::Int(w) => write!(f, "int<{w}>"),
+			Type::Varbit(w) => write!(f, "varbit<{w}>"),
+			Type::InfInt => write!(f, "int"),
+			Type::Bool => write!(f, "bool"),
+			Type::String => write!(f, "string"),
+			Type::Void => write!(f, "void"),
+			Type::Error => write!(f, "error"),
+			Type::MatchKind => write!(f, "match_kind"),
+			Type::DontCare => write!(f, "_"),
+			Type::Header(id, args)
+			| Type::HeaderUnion(id, args)
+			| Type::Struct(id, args)
+			| Type::Extern(id, args)
+			| Type::Package(id, args) => generic(f, id, args),
+			Type::Stack(element, size) => write!(f, "{}[{size}]", element.display(table)),
+			Type::Tuple(elements) => {
+				write!(f, "tuple<")?;
+				list(f, elements)?;
+				write!(f, ">")
+			}
+			Type::List(elements) => {
+				write!(f, "{{ ")?;
+				list(f, elements)?;
+				write!(f, " }}")
+			}
+			Type::StructLiteral(fields) => {
+				write!(f, "{{ ")?;
+				for (i, (field, ty)) in fields.iter().enumerate() {
+					if i > 0 {
+						write!(f, ", ")?;
+					}
+					write!(f, "{field} = {}", ty.display(table))?;
+				}
+				write!(f, " }}")
+			}
+			Type::Enum(id)
+			| Type::NewType(id)
+			| Type::Parser(id)
+			| Type::Control(id)
+			| Type::Table(id)
+			| Type::TypeParameter(id)
+			| Type::Function(id)
+			| Type::Method(_, id) => write!(f, "{}", name(id)),
+			Type::Builtin(builtin) => write!(f, "{}", builtin.name()),
+			Type::Apply(id) => write!(f, "{}.apply", name(id)),
+			Type::ApplyResult(id) => write!(f, "apply_result({})", name(id)),
+			Type::ActionRun(id) => write!(f, "action_list({})", name(id)),
+			Type::Meta(ty) => write!(f, "{}", ty.display(table)),
+			Type::Unknown => write!(f, "{{unknown}}"),
+		}
+	}
+}
+
+/// The result of type checking a program.
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct TypeInfo {
+	expressions: HashMap<Location, Type>,
+	type_refs: HashMap<Location, Type>,
+	symbols: HashMap<SymbolId, Type>,
+	underlying: HashMap<SymbolId, Type>,
+	members: Vec<Reference>,
+}
+
+impl TypeInfo {
+	/// Returns the type of the expression at `location`.
+	pub fn expression_type(&self, location: &Location) -> Option<&Type> { self.expressions.get(location) }
+
+	/// Returns the innermost expression covering `offset` in `file`, along with its type.
+	pub fn expression_at(&self, file: FileId, offset: usize) -> Option<(&Location, &Type)> {
+		self.expressions
+			.iter()
+			.filter(|(location, _)| location.contains(file, offset))
+			.min_by_key(|(l, _)| l.span.len())
+	}
+
+	/// Returns the type a type reference at `location` resolves to.
+	pub fn type_ref(&self, location: &Location) -> Option<&Type> { self.type_refs.get(location) }
+
+	/// Returns the type a `type` declaration or a serializable enum is based on.
+	pub fn underlying_type(&self, id: SymbolId) -> Option<&Type> { self.underlying.get(&id) }
+
+	/// Returns the type of the value (or type) a symbol declares.
+	pub fn symbol_type(&self, id: SymbolId) -> Option<&Type> { self.symbols.get(&id) }
+
+	/// Returns the fields of a header, header union or struct, in order, with their types.
+	pub fn fields<'a>(&'a self, table: &'a SymbolTable, id: SymbolId) -> impl Iterator<Item = (SymbolId, &'a Type)> {
+		let symbols = table.symbol(id).body.map(|body| table.scope(body).symbols.as_slice()).unwrap_or_default();
+		symbols
+			.iter()
+			.filter(|id| table.symbol(**id).kind == SymbolKind::Field)
+			.map(|id| (*id, self.symbol_type(*id).unwrap_or(&Type::Unknown)))
+	}
+
+	/// Returns the size in bits of a value of the type as it is laid out in a packet, counting the maximum size of a
+	/// `varbit`, or `None` if the type has no such layout.
+	pub fn size_in_bits(&self, table: &SymbolTable, ty: &Type) -> Option<u32> {
+		match ty {
+			Type::Bit(w) | Type::Int(w) | Type::Varbit(w) => Some(*w),
+			Type::Bool => Some(1),
+			Type::Header(id, _) | Type::Struct(id, _) => {
+				self.fields(table, *id).map(|(_, ty)| self.size_in_bits(table, ty)).sum()
+			}
+			Type::HeaderUnion(id, _) => {
+				let sizes: Option<Vec<_>> =
+					self.fields(table, *id).map(|(_, ty)| self.size_in_bits(table, ty)).collect();
+				Some(sizes?.into_iter().max().unwrap_or(0))
+			}
+			Type::Stack(element, size) => Some(self.size_in_bits(table, element)? * size),
+			Type::Enum(id) | Type::NewType(id) => self.size_in_bits(table, self.underlying_type(*id)?),
+			_ => None,
+		}
+	}
+
+	/// Returns the resolved member accesses, such as `hdr.ethernet`, which name resolution alone cannot resolve.
+	pub fn member_references(&self) -> &[Reference] { &self.members }
+
+	/// Returns the member access whose member name covers `offset` in `file`.
+	pub fn member_at(&self, file: FileId, offset: usize) -> Option<&Reference> {
+		self.members.iter().find(|r| r.location.contains(file, offset))
+	}
+}
+
+/// Type checks the translation unit rooted at `file_id`.
+#[salsa::tracked(return_ref)]
+pub fn types(db: &dyn crate::Db, fs: Fs, file_id: FileId) -> Option<TypeInfo> {
+	let program = parse(db, fs, file_id).as_ref()?;
+	let table = symbols::symbols(db, fs, file_id).as_ref()?;
+	let (info, diagnostics) = check(symbols::prelude(db), program, table);
+	for diagnostic in diagnostics {
+		Diagnostics::push(db, diagnostic);
+	}
+	Some(info)
+}
+
+/// Type checks `program`, whose names (and those of `prelude`) have been resolved in `table`.
+pub fn check(prelude: &Program, program: &Program, table: &SymbolTable) -> (TypeInfo, Vec<Diagnostic>) {
+	let mut checker = Checker::new(table);
+	checker.index_declarations(&prelude.declarations);
+	checker.index_declarations(&program.declarations);
+	for declaration in &program.declarations {
+		checker.declaration(declaration);
+	}
+	// record the types of the symbols that are never referenced, too
+	let ids: Vec<_> = checker.nodes.keys().copied().collect();
+	for id in ids {
+		checker.symbol_type(id);
+	}
+	(checker.info, checker.diagnostics)
+}
+
+/// The AST node declaring a symbol.
+#[derive(Clone, Copy)]
+enum Node<'a> {
+	Constant(&'a Constant),
+	Variable(&'a Variable),
+	Instantiation(&'a Instantiation),
+	Typedef(&'a Typedef),
+	Field(&'a Field),
+	Enum(&'a Enum),
+	Prototype(&'a FunctionPrototype),
+	Function(&'a Function),
+	Action(&'a Action),
+	Parser(&'a Parser),
+	Control(&'a Control),
+	Package(&'a Package),
+	Parameter(&'a Parameter),
+}
+
+/// A parameter of a callable.
+#[derive(Clone)]
+struct Param {
+	name: String,
+	direction: Option<Direction>,
+	ty: Type,
+	has_default: bool,
+}
+
+#[derive(Clone)]
+struct Signature {
+	type_params: Vec<SymbolId>,
+	params: Vec<Param>,
+	return_type: Type,
+}
+
+struct Checker<'a> {
+	table: &'a SymbolTable,
+	nodes: HashMap<SymbolId, Node<'a>>,
+	/// The symbols declared by the names at a given position.
+	declared: HashMap<(FileId, usize), SymbolId>,
+	/// The symbols referred to by the names at a given position.
+	targets: HashMap<(FileId, usize), SymbolId>,
+	in_progress: HashSet<SymbolId>,
+	constants: HashMap<SymbolId, Option<Value>>,
+	return_type: Option<Type>,
+	info: TypeInfo,
+	diagnostics: Vec<Diagnostic>,
+	reported: HashSet<(FileId, Span, String)>,
+	/// The field names of the structure-valued expressions, by location, until the type they initialize is known.
+	initializers: HashMap<Location, Vec<Identifier>>,
+}
+
+impl<'a> Checker<'a> {
+	fn new(table: &'a SymbolTable) -> Self {
+		let declared = table.symbols().map(|(id, s)| ((s.location.file, s.location.span.start), id)).collect();
+		let targets = table
+			.references()
+			.iter()
+			.filter_map(|r| Some(((r.location.file, r.location.span.start), r.target?)))
+			.collect();
+
+		Self {
+			table,
+			nodes: HashMap::new(),
+			declared,
+			targets,
+			in_progress: HashSet::new(),
+			constants: HashMap::new(),
+			return_type: None,
+			info: TypeInfo::default(),
+			diagnostics: vec![],
+			reported: HashSet::new(),
+			initializers: HashMap::new(),
+		}
+	}
+
+	fn error(&mut self, location: &Location, message: String) {
+		// types are resolved on demand, so the same problem may be found more than once
+		if self.reported.insert((location.file, location.span.clone(), message.clone())) {
+			self.diagnostics
+				.push(Diagnostic::new(location.file, location.span.clone(), Severity::Error, message));
+		}
+	}
+
+	fn show(&self, ty: &Type) -> String { ty.display(self.table).to_string() }
+
+	fn declared_at(&self, name: &Identifier) -> Option<SymbolId> {
+		self.declared.get(&(name.location.file, name.location.span.start)).copied()
+	}
+
+	fn target(&self, name: &Identifier) -> Option<SymbolId> {
+		self.targets.get(&(name.location.file, name.location.span.start)).copied()
+	}
+
+	// indexing
+
+	fn index(&mut self, name: &Identifier, node: Node<'a>) {
+		if let Some(id) = self.declared_at(name) {
+			self.nodes.insert(id, node);
+		}
+	}
+
+	fn index_declarations(&mut self, declarations: &'a [Declaration]) {
+		for declaration in declarations {
+			self.index_declaration(declaration);
+		}
+	}
+
+	fn index_declaration(&mut self, declaration: &'a Declaration) {
+		match declaration {
+			Declaration::Constant(c) => self.index(&c.name, Node::Constant(c)),
+			Declaration::Variable(v) => self.index(&v.name, Node::Variable(v)),
+			Declaration::Instantiation(i) => {
+				self.index(&i.name, Node::Instantiation(i));
+				self.index_declarations(&i.initializer);
+			}
+			Declaration::Typedef(t) => self.index(&t.name, Node::Typedef(t)),
+			Declaration::Struct(s) => {
+				for field in &s.fields {
+					self.index(&field.name, Node::Field(field));
+				}
+			}
+			Declaration::Enum(e) => {
+				self.index(&e.name, Node::Enum(e));
+				for member in &e.members {
+					self.index(&member.name, Node::Enum(e));
+				}
+			}
+			Declaration::Error(_) | Declaration::MatchKind(_) => (),
+			Declaration::ExternObject(e) => {
+				for method in &e.methods {
+					self.index(&method.name, Node::Prototype(method));
+					self.index_parameters(&method.params);
+				}
+			}
+			Declaration::ExternFunction(f) => {
+				self.index(&f.name, Node::Prototype(f));
+				self.index_parameters(&f.params);
+			}
+			Declaration::Parser(p) => {
+				self.index(&p.name, Node::Parser(p));
+				self.index_parameters(&p.params);
+				self.index_parameters(&p.constructor_params);
+				self.index_declarations(&p.locals);
+				for state in &p.states {
+					self.index_statements(&state.statements);
+				}
+			}
+			Declaration::Control(c) => {
+				self.index(&c.name, Node::Control(c));
+				self.index_parameters(&c.params);
+				self.index_parameters(&c.constructor_params);
+				self.index_declarations(&c.locals);
+				if let Some(apply) = &c.apply {
+					self.index_statements(&apply.statements);
+				}
+			}
+			Declaration::Package(p) => {
+				self.index(&p.name, Node::Package(p));
+				self.index_parameters(&p.params);
+			}
+			Declaration::Action(a) => {
+				self.index(&a.name, Node::Action(a));
+				self.index_parameters(&a.params);
+				self.index_statements(&a.body.statements);
+			}
+			Declaration::Function(f) => {
+				self.index(&f.prototype.name, Node::Function(f));
+				self.index_parameters(&f.prototype.params);
+				self.index_statements(&f.body.statements);
+			}
+			Declaration::Table(_) => (),
+		}
+	}
+
+	fn index_parameters(&mut self, params: &'a [Parameter]) {
+		for param in params {
+			self.index(&param.name, Node::Parameter(param));
+		}
+	}
+
+	fn index_statements(&mut self, statements: &'a [Statement]) {
+		for statement in statements {
+			self.index_statement(statement);
+		}
+	}
+
+	fn index_statement(&mut self, statement: &'a Statement) {
+		match &statement.kind {
+			StatementKind::If(_, then, otherwise) => {
+				self.index_statement(then);
+				if let Some(otherwise) = otherwise {
+					self.index_statement(otherwise);
+				}
+			}
+			StatementKind::Switch(_, cases) => {
+				for body in cases.iter().filter_map(|c| c.body.as_ref()) {
+					self.index_statements(&body.statements);
+				}
+			}
+			StatementKind::Block(block) => self.index_statements(&block.statements),
+			StatementKind::Declaration(declaration) => self.index_declaration(declaration),
+			_ => (),
+		}
+	}
+
+	// types of declarations
+
+	/// Returns the type of the value (or, for type declarations, the type) declared by a symbol.
+	fn symbol_type(&mut self, id: SymbolId) -> Type {
+		if let Some(ty) = self.info.symbols.get(&id) {
+			return ty.clone();
+		}
+		if !self.in_progress.insert(id) {
+			return Type::Unknown;
+		}
+
+		let symbol = self.table.symbol(id);
+		let ty = match (&symbol.kind, self.nodes.get(&id).copied()) {
+			(SymbolKind::EnumMember, _) => match self.table.scope(symbol.scope).owner {
+				Some(owner) => Type::Enum(owner),
+				None => Type::Unknown,
+			},
+			(SymbolKind::ErrorMember, _) => Type::Error,
+			(SymbolKind::MatchKind, _) => Type::MatchKind,
+			(SymbolKind::Macro, _) => {
+				const_eval::macro_value(self.table, id).and_then(|v| v.ty()).unwrap_or(Type::Unknown)
+			}
+			(SymbolKind::Table, _) => Type::Table(id),
+			(SymbolKind::Action | SymbolKind::Function | SymbolKind::ExternFunction | SymbolKind::Method, _) => {
+				Type::Function(id)
+			}
+			// `named_type` would see the typedef as part of a cycle, since it is already in progress
+			(SymbolKind::Typedef, Some(Node::Typedef(t))) => Type::Meta(self.resolve(&t.ty).into()),
+			(kind, _) if kind.is_type() || matches!(kind, SymbolKind::Parser | SymbolKind::Control) => {
+				Type::Meta(self.named_type(id, vec![]).into())
+			}
+			(_, Some(Node::Constant(c))) => self.resolve(&c.ty),
+			(_, Some(Node::Variable(v))) => self.resolve(&v.ty),
+			(_, Some(Node::Instantiation(i))) => self.resolve(&i.ty),
+			(_, Some(Node::Field(f))) => self.resolve(&f.ty),
+			(_, Some(Node::Parameter(p))) => self.resolve(&p.ty),
+			_ => Type::Unknown,
+		};
+
+		self.in_progress.remove(&id);
+		self.info.symbols.insert(id, ty.clone());
+		ty
+	}
+
+	/// Returns the type named by a type symbol.
+	fn named_type(&mut self, id: SymbolId, args: Vec<Type>) -> Type {
+		match self.table.symbol(id).kind {
+			SymbolKind::Typedef => match self.nodes.get(&id).copied() {
+				Some(Node::Typedef(t)) if self.in_progress.insert(id) => {
+					let ty = self.resolve(&t.ty);
+					self.in_progress.remove(&id);
+					ty
+				}
+				_ => Type::Unknown,
+			},
+			SymbolKind::Type => Type::NewType(id),
+			SymbolKind::Header => Type::Header(id, args),
+			SymbolKind::HeaderUnion => Type::HeaderUnion(id, args),
+			SymbolKind::Struct => Type::Struct(id, args),
+			SymbolKind::Enum => Type::Enum(id),
+			SymbolKind::ExternObject => Type::Extern(id, args),
+			SymbolKind::Parser | SymbolKind::ParserType => Type::Parser(id),
+			SymbolKind::Control | SymbolKind::ControlType => Type::Control(id),
+			SymbolKind::Package => Type::Package(id, args),
+			SymbolKind::TypeParameter => Type::TypeParameter(id),
+			_ => Type::Unknown,
+		}
+	}
+
+	/// Returns the type parameters declared by a generic type or callable.
+	fn type_params(&self, id: SymbolId) -> Vec<SymbolId> {
+		match self.table.symbol(id).body {
+			Some(body) => self
+				.table
+				.scope(body)
+				.symbols
+				.iter()
+				.copied()
+				.filter(|s| self.table.symbol(*s).kind == SymbolKind::TypeParameter)
+				.collect(),
+			None => vec![],
+		}
+	}
+
+	/// Returns the type a `type` declaration or a serializable enum is based on.
+	fn underlying_type(&mut self, id: SymbolId) -> Option<Type> {
+		if let Some(ty) = self.info.underlying.get(&id) {
+			return Some(ty.clone());
+		}
+		let ty = match self.nodes.get(&id).copied()? {
+			Node::Typedef(t) if t.is_new_type && self.in_progress.insert(id) => {
+				let ty = self.resolve(&t.ty);
+				self.in_progress.remove(&id);
+				ty
+			}
+			Node::Enum(e) if e.name.location == self.table.symbol(id).location => {
+				self.resolve(e.underlying_type.as_ref()?)
+			}
+			_ => return None,
+		};
+		self.info.underlying.insert(id, ty.clone());
+		Some(ty)
+	}
+
+	/// Evaluates an integer expression that must be known at compile time, such as the width of a `bit<W>`.
+	fn evaluate(&mut self, expression: &Expression) -> Option<i128> {
+		const_eval::evaluate(self, expression)?.to_i128()
+	}
+
+	/// Evaluates the width of a `bit<W>`, `int<W>` or `varbit<W>`, or the size of a header stack.
+	fn size(&mut self, expression: &Expression, what: &str) -> Option<u32> {
+		let ty = self.expression(expression);
+		match self.evaluate(expression) {
+			Some(value) => match u32::try_from(value) {
+				Ok(value) => Some(value),
+				Err(_) => {
+					self.error(&expression.location, format!("{what} must be a non-negative number, found {value}"));
+					None
+				}
+			},
+			None => {
+				if !ty.is_unknown() {
+					self.error(&expression.location, format!("{what} must be a compile-time constant"));
+				}
+				None
+			}
+		}
+	}
+
+	/// Resolves a type reference.
+	fn resolve(&mut self, ty: &TypeRef) -> Type {
+		let resolved = self.resolve_kind(ty);
+		self.info.type_refs.insert(ty.location.clone(), resolved.clone());
+		resolved
+	}
+
+	fn resolve_kind(&mut self, ty: &TypeRef) -> Type {
+		match &ty.kind {
+			TypeRefKind::Bit(None) => Type::Bit(1),
+			TypeRefKind::Int(None) => Type::InfInt,
+			TypeRefKind::Bit(Some(width)) => self.size(width, "the width of a `bit`").map_or(Type::Unknown, Type::Bit),
+			TypeRefKind::Int(Some(width)) => match self.size(width, "the width of an `int`") {
+				Some(0) => {
+					self.error(&width.location, "the width of an `int` must be positive".to_string());
+					Type::Unknown
+				}
+				width => width.map_or(Type::Unknown, Type::Int),
+			},
+			TypeRefKind::Varbit(width) => {
+				self.size(width, "the width of a `varbit`").map_or(Type::Unknown, Type::Varbit)
+			}
+			TypeRefKind::Bool => Type::Bool,
+			TypeRefKind::String => Type::String,
+			TypeRefKind::Void => Type::Void,
+			TypeRefKind::Error => Type::Error,
+			TypeRefKind::MatchKind => Type::MatchKind,
+			TypeRefKind::DontCare => Type::DontCare,
+			TypeRefKind::Named(name, args) => {
+				let args: Vec<_> = args.iter().map(|arg| self.resolve(arg)).collect();
+				let Some(id) = self.target(name) else {
+					return Type::Unknown;
+				};
+				let symbol = self.table.symbol(id);
+				if !symbol.kind.is_type() && !matches!(symbol.kind, SymbolKind::Parser | SymbolKind::Control) {
+					if symbol.kind != SymbolKind::Macro {
+						self.error(&name.location, format!("`{}` is not a type", name.name));
+					}
+					return Type::Unknown;
+				}
+
+				let type_params = self.type_params(id);
+				// the type arguments of a generic type may be omitted where they can be inferred
+				if !args.is_empty() && args.len() != type_params.len() {
+					let message =
+						format!("`{}` expects {} type arguments, found {}", name.name, type_params.len(), args.len());
+					self.error(&ty.location, message);
+				}
+				self.named_type(id, args)
+			}
+			TypeRefKind::Stack(element, size) => {
+				let element_type = self.resolve(element);
+				if !matches!(element_type, Type::Header(..) | Type::HeaderUnion(..)) && !element_type.is_unknown() {
+					let message =
+						format!("the elements of a header stack must be headers, found `{}`", self.show(&element_type));
+					self.error(&element.location, message);
+				}
+				match self.size(size, "the size of a header stack") {
+					Some(size) => Type::Stack(element_type.into(), size),
+					None => Type::Unknown,
+				}
+			}
+			TypeRefKind::Tuple(elements) => Type::Tuple(elements.iter().map(|e| self.resolve(e)).collect()),
+		}
+	}
+
+	// declarations
+
+	fn declarations(&mut self, declarations: &[Declaration]) {
+		for declaration in declarations {
+			self.declaration(declaration);
+		}
+	}
+
+	fn declaration(&mut self, declaration: &Declaration) {
+		match declaration {
+			Declaration::Constant(c) => {
+				let ty = self.resolve(&c.ty);
+				let value = self.expression(&c.value);
+				self.check_assign(&ty, &value, &c.value.location);
+			}
+			Declaration::Variable(v) => {
+				let ty = self.resolve(&v.ty);
+				if let Some(initializer) = &v.initializer {
+					let value = self.expression(initializer);
+					self.check_assign(&ty, &value, &initializer.location);
+				}
+			}
+			Declaration::Instantiation(i) => {
+				let ty = self.resolve(&i.ty);
+				self.construct(&ty, &i.args, &i.location);
+				self.declarations(&i.initializer);
+			}
+			Declaration::Typedef(t) => {
+				self.resolve(&t.ty);
+				if let Some(id) = self.declared_at(&t.name) {
+					self.underlying_type(id);
+				}
+			}
+			Declaration::Struct(s) => {
+				for field in &s.fields {
+					let ty = self.resolve(&field.ty);
+					let valid = match s.kind {
+						StructKind::Header => self.is_header_field_type(&ty),
+						StructKind::HeaderUnion => matches!(ty, Type::Header(..)) || ty.is_unknown(),
+						StructKind::Struct => !matches!(ty, Type::Void | Type::Parser(_) | Type::Control(_)),
+					};
+					if !valid {
+						let kind = match s.kind {
+							StructKind::Header => "a header",
+							StructKind::HeaderUnion => "a header union",
+							StructKind::Struct => "a struct",
+						};
+						let message = format!("`{}` cannot be the type of a field of {kind}", self.show(&ty));
+						self.error(&field.ty.location, message);
+					}
+				}
+			}
+			Declaration::Enum(e) => {
+				if let Some(id) = self.declared_at(&e.name) {
+					self.underlying_type(id);
+				}
+				let underlying = e.underlying_type.as_ref().map(|ty| (self.resolve(ty), &ty.location));
+				if let Some((ty, location)) = &underlying {
+					if !matches!(ty, Type::Bit(_) | Type::Int(_)) && !ty.is_unknown() {
+						let message = format!(
+							"the underlying type of an enum must be `bit<W>` or `int<W>`, found `{}`",
+							self.show(ty)
+						);
+						self.error(location, message);
+					}
+				}
+				for member in &e.members {
+					match (&member.value, &underlying) {
+						(Some(value), Some((ty, _))) => {
+							let value_type = self.expression(value);
+							self.check_assign(ty, &value_type, &value.location);
+						}
+						(Some(value), None) => {
+							self.error(
+								&value.location,
+								"only enums with an underlying type can assign values".to_string(),
+							);
+						}
+						(None, Some(_)) => {
+							let message =
+								format!("the member `{}` of a serializable enum must have a value", member.name.name);
+							self.error(&member.name.location, message);
+						}
+						(None, None) => (),
+					}
+				}
+			}
+			Declaration::Error(_) | Declaration::MatchKind(_) => (),
+			Declaration::ExternObject(e) => {
+				for method in &e.methods {
+					self.prototype(method);
+				}
+			}
+			Declaration::ExternFunction(f) => self.prototype(f),
+			Declaration::Parser(p) => {
+				self.parameters(&p.params);
+				self.parameters(&p.constructor_params);
+				self.declarations(&p.locals);
+				for state in &p.states {
+					self.statements(&state.statements);
+					if let Some(transition) = &state.transition {
+						self.transition(transition);
+					}
+				}
+			}
+			Declaration::Control(c) => {
+				self.parameters(&c.params);
+				self.parameters(&c.constructor_params);
+				self.declarations(&c.locals);
+				if let Some(apply) = &c.apply {
+					self.with_return_type(Type::Void, |this| this.statements(&apply.statements));
+				}
+			}
+			Declaration::Package(p) => self.parameters(&p.params),
+			Declaration::Action(a) => {
+				self.parameters(&a.params);
+				self.with_return_type(Type::Void, |this| this.statements(&a.body.statements));
+			}
+			Declaration::Function(f) => {
+				self.prototype(&f.prototype);
+				let return_type = f.prototype.return_type.as_ref().map_or(Type::Void, |ty| self.resolve(ty));
+				self.with_return_type(return_type, |this| this.statements(&f.body.statements));
+			}
+			Declaration::Table(t) => {
+				for property in &t.properties {
+					self.table_property(property);
+				}
+			}
+		}
+	}
+
+	fn is_header_field_type(&mut self, ty: &Type) -> bool {
+		match ty {
+			Type::Bit(_) | Type::Int(_) | Type::Varbit(_) | Type::Bool => true,
+			Type::Enum(id) | Type::NewType(id) => match self.underlying_type(*id) {
+				Some(underlying) => self.is_header_field_type(&underlying),
+				None => false,
+			},
+			ty => ty.is_unknown(),
+		}
+	}
+
+	fn with_return_type(&mut self, return_type: Type, f: impl FnOnce(&mut Self)) {
+		let outer = self.return_type.replace(return_type);
+		f(self);
+		self.return_type = outer;
+	}
+
+	fn prototype(&mut self, prototype: &FunctionPrototype) {
+		if let Some(return_type) = &prototype.return_type {
+			self.resolve(return_type);
+		}
+		self.parameters(&prototype.params);
+	}
+
+	fn parameters(&mut self, params: &[Parameter]) {
+		for param in params {
+			let ty = self.resolve(&param.ty);
+			if let Some(default) = &param.default {
+				let value = self.expression(default);
+				self.check_assign(&ty, &value, &default.location);
+			}
+		}
+	}
+
+	fn transition(&mut self, transition: &Transition) {
+		if let TransitionKind::Select(selectors, cases) = &transition.kind {
+			for selector in selectors {
+				self.expression(selector);
+			}
+			// the shapes of the keysets are checked by the parser analysis
+			for key in cases.iter().flat_map(|c| &c.keyset) {
+				self.expression(key);
+			}
+		}
+	}
+
+	fn table_property(&mut self, property: &TableProperty) {
+		match property {
+			TableProperty::Key(elements, _) => {
+				for element in elements {
+					let ty = self.expression(&element.expression);
+					if matches!(ty, Type::Varbit(_) | Type::Void | Type::Function(_) | Type::Table(_)) {
+						let message = format!("`{}` cannot be used as a table key", self.show(&ty));
+						self.error(&element.expression.location, message);
+					}
+				}
+			}
+			TableProperty::Actions(actions, _) => {
+				for action in actions {
+					self.action_reference(action, true);
+				}
+			}
+			TableProperty::DefaultAction { action, .. } => self.action_reference(action, false),
+			TableProperty::Entries { entries, .. } => {
+				for entry in entries {
+					for key in &entry.keyset {
+						self.expression(key);
+					}
+					self.action_reference(&entry.action, false);
+				}
+			}
+			TableProperty::Custom { value, .. } => {
+				self.expression(value);
+			}
+		}
+	}
+
+	/// Checks a reference to an action in a table. In the `actions` list only the parameters with a direction are
+	/// bound; the others are supplied by the control plane.
+	fn action_reference(&mut self, action: &ActionReference, in_list: bool) {
+		let arg_types: Vec<_> = action
+			.args
+			.iter()
+			.flatten()
+			.map(|arg| (self.expression(&arg.value), arg.value.location.clone()))
+			.collect();
+
+		let Some(id) = self.target(&action.name) else {
+			return;
+		};
+		if self.table.symbol(id).kind != SymbolKind::Action {
+			self.error(&action.name.location, format!("`{}` is not an action", action.name.name));
+			return;
+		}
+		let (Some(args), Some(signature)) = (&action.args, self.signature(id)) else {
+			return;
+		};
+
+		let params: Vec<_> = signature.params.iter().filter(|p| !in_list || p.direction.is_some()).cloned().collect();
+		if args.len() != params.len() {
+			let message =
+				format!("the action `{}` expects {} arguments, found {}", action.name.name, params.len(), args.len());
+			self.error(&action.location, message);
+			return;
+		}
+		for (param, (ty, location)) in params.iter().zip(arg_types) {
+			self.check_assign(&param.ty, &ty, &location);
+		}
+	}
+
+	// statements
+
+	fn statements(&mut self, statements: &[Statement]) {
+		for statement in statements {
+			self.statement(statement);
+		}
+	}
+
+	fn statement(&mut self, statement: &Statement) {
+		match &statement.kind {
+			StatementKind::Assignment(lhs, rhs) => {
+				let target = self.expression(lhs);
+				let value = self.expression(rhs);
+				self.check_assign(&target, &value, &rhs.location);
+			}
+			StatementKind::Call(call) => {
+				self.expression(call);
+			}
+			StatementKind::If(condition, then, otherwise) => {
+				let ty = self.expression(condition);
+				if ty != Type::Bool && !ty.is_unknown() {
+					self.error(
+						&condition.location,
+						format!("expected a condition of type `bool`, found `{}`", self.show(&ty)),
+					);
+				}
+				self.statement(then);
+				if let Some(otherwise) = otherwise {
+					self.statement(otherwise);
+				}
+			}
+			StatementKind::Switch(scrutinee, cases) => {
+				let ty = self.expression(scrutinee);
+				for case in cases {
+					let label = self.expression(&case.label);
+					// the labels of a switch on `action_run` are the names of actions
+					if !matches!(ty, Type::ActionRun(_)) && label != Type::DontCare {
+						self.check_assign(&ty, &label, &case.label.location);
+					}
+					if let Some(body) = &case.body {
+						self.statements(&body.statements);
+					}
+				}
+			}
+			StatementKind::Block(block) => self.statements(&block.statements),
+			StatementKind::Return(value) => {
+				let ty = value.as_ref().map(|v| self.expression(v));
+				match (self.return_type.clone(), ty, value) {
+					(Some(expected), Some(ty), Some(value)) => {
+						if expected == Type::Void {
+							self.error(&value.location, "cannot return a value from here".to_string());
+						} else {
+							self.check_assign(&expected, &ty, &value.location);
+						}
+					}
+					(Some(expected), None, _) if expected != Type::Void && !expected.is_unknown() => {
+						let message = format!("expected a return value of type `{}`", self.show(&expected));
+						self.error(&statement.location, message);
+					}
+					_ => (),
+				}
+			}
+			StatementKind::Exit | StatementKind::Empty => (),
+			StatementKind::Declaration(declaration) => self.declaration(declaration),
+		}
+	}
+
+	// expressions
+
+	fn expression(&mut self, expression: &Expression) -> Type {
+		let ty = self.expression_kind(expression);
+		self.info.expressions.insert(expression.location.clone(), ty.clone());
+		ty
+	}
+
+	fn expression_kind(&mut self, expression: &Expression) -> Type {
+		match &expression.kind {
+			ExpressionKind::Integer(literal) => integer_type(literal),
+			ExpressionKind::Boolean(_) => Type::Bool,
+			ExpressionKind::String(_) => Type::String,
+			ExpressionKind::Name(name) => self.target(name).map_or(Type::Unknown, |id| self.symbol_type(id)),
+			ExpressionKind::Member(base, member) => {
+				// enum and error members have already been resolved
+				if let Some(id) = self.target(member) {
+					if !matches!(&base.kind, ExpressionKind::Name(name) if name.name == "error") {
+						self.expression(base);
+					}
+					return self.symbol_type(id);
+				}
+				if matches!(&base.kind, ExpressionKind::Name(name) if name.name == "error") {
+					return Type::Error;
+				}
+				let base_type = self.expression(base);
+				self.member(&base_type, member)
+			}
+			ExpressionKind::Index(base, index) => {
+				let base_type = self.expression(base);
+				let index_type = self.expression(index);
+				if !index_type.is_numeric() && !index_type.is_unknown() {
+					let message = format!("expected an index of an integer type, found `{}`", self.show(&index_type));
+					self.error(&index.location, message);
+				}
+				match base_type {
+					Type::Stack(element, size) => {
+						if let Some(index) = self.evaluate(index) {
+							if index < 0 || index >= size as i128 {
+								let message = format!("index {index} is out of bounds for a stack of size {size}");
+								self.error(&expression.location, message);
+							}
+						}
+						*element
+					}
+					ty if ty.is_unknown() => Type::Unknown,
+					ty => {
+						self.error(&base.location, format!("cannot index into a value of type `{}`", self.show(&ty)));
+						Type::Unknown
+					}
+				}
+			}
+			ExpressionKind::Slice(base, high, low) => {
+				let base_type = self.expression(base);
+				self.expression(high);
+				self.expression(low);
+				let width = match &base_type {
+					Type::Bit(w) | Type::Int(w) => Some(*w),
+					Type::InfInt => None,
+					ty if ty.is_unknown() => return Type::Unknown,
+					ty => {
+						self.error(&base.location, format!("cannot slice a value of type `{}`", self.show(ty)));
+						return Type::Unknown;
+					}
+				};
+				let (Some(h), Some(l)) = (self.evaluate(high), self.evaluate(low)) else {
+					self.error(&expression.location, "the bounds of a slice must be compile-time constants".to_string());
+					return Type::Unknown;
+				};
+				if l < 0 || h < l {
+					self.error(&expression.location, format!("invalid slice `[{h}:{l}]`"));
+					return Type::Unknown;
+				}
+				if let Some(width) = width {
+					if h >= width as i128 {
+						let message = format!("slice `[{h}:{l}]` is out of range for `{}`", self.show(&base_type));
+						self.error(&expression.location, message);
+					}
+				}
+				Type::Bit((h - l + 1) as u32)
+			}
+			ExpressionKind::Call(callee, type_args, args) => self.call(callee, type_args, args, &expression.location),
+			ExpressionKind::Unary(op, operand) => {
+				let ty = self.expression(operand);
+				if ty.is_unknown() {
+					return if *op == UnaryOp::Not { Type::Bool } else { Type::Unknown };
+				}
+				let valid = match op {
+					UnaryOp::Not => ty == Type::Bool,
+					UnaryOp::Complement => matches!(ty, Type::Bit(_) | Type::Int(_)),
+					UnaryOp::Negate | UnaryOp::Plus => ty.is_numeric(),
+				};
+				if !valid {
+					let message = format!("cannot apply `{}` to a value of type `{}`", unary_text(*op), self.show(&ty));
+					self.error(&expression.location, message);
+				}
+				match op {
+					UnaryOp::Not => Type::Bool,
+					_ if valid => ty,
+					_ => Type::Unknown,
+				}
+			}
+			ExpressionKind::Binary(op, lhs, rhs) => {
+				let lhs_type = self.expression(lhs);
+				let rhs_type = self.expression(rhs);
+				self.binary(*op, lhs_type, rhs_type, &expression.location)
+			}
+			ExpressionKind::Ternary(condition, then, otherwise) => {
+				let condition_type = self.expression(condition);
+				if condition_type != Type::Bool && !condition_type.is_unknown() {
+					let message =
+						format!("expected a condition of type `bool`, found `{}`", self.show(&condition_type));
+					self.error(&condition.location, message);
+				}
+				let then_type = self.expression(then);
+				let otherwise_type = self.expression(otherwise);
+				match self.unify(&then_type, &otherwise_type) {
+					Ok(ty) => ty,
+					Err(message) => {
+						self.error(&expression.location, message);
+						Type::Unknown
+					}
+				}
+			}
+			ExpressionKind::Cast(ty, operand) => {
+				let target = self.resolve(ty);
+				let source = self.expression(operand);
+				if !self.can_cast(&target, &source) {
+					let message = format!("cannot cast `{}` to `{}`", self.show(&source), self.show(&target));
+					self.error(&expression.location, message);
+				}
+				target
+			}
+			ExpressionKind::List(elements) => Type::List(elements.iter().map(|e| self.expression(e)).collect()),
+			ExpressionKind::StructInitializer(fields) => {
+				let names = fields.iter().map(|(name, _)| name.clone()).collect();
+				self.initializers.insert(expression.location.clone(), names);
+				Type::StructLiteral(
+					fields.iter().map(|(name, value)| (name.name.clone(), self.expression(value))).collect(),
+				)
+			}
+			ExpressionKind::Type(ty) => Type::Meta(self.resolve(ty).into()),
+			ExpressionKind::DontCare | ExpressionKind::Default => Type::DontCare,
+			ExpressionKind::Missing => Type::Unknown,
+		}
+	}
+
+	fn member_reference(&mut self, member: &Identifier, qualifier: &Type, target: SymbolId) {
+		let scope = self.table.scope_at(member.location.file, member.location.span.start);
+		self.info.members.push(Reference {
+			name: member.name.clone(),
+			location: member.location.clone(),
+			scope,
+			qualifier: Some(self.show(qualifier)),
+			target: Some(target),
+		});
+	}
+
+	/// Returns the type of `member` of a value of type `ty`.
+	fn member(&mut self, ty: &Type, member: &Identifier) -> Type {
+		let name = member.name.as_str();
+		let field = |this: &Self, id: &SymbolId, kind: SymbolKind| {
+			let body = this.table.symbol(*id).body?;
+			this.table.members(body, name).iter().copied().find(|m| this.table.symbol(*m).kind == kind)
+		};
+
+		let result = match ty {
+			Type::Header(id, args) | Type::HeaderUnion(id, args) | Type::Struct(id, args) => {
+				if let Some(field) = field(self, id, SymbolKind::Field) {
+					self.member_reference(member, ty, field);
+					let field_type = self.symbol_type(field);
+					let bindings: Vec<_> = self.type_params(*id).into_iter().zip(args.iter().cloned()).collect();
+					Some(substitute(&field_type, &bindings))
+				} else {
+					match (ty, name) {
+						(Type::Header(..) | Type::HeaderUnion(..), "isValid") => Some(Type::Builtin(Builtin::IsValid)),
+						(Type::Header(..), "setValid") => Some(Type::Builtin(Builtin::SetValid)),
+						(Type::Header(..), "setInvalid") => Some(Type::Builtin(Builtin::SetInvalid)),
+						(_, "minSizeInBits") => Some(Type::Builtin(Builtin::MinSizeInBits)),
+						(_, "minSizeInBytes") => Some(Type::Builtin(Builtin::MinSizeInBytes)),
+						_ => None,
+					}
+				}
+			}
+			Type::Stack(element, _) => match name {
+				"next" | "last" => Some(element.as_ref().clone()),
+				"size" | "lastIndex" | "nextIndex" => Some(Type::Bit(32)),
+				"push_front" => Some(Type::Builtin(Builtin::PushFront)),
+				"pop_front" => Some(Type::Builtin(Builtin::PopFront)),
+				_ => None,
+			},
+			Type::Extern(id, _) => field(self, id, SymbolKind::Method).map(|method| {
+				self.member_reference(member, ty, method);
+				Type::Method(ty.clone().into(), method)
+			}),
+			Type::Table(id) | Type::Parser(id) | Type::Control(id) if name == "apply" => Some(Type::Apply(*id)),
+			Type::ApplyResult(id) => match name {
+				"hit" | "miss" => Some(Type::Bool),
+				"action_run" => Some(Type::ActionRun(*id)),
+				_ => None,
+			},
+			Type::Meta(_) => match name {
+				"minSizeInBits" => Some(Type::Builtin(Builtin::MinSizeInBits)),
+				"minSizeInBytes" => Some(Type::Builtin(Builtin::MinSizeInBytes)),
+				_ => Some(Type::Unknown),
+			},
+			ty if ty.is_unknown() => Some(Type::Unknown),
+			_ => None,
+		};
+
+		result.unwrap_or_else(|| {
+			self.error(&member.location, format!("no member `{name}` in `{}`", self.show(ty)));
+			Type::Unknown
+		})
+	}
+
+	fn binary(&mut self, op: BinaryOp, lhs: Type, rhs: Type, location: &Location) -> Type {
+		use BinaryOp::*;
+
+		let is_comparison = matches!(op, Equals | NotEquals | LessThan | LessOrEqual | GreaterThan | GreaterOrEqual);
+		if lhs.is_unknown() || rhs.is_unknown() {
+			return match op {
+				LogicalAnd | LogicalOr => Type::Bool,
+				_ if is_comparison => Type::Bool,
+				ShiftLeft | ShiftRight if !lhs.is_unknown() => lhs,
+				_ => Type::Unknown,
+			};
+		}
+
+		let text = binary_text(op);
+		let invalid_operand = |this: &mut Self, ty: &Type| {
+			let message = format!("cannot apply `{text}` to a value of type `{}`", this.show(ty));
+			this.error(location, message);
+		};
+
+		match op {
+			LogicalAnd | LogicalOr => {
+				for ty in [&lhs, &rhs] {
+					if *ty != Type::Bool {
+						invalid_operand(self, ty);
+					}
+				}
+				Type::Bool
+			}
+			Equals | NotEquals => {
+				if let Err(message) = self.unify(&lhs, &rhs) {
+					self.error(location, message);
+				}
+				Type::Bool
+			}
+			LessThan | LessOrEqual | GreaterThan | GreaterOrEqual => {
+				match [&lhs, &rhs].into_iter().find(|ty| !ty.is_numeric()) {
+					Some(ty) => invalid_operand(self, ty),
+					None => {
+						if let Err(message) = self.unify(&lhs, &rhs) {
+							self.error(location, message);
+						}
+					}
+				}
+				Type::Bool
+			}
+			ShiftLeft | ShiftRight => {
+				if !lhs.is_numeric() {
+					invalid_operand(self, &lhs);
+					return Type::Unknown;
+				}
+				if !matches!(rhs, Type::Bit(_) | Type::InfInt) {
+					let message = format!("the shift amount must be unsigned, found `{}`", self.show(&rhs));
+					self.error(location, message);
+				}
+				lhs
+			}
+			Concatenate => match (&lhs, &rhs) {
+				(Type::Bit(a), Type::Bit(b) | Type::Int(b)) => Type::Bit(a + b),
+				(Type::Int(a), Type::Bit(b) | Type::Int(b)) => Type::Int(a + b),
+				_ => {
+					for ty in [&lhs, &rhs] {
+						if !matches!(ty, Type::Bit(_) | Type::Int(_)) {
+							let message = format!(
+								"cannot concatenate a value of type `{}`; only `bit<W>` and `int<W>` have a known width",
+								self.show(ty)
+							);
+							self.error(location, message);
+						}
+					}
+					Type::Unknown
+				}
+			},
+			_ => {
+				let bitwise = matches!(op, BitAnd | BitOr | BitXor);
+				match [&lhs, &rhs].into_iter().find(|ty| !ty.is_numeric()) {
+					Some(ty) => {
+						invalid_operand(self, ty);
+						Type::Unknown
+					}
+					None if bitwise && lhs == Type::InfInt && rhs == Type::InfInt => Type::InfInt,
+					None => match self.unify(&lhs, &rhs) {
+						Ok(ty) => ty,
+						Err(message) => {
+							self.error(location, message);
+							Type::Unknown
+						}
+					},
+				}
+			}
+		}
+	}
+
+	fn call(&mut self, callee: &Expression, type_args: &[TypeRef], args: &[Argument], location: &Location) -> Type {
+		let callee_type = self.expression(callee);
+		let type_args: Vec<_> = type_args.iter().map(|ty| self.resolve(ty)).collect();
+
+		match callee_type {
+			Type::Meta(ty) => self.construct(&ty, args, location),
+			Type::Function(id) => {
+				let symbol = self.table.symbol(id);
+				let candidates = self.table.members(symbol.scope, &symbol.name).to_vec();
+				self.invoke(&candidates, vec![], type_args, args, location)
+			}
+			Type::Method(owner, id) => {
+				let symbol = self.table.symbol(id);
+				let candidates = self.table.members(symbol.scope, &symbol.name).to_vec();
+				let bindings = match owner.as_ref() {
+					Type::Extern(extern_id, args) => {
+						self.type_params(*extern_id).into_iter().zip(args.clone()).collect()
+					}
+					_ => vec![],
+				};
+				self.invoke(&candidates, bindings, type_args, args, location)
+			}
+			Type::Apply(id) => self.invoke(&[id], vec![], type_args, args, location),
+			Type::Builtin(builtin) => {
+				for arg in args {
+					self.expression(&arg.value);
+				}
+				let expected = if matches!(builtin, Builtin::PushFront | Builtin::PopFront) { 1 } else { 0 };
+				if args.len() != expected {
+					let message = format!("`{}` expects {expected} arguments, found {}", builtin.name(), args.len());
+					self.error(location, message);
+				}
+				match builtin {
+					Builtin::IsValid => Type::Bool,
+					Builtin::MinSizeInBits | Builtin::MinSizeInBytes => Type::InfInt,
+					_ => Type::Void,
+				}
+			}
+			ty => {
+				for arg in args {
+					self.expression(&arg.value);
+				}
+				if !ty.is_unknown() {
+					self.error(&callee.location, format!("a value of type `{}` cannot be called", self.show(&ty)));
+				}
+				Type::Unknown
+			}
+		}
+	}
+
+	/// Returns the signature of an action, function, method, table, parser or control (i.e. of its `apply`).
+	fn signature(&mut self, id: SymbolId) -> Option<Signature> {
+		if self.table.symbol(id).kind == SymbolKind::Table {
+			return Some(Signature { type_params: vec![], params: vec![], return_type: Type::ApplyResult(id) });
+		}
+
+		let type_params =
+			|this: &Self, params: &[Identifier]| params.iter().filter_map(|p| this.declared_at(p)).collect();
+		match self.nodes.get(&id).copied()? {
+			Node::Prototype(p) => Some(Signature {
+				type_params: type_params(self, &p.type_params),
+				params: self.params(&p.params),
+				return_type: p.return_type.as_ref().map_or(Type::Void, |ty| self.resolve(ty)),
+			}),
+			Node::Function(f) => Some(Signature {
+				type_params: type_params(self, &f.prototype.type_params),
+				params: self.params(&f.prototype.params),
+				return_type: f.prototype.return_type.as_ref().map_or(Type::Void, |ty| self.resolve(ty)),
+			}),
+			Node::Action(a) => {
+				Some(Signature { type_params: vec![], params: self.params(&a.params), return_type: Type::Void })
+			}
+			Node::Parser(p) => {
+				Some(Signature { type_params: vec![], params: self.params(&p.params), return_type: Type::Void })
+			}
+			Node::Control(c) => {
+				Some(Signature { type_params: vec![], params: self.params(&c.params), return_type: Type::Void })
+			}
+			_ => None,
+		}
+	}
+
+	fn params(&mut self, params: &[Parameter]) -> Vec<Param> {
+		params
+			.iter()
+			.map(|p| Param {
+				name: p.name.name.clone(),
+				direction: p.direction,
+				ty: self.resolve(&p.ty),
+				has_default: p.default.is_some(),
+			})
+			.collect()
+	}
+
+	/// Checks a call to one of a set of overloaded callables, which are told apart by their number of parameters.
+	fn invoke(
+		&mut self,
+		candidates: &[SymbolId],
+		bindings: Vec<(SymbolId, Type)>,
+		type_args: Vec<Type>,
+		args: &[Argument],
+		location: &Location,
+	) -> Type {
+		let arg_types: Vec<_> = args.iter().map(|arg| self.expression(&arg.value)).collect();
+
+		let signatures: Vec<_> = candidates.iter().filter_map(|id| Some((*id, self.signature(*id)?))).collect();
+		let accepts = |s: &Signature| {
+			let required = s.params.iter().filter(|p| !p.has_default).count();
+			required <= args.len() && args.len() <= s.params.len()
+		};
+
+		let Some((_, signature)) = signatures.iter().find(|(_, s)| accepts(s)).cloned() else {
+			if let Some((id, signature)) = signatures.first() {
+				let expected = signature.params.len();
+				let message = format!("`{}` expects {expected} arguments, found {}", self.table.symbol(*id).name, args.len());
+				self.error(location, message);
+			}
+			return Type::Unknown;
+		};
+
+		let mut bindings = bindings;
+		bindings.extend(signature.type_params.iter().copied().zip(type_args));
+
+		for (i, (arg, arg_type)) in args.iter().zip(arg_types).enumerate() {
+			let param = match &arg.name {
+				Some(name) => match signature.params.iter().find(|p| p.name == name.name) {
+					Some(param) => param,
+					None => {
+						self.error(&name.location, format!("no parameter named `{}`", name.name));
+						continue;
+					}
+				},
+				None => &signature.params[i],
+			};
+			if arg_type == Type::DontCare {
+				continue;
+			}
+			let param_type = substitute(&param.ty, &bindings);
+			match param_type {
+				// infer the type arguments of generic callables from their arguments
+				Type::TypeParameter(t) if signature.type_params.contains(&t) => bindings.push((t, arg_type)),
+				param_type => self.check_assign(&param_type, &arg_type, &arg.value.location),
+			}
+		}
+
+		substitute(&signature.return_type, &bindings)
+	}
+
+	/// Checks a constructor call (or an instantiation) of `ty`.
+	fn construct(&mut self, ty: &Type, args: &[Argument], location: &Location) -> Type {
+		match ty {
+			Type::Extern(id, type_args) => {
+				let name = &self.table.symbol(*id).name;
+				let constructors: Vec<_> = match self.table.symbol(*id).body {
+					Some(body) => self.table.members(body, name).to_vec(),
+					None => vec![],
+				};
+				if constructors.is_empty() {
+					for arg in args {
+						self.expression(&arg.value);
+					}
+					if !args.is_empty() {
+						self.error(location, format!("`{name}` has no constructor taking arguments"));
+					}
+				} else {
+					let bindings = self.type_params(*id).into_iter().zip(type_args.clone()).collect();
+					self.invoke(&constructors, bindings, vec![], args, location);
+				}
+				ty.clone()
+			}
+			Type::Parser(id) | Type::Control(id) | Type::Package(id, _) => {
+				let params = match self.nodes.get(id).copied() {
+					Some(Node::Parser(p)) => self.params(&p.constructor_params),
+					Some(Node::Control(c)) => self.params(&c.constructor_params),
+					Some(Node::Package(p)) => self.params(&p.params),
+					_ => vec![],
+				};
+				let arg_types: Vec<_> = args.iter().map(|arg| self.expression(&arg.value)).collect();
+				if params.len() != args.len() {
+					let name = &self.table.symbol(*id).name;
+					let message =
+						format!("`{name}` expects {} constructor arguments, found {}", params.len(), args.len());
+					self.error(location, message);
+				} else {
+					for ((param, arg_type), arg) in params.iter().zip(arg_types).zip(args) {
+						self.check_assign(&param.ty, &arg_type, &arg.value.location);
+					}
+				}
+				ty.clone()
+			}
+			ty if ty.is_unknown() => {
+				for arg in args {
+					self.expression(&arg.value);
+				}
+				Type::Unknown
+			}
+			ty => {
+				for arg in args {
+					self.expression(&arg.value);
+				}
+				self.error(location, format!("`{}` cannot be instantiated", self.show(ty)));
+				Type::Unknown
+			}
+		}
+	}
+
+	// compatibility
+
+	/// Returns the common type of two operands, or an error message explaining why there isn't one.
+	fn unify(&mut self, a: &Type, b: &Type) -> Result<Type, String> {
+		if a.is_unknown() || b.is_unknown() {
+			return Ok(Type::Unknown);
+		}
+		match (a, b) {
+			(Type::Bit(x), Type::Bit(y)) | (Type::Int(x), Type::Int(y)) if x != y => {
+				Err(format!("width mismatch between `{}` and `{}`", self.show(a), self.show(b)))
+			}
+			_ if self.assignable(a, b).is_ok() => Ok(a.clone()),
+			_ if self.assignable(b, a).is_ok() => Ok(b.clone()),
+			_ => Err(format!("mismatched types `{}` and `{}`", self.show(a), self.show(b))),
+		}
+	}
+
+	fn check_assign(&mut self, target: &Type, source: &Type, location: &Location) {
+		if let Err(message) = self.assignable(target, source) {
+			self.error(location, message);
+		}
+
+		// the names of a structure-valued expression refer to the fields of the header or struct it initializes
+		if let (Type::Header(id, _) | Type::Struct(id, _), Some(names)) = (target, self.initializers.remove(location)) {
+			let Some(body) = self.table.symbol(*id).body else { return };
+			for name in names {
+				let field = self.table.members(body, &name.name).first().copied();
+				if let Some(field) = field.filter(|field| self.table.symbol(*field).kind == SymbolKind::Field) {
+					self.member_reference(&name, target, field);
+				}
+			}
+		}
+	}
+
+	/// Checks whether a value of type `source` can be used where a `target` is expected, implicitly casting it if
+	/// necessary.
+	fn assignable(&mut self, target: &Type, source: &Type) -> Result<(), String> {
+		let mismatch =
+			|this: &Self| Err(format!("cannot implicitly cast `{}` to `{}`", this.show(source), this.show(target)));
+
+		match (target, source) {
+			_ if target.is_unknown() || source.is_unknown() => Ok(()),
+			(Type::DontCare, _) | (_, Type::DontCare) => Ok(()),
+			_ if target == source => Ok(()),
+			(Type::Bit(_) | Type::Int(_), Type::InfInt) => Ok(()),
+			(Type::Bit(x), Type::Bit(y)) | (Type::Int(x), Type::Int(y)) => Err(format!(
+				"width mismatch: expected `{}`, found `{}` ({y} bits instead of {x})",
+				self.show(target),
+				self.show(source)
+			)),
+			// serializable enums are implicitly cast to their underlying type
+			(_, Type::Enum(id)) => match self.underlying_type(*id) {
+				Some(underlying) if underlying == *target => Ok(()),
+				_ => mismatch(self),
+			},
+			(Type::Header(id, args) | Type::Struct(id, args), Type::List(elements)) => {
+				let fields = self.fields(*id, args);
+				if fields.len() != elements.len() {
+					return Err(format!(
+						"expected {} fields to initialize `{}`, found {}",
+						fields.len(),
+						self.show(target),
+						elements.len()
+					));
+				}
+				fields.iter().zip(elements).try_for_each(|((_, field), element)| self.assignable(field, element))
+			}
+			(Type::Header(id, args) | Type::Struct(id, args), Type::StructLiteral(values)) => {
+				let fields = self.fields(*id, args);
+				for (name, value) in values {
+					match fields.iter().find(|(field, _)| field == name) {
+						Some((_, field)) => self.assignable(field, value)?,
+						None => return Err(format!("no field `{name}` in `{}`", self.show(target))),
+					}
+				}
+				match fields.iter().find(|(field, _)| !values.iter().any(|(name, _)| name == field)) {
+					Some((missing, _)) => {
+						Err(format!("missing field `{missing}` to initialize `{}`", self.show(target)))
+					}
+					None => Ok(()),
+				}
+			}
+			(Type::Tuple(types), Type::List(elements)) | (Type::Tuple(types), Type::Tuple(elements)) => {
+				if types.len() != elements.len() {
+					return Err(format!("expected {} elements, found {}", types.len(), elements.len()));
+				}
+				types.iter().zip(elements).try_for_each(|(ty, element)| self.assignable(ty, element))
+			}
+			(Type::Stack(x, n), Type::Stack(y, m)) if n == m => self.assignable(x, y),
+			// generic types are compatible if they are instances of the same declaration, since their type arguments
+			// may be inferred
+			(Type::Extern(x, _), Type::Extern(y, _))
+			| (Type::Package(x, _), Type::Package(y, _))
+			| (Type::Header(x, _), Type::Header(y, _))
+			| (Type::Struct(x, _), Type::Struct(y, _))
+			| (Type::HeaderUnion(x, _), Type::HeaderUnion(y, _))
+				if x == y =>
+			{
+				Ok(())
+			}
+			// the signatures of blocks are checked against the architecture
+			(Type::Parser(_), Type::Parser(_)) | (Type::Control(_), Type::Control(_)) => Ok(()),
+			_ => mismatch(self),
+		}
+	}
+
+	/// Checks whether a value of type `source` can be explicitly cast to `target`.
+	fn can_cast(&mut self, target: &Type, source: &Type) -> bool {
+		if self.assignable(target, source).is_ok() {
+			return true;
+		}
+		match (target, source) {
+			(Type::Bit(_), Type::Bit(_)) | (Type::Int(_), Type::Int(_)) => true,
+			(Type::Bit(x), Type::Int(y)) | (Type::Int(x), Type::Bit(y)) => x == y,
+			(Type::Bool, Type::Bit(1) | Type::InfInt) | (Type::Bit(1), Type::Bool) => true,
+			(Type::Enum(id), ty) | (ty, Type::Enum(id)) | (Type::NewType(id), ty) | (ty, Type::NewType(id)) => {
+				match self.underlying_type(*id) {
+					Some(underlying) => self.can_cast(&underlying, ty) || self.can_cast(ty, &underlying),
+					None => false,
+				}
+			}
+			_ => false,
+		}
+	}
+
+	/// Returns the names and types of the fields of a struct-like type.
+	fn fields(&mut self, id: SymbolId, args: &[Type]) -> Vec<(String, Type)> {
+		let Some(body) = self.table.symbol(id).body else {
+			return vec![];
+		};
+		let bindings: Vec<_> = self.type_params(id).into_iter().zip(args.iter().cloned()).collect();
+		let fields: Vec<_> = self
+			.table
+			.scope(body)
+			.symbols
+			.iter()
+			.copied()
+			.filter(|s| self.table.symbol(*s).kind == SymbolKind::Field)
+			.collect();
+		fields
+			.into_iter()
+			.map(|field| (self.table.symbol(field).name.clone(), substitute(&self.symbol_type(field), &bindings)))
+			.collect()
+	}
+}
+
+impl const_eval::Environment for Checker<'_> {
+	fn value_of(&mut self, name: &Identifier) -> Option<Value> {
+		let id = self.target(name)?;
+		if let Some(value) = self.constants.get(&id) {
+			return value.clone();
+		}
+		self.constants.insert(id, None);
+
+		let symbol = self.table.symbol(id);
+		let value = match (&symbol.kind, self.nodes.get(&id).copied()) {
+			(SymbolKind::Constant, Some(Node::Constant(c))) => {
+				let ty = self.symbol_type(id);
+				const_eval::evaluate(self, &c.value).and_then(|value| const_eval::cast_value(self, value, &ty))
+			}
+			(SymbolKind::EnumMember, Some(Node::Enum(e))) => {
+				match e.members.iter().find(|m| m.name.location == symbol.location).and_then(|m| m.value.as_ref()) {
+					Some(value) => {
+						let ty = self.symbol_type(id);
+						const_eval::evaluate(self, value).and_then(|value| const_eval::cast_value(self, value, &ty))
+					}
+					None => Some(Value::Member(id)),
+				}
+			}
+			(SymbolKind::ErrorMember | SymbolKind::MatchKind, _) => Some(Value::Member(id)),
+			(SymbolKind::Macro, _) => const_eval::macro_value(self.table, id),
+			_ => None,
+		};
+
+		self.constants.insert(id, value.clone());
+		value
+	}
+
+	fn resolve_type(&mut self, ty: &TypeRef) -> Type { self.resolve(ty) }
+
+	fn underlying_type(&mut self, id: SymbolId) -> Option<Type> { Checker::underlying_type(self, id) }
+}
+
+fn integer_type(literal: &crate::lexer::Literal) -> Type {
+	match literal.width {
+		Some(width) if literal.signed => Type::Int(width),
+		Some(width) => Type::Bit(width),
+		None => Type::InfInt,
+	}
+}
+
+/// Replaces the type parameters bound in `bindings` in `ty`.
+pub fn substitute(ty: &Type, bindings: &[(SymbolId, Type)]) -> Type {
+	let all = |types: &[Type]| types.iter().map(|t| substitute(t, bindings)).collect();
+	match ty {
+		Type::TypeParameter(id) => match bindings.iter().find(|(param, _)| param == id) {
+			Some((_, bound)) => bound.clone(),
+			None => ty.clone(),
+		},
+		Type::Header(id, args) => Type::Header(*id, all(args)),
+		Type::HeaderUnion(id, args) => Type::HeaderUnion(*id, all(args)),
+		Type::Struct(id, args) => Type::Struct(*id, all(args)),
+		Type::Extern(id, args) => Type::Extern(*id, all(args)),
+		Type::Package(id, args) => Type::Package(*id, all(args)),
+		Type::Stack(element, size) => Type::Stack(substitute(element, bindings).into(), *size),
+		Type::Tuple(elements) => Type::Tuple(all(elements)),
+		ty => ty.clone(),
+	}
+}
+
+fn unary_text(op: UnaryOp) -> &'static str {
+	match op {
+		UnaryOp::Not => "!",
+		UnaryOp::Complement => "~",
+		UnaryOp::Negate => "-",
+		UnaryOp::Plus => "+",
+	}
+}
+
+fn binary_text(op: BinaryOp) -> &'static str {
+	use BinaryOp::*;
+	match op {
+		Multiply => "*",
+		Divide => "/",
+		Modulo => "%",
+		Add => "+",
+		Subtract => "-",
+		SaturatingAdd => "|+|",
+		SaturatingSubtract => "|-|",
+		ShiftLeft => "<<",
+		ShiftRight => ">>",
+		LessThan => "<",
+		LessOrEqual => "<=",
+		GreaterThan => ">",
+		GreaterOrEqual => ">=",
+		Equals => "==",
+		NotEquals => "!=",
+		BitAnd => "&",
+		BitXor => "^",
+		BitOr => "|",
+		Concatenate => "++",
+		LogicalAnd => "&&",
+		LogicalOr => "||",
+		Mask => "&&&",
+		Range => "..",
+	}
+}
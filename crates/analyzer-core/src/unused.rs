@@ -0,0 +1,186 @@
+//! Unused declarations.
+//!
+//! This reports the actions that are neither called nor listed by a table, the tables that are never applied, the
+//! local variables, constants and types that are never named, the fields of headers that are never accessed, and the
+//! `#include`s of files none of whose declarations are used. Unreachable parser states are reported by
+//! [`crate::parser_states`]. An action listed in the `actions` of a table is used, since the control plane may add
+//! entries running it, even if the data plane never calls it, unless the action or its listing is `@hidden` from the
+//! control plane.
+//!
+//! A file may be included by several others, so the declarations of a file are checked against every translation
+//! unit containing it: [`unused_declarations`] finds those unused in the translation unit rooted at the file, and
+//! [`crate::Analyzer::diagnostics`] drops those that another translation unit [`uses`].
+
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+	annotations,
+	ast::{
+		visit::{self, Visitor},
+		Declaration, TableProperty,
+	},
+	base_abstractions::*,
+	embedded_path, file_lexemes,
+	lexer::Token,
+	lints::{self, Lint},
+	parse, preprocess,
+	preprocessor::PreprocessorDirective,
+	resolve_include,
+	symbols::{self, SymbolKind},
+	types, Fs,
+};
+
+/// The lints reporting unused declarations.
+pub static LINTS: &[&Lint] = &[
+	&lints::UNUSED_VARIABLE,
+	&lints::UNUSED_CONSTANT,
+	&lints::UNUSED_ACTION,
+	&lints::UNUSED_TABLE,
+	&lints::UNUSED_FIELD,
+	&lints::UNUSED_TYPE,
+	&lints::UNUSED_INCLUDE,
+];
+
+/// The declarations used in a translation unit, by the location of their names, with the files using them.
+pub type Uses = HashMap<Location, HashSet<FileId>>;
+
+/// Finds the declarations used in the translation unit rooted at `file_id`.
+#[salsa::tracked(return_ref)]
+pub fn uses(db: &dyn crate::Db, fs: Fs, file_id: FileId) -> Option<Uses> {
+	let program = parse(db, fs, file_id).as_ref()?;
+	let table = symbols::symbols(db, fs, file_id).as_ref()?;
+	let info = types::types(db, fs, file_id).as_ref()?;
+	let annotations = annotations::annotations(db, fs, file_id).as_ref()?;
+
+	let mut listings = Listings::default();
+	visit::walk_program(&mut listings, program);
+
+	let mut uses = Uses::new();
+	for reference in table.references().iter().chain(info.member_references()) {
+		let Some(target) = reference.target else { continue };
+		let location = &table.symbol(target).location;
+		// the control plane cannot run the actions hidden from it
+		let hidden = |location| annotations.find(location, "hidden").is_some();
+		if listings.0.contains(&reference.location) && (hidden(&reference.location) || hidden(location)) {
+			continue;
+		}
+		uses.entry(location.clone()).or_default().insert(reference.location.file);
+	}
+	Some(uses)
+}
+
+/// The names of the actions listed in the `actions` of tables.
+#[derive(Default)]
+struct Listings(HashSet<Location>);
+
+impl<'a> Visitor<'a> for Listings {
+	fn visit_declaration(&mut self, declaration: &'a Declaration) {
+		if let Declaration::Table(t) = declaration {
+			for property in &t.properties {
+				if let TableProperty::Actions(actions, _) = property {
+					self.0.extend(actions.iter().map(|action| action.name.location.clone()));
+				}
+			}
+		}
+		visit::walk_declaration(self, declaration);
+	}
+}
+
+/// An unused declaration or `#include`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Unused {
+	pub diagnostic: Diagnostic,
+	/// The names of the declarations whose use would make this used: the declaration itself, or those of the
+	/// included files.
+	pub declarations: Vec<Location>,
+	/// The files whose uses of the declarations don't count, i.e. the included files.
+	pub files: HashSet<FileId>,
+}
+
+impl Unused {
+	pub fn is_used(&self, uses: &Uses) -> bool {
+		let mut users = self.declarations.iter().filter_map(|declaration| uses.get(declaration)).flatten();
+		users.any(|file| !self.files.contains(file))
+	}
+}
+
+/// Finds the declarations of the file `file_id` which are unused in the translation unit rooted at it.
+#[salsa::tracked(return_ref)]
+pub fn unused_declarations(db: &dyn crate::Db, fs: Fs, file_id: FileId) -> Option<Vec<Unused>> {
+	let table = symbols::symbols(db, fs, file_id).as_ref()?;
+	let uses = uses(db, fs, file_id).as_ref()?;
+	let is_used = |location: &Location| uses.contains_key(location);
+
+	let mut unused = vec![];
+	for (_, symbol) in table.symbols().filter(|(_, symbol)| symbol.location.file == file_id) {
+		let name = &symbol.name;
+		let (lint, message): (&'static Lint, _) = match symbol.kind {
+			SymbolKind::Variable => (&lints::UNUSED_VARIABLE, format!("the variable `{name}` is never used")),
+			SymbolKind::Constant => (&lints::UNUSED_CONSTANT, format!("the constant `{name}` is never used")),
+			SymbolKind::Action => (&lints::UNUSED_ACTION, format!("the action `{name}` is never used")),
+			SymbolKind::Table => (&lints::UNUSED_TABLE, format!("the table `{name}` is never applied")),
+			SymbolKind::Typedef | SymbolKind::Type => (&lints::UNUSED_TYPE, format!("the type `{name}` is never used")),
+			SymbolKind::Field => {
+				// the fields of an unused header would only repeat that the header is unused
+				let Some(header) = table.scope(symbol.scope).owner.map(|id| table.symbol(id)) else { continue };
+				if header.kind != SymbolKind::Header || !is_used(&header.location) {
+					continue;
+				}
+				let message = format!("the field `{name}` of the header `{}` is never used", header.name);
+				(&lints::UNUSED_FIELD, message)
+			}
+			_ => continue,
+		};
+		if !is_used(&symbol.location) {
+			unused.push(Unused {
+				diagnostic: lint.diagnostic(&symbol.location, message).with_tag(DiagnosticTag::Unnecessary),
+				declarations: vec![symbol.location.clone()],
+				files: HashSet::new(),
+			});
+		}
+	}
+
+	// the files actually included, leaving out those in disabled conditionals
+	let included: HashSet<_> = preprocess(db, fs, file_id).as_ref()?.iter().map(|(file, _, _)| *file).collect();
+	for (token, span) in file_lexemes(db, fs, file_id)? {
+		let Token::PreprocessorDirective(PreprocessorDirective::Include(_, path)) = token else { continue };
+		let target = resolve_include(db, fs, file_id, path);
+		// every program needs the core library, whether or not it names its declarations
+		if embedded_path(path) == Some(symbols::PRELUDE_PATH) || !included.contains(&target) {
+			continue;
+		}
+
+		let mut files = HashSet::from([target]);
+		included_files(db, fs, file_id, target, &mut files);
+		let declared = table.symbols().filter(|(_, symbol)| files.contains(&symbol.location.file));
+		let (macros, declarations): (Vec<_>, Vec<_>) =
+			declared.partition(|(_, symbol)| symbol.kind == SymbolKind::Macro);
+		// macros are expanded before their uses could be recorded
+		if !macros.is_empty() {
+			continue;
+		}
+
+		let declarations = declarations.into_iter().map(|(_, symbol)| symbol.location.clone()).collect();
+		let location = Location::new(file_id, span.clone());
+		let message = format!("nothing declared in `{path}` is used");
+		let diagnostic = lints::UNUSED_INCLUDE.diagnostic(&location, message).with_tag(DiagnosticTag::Unnecessary);
+		let include = Unused { diagnostic, declarations, files };
+		if !include.is_used(uses) {
+			unused.push(include);
+		}
+	}
+
+	Some(unused)
+}
+
+/// Adds the files included by `file`, directly or not, to `files`.
+fn included_files(db: &dyn crate::Db, fs: Fs, root: FileId, file: FileId, files: &mut HashSet<FileId>) {
+	for (token, _) in file_lexemes(db, fs, file).into_iter().flatten() {
+		if let Token::PreprocessorDirective(PreprocessorDirective::Include(_, path)) = token {
+			let target = resolve_include(db, fs, root, path);
+			if files.insert(target) {
+				included_files(db, fs, root, target, files);
+			}
+		}
+	}
+}
@@ -0,0 +1,537 @@
+//! The header validity analysis.
+//!
+//! Reading or writing a field of an invalid header is undefined, and a common source of bugs on hardware targets.
+//! This flow-sensitive analysis tracks the headers which are definitely valid at each point of the parsers, controls
+//! and actions of a program, and warns about the accesses to the fields of the others.
+//!
+//! A header becomes valid when it is extracted, when `setValid()` is called on it, and when a list expression or a
+//! valid header is assigned to it. An `isValid()` guard makes it valid within the guarded code, and within the other
+//! arguments of a call taking the guard as an argument (as the checksum externs of v1model do).
+//!
+//! Every header is possibly invalid when a parser or control starts. The states of a parser are analyzed until the
+//! validity of the headers at their start reaches a fixpoint; an action is analyzed with the headers valid at every
+//! invocation of it, directly or through the tables listing it, and a table application leaves valid the headers
+//! left valid by all the actions of the table.
+//!
+//! Headers are identified by their paths, such as `hdr.ipv4`; the elements of header stacks are not tracked.
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use crate::{
+	ast::*,
+	base_abstractions::*,
+	lints, parse,
+	symbols::{self, SymbolId, SymbolKind, SymbolTable},
+	types::{self, Builtin, Type, TypeInfo},
+	Fs,
+};
+
+/// The headers definitely valid at a point of a program, or `None` if the point is unreachable.
+type State = Option<BTreeSet<String>>;
+
+/// Checks the accesses to the fields of possibly invalid headers in the translation unit rooted at `file_id`.
+#[salsa::tracked]
+pub fn header_validity(db: &dyn crate::Db, fs: Fs, file_id: FileId) {
+	let Some(program) = parse(db, fs, file_id).as_ref() else { return };
+	let Some(table) = symbols::symbols(db, fs, file_id).as_ref() else { return };
+	let Some(info) = types::types(db, fs, file_id).as_ref() else { return };
+
+	let symbols: HashMap<_, _> = table.symbols().map(|(id, symbol)| (symbol.location.clone(), id)).collect();
+	let mut analysis = Analysis {
+		table,
+		info,
+		actions: vec![],
+		tables: HashMap::new(),
+		references: table.references().iter().filter_map(|r| Some((r.location.clone(), r.target?))).collect(),
+		entries: HashMap::new(),
+		returned: None,
+		report: true,
+		reported: HashSet::new(),
+		diagnostics: vec![],
+	};
+	let locals = program.declarations.iter().flat_map(|declaration| match declaration {
+		Declaration::Control(control) => control.locals.as_slice(),
+		_ => &[],
+	});
+	for declaration in program.declarations.iter().chain(locals) {
+		let Some(&id) = declaration.name().and_then(|name| symbols.get(&name.location)) else { continue };
+		match declaration {
+			Declaration::Action(action) => analysis.actions.push((id, action)),
+			Declaration::Table(t) => {
+				analysis.tables.insert(id, t);
+			}
+			_ => (),
+		}
+	}
+
+	for declaration in &program.declarations {
+		match declaration {
+			Declaration::Parser(parser) if !parser.is_type_declaration => analysis.parser(parser),
+			Declaration::Control(control) => {
+				if let Some(apply) = &control.apply {
+					analysis.statements(&apply.statements, Some(BTreeSet::new()));
+				}
+			}
+			_ => (),
+		}
+	}
+	// the actions are checked once all their invocations are known
+	for (id, action) in analysis.actions.clone() {
+		if let Some(entry) = analysis.entries.get(&id).cloned() {
+			analysis.body(&action.body, entry);
+		}
+	}
+
+	for diagnostic in analysis.diagnostics {
+		Diagnostics::push(db, diagnostic);
+	}
+}
+
+struct Analysis<'a> {
+	table: &'a SymbolTable,
+	info: &'a TypeInfo,
+	actions: Vec<(SymbolId, &'a Action)>,
+	tables: HashMap<SymbolId, &'a Table>,
+	/// The symbols the names at a given location refer to.
+	references: HashMap<Location, SymbolId>,
+	/// The headers valid at every invocation of each action.
+	entries: HashMap<SymbolId, State>,
+	/// The headers valid at the `return` statements of the body being analyzed.
+	returned: State,
+	/// `false` while analyzing code whose entry state isn't final yet.
+	report: bool,
+	reported: HashSet<Location>,
+	diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> Analysis<'a> {
+	fn parser(&mut self, parser: &'a Parser) {
+		let index: HashMap<_, _> =
+			parser.states.iter().enumerate().map(|(i, state)| (state.name.name.as_str(), i)).collect();
+		let Some(&start) = index.get("start") else { return };
+		let mut entries: Vec<State> = vec![None; parser.states.len()];
+		entries[start] = Some(BTreeSet::new());
+
+		self.report = false;
+		let mut worklist = vec![start];
+		while let Some(i) = worklist.pop() {
+			let state = &parser.states[i];
+			let exit = self.parser_state(state, entries[i].clone());
+			for target in state.targets() {
+				let Some(&j) = index.get(target.name.as_str()) else { continue };
+				let entry = meet(entries[j].clone(), exit.clone());
+				if entry != entries[j] {
+					entries[j] = entry;
+					worklist.push(j);
+				}
+			}
+		}
+
+		self.report = true;
+		for (state, entry) in parser.states.iter().zip(entries) {
+			self.parser_state(state, entry);
+		}
+	}
+
+	fn parser_state(&mut self, state: &'a ParserState, entry: State) -> State {
+		let mut valid = self.statements(&state.statements, entry)?;
+		if let Some(Transition { kind: TransitionKind::Select(selectors, _), .. }) = &state.transition {
+			for selector in selectors {
+				self.expression(selector, &mut valid);
+			}
+		}
+		Some(valid)
+	}
+
+	/// Analyzes the body of an action, returning the headers valid when it returns.
+	fn body(&mut self, body: &'a Block, entry: State) -> State {
+		let returned = self.returned.take();
+		let end = self.statements(&body.statements, entry);
+		let returned = std::mem::replace(&mut self.returned, returned);
+		meet(end, returned)
+	}
+
+	fn statements(&mut self, statements: &'a [Statement], mut state: State) -> State {
+		for statement in statements {
+			state = self.statement(statement, state);
+		}
+		state
+	}
+
+	fn statement(&mut self, statement: &'a Statement, state: State) -> State {
+		let mut valid = state?;
+		match &statement.kind {
+			StatementKind::Assignment(lhs, rhs) => {
+				self.expression(rhs, &mut valid);
+				self.target(lhs, &mut valid);
+				self.assign(lhs, Some(rhs), &mut valid);
+			}
+			StatementKind::Call(call) => self.expression(call, &mut valid),
+			StatementKind::If(condition, then, otherwise) => {
+				let (when_true, when_false) = self.condition(condition, valid);
+				let then = self.statement(then, Some(when_true));
+				let otherwise = match otherwise {
+					Some(otherwise) => self.statement(otherwise, Some(when_false)),
+					None => Some(when_false),
+				};
+				return meet(then, otherwise);
+			}
+			StatementKind::Switch(scrutinee, cases) => {
+				self.expression(scrutinee, &mut valid);
+				let mut exit = None;
+				for body in cases.iter().filter_map(|case| case.body.as_ref()) {
+					exit = meet(exit, self.statements(&body.statements, Some(valid.clone())));
+				}
+				if !self.is_exhaustive(scrutinee, cases) {
+					exit = meet(exit, Some(valid));
+				}
+				return exit;
+			}
+			StatementKind::Block(block) => return self.statements(&block.statements, Some(valid)),
+			StatementKind::Return(value) => {
+				if let Some(value) = value {
+					self.expression(value, &mut valid);
+				}
+				self.returned = meet(self.returned.take(), Some(valid));
+				return None;
+			}
+			StatementKind::Exit => return None,
+			StatementKind::Empty => (),
+			StatementKind::Declaration(declaration) => match declaration.as_ref() {
+				Declaration::Variable(variable) => {
+					if let Some(initializer) = &variable.initializer {
+						self.expression(initializer, &mut valid);
+					}
+					let name = Expression {
+						kind: ExpressionKind::Name(variable.name.clone()),
+						location: variable.name.location.clone(),
+					};
+					self.assign(&name, variable.initializer.as_ref(), &mut valid);
+				}
+				Declaration::Constant(constant) => self.expression(&constant.value, &mut valid),
+				_ => (),
+			},
+		}
+		Some(valid)
+	}
+
+	/// Returns the headers valid when `condition` holds, and when it doesn't.
+	fn condition(
+		&mut self,
+		condition: &'a Expression,
+		mut valid: BTreeSet<String>,
+	) -> (BTreeSet<String>, BTreeSet<String>) {
+		if let Some(header) = self.validity_check(condition) {
+			let mut when_true = valid.clone();
+			self.set_valid(header, &mut when_true);
+			return (when_true, valid);
+		}
+		match &condition.kind {
+			ExpressionKind::Unary(UnaryOp::Not, operand) => {
+				let (when_true, when_false) = self.condition(operand, valid);
+				return (when_false, when_true);
+			}
+			ExpressionKind::Binary(BinaryOp::LogicalAnd, lhs, rhs) => {
+				let (lhs_true, lhs_false) = self.condition(lhs, valid);
+				let (when_true, rhs_false) = self.condition(rhs, lhs_true);
+				return (when_true, &lhs_false & &rhs_false);
+			}
+			ExpressionKind::Binary(BinaryOp::LogicalOr, lhs, rhs) => {
+				let (lhs_true, lhs_false) = self.condition(lhs, valid);
+				let (rhs_true, when_false) = self.condition(rhs, lhs_false);
+				return (&lhs_true & &rhs_true, when_false);
+			}
+			_ => (),
+		}
+		self.expression(condition, &mut valid);
+		(valid.clone(), valid)
+	}
+
+	/// Returns the header whose validity `expression` checks, if it is a call to `isValid()`.
+	fn validity_check<'e>(&self, expression: &'e Expression) -> Option<&'e Expression> {
+		let ExpressionKind::Call(callee, _, args) = &expression.kind else { return None };
+		let ExpressionKind::Member(header, _) = &callee.kind else { return None };
+		match self.info.expression_type(&callee.location) {
+			Some(Type::Builtin(Builtin::IsValid)) if args.is_empty() => Some(header),
+			_ => None,
+		}
+	}
+
+	/// Checks the accesses in an expression, and applies the effects of the calls it makes.
+	fn expression(&mut self, expression: &'a Expression, valid: &mut BTreeSet<String>) {
+		match &expression.kind {
+			ExpressionKind::Integer(_)
+			| ExpressionKind::Boolean(_)
+			| ExpressionKind::String(_)
+			| ExpressionKind::Name(_)
+			| ExpressionKind::DontCare
+			| ExpressionKind::Default
+			| ExpressionKind::Type(_)
+			| ExpressionKind::Missing => (),
+			ExpressionKind::Member(base, member) => {
+				self.access(base, member, &expression.location, valid, "read");
+				self.expression(base, valid);
+			}
+			ExpressionKind::Index(base, index) => {
+				self.expression(base, valid);
+				self.expression(index, valid);
+			}
+			ExpressionKind::Slice(base, high, low) => {
+				self.expression(base, valid);
+				self.expression(high, valid);
+				self.expression(low, valid);
+			}
+			ExpressionKind::Call(callee, _, args) => self.call(callee, args, valid),
+			ExpressionKind::Unary(UnaryOp::Not, _)
+			| ExpressionKind::Binary(BinaryOp::LogicalAnd | BinaryOp::LogicalOr, _, _) => {
+				let (when_true, when_false) = self.condition(expression, valid.clone());
+				*valid = &when_true & &when_false;
+			}
+			ExpressionKind::Unary(_, operand) | ExpressionKind::Cast(_, operand) => self.expression(operand, valid),
+			ExpressionKind::Binary(_, lhs, rhs) => {
+				self.expression(lhs, valid);
+				self.expression(rhs, valid);
+			}
+			ExpressionKind::Ternary(condition, then, otherwise) => {
+				let (mut when_true, mut when_false) = self.condition(condition, valid.clone());
+				self.expression(then, &mut when_true);
+				self.expression(otherwise, &mut when_false);
+				*valid = &when_true & &when_false;
+			}
+			ExpressionKind::List(elements) => {
+				for element in elements {
+					self.expression(element, valid);
+				}
+			}
+			ExpressionKind::StructInitializer(fields) => {
+				for (_, value) in fields {
+					self.expression(value, valid);
+				}
+			}
+		}
+	}
+
+	/// Checks the accesses in the left-hand side of an assignment, which writes the field it names.
+	fn target(&mut self, target: &'a Expression, valid: &mut BTreeSet<String>) {
+		if self.info.expression_type(&target.location).map_or(true, Type::is_unknown) {
+			return;
+		}
+		match &target.kind {
+			ExpressionKind::Member(base, member) => {
+				self.access(base, member, &target.location, valid, "written");
+				self.target(base, valid);
+			}
+			ExpressionKind::Index(base, index) => {
+				self.target(base, valid);
+				self.expression(index, valid);
+			}
+			ExpressionKind::Slice(base, high, low) => {
+				self.target(base, valid);
+				self.expression(high, valid);
+				self.expression(low, valid);
+			}
+			_ => (),
+		}
+	}
+
+	/// Reports the access to the field `member` of `base` if `base` is a possibly invalid header.
+	fn access(
+		&mut self,
+		base: &Expression,
+		member: &Identifier,
+		location: &Location,
+		valid: &BTreeSet<String>,
+		action: &str,
+	) {
+		if !self.report || !matches!(self.info.expression_type(&base.location), Some(Type::Header(..))) {
+			return;
+		}
+		// the methods of headers, such as `isValid`, can be called on invalid headers
+		match self.info.expression_type(location) {
+			Some(Type::Builtin(_)) | None => return,
+			Some(ty) if ty.is_unknown() => return,
+			_ => (),
+		}
+		let Some(header) = self.path(base) else { return };
+		if !valid.contains(&header) && self.reported.insert(location.clone()) {
+			let message = format!("`{header}` may be invalid when its field `{}` is {action}", member.name);
+			self.diagnostics.push(lints::INVALID_HEADER_ACCESS.diagnostic(location, message));
+		}
+	}
+
+	fn call(&mut self, callee: &'a Expression, args: &'a [Argument], valid: &mut BTreeSet<String>) {
+		if let ExpressionKind::Member(base, _) = &callee.kind {
+			self.expression(base, valid);
+		}
+		// the arguments are guarded by the validity conditions passed along with them, as in the checksum externs of
+		// v1model, e.g. `update_checksum(hdr.ipv4.isValid(), { hdr.ipv4.ttl, ... }, ...)`
+		let mut guarded = valid.clone();
+		for header in args.iter().filter_map(|arg| self.validity_check(&arg.value)) {
+			self.set_valid(header, &mut guarded);
+		}
+		for arg in args {
+			self.expression(&arg.value, &mut guarded);
+		}
+		valid.retain(|header| guarded.contains(header));
+
+		let table = self.table;
+		let base = match &callee.kind {
+			ExpressionKind::Member(base, _) => Some(base.as_ref()),
+			_ => None,
+		};
+		match (self.info.expression_type(&callee.location), base) {
+			(Some(Type::Builtin(Builtin::SetValid)), Some(header)) => return self.set_valid(header, valid),
+			(Some(Type::Builtin(Builtin::SetInvalid)), Some(header)) => return self.invalidate(header, valid),
+			(Some(Type::Method(object, method)), _) if table.symbol(*method).name == "extract" => {
+				if let (Type::Extern(object, _), Some(header)) = (object.as_ref(), args.first()) {
+					if table.symbol(*object).name == "packet_in" {
+						return self.set_valid(&header.value, valid);
+					}
+				}
+			}
+			(Some(Type::Apply(id)), _) if self.tables.contains_key(id) => return self.apply(*id, valid),
+			(Some(Type::Function(id)), _) if table.symbol(*id).kind == SymbolKind::Action => {
+				if let Some(exit) = self.invoke(*id, valid) {
+					*valid = exit;
+				}
+				return;
+			}
+			_ => (),
+		}
+
+		// the headers passed to `out` and `inout` parameters may be invalidated
+		let params = self.params(callee);
+		for (position, arg) in args.iter().enumerate() {
+			let param = match &arg.name {
+				Some(name) => params.iter().find(|param| param.name == name.name),
+				None => params.get(position),
+			};
+			if let Some(SymbolKind::Parameter(Some(Direction::Out | Direction::InOut))) = param.map(|p| &p.kind) {
+				self.invalidate(&arg.value, valid);
+			}
+		}
+	}
+
+	/// Returns the parameters of the function, method, action, parser or control called by `callee`.
+	fn params(&self, callee: &Expression) -> Vec<&'a symbols::Symbol> {
+		match self.info.expression_type(&callee.location) {
+			Some(Type::Method(_, id) | Type::Function(id) | Type::Apply(id)) => self.table.parameters(*id).collect(),
+			_ => vec![],
+		}
+	}
+
+	/// Returns `true` if a case of the switch statement is taken whatever the value of `scrutinee`.
+	fn is_exhaustive(&self, scrutinee: &Expression, cases: &[SwitchCase]) -> bool {
+		if cases.iter().any(|case| matches!(case.label.kind, ExpressionKind::Default)) {
+			return true;
+		}
+		let Some(Type::ActionRun(id)) = self.info.expression_type(&scrutinee.location) else { return false };
+		let Some(t) = self.tables.get(id) else { return false };
+		let labels: Vec<_> = cases.iter().filter_map(|case| self.references.get(&case.label.location)).collect();
+		t.actions()
+			.all(|action| self.references.get(&action.name.location).map_or(false, |action| labels.contains(&action)))
+	}
+
+	/// Applies a table, leaving valid the headers its actions leave valid.
+	fn apply(&mut self, id: SymbolId, valid: &mut BTreeSet<String>) {
+		let t = self.tables[&id];
+		for property in &t.properties {
+			if let TableProperty::Key(elements, _) = property {
+				for element in elements {
+					self.expression(&element.expression, valid);
+				}
+			}
+		}
+
+		let mut exit = None;
+		for action in t.actions() {
+			for arg in action.args.iter().flatten() {
+				self.expression(&arg.value, valid);
+			}
+			if let Some(&id) = self.references.get(&action.name.location) {
+				exit = meet(exit, self.invoke(id, valid));
+			}
+		}
+		if let Some(exit) = exit {
+			*valid = &*valid & &exit;
+		}
+	}
+
+	/// Records an invocation of an action, returning the headers valid once it returns.
+	fn invoke(&mut self, id: SymbolId, valid: &BTreeSet<String>) -> State {
+		let (_, action) = *self.actions.iter().find(|(action, _)| *action == id)?;
+		let entry = self.entries.entry(id).or_insert(None);
+		*entry = meet(entry.take(), Some(valid.clone()));
+
+		let report = std::mem::replace(&mut self.report, false);
+		let exit = self.body(&action.body, Some(valid.clone()));
+		self.report = report;
+		exit
+	}
+
+	/// Applies an assignment of `value` (or of nothing) to `target`.
+	fn assign(&self, target: &Expression, value: Option<&Expression>, valid: &mut BTreeSet<String>) {
+		let Some(target_path) = self.path(target) else { return };
+		self.invalidate(target, valid);
+		let Some(value) = value else { return };
+		match (self.info.expression_type(&target.location), &value.kind) {
+			(Some(Type::Header(..)), ExpressionKind::List(_) | ExpressionKind::StructInitializer(_)) => {
+				valid.insert(target_path);
+			}
+			_ => {
+				// the target is valid where the value is, e.g. when assigning a struct of headers
+				let Some(value_path) = self.path(value) else { return };
+				let copied: Vec<_> = valid
+					.iter()
+					.filter_map(|header| Some(format!("{target_path}{}", within(header, &value_path)?)))
+					.collect();
+				valid.extend(copied);
+			}
+		}
+	}
+
+	fn set_valid(&self, header: &Expression, valid: &mut BTreeSet<String>) {
+		// the other members of a header union become invalid
+		if let ExpressionKind::Member(union, _) = &header.kind {
+			if matches!(self.info.expression_type(&union.location), Some(Type::HeaderUnion(..))) {
+				self.invalidate(union, valid);
+			}
+		}
+		if let Some(header) = self.path(header) {
+			valid.insert(header);
+		}
+	}
+	/// Forgets the validity of the headers at `expression`, or contained in it.
+	fn invalidate(&self, expression: &Expression, valid: &mut BTreeSet<String>) {
+		if let Some(prefix) = self.path(expression) {
+			valid.retain(|header| within(header, &prefix).is_none());
+		}
+	}
+
+	/// Returns the path of a header, or of an aggregate containing headers, such as `hdr.ipv4`. The elements of
+	/// header stacks have none.
+	fn path(&self, expression: &Expression) -> Option<String> {
+		match &expression.kind {
+			ExpressionKind::Name(name) => Some(name.name.clone()),
+			ExpressionKind::Member(base, member) => {
+				if let Some(Type::Stack(..)) = self.info.expression_type(&base.location) {
+					return None;
+				}
+				Some(format!("{}.{}", self.path(base)?, member.name))
+			}
+			_ => None,
+		}
+	}
+}
+
+/// Returns the rest of the path `header` if it is `prefix`, or a path within it.
+fn within<'h>(header: &'h str, prefix: &str) -> Option<&'h str> {
+	header.strip_prefix(prefix).filter(|rest| rest.is_empty() || rest.starts_with('.'))
+}
+
+fn meet(a: State, b: State) -> State {
+	match (a, b) {
+		(Some(a), Some(b)) => Some(&a & &b),
+		(a, None) | (None, a) => a,
+	}
+}
@@ -0,0 +1,125 @@
+extern crate analyzer_core;
+
+use analyzer_core::*;
+use pretty_assertions::assert_eq;
+
+mod common;
+
+/// Analyzes the program, allowing the lints about unused declarations.
+fn analyzer(s: &str) -> (Analyzer, base_abstractions::FileId) { common::analyze(s, &[]) }
+
+/// Returns the source text and message of each diagnostic of the program.
+fn diagnostics(s: &str) -> Vec<(String, String)> {
+	let (analyzer, file_id) = analyzer(s);
+	analyzer.diagnostics(file_id).into_iter().map(|d| (s[d.location].to_string(), d.message)).collect()
+}
+
+fn error(text: &str, message: &str) -> (String, String) { (text.to_string(), message.to_string()) }
+
+#[test]
+fn collects_annotations_by_element() {
+	let program = r#"
+		control C() {
+			@name("forward") @brief("Forwards the packet")
+			action fwd(@optional bit<9> port) { }
+			table t { actions = { @defaultonly fwd; } }
+			apply { t.apply(); }
+		}
+	"#;
+	let (analyzer, file_id) = analyzer(program);
+	let annotations = analyzer.annotations(file_id).unwrap();
+	let table = analyzer.symbols(file_id).unwrap();
+	let location = |name: &str| table.symbols().find(|(_, s)| s.name == name).unwrap().1.location.clone();
+
+	let names: Vec<_> = annotations.get(&location("fwd")).iter().map(|a| a.name.name.as_str()).collect();
+	assert_eq!(names, vec!["name", "brief"]);
+	assert_eq!(annotations.find(&location("fwd"), "brief").and_then(|a| a.string()), Some("Forwards the packet"));
+	assert!(annotations.find(&location("port"), "optional").is_some());
+	assert_eq!(annotations.control_plane_name(&location("fwd")), Some("C.forward"));
+	assert_eq!(annotations.control_plane_name(&location("t")), Some("C.t"));
+	assert_eq!(diagnostics(program), vec![]);
+}
+
+#[test]
+fn checks_arguments() {
+	let program = r#"
+		@name(1) const bit<8> A = 1;
+		@id("x") const bit<8> B = 2;
+		@hidden(true) const bit<8> C = 3;
+		@name("d") @id(4) @vendor(anything [goes]) const bit<8> D = 4;
+	"#;
+	assert_eq!(
+		diagnostics(program),
+		vec![
+			error("@name(1)", "the annotation `@name` expects a string literal"),
+			error("@id(\"x\")", "the annotation `@id` expects an integer literal"),
+			error("@hidden(true)", "the annotation `@hidden` takes no arguments"),
+		]
+	);
+}
+
+#[test]
+fn checks_placement() {
+	let program = r#"
+		struct s { @optional bit<8> f; }
+		control C(inout s x) {
+			@defaultonly action a() { }
+			table t { key = { x.f: exact; } actions = { a; } const entries = { 1 : a() @priority(1); } }
+			apply { @atomic { t.apply(); } @name("stmt") x.f = 1; }
+		}
+	"#;
+	assert_eq!(
+		diagnostics(program),
+		vec![
+			error("optional", "the annotation `@optional` has no effect on a field"),
+			error("defaultonly", "the annotation `@defaultonly` has no effect on a declaration"),
+			error("name", "the annotation `@name` has no effect on a statement"),
+		]
+	);
+}
+
+#[test]
+fn reports_repeated_annotations() {
+	let program = r#"
+		control C() {
+			action a() { }
+			@id(1) @id(2) @noWarn("unused") @noWarn("uninitialized")
+			table t { actions = { @defaultonly @tableonly a; } }
+			apply { t.apply(); }
+		}
+	"#;
+	assert_eq!(
+		diagnostics(program),
+		vec![
+			error("@id(2)", "the annotation `@id` is repeated"),
+			error("@tableonly", "an action cannot be both `@defaultonly` and `@tableonly`"),
+		]
+	);
+}
+
+#[test]
+fn reports_duplicate_control_plane_names() {
+	let program = r#"
+		action drop() { }
+		control C() {
+			@name("t") table t1 { actions = { drop; } }
+			@name("t") table t2 { actions = { drop; } }
+			@name(".drop") action local_drop() { }
+			@name(".D.x") action x() { }
+			apply { t1.apply(); t2.apply(); local_drop(); }
+		}
+		control D() {
+			@name("t") table t1 { actions = { drop; } }
+			action x() { }
+			apply { t1.apply(); x(); }
+		}
+	"#;
+	assert_eq!(
+		diagnostics(program),
+		vec![
+			error("@name(\"t\")", "the control-plane name `C.t` is already given to `t1`"),
+			error("@name(\".drop\")", "the control-plane name `drop` is already given to `drop`"),
+			error("x", "the control-plane name `D.x` is already given to `x`"),
+		]
+	);
+}
@@ -0,0 +1,220 @@
+extern crate analyzer_core;
+
+use analyzer_core::{arch::Architecture, *};
+use pretty_assertions::assert_eq;
+
+mod common;
+
+fn analyze(s: &str) -> (Analyzer, base_abstractions::FileId) {
+	// the architecture headers are never found, so the embedded ones are used in their place
+	common::analyze(s, &[])
+}
+
+fn errors(s: &str) -> Vec<(String, String)> {
+	let (analyzer, file_id) = analyze(s);
+	analyzer.diagnostics(file_id).into_iter().map(|d| (s[d.location].to_string(), d.message)).collect()
+}
+
+fn error(text: &str, message: &str) -> (String, String) { (text.to_string(), message.to_string()) }
+
+fn v1model_program(ingress: &str, main: &str) -> String {
+	format!(
+		r"
+		#include <v1model.p4>
+		header ethernet_t {{ bit<48> dst; bit<48> src; bit<16> etherType; }}
+		struct headers {{ ethernet_t ethernet; }}
+		struct metadata {{ bit<8> x; }}
+
+		parser MyParser(packet_in packet, out headers hdr, inout metadata meta, inout standard_metadata_t sm) {{
+			state start {{ packet.extract(hdr.ethernet); transition accept; }}
+		}}
+		control MyVerifyChecksum(inout headers hdr, inout metadata meta) {{ apply {{ }} }}
+		control MyIngress({ingress}) {{ apply {{ }} }}
+		control MyEgress(inout headers hdr, inout metadata meta, inout standard_metadata_t sm) {{ apply {{ }} }}
+		control MyComputeChecksum(inout headers hdr, inout metadata meta) {{ apply {{ }} }}
+		control MyDeparser(packet_out packet, in headers hdr) {{ apply {{ packet.emit(hdr.ethernet); }} }}
+		{main}
+	"
+	)
+}
+
+const V1SWITCH: &str =
+	"V1Switch(MyParser(), MyVerifyChecksum(), MyIngress(), MyEgress(), MyComputeChecksum(), MyDeparser()) main;";
+
+const INGRESS: &str = "inout headers hdr, inout metadata meta, inout standard_metadata_t standard_metadata";
+
+#[test]
+fn models_v1model() {
+	let program = v1model_program(INGRESS, V1SWITCH);
+	let (analyzer, file_id) = analyze(&program);
+	let table = analyzer.symbols(file_id).unwrap();
+	let model = analyzer.architecture(file_id).unwrap();
+	let names = |ids: &[symbols::SymbolId]| ids.iter().map(|id| table.symbol(*id).name.as_str()).collect::<Vec<_>>();
+
+	assert_eq!(model.architecture, Architecture::V1Model);
+	assert_eq!(analyzer.path(model.file), "builtin:///v1model.p4");
+	assert_eq!(names(&model.main_packages), ["V1Switch"]);
+	assert_eq!(
+		names(&model.blocks),
+		["Parser", "VerifyChecksum", "Ingress", "Egress", "ComputeChecksum", "Deparser"]
+	);
+	assert_eq!(
+		model.main_blocks.iter().map(|blocks| names(blocks)).collect::<Vec<_>>(),
+		[["MyParser", "MyVerifyChecksum", "MyIngress", "MyEgress", "MyComputeChecksum", "MyDeparser"]]
+	);
+	assert_eq!(names(&model.metadata), ["standard_metadata_t"]);
+	assert_eq!(names(&model.match_kinds), ["range", "optional", "selector"]);
+	assert!(names(&model.externs).contains(&"mark_to_drop"));
+	assert!(names(&model.externs).contains(&"register"));
+}
+
+#[test]
+fn models_psa() {
+	let (analyzer, file_id) = analyze("#include <psa.p4>");
+	let table = analyzer.symbols(file_id).unwrap();
+	let model = analyzer.architecture(file_id).unwrap();
+	let names = |ids: &[symbols::SymbolId]| ids.iter().map(|id| table.symbol(*id).name.as_str()).collect::<Vec<_>>();
+
+	assert_eq!(model.architecture, Architecture::Psa);
+	assert_eq!(names(&model.main_packages), ["PSA_Switch"]);
+	assert_eq!(names(&model.packages), ["IngressPipeline", "EgressPipeline", "PSA_Switch"]);
+	assert!(names(&model.metadata).contains(&"psa_ingress_output_metadata_t"));
+	assert_eq!(analyzer.diagnostics(file_id).len(), 0);
+}
+
+#[test]
+fn models_an_included_header() {
+	let header = "#include <core.p4>\ncontrol Ingress<H>(inout H hdr);\npackage V1Switch<H>(Ingress<H> ig);";
+	let program = r"
+		#include <core.p4>
+		#include <v1model.p4>
+		control MyIngress(inout bit<8> hdr) { apply { } }
+		V1Switch(MyIngress()) main;
+	";
+	let analyzer =
+		common::workspace(&[("core.p4", "match_kind { exact }"), ("v1model.p4", header), ("foo.p4", program)]);
+
+	let file_id = analyzer.file_id("foo.p4");
+	let model = analyzer.architecture(file_id).unwrap();
+	assert_eq!(analyzer.path(model.file), "v1model.p4");
+	assert_eq!(model.blocks.len(), 1);
+	// `core.p4` is only included once, although both files include it
+	assert_eq!(analyzer.diagnostics(file_id).len(), 0);
+}
+
+#[test]
+fn includes_the_embedded_core_library() {
+	// `core.p4` is never found either, so it is replaced by the core library already declared by every program
+	let program = format!("#include <core.p4>\n{}", v1model_program(INGRESS, V1SWITCH));
+	assert_eq!(errors(&program), []);
+
+	let (analyzer, file_id) = analyze(&program);
+	let core = analyzer.file_id("builtin:///core.p4");
+	assert!(analyzer.preprocessed(file_id).unwrap().iter().any(|(file, _, _)| *file == core));
+}
+
+#[test]
+fn serves_the_embedded_headers() {
+	for uri in ["builtin:///v1model.p4", "builtin:/v1model.p4"] {
+		assert_eq!(builtin_source(uri), Some(Architecture::V1Model.source()));
+	}
+	assert!(builtin_source("builtin:/core.p4").unwrap().contains("match_kind"));
+	assert_eq!(builtin_source("builtin:/unknown.p4"), None);
+	assert_eq!(builtin_source("file:///core.p4"), None);
+}
+
+#[test]
+fn has_no_model_without_an_architecture() {
+	let (analyzer, file_id) = analyze("control C() { apply { } }");
+	assert_eq!(analyzer.architecture(file_id), None);
+}
+
+#[test]
+fn checks_a_v1model_program() {
+	assert_eq!(errors(&v1model_program(INGRESS, V1SWITCH)), vec![]);
+}
+
+#[test]
+fn reports_blocks_with_mismatched_parameters() {
+	let program = v1model_program("inout headers hdr, in metadata meta, inout standard_metadata_t sm", V1SWITCH);
+	assert_eq!(
+		errors(&program),
+		vec![error(
+			"in metadata meta",
+			"the parameter `meta` should be `inout` to match `Ingress` in `V1Switch`, found `in`"
+		)]
+	);
+
+	let program = v1model_program("inout metadata hdr, inout metadata meta, inout standard_metadata_t sm", V1SWITCH);
+	assert_eq!(
+		errors(&program),
+		vec![error(
+			"metadata",
+			"the parameter `hdr` should have type `headers` to match `Ingress` in `V1Switch`, found `metadata`"
+		)]
+	);
+
+	let program = v1model_program("inout headers hdr, inout metadata meta", V1SWITCH);
+	assert_eq!(
+		errors(&program),
+		vec![error("MyIngress()", "`MyIngress` should have 3 parameters to match `Ingress` in `V1Switch`, found 2")]
+	);
+}
+
+#[test]
+fn checks_nested_packages() {
+	let program = r"
+		#include <psa.p4>
+		struct headers { }
+		struct metadata { }
+		struct empty_t { }
+
+		parser IP(packet_in buffer, out headers hdr, inout metadata meta, in psa_ingress_parser_input_metadata_t istd,
+			in empty_t resubmit_meta, in empty_t recirculate_meta) { state start { transition accept; } }
+		control I(inout headers hdr, inout metadata meta, in psa_ingress_input_metadata_t istd,
+			inout psa_ingress_output_metadata_t ostd) { apply { } }
+		control ID(packet_out buffer, out empty_t clone_i2e_meta, out empty_t resubmit_meta, out empty_t normal_meta,
+			inout headers hdr, in metadata meta, in psa_ingress_output_metadata_t istd) { apply { } }
+		parser EP(packet_in buffer, out headers hdr, inout metadata meta, in psa_egress_parser_input_metadata_t istd,
+			in metadata normal_meta, in empty_t clone_i2e_meta, in empty_t clone_e2e_meta) { state start { transition accept; } }
+		control E(inout headers hdr, inout metadata meta, in psa_egress_input_metadata_t istd,
+			inout psa_egress_output_metadata_t ostd) { apply { } }
+		control ED(packet_out buffer, out empty_t clone_e2e_meta, out empty_t recirculate_meta, inout headers hdr,
+			in metadata meta, in psa_egress_output_metadata_t istd, in psa_egress_deparser_input_metadata_t edstd) { apply { } }
+
+		PSA_Switch(IngressPipeline(IP(), I(), ID()), PacketReplicationEngine(), EgressPipeline(EP(), E(), ED()),
+			BufferingQueueingEngine()) main;
+	";
+
+	// the normal metadata is bound to `empty_t` by the ingress deparser
+	assert_eq!(
+		errors(program),
+		vec![error(
+			"metadata",
+			"the parameter `normal_meta` should have type `empty_t` to match `EgressParser` in `EgressPipeline`, found `metadata`"
+		)]
+	);
+}
+
+#[test]
+fn reports_main_instantiating_a_part_of_a_package() {
+	let program = r"
+		#include <psa.p4>
+		struct headers { }
+		struct metadata { }
+		parser IP(packet_in buffer, out headers hdr, inout metadata meta, in psa_ingress_parser_input_metadata_t istd,
+			in metadata resubmit_meta, in metadata recirculate_meta) { state start { transition accept; } }
+		control I(inout headers hdr, inout metadata meta, in psa_ingress_input_metadata_t istd,
+			inout psa_ingress_output_metadata_t ostd) { apply { } }
+		control ID(packet_out buffer, out metadata clone_i2e_meta, out metadata resubmit_meta, out metadata normal_meta,
+			inout headers hdr, in metadata meta, in psa_ingress_output_metadata_t istd) { apply { } }
+		IngressPipeline(IP(), I(), ID()) main;
+	";
+	assert_eq!(
+		errors(program),
+		vec![error(
+			"IngressPipeline",
+			"`IngressPipeline` is a part of another package of the PSA architecture; `main` must instantiate `PSA_Switch`"
+		)]
+	);
+}
@@ -0,0 +1,30 @@
+//! Helpers shared by the tests of the analyses.
+
+// each test crate only uses some of the helpers
+#![allow(dead_code)]
+
+use analyzer_core::{base_abstractions::FileId, *};
+
+/// Analyzes the files at the given paths, with the given sources, in a workspace where an included path is looked up
+/// as it is written.
+pub fn workspace(files: &[(&str, &str)]) -> Analyzer {
+	let mut analyzer = Analyzer::new(|_, path| Ok(path.into()), |_| ());
+	for (path, source) in files {
+		let file_id = analyzer.file_id(path);
+		analyzer.update(file_id, &source.to_string());
+	}
+	analyzer
+}
+
+/// Analyzes `source` as `foo.p4`, allowing the lints about unused declarations along with the `allowed` ones, since
+/// most declarations of the programs in the tests are only there to be checked, not used.
+pub fn analyze(source: &str, allowed: &[&str]) -> (Analyzer, FileId) {
+	let mut analyzer = workspace(&[("foo.p4", source)]);
+	let mut config = lints::LintConfig::default();
+	for code in unused::LINTS.iter().map(|lint| lint.code).chain(allowed.iter().copied()) {
+		assert!(config.set(code, lints::Level::Allow), "unknown lint `{code}`");
+	}
+	analyzer.configure_lints(config);
+	let file_id = analyzer.file_id("foo.p4");
+	(analyzer, file_id)
+}
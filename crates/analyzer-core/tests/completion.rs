@@ -0,0 +1,264 @@
+extern crate analyzer_core;
+
+use analyzer_core::{base_abstractions::FileId, completion::CompletionKind, symbols::SymbolKind, *};
+use pretty_assertions::assert_eq;
+
+mod common;
+
+const PROGRAM: &str = r#"
+	#include <core.p4>
+	#include <v1model.p4>
+	TOP
+	const bit<32> SIZE = 1024;
+	header ethernet_t { bit<48> dst; bit<16> type; }
+	header vlan_t { bit<16> tci; }
+	struct headers_t { ethernet_t eth; vlan_t[2] vlans; }
+	struct metadata_t { bit<9> port; }
+
+	parser MyParser(packet_in pkt, out headers_t hdr, inout metadata_t meta, inout standard_metadata_t std) {
+		state start { pkt.extract(hdr.eth); PARSER }
+		STATES
+		state parse_vlan { transition accept; }
+	}
+
+	control MyIngress(inout headers_t hdr, inout metadata_t meta, inout standard_metadata_t std) {
+		counter(SIZE, CounterType.packets) hits;
+		// Drops the packet.
+		action drop() { mark_to_drop(std); }
+		action forward(bit<9> port) { std.egress_spec = port; }
+		table routes {
+			key = { KEY }
+			actions = { drop; forward; }
+		}
+		CONTROL
+		apply { APPLY }
+	}
+"#;
+
+/// Returns the labels of the completions at the end of `code`, written in place of `marker` in [`PROGRAM`].
+fn labels(marker: &str, code: &str) -> Vec<String> {
+	completions(marker, code).into_iter().map(|(label, _)| label).collect()
+}
+
+fn completions(marker: &str, code: &str) -> Vec<(String, CompletionKind)> {
+	let (analyzer, file_id, offset) = analyzer(marker, code);
+	let completions = analyzer.completions(file_id, offset);
+	completions.into_iter().map(|completion| (completion.label, completion.kind)).collect()
+}
+
+/// Returns an analyzer of [`PROGRAM`] with `code` written in place of `marker`, along with the offset of its end.
+fn analyzer(marker: &str, code: &str) -> (Analyzer, FileId, usize) {
+	let mut source = PROGRAM.to_string();
+	// the other markers are blanked out, keeping the offsets before the one replaced
+	for other in ["TOP", "KEY", "PARSER", "STATES", "CONTROL", "APPLY"].into_iter().filter(|other| *other != marker) {
+		source = source.replace(other, &" ".repeat(other.len()));
+	}
+	let offset = source.find(marker).unwrap() + code.len();
+	let source = source.replace(marker, code);
+	let analyzer = common::workspace(&[("foo.p4", &source)]);
+	let file_id = analyzer.file_id("foo.p4");
+	(analyzer, file_id, offset)
+}
+
+fn contains(labels: &[String], expected: &[&str]) -> bool {
+	expected.iter().all(|expected| labels.iter().any(|label| label == expected))
+}
+
+#[test]
+fn completes_members() {
+	assert_eq!(
+		labels("APPLY", "hdr.eth."),
+		vec!["dst", "type", "isValid", "setValid", "setInvalid", "minSizeInBits", "minSizeInBytes"]
+	);
+	assert_eq!(labels("APPLY", "hdr."), vec!["eth", "vlans", "minSizeInBits", "minSizeInBytes"]);
+	// the word being written is left to the client to match
+	assert_eq!(labels("APPLY", "hdr.vl"), vec!["eth", "vlans", "minSizeInBits", "minSizeInBytes"]);
+	assert!(contains(&labels("APPLY", "hdr.vlans[0]."), &["tci", "isValid"]));
+	assert!(contains(&labels("APPLY", "hdr.vlans."), &["next", "last", "size", "push_front"]));
+	assert_eq!(labels("APPLY", "routes."), vec!["apply"]);
+	assert_eq!(labels("APPLY", "routes.apply()."), vec!["hit", "miss", "action_run"]);
+	assert_eq!(labels("APPLY", "hits."), vec!["count"]);
+	assert!(contains(&labels("APPLY", "error."), &["NoError", "PacketTooShort"]));
+}
+
+#[test]
+fn completes_member_kinds() {
+	let completions = completions("APPLY", "hdr.eth.");
+	assert_eq!(completions[0], ("dst".to_string(), CompletionKind::Symbol(SymbolKind::Field)));
+	assert_eq!(completions[2], ("isValid".to_string(), CompletionKind::Symbol(SymbolKind::Method)));
+}
+
+#[test]
+fn completes_parser_states() {
+	let labels = labels("PARSER", "transition ");
+	assert!(contains(&labels, &["start", "parse_vlan", "accept", "reject", "select"]));
+	assert!(!labels.iter().any(|label| label == "hdr"));
+
+	let labels = self::labels("PARSER", "transition select(hdr.eth.type) { 0x8100: ");
+	assert!(contains(&labels, &["parse_vlan", "accept"]));
+	assert!(!labels.iter().any(|label| label == "select"));
+}
+
+#[test]
+fn completes_keys_and_match_kinds() {
+	let labels = labels("KEY", "");
+	assert!(contains(&labels, &["hdr", "meta"]));
+	assert!(!labels.iter().any(|label| label == "key"));
+
+	let labels = self::labels("KEY", "hdr.eth.dst: ");
+	assert!(contains(&labels, &["exact", "ternary", "lpm"]));
+	assert!(!labels.iter().any(|label| label == "hdr"));
+}
+
+#[test]
+fn completes_types() {
+	let labels = labels("CONTROL", "register<");
+	assert!(contains(&labels, &["bit", "ethernet_t", "headers_t"]));
+	assert!(!labels.iter().any(|label| label == "hdr"));
+
+	let labels = self::labels("CONTROL", "register<bit<");
+	assert!(contains(&labels, &["SIZE"]));
+	assert!(!labels.iter().any(|label| label == "hdr" || label == "bit"));
+
+	let labels = self::labels("CONTROL", "action set_port(");
+	assert!(contains(&labels, &["in", "out", "inout", "bit", "metadata_t"]));
+	let labels = self::labels("CONTROL", "action set_port(in ");
+	assert!(contains(&labels, &["bit", "metadata_t"]));
+	assert!(!labels.iter().any(|label| label == "in"));
+}
+
+#[test]
+fn completes_keywords_at_the_cursor() {
+	let labels = labels("CONTROL", "");
+	assert!(contains(&labels, &["action", "table", "apply", "counter"]));
+	assert!(!labels.iter().any(|label| label == "state" || label == "hdr"));
+
+	let labels = self::labels("APPLY", "");
+	assert!(contains(&labels, &["if", "return", "hdr", "routes", "forward"]));
+	assert!(!labels.iter().any(|label| label == "table" || label == "transition"));
+
+	let labels = self::labels("PARSER", "");
+	assert!(contains(&labels, &["transition", "hdr", "pkt"]));
+	assert!(!labels.iter().any(|label| label == "if"));
+
+	let labels = self::labels("TOP", "");
+	assert!(contains(&labels, &["header", "control", "parser", "bit", "standard_metadata_t"]));
+	assert!(!labels.iter().any(|label| label == "state" || label == "SIZE"));
+}
+
+#[test]
+fn resolves_documentation() {
+	let (analyzer, file_id, offset) = analyzer("APPLY", "");
+	let completions = analyzer.completions(file_id, offset);
+	let completion = completions.iter().find(|completion| completion.label == "drop").unwrap();
+	let resolution = analyzer.resolve_completion(file_id, completion.symbol.as_ref().unwrap()).unwrap();
+	assert_eq!(resolution.detail.as_deref(), Some("action drop()"));
+	assert_eq!(resolution.documentation.as_deref(), Some("Drops the packet."));
+	assert_eq!(resolution.include, None);
+}
+
+/// Returns the text of the snippet labelled `label` at the end of `code`, written in place of `marker` in [`PROGRAM`].
+fn snippet(marker: &str, code: &str, label: &str) -> Option<String> {
+	let (analyzer, file_id, offset) = analyzer(marker, code);
+	let completions = analyzer.completions(file_id, offset).into_iter();
+	let mut snippets = completions.filter(|completion| completion.kind == CompletionKind::Snippet);
+	snippets.find(|completion| completion.label == label)?.insert_text
+}
+
+#[test]
+fn fills_in_snippets() {
+	let table = snippet("CONTROL", "", "table").unwrap();
+	assert!(table.contains("\t\tdrop;\n\t\tforward;\n\t\tNoAction;\n\t\t$4\n"));
+	assert!(table.contains("${2:field}: ${3|exact,"));
+	assert!(table.contains("default_action = ${6|NoAction,drop,forward|};"));
+
+	let state = snippet("STATES", "", "state").unwrap();
+	assert!(state.contains("${2:pkt.extract(hdr.header);}"));
+	assert!(state.contains("${4:value}: ${5|start,parse_vlan,accept,reject|};"));
+	assert!(state.contains("default: ${6|accept,reject|};"));
+
+	let control = snippet("TOP", "", "control").unwrap();
+	assert!(control.starts_with("control ${1:name}(inout ${2:headers_t} hdr, inout ${3:metadata_t} meta, inout"));
+	assert!(snippet("TOP", "", "header").is_some());
+}
+
+#[test]
+fn offers_snippets_where_declarations_start() {
+	assert_eq!(snippet("APPLY", "", "table"), None);
+	assert_eq!(snippet("PARSER", "", "state"), None);
+	assert_eq!(snippet("STATES", "", "table"), None);
+	assert_eq!(snippet("CONTROL", "bit<8> ", "table"), None);
+	assert_eq!(snippet("KEY", "", "table"), None);
+}
+
+#[test]
+fn fills_in_the_program_skeleton() {
+	let skeleton = |source: &str| {
+		let analyzer = common::workspace(&[("foo.p4", source)]);
+		let file_id = analyzer.file_id("foo.p4");
+		let completions = analyzer.completions(file_id, source.len());
+		completions.into_iter().find(|completion| completion.label == "main")?.insert_text
+	};
+
+	let source =
+		"#include <v1model.p4>\nheader h_t { bit<8> f; }\nstruct meta_t { bit<8> x; }\nstruct hdr_t { h_t h; }\n";
+	let main = skeleton(source).unwrap();
+	assert!(main.starts_with("parser MyParser(packet_in packet, out ${1:hdr_t} hdr, inout ${2:meta_t} meta, inout"));
+	assert!(main.ends_with(
+		"V1Switch(MyParser(), MyVerifyChecksum(), MyIngress(), MyEgress(), MyComputeChecksum(), \
+		MyDeparser()) main;"
+	));
+
+	let main = skeleton("").unwrap();
+	assert!(main.starts_with("#include <core.p4>\n#include <v1model.p4>\n\nstruct ${1:headers_t} {\n}\n\n"));
+
+	// a program has a single `main`
+	let source = format!("{source}V1Switch(P(), V(), I(), E(), C(), D()) main;\n");
+	assert_eq!(skeleton(&source), None);
+}
+
+/// Resolves `path` relatively to the directory of `base`, as the host does for URLs.
+fn resolve_path(base: &str, path: &str) -> Result<String, String> {
+	let mut components: Vec<_> = base.split('/').collect();
+	components.pop();
+	for component in path.split('/') {
+		match component {
+			".." => drop(components.pop()),
+			component => components.push(component),
+		}
+	}
+	Ok(components.join("/"))
+}
+
+#[test]
+fn includes_headers_of_the_workspace() {
+	let mut analyzer = Analyzer::new(resolve_path, |_| ());
+	let main = analyzer.file_id("src/main.p4");
+	let source = "#include <core.p4>\n#include <v1model.p4>\ncontrol C() { apply { } }\n".to_string();
+	analyzer.update(main, &source);
+	let header = analyzer.file_id("include/eth.p4");
+	analyzer.update(header, &"// The EtherType of IPv4.\nconst bit<16> ETHERTYPE_IPV4 = 0x0800;\n".to_string());
+	let program = analyzer.file_id("src/other.p4");
+	let program_source = "const bit<16> ETHERTYPE_ARP = 0x0806;\nV1Switch(P(), V(), I(), E(), C(), D()) main;\n";
+	analyzer.update(program, &program_source.to_string());
+
+	let offset = source.find("apply {").unwrap() + "apply {".len();
+	let completions = analyzer.completions(main, offset);
+	assert!(!completions.iter().any(|completion| completion.label == "ETHERTYPE_ARP"));
+	let completion = completions.iter().find(|completion| completion.label == "ETHERTYPE_IPV4").unwrap();
+	let resolution = analyzer.resolve_completion(main, completion.symbol.as_ref().unwrap()).unwrap();
+	assert_eq!(resolution.detail.as_deref(), Some("const bit<16> ETHERTYPE_IPV4 = 0x0800"));
+	assert_eq!(resolution.documentation.as_deref(), Some("The EtherType of IPv4."));
+	let offset = source.find("control").unwrap();
+	assert_eq!(resolution.include, Some((offset, "#include \"../include/eth.p4\"\n".to_string())));
+
+	// once included, the header declares a visible name
+	let source = source.replace("control", "#include \"../include/eth.p4\"\ncontrol");
+	analyzer.update(main, &source);
+	let offset = source.find("apply {").unwrap() + "apply {".len();
+	let completions = analyzer.completions(main, offset);
+	let completions: Vec<_> = completions.iter().filter(|completion| completion.label == "ETHERTYPE_IPV4").collect();
+	assert_eq!(completions.len(), 1);
+	let resolution = analyzer.resolve_completion(main, completions[0].symbol.as_ref().unwrap()).unwrap();
+	assert_eq!(resolution.include, None);
+}
@@ -0,0 +1,215 @@
+extern crate analyzer_core;
+
+use analyzer_core::*;
+use pretty_assertions::assert_eq;
+
+mod common;
+
+fn analyze(s: &str) -> (Analyzer, base_abstractions::FileId) { common::analyze(s, &[]) }
+
+fn errors(s: &str) -> Vec<(String, String)> {
+	let (analyzer, file_id) = analyze(s);
+	analyzer.diagnostics(file_id).into_iter().map(|d| (s[d.location].to_string(), d.message)).collect()
+}
+
+/// Returns the displayed values of the constants declared in `s`, in order of declaration.
+fn constants(s: &str) -> Vec<(String, Option<String>)> {
+	let (analyzer, file_id) = analyze(s);
+	let table = analyzer.symbols(file_id).unwrap();
+	let constants = analyzer.constants(file_id).unwrap();
+	let mut symbols: Vec<_> = table
+		.symbols()
+		.filter(|(_, symbol)| symbol.kind == symbols::SymbolKind::Constant && symbol.location.file == file_id)
+		.collect();
+	symbols.sort_by_key(|(_, symbol)| symbol.location.span.start);
+	symbols
+		.into_iter()
+		.map(|(id, symbol)| {
+			(symbol.name.clone(), constants.symbol_value(id).map(|value| value.display(table).to_string()))
+		})
+		.collect()
+}
+
+fn values(names_and_values: &[(&str, &str)]) -> Vec<(String, Option<String>)> {
+	names_and_values.iter().map(|(name, value)| (name.to_string(), Some(value.to_string()))).collect()
+}
+
+#[test]
+fn wraps_around_fixed_widths() {
+	let program = r"
+		const bit<8> a = 255 + 1;
+		const bit<8> b = 8w3 - 8w5;
+		const int<8> c = 127 + 1;
+		const int<8> d = -128 - 1;
+		const bit<8> e = 8w16 * 8w17;
+		const bit<4> f = ~4w5;
+		const int<4> g = ~4s5;
+		const bit<8> h = -8w1;
+	";
+	assert_eq!(
+		constants(program),
+		values(&[
+			("a", "8w0"),
+			("b", "8w254"),
+			("c", "-8s128"),
+			("d", "8s127"),
+			("e", "8w16"),
+			("f", "4w10"),
+			("g", "-4s6"),
+			("h", "8w255")
+		])
+	);
+}
+
+#[test]
+fn evaluates_arbitrary_widths() {
+	let program = r"
+		const bit<256> a = (1 << 255) + (1 << 255);
+		const bit<200> b = -1;
+		const int<128> c = (int<128>) ((bit<128>) -1 >> 1);
+		const int e = 1 << 100;
+	";
+	assert_eq!(
+		constants(program),
+		values(&[
+			("a", "256w0"),
+			("b", "200w1606938044258990275541962092341162602522202993782792835301375"),
+			("c", "128s170141183460469231731687303715884105727"),
+			("e", "1267650600228229401496703205376"),
+		])
+	);
+}
+
+#[test]
+fn evaluates_literals_wider_than_64_bits() {
+	let program = r"
+		const bit<64> all_ones = 64w0xFFFF_FFFF_FFFF_FFFF;
+		const bit<128> mask = 128w0xFFFF_FFFF_FFFF_FFFF_FFFF_FFFF_0000_0000;
+		const int<128> wrapped = 128s0x8000_0000_0000_0000_0000_0000_0000_0000;
+		const int huge = 0x1_0000_0000_0000_0000_0000_0000_0000_0000;
+	";
+	assert_eq!(errors(program), vec![]);
+	assert_eq!(
+		constants(program),
+		values(&[
+			("all_ones", "64w18446744073709551615"),
+			("mask", "128w340282366920938463463374607427473244160"),
+			("wrapped", "-128s170141183460469231731687303715884105728"),
+			("huge", "340282366920938463463374607431768211456"),
+		])
+	);
+}
+
+#[test]
+fn evaluates_slices_concatenation_and_casts() {
+	let program = r"
+		const bit<16> x = 0xABCD;
+		const bit<8> hi = x[15:8];
+		const bit<4> nibble = x[3:0];
+		const bit<24> wide = x ++ hi;
+		const int<12> signed = (int<4>) -1 ++ 8w0;
+		const bit<4> truncated = (bit<4>) x;
+		const int<8> reinterpreted = (int<8>) 8w200;
+		const bit<16> extended = (bit<16>) (int<8>) -1;
+		const bool flag = (bool) 1w1;
+		const bit<8> from_bool = (bit<8>) true;
+	";
+	assert_eq!(
+		constants(program),
+		values(&[
+			("x", "16w43981"),
+			("hi", "8w171"),
+			("nibble", "4w13"),
+			("wide", "24w11259307"),
+			("signed", "-12s256"),
+			("truncated", "4w13"),
+			("reinterpreted", "-8s56"),
+			("extended", "16w65535"),
+			("flag", "true"),
+			("from_bool", "8w1"),
+		])
+	);
+}
+
+#[test]
+fn evaluates_saturating_and_shift_operators() {
+	let program = r"
+		const bit<8> a = 8w250 |+| 8w10;
+		const bit<8> b = 8w5 |-| 8w10;
+		const int<8> c = 8s120 |+| 8s10;
+		const int<8> d = -8s120 |-| 8s10;
+		const bit<8> e = 8w1 << 9;
+		const int<8> f = -8s128 >> 7;
+		const bit<8> g = 8w128 >> 7;
+		const bool h = 8w3 < 8w4 && !(-8s1 > 8s0);
+	";
+	assert_eq!(
+		constants(program),
+		values(&[
+			("a", "8w255"),
+			("b", "8w0"),
+			("c", "8s127"),
+			("d", "-8s128"),
+			("e", "8w0"),
+			("f", "-8s1"),
+			("g", "8w1"),
+			("h", "true"),
+		])
+	);
+}
+
+#[test]
+fn evaluates_names_enums_and_macros() {
+	let program = r"
+		#define SIZE 16
+		enum bit<8> Color { Red = 1, Green = 2 }
+		const bit<16> A = SIZE * 2;
+		const bit<16> B = A + (bit<16>) Color.Green;
+		const bool C = A == 32 ? true : false;
+		const error D = error.NoMatch;
+	";
+	assert_eq!(constants(program), values(&[("A", "16w32"), ("B", "16w34"), ("C", "true"), ("D", "error.NoMatch")]));
+}
+
+#[test]
+fn records_constant_subexpressions() {
+	let program = r"
+		control C(inout bit<8> x) {
+			apply {
+				x = x + (8w2 + 3);
+				bit<(4 + 4)> y = x[7:4] ++ 4w0;
+			}
+		}
+	";
+	let (analyzer, file_id) = analyze(program);
+	let table = analyzer.symbols(file_id).unwrap();
+	let constants = analyzer.constants(file_id).unwrap();
+	let value_at = |marker: &str| {
+		let offset = program.find(marker).unwrap();
+		constants.value_at(file_id, offset).map(|(_, value)| value.display(table).to_string())
+	};
+
+	assert_eq!(value_at("8w2 +").as_deref(), Some("8w2"));
+	assert_eq!(value_at("+ 3").as_deref(), Some("8w5"));
+	assert_eq!(value_at("+ 4)").as_deref(), Some("8"));
+	assert_eq!(value_at("x +"), None);
+}
+
+#[test]
+fn reports_invalid_constants() {
+	let program = r"
+		const bit<8> a = 8w1 / 0;
+		const int b = 7 % (2 - 2);
+		const bit<8> c = 8w1 << -1;
+		const bool d = (bool) 2;
+	";
+	assert_eq!(
+		errors(program),
+		vec![
+			("8w1 / 0".to_string(), "division by zero".to_string()),
+			("7 % (2 - 2)".to_string(), "modulo by zero".to_string()),
+			("8w1 << -1".to_string(), "cannot shift by a negative amount (-1)".to_string()),
+			("(bool) 2".to_string(), "only 0 and 1 can be cast to `bool`, found 2".to_string()),
+		]
+	);
+}
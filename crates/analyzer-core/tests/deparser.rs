@@ -0,0 +1,126 @@
+extern crate analyzer_core;
+
+use pretty_assertions::assert_eq;
+
+mod common;
+
+fn warnings(s: &str) -> Vec<(String, String)> {
+	let (analyzer, file_id) = common::analyze(s, &[]);
+	analyzer.diagnostics(file_id).into_iter().map(|d| (s[d.location].to_string(), d.message)).collect()
+}
+
+fn warning(text: &str, message: &str) -> (String, String) { (text.to_string(), message.to_string()) }
+
+fn v1model_program(states: &str, ingress: &str, deparser: &str) -> String {
+	format!(
+		r"
+		#include <v1model.p4>
+		header ethernet_t {{ bit<48> dst; bit<48> src; bit<16> etherType; }}
+		header vlan_t {{ bit<16> tci; bit<16> etherType; }}
+		header ipv4_t {{ bit<8> ttl; bit<8> protocol; }}
+		header tunnel_t {{ bit<32> id; }}
+		header mpls_t {{ bit<20> label; bit<3> tc; bit<1> bos; bit<8> ttl; }}
+		struct headers {{ ethernet_t ethernet; vlan_t vlan; mpls_t[4] mpls; ipv4_t ipv4; tunnel_t tunnel; }}
+		struct metadata {{ }}
+
+		parser MyParser(packet_in packet, out headers hdr, inout metadata meta, inout standard_metadata_t sm) {{
+			{states}
+		}}
+		control MyVerifyChecksum(inout headers hdr, inout metadata meta) {{ apply {{ }} }}
+		control MyIngress(inout headers hdr, inout metadata meta, inout standard_metadata_t sm) {{ {ingress} }}
+		control MyEgress(inout headers hdr, inout metadata meta, inout standard_metadata_t sm) {{ apply {{ }} }}
+		control MyComputeChecksum(inout headers hdr, inout metadata meta) {{ apply {{ }} }}
+		control MyDeparser(packet_out packet, in headers h) {{ apply {{ {deparser} }} }}
+		V1Switch(MyParser(), MyVerifyChecksum(), MyIngress(), MyEgress(), MyComputeChecksum(), MyDeparser()) main;
+	"
+	)
+}
+
+const STATES: &str = r"
+	state start {
+		packet.extract(hdr.ethernet);
+		transition select(hdr.ethernet.etherType) { 0x8100: parse_vlan; 0x8847: parse_mpls; default: parse_ipv4; }
+	}
+	state parse_vlan { packet.extract(hdr.vlan); transition parse_ipv4; }
+	state parse_mpls {
+		packet.extract(hdr.mpls.next);
+		transition select(hdr.mpls.last.bos) { 0: parse_mpls; default: parse_ipv4; }
+	}
+	state parse_ipv4 { packet.extract(hdr.ipv4); transition accept; }
+";
+
+#[test]
+fn accepts_the_wire_order() {
+	let program = v1model_program(
+		STATES,
+		"apply { hdr.tunnel.setValid(); }",
+		"packet.emit(h.ethernet); packet.emit(h.vlan); packet.emit(h.mpls); packet.emit(h.ipv4); packet.emit(h.tunnel);",
+	);
+	assert_eq!(warnings(&program), vec![]);
+
+	// emitting the struct emits its fields in order
+	let program = v1model_program(STATES, "apply { hdr.tunnel.setValid(); }", "packet.emit(h);");
+	assert_eq!(warnings(&program), vec![]);
+}
+
+#[test]
+fn reports_headers_extracted_but_never_emitted() {
+	let program =
+		v1model_program(STATES, "apply { }", "packet.emit(h.ethernet); packet.emit(h.mpls); packet.emit(h.ipv4);");
+	assert_eq!(
+		warnings(&program),
+		vec![warning(
+			"hdr.vlan",
+			"the header `hdr.vlan` is extracted by the parser `MyParser`, but never emitted by the deparser `MyDeparser`"
+		)]
+	);
+}
+
+#[test]
+fn reports_headers_emitted_but_never_valid() {
+	let program = v1model_program(
+		STATES,
+		"apply { }",
+		"packet.emit(h.ethernet); packet.emit(h.vlan); packet.emit(h.mpls); packet.emit(h.ipv4); packet.emit(h.tunnel);",
+	);
+	assert_eq!(
+		warnings(&program),
+		vec![warning(
+			"h.tunnel",
+			"the header `h.tunnel` is emitted by the deparser `MyDeparser`, but never extracted by the parser \
+			 `MyParser` or set valid"
+		)]
+	);
+
+	let program = v1model_program(STATES, "apply { }", "packet.emit(h);");
+	assert_eq!(
+		warnings(&program),
+		vec![warning(
+			"h",
+			"the header `h.tunnel` is emitted by the deparser `MyDeparser`, but never extracted by the parser \
+			 `MyParser` or set valid"
+		)]
+	);
+}
+
+#[test]
+fn reports_headers_emitted_out_of_order() {
+	let program = v1model_program(
+		STATES,
+		"apply { }",
+		"packet.emit(h.ethernet); packet.emit(h.ipv4); packet.emit(h.vlan); packet.emit(h.mpls);",
+	);
+	assert_eq!(
+		warnings(&program),
+		vec![
+			warning(
+				"h.vlan",
+				"the header `h.vlan` is emitted after `h.ipv4`, but the parser `MyParser` extracts it before"
+			),
+			warning(
+				"h.mpls",
+				"the header `h.mpls` is emitted after `h.ipv4`, but the parser `MyParser` extracts it before"
+			),
+		]
+	);
+}
@@ -0,0 +1,105 @@
+extern crate analyzer_core;
+
+use analyzer_core::folding::FoldingKind;
+use pretty_assertions::assert_eq;
+
+mod common;
+
+const PROGRAM: &str = r#"#include <core.p4>
+
+#include <v1model.p4>
+
+/* The headers
+   of the program. */
+header h_t { bit<8> f; }
+
+#ifdef BIG
+const bit<8> SIZE = 2;
+#else
+const bit<8> SIZE = 1;
+#endif
+
+// The ingress.
+// It forwards.
+control MyIngress(inout h_t h) {
+    action drop() {
+        mark_to_drop();
+    }
+    table t {
+        key = {
+            h.f: exact;
+        }
+        actions = { drop; }
+    }
+    apply {
+        if (h.isValid()) {
+            t.apply();
+        }
+    }
+}
+
+parser MyParser(packet_in packet, out h_t h) {
+    state start {
+        transition select(h.f) {
+            0: accept;
+            default: reject;
+        }
+    }
+}
+"#;
+
+/// Returns the lines of the folding ranges of `source`, counted from 0, and their kind.
+fn folding_ranges(source: &str) -> Vec<(usize, usize, FoldingKind)> {
+	let analyzer = common::workspace(&[("foo.p4", source)]);
+	let file_id = analyzer.file_id("foo.p4");
+	let line = |offset: usize| source[..offset].matches('\n').count();
+	let ranges = analyzer.folding_ranges(file_id);
+	ranges.into_iter().map(|range| (line(range.span.start), line(range.span.end), range.kind)).collect()
+}
+
+#[test]
+fn folds_blocks_from_the_syntax_tree() {
+	use FoldingKind::*;
+	assert_eq!(
+		folding_ranges(PROGRAM),
+		vec![
+			(0, 2, Includes),
+			(4, 5, Comment),
+			(8, 9, Region),
+			(10, 11, Region),
+			(14, 15, Comment),
+			(16, 30, Block),
+			(17, 18, Block),
+			(20, 24, Block),
+			(21, 22, Block),
+			(26, 29, Block),
+			(27, 28, Block),
+			(33, 39, Block),
+			(34, 38, Block),
+			(35, 37, Block),
+		]
+	);
+
+	let source = "control C()\n{\n    apply {\n        f();\n    }\n}\n";
+	assert_eq!(folding_ranges(source), vec![(0, 4, Block), (2, 3, Block)]);
+}
+
+#[test]
+fn folds_braces_while_the_file_does_not_parse() {
+	let source = PROGRAM.replace("mark_to_drop();", "mark_to_drop(;");
+	let ranges = folding_ranges(&source);
+	assert!(ranges.contains(&(17, 18, FoldingKind::Block)));
+	assert!(ranges.contains(&(20, 24, FoldingKind::Block)));
+	assert!(ranges.contains(&(0, 2, FoldingKind::Includes)));
+
+	// the blocks are folded from their braces rather than from the start of their declaration
+	let source = "control C()\n{\n    apply {\n        f(;\n    }\n}\n";
+	assert_eq!(folding_ranges(source), vec![(1, 4, FoldingKind::Block), (2, 3, FoldingKind::Block)]);
+}
+
+#[test]
+fn folds_nested_regions() {
+	let source = "#if A\n#if B\nconst bit<8> X = 1;\n#elif C\nconst bit<8> X = 2;\n#endif\n#endif\n";
+	use FoldingKind::*;
+	assert_eq!(folding_ranges(source), vec![(0, 5, Region), (1, 2, Region), (3, 4, Region)]);
+}
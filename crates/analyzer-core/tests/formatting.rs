@@ -0,0 +1,245 @@
+extern crate analyzer_core;
+
+use analyzer_core::{
+	base_abstractions::FileId,
+	formatting::{BraceStyle, FormatOptions, TextEdit},
+	*,
+};
+use pretty_assertions::assert_eq;
+
+mod common;
+
+const PROGRAM: &str = r#"#include <core.p4>
+#include <v1model.p4>
+
+// The headers.
+header ethernet_t { bit<48> dst; bit<48> src;
+  bit<16> ether_type; }
+struct headers_t{ethernet_t ethernet;}
+
+#ifdef DEBUG
+const bit<8> LEVEL=1;
+#endif
+
+/* Parses
+   the packet. */
+parser MyParser(packet_in packet,out headers_t hdr) {
+state start {
+packet.extract(hdr.ethernet);
+transition select(hdr.ethernet.ether_type){
+0x800 : accept;
+default : reject;
+}
+}
+}
+
+control MyIngress(inout headers_t hdr , inout standard_metadata_t meta) {
+    @name("drop") action drop() { mark_to_drop(meta); }
+    action forward(bit<9> port) { meta.egress_spec=port; }   // Forwards.
+    table t { key = { hdr.ethernet.dst : exact; } actions = { drop; forward; } size = 1024; default_action = drop(); }
+    apply {
+        if(hdr.ethernet.isValid()&&!(hdr.ethernet.src==0)){ t.apply(); }
+        else { bit<16> x = -1 + (hdr.ethernet.ether_type >> 2) << 1; meta.egress_spec = x > 2 ? 1 : 0; }
+    }
+}
+"#;
+
+const FORMATTED: &str = r#"#include <core.p4>
+#include <v1model.p4>
+
+// The headers.
+header ethernet_t {
+    bit<48> dst;
+    bit<48> src;
+    bit<16> ether_type;
+}
+struct headers_t {
+    ethernet_t ethernet;
+}
+
+#ifdef DEBUG
+const bit<8> LEVEL = 1;
+#endif
+
+/* Parses
+   the packet. */
+parser MyParser(packet_in packet, out headers_t hdr) {
+    state start {
+        packet.extract(hdr.ethernet);
+        transition select(hdr.ethernet.ether_type) {
+            0x800: accept;
+            default: reject;
+        }
+    }
+}
+
+control MyIngress(inout headers_t hdr, inout standard_metadata_t meta) {
+    @name("drop") action drop() {
+        mark_to_drop(meta);
+    }
+    action forward(bit<9> port) {
+        meta.egress_spec = port;
+    } // Forwards.
+    table t {
+        key = {
+            hdr.ethernet.dst: exact;
+        }
+        actions = {
+            drop;
+            forward;
+        }
+        size = 1024;
+        default_action = drop();
+    }
+    apply {
+        if (hdr.ethernet.isValid() && !(hdr.ethernet.src == 0)) {
+            t.apply();
+        } else {
+            bit<16> x = -1 + (hdr.ethernet.ether_type >> 2) << 1;
+            meta.egress_spec = x > 2 ? 1 : 0;
+        }
+    }
+}
+"#;
+
+fn analyzer(source: &str) -> (Analyzer, FileId) {
+	let analyzer = common::workspace(&[("foo.p4", source)]);
+	let file_id = analyzer.file_id("foo.p4");
+	(analyzer, file_id)
+}
+
+/// Applies `edits`, which are in order and do not overlap, to `source`.
+fn apply(source: &str, edits: &[TextEdit]) -> String {
+	let mut result = source.to_string();
+	for edit in edits.iter().rev() {
+		result.replace_range(edit.span.clone(), &edit.text);
+	}
+	result
+}
+
+fn format_with(source: &str, options: &FormatOptions) -> String {
+	let (analyzer, file_id) = analyzer(source);
+	apply(source, &analyzer.format(file_id, options, None))
+}
+
+fn format(source: &str) -> String { format_with(source, &FormatOptions::default()) }
+
+#[test]
+fn formats_documents() {
+	assert_eq!(format(PROGRAM), FORMATTED);
+}
+
+#[test]
+fn formatting_is_idempotent() {
+	assert_eq!(format(FORMATTED), FORMATTED);
+
+	let (analyzer, file_id) = analyzer(FORMATTED);
+	assert_eq!(analyzer.format(file_id, &FormatOptions::default(), None), vec![]);
+}
+
+#[test]
+fn keeps_comments_and_directives() {
+	let source = "control C() {\n// First.\n  apply { /* a */ f( /* b */ 1); // c\n}\n#if X\n}\n#endif\n";
+	let expected =
+		"control C() {\n    // First.\n    apply { /* a */\n        f( /* b */ 1); // c\n    }\n#if X\n}\n#endif\n";
+	assert_eq!(format(source), expected);
+	assert_eq!(format(expected), expected);
+}
+
+#[test]
+fn places_braces_by_style() {
+	let options = FormatOptions { brace_style: BraceStyle::NextLine, ..FormatOptions::default() };
+	let source = "control C() { apply { if (true) { f(); } else { g(); } } }\n";
+	let expected = r#"control C()
+{
+    apply
+    {
+        if (true)
+        {
+            f();
+        }
+        else
+        {
+            g();
+        }
+    }
+}
+"#;
+	assert_eq!(format_with(source, &options), expected);
+	assert_eq!(format_with(expected, &options), expected);
+
+	let options = FormatOptions { tab_size: 2, insert_spaces: false, ..FormatOptions::default() };
+	assert_eq!(
+		format_with(expected, &options),
+		"control C() {\n\tapply {\n\t\tif (true) {\n\t\t\tf();\n\t\t} else {\n\t\t\tg();\n\t\t}\n\t}\n}\n"
+	);
+}
+
+#[test]
+fn breaks_long_lines() {
+	let options = FormatOptions { max_width: 40, ..FormatOptions::default() };
+	let source = "control C() { apply { verify_checksum(true, { hdr.a, hdr.b }, hdr.c, f(1, 2)); } }\n";
+	let expected = r#"control C() {
+    apply {
+        verify_checksum(
+            true,
+            { hdr.a, hdr.b },
+            hdr.c,
+            f(1, 2)
+        );
+    }
+}
+"#;
+	assert_eq!(format_with(source, &options), expected);
+	assert_eq!(format_with(expected, &options), expected);
+}
+
+#[test]
+fn formats_after_syntax_errors() {
+	let source = "control C() {\napply { f(1, ; }\n}\nconst   bit<8>  X=1 ;\n";
+	assert_eq!(format(source), "control C() {\n    apply {\n        f(1,;\n    }\n}\nconst bit<8> X = 1;\n");
+
+	// a long line may end the file with an opening parenthesis
+	let options = FormatOptions { max_width: 40, ..FormatOptions::default() };
+	let source = "const bit<8> a_constant_with_a_long_name = f(";
+	assert_eq!(format_with(source, &options), format!("{source}\n"));
+}
+
+#[test]
+fn leaves_empty_files() {
+	for source in ["", "\n", "  \n\t\n"] {
+		assert_eq!(format(source), source);
+		let (analyzer, file_id) = analyzer(source);
+		assert_eq!(analyzer.format(file_id, &FormatOptions::default(), Some(0..source.len())), vec![]);
+		assert_eq!(analyzer.format_on_type(file_id, &FormatOptions::default(), source.len(), '\n'), vec![]);
+	}
+}
+
+#[test]
+fn formats_ranges() {
+	let source = "const bit<8> A=1;\n  const bit<8> B=2;\nconst bit<8> C=3;\n";
+	let (analyzer, file_id) = analyzer(source);
+	let start = source.find("  const").unwrap();
+	let end = source.find("\nconst bit<8> C").unwrap();
+	let edits = analyzer.format(file_id, &FormatOptions::default(), Some(start..end));
+	assert_eq!(apply(source, &edits), "const bit<8> A=1;\nconst bit<8> B = 2;\nconst bit<8> C=3;\n");
+}
+
+#[test]
+fn formats_on_type() {
+	let source = "const bit<8> A=1;\ncontrol C() {\napply {\nx=1;\n  y  =2;\n}";
+	let (analyzer, file_id) = analyzer(source);
+	let options = FormatOptions::default();
+
+	let edits = analyzer.format_on_type(file_id, &options, source.find("2;").unwrap() + 2, ';');
+	assert_eq!(apply(source, &edits), "const bit<8> A=1;\ncontrol C() {\napply {\nx=1;\n        y = 2;\n}");
+
+	let edits = analyzer.format_on_type(file_id, &options, source.len(), '}');
+	assert_eq!(
+		apply(source, &edits),
+		"const bit<8> A=1;\ncontrol C() {\n    apply {\n        x = 1;\n        y = 2;\n    }"
+	);
+
+	let edits = analyzer.format_on_type(file_id, &options, source.find("control").unwrap(), '\n');
+	assert_eq!(apply(source, &edits), source.replace("A=1", "A = 1"));
+}
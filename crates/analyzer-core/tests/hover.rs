@@ -0,0 +1,121 @@
+extern crate analyzer_core;
+
+use pretty_assertions::assert_eq;
+
+mod common;
+
+/// Hovers over the first occurrence of `target` in the program, returning the contents and the hovered text.
+fn hover(s: &str, target: &str) -> Option<(String, String)> {
+	let analyzer = common::workspace(&[("foo.p4", s)]);
+	let file_id = analyzer.file_id("foo.p4");
+
+	let offset = s.find(target).expect("the target is in the program");
+	let hover = analyzer.hover(file_id, offset + 1)?;
+	Some((hover.contents, s[hover.span].to_string()))
+}
+
+const PROGRAM: &str = r#"
+	#define MAX_PORT 511
+	#define LAST_PORT (MAX_PORT - 1)
+
+	typedef bit<48> mac_t;
+
+	/// An Ethernet header.
+	header ethernet_t {
+		mac_t dst;
+		mac_t src;
+		bit<16> type;
+	}
+
+	struct headers_t { ethernet_t eth; bit<3> pad; }
+
+	control C(inout headers_t hdr) {
+		// p4analyzer: allow(unused-constant)
+		/** The port of the CPU.
+		 * Packets sent there reach the controller.
+		 */
+		const bit<9> CPU = 8w10 + 1;
+
+		@name("fwd") action forward(bit<9> port) { }
+		table t { actions = { forward; } }
+		apply {
+			bit<9> p = LAST_PORT;
+			hdr.eth.type = 0x0800;
+			t.apply();
+		}
+	}
+"#;
+
+#[test]
+fn shows_declarations() {
+	let (contents, text) = hover(PROGRAM, "forward(bit").unwrap();
+	assert_eq!(text, "forward");
+	assert_eq!(contents, "```p4\n@name(\"fwd\") action forward(bit<9> port)\n```\n\nControl-plane name: `C.fwd`");
+
+	let (contents, text) = hover(PROGRAM, "p = LAST").unwrap();
+	assert_eq!(text, "p");
+	assert_eq!(contents, "```p4\nbit<9> p = LAST_PORT\n```\n\nType: `bit<9>`");
+
+	let (contents, _) = hover(PROGRAM, "mac_t;").unwrap();
+	assert_eq!(contents, "```p4\ntypedef bit<48> mac_t\n```\n\nType: `bit<48>`");
+}
+
+#[test]
+fn shows_layouts() {
+	let (contents, text) = hover(PROGRAM, "ethernet_t eth").unwrap();
+	assert_eq!(text, "ethernet_t");
+	assert_eq!(
+		contents,
+		"```p4\nheader ethernet_t {\n    bit<48> dst; // offset 0, 48 bits\n    bit<48> src; // offset 48, 48 bits\n    \
+		 bit<16> type; // offset 96, 16 bits\n}\n```\n\nSize: 112 bits (14 bytes)\n\n---\n\nAn Ethernet header."
+	);
+
+	let (contents, _) = hover(PROGRAM, "headers_t {").unwrap();
+	assert!(contents.ends_with("\n```\n\nSize: 115 bits"));
+
+	let (contents, text) = hover(PROGRAM, "type = 0x").unwrap();
+	assert_eq!(text, "type");
+	assert_eq!(
+		contents,
+		"```p4\nbit<16> type\n```\n\nType: `bit<16>`\n\nOffset: 96 bits into `ethernet_t`\n\nSize: 16 bits (2 bytes)"
+	);
+}
+
+#[test]
+fn shows_documentation_and_values() {
+	let (contents, _) = hover(PROGRAM, "CPU =").unwrap();
+	assert_eq!(
+		contents,
+		"```p4\nconst bit<9> CPU = 8w10 + 1\n```\n\nType: `bit<9>`\n\nValue: `9w11` = `0xb`\n\n---\n\nThe port of the \
+		 CPU.\nPackets sent there reach the controller."
+	);
+}
+
+#[test]
+fn expands_macros() {
+	let (contents, text) = hover(PROGRAM, "LAST_PORT;").unwrap();
+	assert_eq!(text, "LAST_PORT");
+	assert_eq!(contents, "```p4\n#define LAST_PORT (MAX_PORT - 1)\n```\n\nExpands to: `(511 - 1)`");
+}
+
+#[test]
+fn shows_integer_values() {
+	let (contents, text) = hover(PROGRAM, "0x0800").unwrap();
+	assert_eq!(text, "0x0800");
+	assert_eq!(
+		contents,
+		"```p4\n0x0800\n```\n\nType: `int`, of arbitrary precision\n\nValue: `2048` = `0x800` = `0b100000000000`"
+	);
+
+	let (contents, _) = hover(PROGRAM, "8w10").unwrap();
+	assert!(contents.contains("Type: `bit<8>`, 8 bits\n\nValue: `10` = `0xa` = `0b1010`"));
+
+	let (contents, _) = hover("const bit<64> x = 64w0xFFFF_FFFF_FFFF_FFFF;", "64w").unwrap();
+	assert!(contents.contains("Value: `18446744073709551615` = `0xffffffffffffffff`"));
+}
+
+#[test]
+fn ignores_other_tokens() {
+	assert_eq!(hover(PROGRAM, "apply {"), None);
+	assert_eq!(hover(PROGRAM, "{ ethernet_t"), None);
+}
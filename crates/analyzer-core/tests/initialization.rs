@@ -0,0 +1,107 @@
+extern crate analyzer_core;
+
+use pretty_assertions::assert_eq;
+
+mod common;
+
+fn warnings(s: &str) -> Vec<(String, String)> {
+	let (analyzer, file_id) = common::analyze(s, &[]);
+	analyzer.diagnostics(file_id).into_iter().map(|d| (s[d.location].to_string(), d.message)).collect()
+}
+
+fn warning(text: &str, message: &str) -> (String, String) { (text.to_string(), message.to_string()) }
+
+#[test]
+fn reports_reads_before_assignment() {
+	let program = r"
+		struct pair_t { bit<8> a; bit<8> b; }
+		control C(inout bit<8> x) {
+			apply {
+				bit<8> y;
+				bit<8> z;
+				pair_t p;
+				if (x == 0) { y = 1; z = 1; } else { z = 2; }
+				x = y + z;
+				p.a = 1;
+				x = p.a + p.b;
+				bit<8> w = x;
+				x = w;
+			}
+		}
+	";
+	assert_eq!(
+		warnings(program),
+		vec![
+			warning("y", "the variable `y` may be read before it is initialized"),
+			warning("p.b", "the variable `p` may be read before it is initialized"),
+		]
+	);
+}
+
+#[test]
+fn reports_unassigned_out_parameters() {
+	let program = r"
+		struct pair_t { bit<8> a; bit<8> b; }
+		action a(in bool c, out bit<8> x, out bit<8> y) {
+			if (c) { x = 1; return; }
+			x = y;
+			y = 2;
+		}
+		bit<8> f(in bool c, out pair_t p) {
+			if (c) { p = { 1, 2 }; }
+			return 0;
+		}
+		control C(inout bool c) {
+			apply { bit<8> x; bit<8> y; a(c, x, y); x = x + y; }
+		}
+	";
+	assert_eq!(
+		warnings(program),
+		vec![
+			warning("y", "the `out` parameter `y` may be read before it is assigned"),
+			warning("y", "the `out` parameter `y` may be left unassigned"),
+			warning("p", "the `out` parameter `p` may be left unassigned"),
+		]
+	);
+}
+
+#[test]
+fn follows_actions_invoked_by_tables() {
+	let program = r"
+		control C(out bit<8> port) {
+			action forward(bit<8> p) { port = p; }
+			action drop() { port = 0; }
+			table t { actions = { forward; drop; } default_action = drop(); }
+			apply {
+				bit<8> tmp;
+				t.apply();
+				tmp = port;
+			}
+		}
+	";
+	assert_eq!(warnings(program), vec![]);
+}
+
+#[test]
+fn follows_parser_states() {
+	let program = r"
+		header h_t { bit<8> kind; }
+		parser P(packet_in packet, out h_t h, out bit<8> kind) {
+			bit<8> seen;
+			state start {
+				packet.extract(h);
+				transition select(h.kind) { 1: one; default: two; }
+			}
+			state one { kind = 1; seen = 1; transition two; }
+			state two { transition select(seen) { default: accept; } }
+		}
+	";
+	// the header `h` is invalid, rather than unspecified, until it is extracted
+	assert_eq!(
+		warnings(program),
+		vec![
+			warning("seen", "the variable `seen` may be read before it is initialized"),
+			warning("kind", "the `out` parameter `kind` may be left unassigned"),
+		]
+	);
+}
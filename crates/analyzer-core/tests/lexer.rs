@@ -33,18 +33,29 @@ fn int_literals() {
 		),
 		vec![
 			Whitespace,
-			Integer(Literal { base: 10, signed: false, width: None, value: 123 }),
+			Integer(Literal { base: 10, signed: false, width: None, value: 123.into() }),
 			Whitespace,
-			Integer(Literal { base: 10, signed: true, width: Some(10), value: 5 }),
+			Integer(Literal { base: 10, signed: true, width: Some(10), value: 5.into() }),
 			Whitespace,
-			Integer(Literal { base: 10, signed: false, width: Some(2), value: 11 }),
+			Integer(Literal { base: 10, signed: false, width: Some(2), value: 11.into() }),
 			Whitespace,
-			Integer(Literal { base: 16, signed: false, width: None, value: 255 }),
+			Integer(Literal { base: 16, signed: false, width: None, value: 255.into() }),
 			Whitespace,
 		]
 	);
 }
 
+#[test]
+fn conditional_directives_with_comments() {
+	use preprocessor::{PreprocessorDirective::*, PreprocessorExpression::*};
+	use Token::*;
+
+	let defined = || Defined("FOO".to_string());
+	assert_eq!(lex_str("#ifdef FOO // comment"), vec![PreprocessorDirective(If(defined()))]);
+	assert_eq!(lex_str("#ifdef FOO/* comment */"), vec![PreprocessorDirective(If(defined()))]);
+	assert_eq!(lex_str("#ifndef FOO /* comment */"), vec![PreprocessorDirective(If(Not(defined().into())))]);
+}
+
 #[test]
 fn real_p4() {
 	use preprocessor::{PreprocessorDirective::*, *};
@@ -95,7 +106,7 @@ fn real_p4() {
 			Whitespace,
 			Identifier("bit".to_string()),
 			OpenChevron,
-			Integer(Literal { base: 10, signed: false, width: None, value: 48 }),
+			Integer(Literal { base: 10, signed: false, width: None, value: 48.into() }),
 			CloseChevron,
 			Whitespace,
 			Identifier("EthernetAddress".to_string()),
@@ -105,7 +116,7 @@ fn real_p4() {
 			Whitespace,
 			Identifier("bit".to_string()),
 			OpenChevron,
-			Integer(Literal { base: 10, signed: false, width: None, value: 32 }),
+			Integer(Literal { base: 10, signed: false, width: None, value: 32.into() }),
 			CloseChevron,
 			Whitespace,
 			Identifier("IPv4Address".to_string()),
@@ -131,7 +142,7 @@ fn real_p4() {
 			Whitespace,
 			Identifier("bit".to_string()),
 			OpenChevron,
-			Integer(Literal { base: 10, signed: false, width: None, value: 16 }),
+			Integer(Literal { base: 10, signed: false, width: None, value: 16.into() }),
 			CloseChevron,
 			Whitespace,
 			Identifier("etherType".to_string()),
@@ -0,0 +1,95 @@
+extern crate analyzer_core;
+
+use analyzer_core::{
+	base_abstractions::Severity,
+	lints::{self, Level, LintConfig},
+};
+use pretty_assertions::assert_eq;
+
+mod common;
+
+fn lints(s: &str, config: LintConfig) -> Vec<(String, Option<&'static str>, Severity)> {
+	let mut analyzer = common::workspace(&[("foo.p4", s)]);
+	analyzer.configure_lints(config);
+	let file_id = analyzer.file_id("foo.p4");
+	analyzer
+		.diagnostics(file_id)
+		.into_iter()
+		.map(|d| (s[d.location].to_string(), d.code, d.severity))
+		.collect()
+}
+
+const PARSERS: &str = r"
+	parser P() {
+		state start { transition accept; }
+		state p_unused { transition accept; }
+	}
+	parser Q() {
+		state start { transition accept; }
+		state q_unused { transition accept; }
+	}
+";
+
+fn unreachable(state: &str, severity: Severity) -> (String, Option<&'static str>, Severity) {
+	(format!("state {state} {{ transition accept; }}"), Some("P4W0001"), severity)
+}
+
+#[test]
+fn registers_lints() {
+	assert_eq!(lints::lint("unreachable-state"), Some(&lints::UNREACHABLE_STATE));
+	assert_eq!(lints::lint("P4W0001"), Some(&lints::UNREACHABLE_STATE));
+	assert_eq!(lints::lint("unreachable"), None);
+
+	let mut codes: Vec<_> = lints::LINTS.iter().map(|lint| lint.code).collect();
+	codes.dedup();
+	assert_eq!(codes.len(), lints::LINTS.len());
+	assert!(codes.windows(2).all(|pair| pair[0] < pair[1]));
+}
+
+#[test]
+fn configures_levels() {
+	assert_eq!(
+		lints(PARSERS, LintConfig::default()),
+		vec![unreachable("p_unused", Severity::Warning), unreachable("q_unused", Severity::Warning)]
+	);
+
+	let mut config = LintConfig::default();
+	assert!(config.set("unreachable-state", Level::Error));
+	assert!(!config.set("unreachable", Level::Error));
+	assert_eq!(
+		lints(PARSERS, config),
+		vec![unreachable("p_unused", Severity::Error), unreachable("q_unused", Severity::Error)]
+	);
+
+	let mut config = LintConfig::default();
+	config.set("P4W0001", Level::parse("allow").unwrap());
+	assert_eq!(lints(PARSERS, config), vec![]);
+}
+
+#[test]
+fn allows_lints_in_the_next_declaration() {
+	let program = format!("// p4analyzer: allow(unreachable-state)\n{PARSERS}");
+	assert_eq!(lints(&program, LintConfig::default()), vec![unreachable("q_unused", Severity::Warning)]);
+
+	let program =
+		PARSERS.replace("state q_unused", "// p4analyzer: allow(nonterminating-state, P4W0001)\nstate q_unused");
+	assert_eq!(lints(&program, LintConfig::default()), vec![unreachable("p_unused", Severity::Warning)]);
+}
+
+#[test]
+fn allows_lints_in_the_whole_file() {
+	let program = format!("{PARSERS}\n// p4analyzer: allow-file(unreachable-state)\n");
+	assert_eq!(lints(&program, LintConfig::default()), vec![]);
+}
+
+#[test]
+fn reports_unknown_lints() {
+	let program = format!("// p4analyzer: allow(unreachable-state, unused-everything)\n{PARSERS}");
+	assert_eq!(
+		lints(&program, LintConfig::default()),
+		vec![
+			unreachable("q_unused", Severity::Warning),
+			("unused-everything".to_string(), Some("P4W0100"), Severity::Warning),
+		]
+	);
+}
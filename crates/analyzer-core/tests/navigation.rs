@@ -0,0 +1,127 @@
+extern crate analyzer_core;
+
+use analyzer_core::*;
+use pretty_assertions::assert_eq;
+
+mod common;
+
+const PROGRAM: &str = r#"
+	#include <core.p4>
+	#include <v1model.p4>
+	#define ETHERTYPE_IPV4 0x0800
+
+	header ethernet_t { bit<48> dst; bit<48> src; bit<16> type; }
+	struct headers_t { ethernet_t eth; ethernet_t[2] tags; }
+	struct metadata_t { }
+
+	extern Checker { Checker(); abstract bool check(in bit<16> x); }
+
+	parser P(packet_in pkt, out headers_t hdr, inout metadata_t meta, inout standard_metadata_t sm) {
+		state start { pkt.extract(hdr.eth); transition accept; }
+	}
+	control VC(inout headers_t hdr, inout metadata_t meta) { apply { } }
+	control I(inout headers_t hdr, inout metadata_t meta, inout standard_metadata_t sm) {
+		Checker() checker = { bool check(in bit<16> x) { return x == ETHERTYPE_IPV4; } };
+		apply {
+			if (checker.check(hdr.eth.type)) { mark_to_drop(sm); }
+		}
+	}
+	control E(inout headers_t hdr, inout metadata_t meta, inout standard_metadata_t sm) { apply { } }
+	control CC(inout headers_t hdr, inout metadata_t meta) { apply { } }
+	control D(packet_out pkt, in headers_t hdr) { apply { pkt.emit(hdr.eth); } }
+
+	V1Switch(P(), VC(), I(), E(), CC(), D()) main;
+"#;
+
+fn analyzer_of(program: &str) -> (Analyzer, base_abstractions::FileId) {
+	let analyzer = common::workspace(&[("foo.p4", program)]);
+	let file_id = analyzer.file_id("foo.p4");
+	(analyzer, file_id)
+}
+
+/// Returns the offset of the first occurrence of `target` in the program.
+fn offset(target: &str) -> usize { PROGRAM.find(target).expect("the target is in the program") + 1 }
+
+/// Returns the path of each location, along with its text.
+fn texts(analyzer: &Analyzer, locations: Vec<base_abstractions::Location>) -> Vec<(String, String)> {
+	locations
+		.into_iter()
+		.map(|location| {
+			let text = analyzer.source(location.file).map_or(String::new(), |s| s[location.span].to_string());
+			(analyzer.path(location.file), text)
+		})
+		.collect()
+}
+
+fn at(path: &str, text: &str) -> (String, String) { (path.to_string(), text.to_string()) }
+
+#[test]
+fn goes_to_definitions() {
+	let (analyzer, file_id) = analyzer_of(PROGRAM);
+	let definition = |target| texts(&analyzer, analyzer.definition(file_id, offset(target)));
+
+	assert_eq!(definition("hdr.eth)"), vec![at("foo.p4", "hdr")]);
+	assert_eq!(definition("eth.type))"), vec![at("foo.p4", "eth")]);
+	assert_eq!(definition("type))"), vec![at("foo.p4", "type")]);
+	assert_eq!(definition("ETHERTYPE_IPV4; }"), vec![at("foo.p4", "#define ETHERTYPE_IPV4 0x0800\n")]);
+	assert_eq!(definition("apply { }"), vec![]);
+}
+
+#[test]
+fn goes_into_headers() {
+	let (analyzer, file_id) = analyzer_of(PROGRAM);
+	let definition = |target| texts(&analyzer, analyzer.definition(file_id, offset(target)));
+
+	assert_eq!(definition("packet_in pkt"), vec![at("builtin:///core.p4", "packet_in")]);
+	assert_eq!(definition("mark_to_drop"), vec![at("builtin:///v1model.p4", "mark_to_drop")]);
+	assert_eq!(definition("V1Switch("), vec![at("builtin:///v1model.p4", "V1Switch")]);
+	assert_eq!(definition("include <v1model"), vec![at("builtin:///v1model.p4", "")]);
+}
+
+#[test]
+fn goes_to_declarations_of_abstract_methods() {
+	let (analyzer, file_id) = analyzer_of(PROGRAM);
+	let implementation = PROGRAM.find("check(in bit<16> x) {").unwrap() + 1;
+	let declaration = texts(&analyzer, analyzer.declaration(file_id, implementation));
+	assert_eq!(declaration, vec![at("foo.p4", "check")]);
+	assert_ne!(analyzer.declaration(file_id, implementation), analyzer.definition(file_id, implementation));
+
+	assert_eq!(analyzer.declaration(file_id, offset("hdr.eth)")), analyzer.definition(file_id, offset("hdr.eth)")));
+}
+
+#[test]
+fn goes_to_type_definitions() {
+	let (analyzer, file_id) = analyzer_of(PROGRAM);
+	let type_definition = |target| {
+		let locations = analyzer.type_definition(file_id, offset(target));
+		locations.into_iter().map(|location| location.span.start).collect::<Vec<_>>()
+	};
+	let declaration = |target: &str| vec![PROGRAM.find(target).unwrap()];
+
+	assert_eq!(type_definition("hdr.eth)"), declaration("headers_t {"));
+	assert_eq!(type_definition("eth.type))"), declaration("ethernet_t {"));
+	assert_eq!(type_definition("tags;"), declaration("ethernet_t {"));
+	assert_eq!(type_definition("checker ="), declaration("Checker {"));
+	assert_eq!(type_definition("type))"), vec![]);
+}
+
+#[test]
+fn finds_implementations() {
+	let program = r#"
+		control Pipe(inout bit<8> x);
+		package Top(Pipe first, Pipe second);
+		control A(inout bit<8> x) { apply { } }
+		control B(inout bit<8> x) { apply { } }
+		Top(second = B(), first = A()) main;
+	"#;
+	let (analyzer, file_id) = analyzer_of(program);
+	let implementations = texts(&analyzer, analyzer.implementations(file_id, program.find("Pipe(").unwrap()));
+	assert_eq!(implementations, vec![at("foo.p4", "B"), at("foo.p4", "A")]);
+
+	let (analyzer, file_id) = analyzer_of(PROGRAM);
+	let method = PROGRAM.find("check(in bit<16> x);").unwrap() + 1;
+	let implementations = analyzer.implementations(file_id, method);
+	let spans: Vec<_> = implementations.into_iter().map(|location| location.span.start).collect();
+	assert_eq!(spans, vec![PROGRAM.find("check(in bit<16> x) {").unwrap()]);
+	assert_eq!(analyzer.implementations(file_id, offset("VC(inout")), vec![]);
+}
@@ -0,0 +1,98 @@
+extern crate analyzer_core;
+
+use analyzer_core::{
+	outline::{fuzzy_score, DocumentSymbol},
+	symbols::SymbolKind,
+};
+use pretty_assertions::assert_eq;
+
+mod common;
+
+const PROGRAM: &str = r#"
+	header ethernet_t { bit<48> dst; bit<16> type; }
+	error { BadChecksum }
+	parser MyParser(packet_in pkt, out ethernet_t eth) {
+		state start { transition parse_ethernet; }
+		state parse_ethernet { pkt.extract(eth); transition accept; }
+	}
+	control MyIngress(inout ethernet_t eth) {
+		bit<9> port;
+		action forward(bit<9> p) { port = p; }
+		table routes { key = { eth.dst: exact; } actions = { forward; } }
+		apply { routes.apply(); }
+	}
+"#;
+
+/// Flattens an outline into the kind, name, detail and depth of each symbol, checking that the name of each symbol
+/// is within its span.
+fn flatten(symbols: &[DocumentSymbol], depth: usize, out: &mut Vec<(usize, SymbolKind, String, Option<String>)>) {
+	for symbol in symbols {
+		assert!(symbol.span.start <= symbol.selection_span.start && symbol.selection_span.end <= symbol.span.end);
+		assert_eq!(&PROGRAM[symbol.selection_span.clone()], symbol.name);
+		out.push((depth, symbol.kind.clone(), symbol.name.clone(), symbol.detail.clone()));
+		flatten(&symbol.children, depth + 1, out);
+	}
+}
+
+#[test]
+fn outlines_documents() {
+	let analyzer = common::workspace(&[("foo.p4", PROGRAM)]);
+	let mut symbols = vec![];
+	flatten(analyzer.document_symbols(analyzer.file_id("foo.p4")), 0, &mut symbols);
+
+	let symbol =
+		|depth, kind, name: &str, detail: Option<&str>| (depth, kind, name.to_string(), detail.map(str::to_string));
+	assert_eq!(
+		symbols,
+		vec![
+			symbol(0, SymbolKind::Header, "ethernet_t", None),
+			symbol(1, SymbolKind::Field, "dst", Some("bit<48>")),
+			symbol(1, SymbolKind::Field, "type", Some("bit<16>")),
+			symbol(0, SymbolKind::Enum, "error", None),
+			symbol(1, SymbolKind::ErrorMember, "BadChecksum", None),
+			symbol(0, SymbolKind::Parser, "MyParser", None),
+			symbol(1, SymbolKind::State, "start", None),
+			symbol(1, SymbolKind::State, "parse_ethernet", None),
+			symbol(0, SymbolKind::Control, "MyIngress", None),
+			symbol(1, SymbolKind::Variable, "port", Some("bit<9>")),
+			symbol(1, SymbolKind::Action, "forward", None),
+			symbol(1, SymbolKind::Table, "routes", None),
+			symbol(1, SymbolKind::Method, "apply", None),
+		]
+	);
+
+	let control = &analyzer.document_symbols(analyzer.file_id("foo.p4"))[3];
+	let apply = control.children.last().unwrap();
+	assert_eq!(&PROGRAM[apply.span.clone()], "apply { routes.apply(); }");
+}
+
+#[test]
+fn searches_the_workspace() {
+	let analyzer = common::workspace(&[("foo.p4", PROGRAM), ("bar.p4", "control Forwarding() { apply { } }")]);
+	let search = |query| {
+		let symbols = analyzer.workspace_symbols(query);
+		symbols
+			.into_iter()
+			.map(|s| (s.name, s.container, analyzer.path(s.location.file)))
+			.collect::<Vec<_>>()
+	};
+
+	assert_eq!(
+		search("fwd"),
+		vec![
+			("forward".to_string(), Some("MyIngress".to_string()), "foo.p4".to_string()),
+			("Forwarding".to_string(), None, "bar.p4".to_string()),
+		]
+	);
+	assert_eq!(search("routes"), vec![("routes".to_string(), Some("MyIngress".to_string()), "foo.p4".to_string())]);
+	assert_eq!(search("xyz"), vec![]);
+}
+
+#[test]
+fn scores_fuzzy_matches() {
+	assert_eq!(fuzzy_score("eth", "ipv4"), None);
+	assert!(fuzzy_score("eth", "ethernet_t") < fuzzy_score("eth", "parse_ethernet"));
+	assert!(fuzzy_score("pe", "parse_ethernet") > fuzzy_score("pa", "parse_ethernet"));
+	assert!(fuzzy_score("ETH", "ethernet_t").is_some());
+	assert!(fuzzy_score("", "anything").is_some());
+}
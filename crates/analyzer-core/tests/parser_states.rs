@@ -0,0 +1,185 @@
+extern crate analyzer_core;
+
+use analyzer_core::*;
+use pretty_assertions::assert_eq;
+
+mod common;
+
+fn analyze(s: &str) -> (Analyzer, base_abstractions::FileId) { common::analyze(s, &[]) }
+
+fn errors(s: &str) -> Vec<(String, String)> {
+	let (analyzer, file_id) = analyze(s);
+	analyzer.diagnostics(file_id).into_iter().map(|d| (s[d.location].to_string(), d.message)).collect()
+}
+
+fn error(text: &str, message: &str) -> (String, String) { (text.to_string(), message.to_string()) }
+
+fn parser(states: &str) -> String {
+	format!(
+		r"
+		header ethernet_t {{ bit<48> dst; bit<48> src; bit<16> etherType; }}
+		header mpls_t {{ bit<20> label; bit<3> tc; bit<1> bos; bit<8> ttl; }}
+		struct headers {{ ethernet_t ethernet; mpls_t[4] mpls; }}
+		parser P(packet_in packet, out headers hdr) {{
+			{states}
+		}}
+	"
+	)
+}
+
+#[test]
+fn builds_the_state_graph() {
+	let program = parser(
+		r"
+		state start {
+			packet.extract(hdr.ethernet);
+			transition select(hdr.ethernet.etherType) { 0x8847: parse_mpls; default: accept; }
+		}
+		state parse_mpls { transition accept; }
+		",
+	);
+	let (analyzer, file_id) = analyze(&program);
+	let graphs = analyzer.state_graphs(file_id).unwrap();
+	assert_eq!(graphs.len(), 1);
+
+	let graph = &graphs[0];
+	assert_eq!(graph.states, ["start", "parse_mpls", "accept", "reject"]);
+	// `start` may reject when the packet is too short to extract the ethernet header
+	assert_eq!(graph.successors(0).collect::<Vec<_>>(), [1, 2, 3]);
+	assert_eq!(graph.successors(1).collect::<Vec<_>>(), [2]);
+	assert_eq!(analyzer.diagnostics(file_id).len(), 0);
+}
+
+#[test]
+fn reports_unreachable_states() {
+	let program = parser("state start { transition accept; } state unused { transition accept; }");
+	assert_eq!(
+		errors(&program),
+		vec![error("state unused { transition accept; }", "the state `unused` is unreachable from `start`")]
+	);
+
+	let program = "parser P() { state first { transition accept; } }";
+	assert_eq!(errors(program), vec![error("P", "the parser `P` has no `start` state")]);
+}
+
+#[test]
+fn reports_transitions_to_undefined_states() {
+	let program = parser(
+		r"
+		state start {
+			packet.extract(hdr.ethernet);
+			transition select(hdr.ethernet.etherType) { 0x800: parse_ipv4; default: accept; }
+		}
+		",
+	);
+	assert_eq!(errors(&program), vec![error("parse_ipv4", "the parser `P` has no state `parse_ipv4`")]);
+}
+
+#[test]
+fn reports_states_that_never_terminate() {
+	let program = parser("state start { transition spin; } state spin { transition start; }");
+	assert_eq!(
+		errors(&program),
+		vec![
+			error("state start { transition spin; }", "the state `start` can never reach `accept` or `reject`"),
+			error("state spin { transition start; }", "the state `spin` can never reach `accept` or `reject`"),
+		]
+	);
+
+	// a state without a `transition` statement rejects the packet
+	assert_eq!(errors(&parser("state start { }")), vec![]);
+}
+
+#[test]
+fn checks_keysets() {
+	let program = parser(
+		r"
+		state start {
+			packet.extract(hdr.ethernet);
+			transition select(hdr.ethernet.etherType, hdr.ethernet.dst[7:0]) {
+				(0x800, 1): accept;
+				(0x86dd &&& 0xffff, 0 .. 9): accept;
+				(8w1, _): accept;
+				(0x10000, 0): accept;
+				0x800: accept;
+				default: reject;
+			}
+		}
+		",
+	);
+	assert_eq!(
+		errors(&program),
+		vec![
+			error(
+				"8w1",
+				"expected a keyset value of type `bit<16>` to match the width of the selector, found `bit<8>`"
+			),
+			error("0x10000", "the value 65536 doesn't fit in the selector's type `bit<16>`"),
+			error("0x800", "expected a keyset of 2 values to match the selector, found 1"),
+		]
+	);
+}
+
+#[test]
+fn reports_empty_keysets() {
+	let program = parser(
+		r"
+		state start {
+			packet.extract(hdr.ethernet);
+			transition select(hdr.ethernet.etherType) { (): accept; }
+		}
+		",
+	);
+	assert_eq!(errors(&program), vec![error("()", "a keyset cannot be empty")]);
+}
+
+#[test]
+fn reports_unbounded_loops() {
+	// the loop ends when the stack is full at the latest
+	let program = parser(
+		r"
+		state start { transition parse_mpls; }
+		state parse_mpls {
+			packet.extract(hdr.mpls.next);
+			transition select(hdr.mpls.last.bos) { 0: parse_mpls; default: accept; }
+		}
+		",
+	);
+	assert_eq!(errors(&program), vec![]);
+
+	let program = parser(
+		r"
+		state start { transition parse_mpls; }
+		state parse_mpls {
+			packet.extract(hdr.mpls[0]);
+			transition select(hdr.mpls.last.bos) { 0: parse_mpls; default: accept; }
+		}
+		",
+	);
+	assert_eq!(
+		errors(&program),
+		vec![error(
+			"state parse_mpls {
+			packet.extract(hdr.mpls[0]);
+			transition select(hdr.mpls.last.bos) { 0: parse_mpls; default: accept; }
+		}",
+			"the loop through the state `parse_mpls` accesses the header stack `hdr.mpls` without extracting into \
+			 `hdr.mpls.next`, so it is not bounded by the size of the stack"
+		)]
+	);
+
+	let program = parser(
+		r"
+		state start { packet.extract(hdr.ethernet); transition a; }
+		state a { transition select(hdr.ethernet.etherType) { 0: b; default: accept; } }
+		state b { transition a; }
+		",
+	);
+	assert_eq!(
+		errors(&program),
+		vec![error(
+			"state a { transition select(hdr.ethernet.etherType) { 0: b; default: accept; } }",
+			"the loop through the states `a` and `b` never extracts from the packet, so it may never end"
+		)]
+	);
+}
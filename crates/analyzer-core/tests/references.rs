@@ -0,0 +1,104 @@
+extern crate analyzer_core;
+
+use analyzer_core::{references::Access, *};
+use pretty_assertions::assert_eq;
+
+mod common;
+
+const DEFS: &str = r#"
+	#define PORT_CPU 64
+	header ethernet_t { bit<48> dst; bit<48> src; bit<16> type; }
+	struct headers_t { ethernet_t eth; }
+"#;
+
+const INGRESS: &str = r#"
+	#include "defs.p4"
+	control I(inout headers_t hdr, out bit<9> port) {
+		action set(bit<9> p) { port = p; }
+		apply {
+			hdr.eth.type = 0x0800;
+			if (hdr.eth.src == 0) { set(PORT_CPU); }
+	#if 0
+			ethernet_t unused;
+	#endif
+		}
+	}
+"#;
+
+const EGRESS: &str = r#"
+	#include "defs.p4"
+	control E(inout headers_t hdr) {
+		ethernet_t copy;
+		apply { copy = hdr.eth; }
+	}
+"#;
+
+fn workspace() -> Analyzer { common::workspace(&[("defs.p4", DEFS), ("ingress.p4", INGRESS), ("egress.p4", EGRESS)]) }
+
+fn source(path: &str) -> &'static str {
+	match path {
+		"defs.p4" => DEFS,
+		"ingress.p4" => INGRESS,
+		_ => EGRESS,
+	}
+}
+
+/// Returns the references to the first occurrence of `target` in the file at `path`, as the path and line of each.
+fn references(path: &str, target: &str, include_declaration: bool, include_inactive: bool) -> Vec<(String, usize)> {
+	let analyzer = workspace();
+	let offset = source(path).find(target).expect("the target is in the file");
+	let references = analyzer.references(analyzer.file_id(path), offset, include_declaration, include_inactive);
+	references
+		.into_iter()
+		.map(|location| {
+			let path = analyzer.path(location.file);
+			let line = source(&path)[..location.span.start].lines().count();
+			(path, line)
+		})
+		.collect()
+}
+
+fn at(path: &str, line: usize) -> (String, usize) { (path.to_string(), line) }
+
+#[test]
+fn finds_references_across_the_workspace() {
+	assert_eq!(
+		references("ingress.p4", "eth.type", true, false),
+		vec![at("defs.p4", 4), at("egress.p4", 5), at("ingress.p4", 6), at("ingress.p4", 7)]
+	);
+	assert_eq!(references("defs.p4", "ethernet_t {", false, false), vec![at("defs.p4", 4), at("egress.p4", 4)]);
+	assert_eq!(references("ingress.p4", "p; }", false, false), vec![at("ingress.p4", 4)]);
+}
+
+#[test]
+fn finds_references_in_inactive_regions() {
+	assert_eq!(
+		references("defs.p4", "ethernet_t {", false, true),
+		vec![at("defs.p4", 4), at("egress.p4", 4), at("ingress.p4", 9)]
+	);
+}
+
+#[test]
+fn finds_uses_of_macros() {
+	assert_eq!(references("ingress.p4", "PORT_CPU", true, false), vec![at("defs.p4", 2), at("ingress.p4", 7)]);
+}
+
+#[test]
+fn highlights_reads_and_writes() {
+	let analyzer = workspace();
+	let file_id = analyzer.file_id("ingress.p4");
+	let highlights = |target: &str| {
+		let offset = INGRESS.find(target).expect("the target is in the file");
+		let highlights = analyzer.highlights(file_id, offset);
+		highlights
+			.into_iter()
+			.map(|h| (INGRESS[..h.location.span.start].lines().count(), h.access))
+			.collect::<Vec<_>>()
+	};
+
+	// the declarations are in another file
+	assert_eq!(highlights("type ="), vec![(6, Access::Write)]);
+	assert_eq!(highlights("src =="), vec![(7, Access::Read)]);
+	assert_eq!(highlights("port = p"), vec![(3, Access::Declaration), (4, Access::Write)]);
+	assert_eq!(highlights("hdr.eth.type"), vec![(3, Access::Declaration), (6, Access::Read), (7, Access::Read)]);
+}
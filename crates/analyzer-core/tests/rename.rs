@@ -0,0 +1,116 @@
+extern crate analyzer_core;
+
+use analyzer_core::*;
+use pretty_assertions::assert_eq;
+
+mod common;
+
+const DEFS: &str = r#"
+	header ethernet_t { bit<48> dst; bit<48> src; bit<16> type; }
+	struct meta_t { bit<9> port; bit<1> drop; }
+	struct headers_t { ethernet_t eth; }
+"#;
+
+const PROGRAM: &str = r#"
+	#include <core.p4>
+	#include <v1model.p4>
+	#include "defs.p4"
+	#define IS_IPV4 (hdr.eth.type == 0x0800)
+
+	parser P(packet_in pkt, out headers_t hdr, inout meta_t meta, inout standard_metadata_t sm) {
+		state start { pkt.extract(hdr.eth); transition parse_more; }
+		state parse_more { transition accept; }
+	}
+	control I(inout headers_t hdr, inout meta_t meta, inout standard_metadata_t sm) {
+		action forward(bit<9> port) { meta = { port = port, drop = 0 }; }
+		table routes { key = { hdr.eth.dst: exact; } actions = { forward; } }
+		apply {
+			routes.apply();
+			if (IS_IPV4) { mark_to_drop(sm); }
+			meta.port = sm.ingress_port;
+		}
+	}
+"#;
+
+fn workspace() -> Analyzer { common::workspace(&[("defs.p4", DEFS), ("main.p4", PROGRAM)]) }
+
+/// Renames the first occurrence of `target` in the program, returning the path and text replaced by each edit.
+fn rename(target: &str, new_name: &str) -> Result<Vec<(String, usize)>, String> {
+	let analyzer = workspace();
+	let file_id = analyzer.file_id("main.p4");
+	let offset = PROGRAM.find(target).expect("the target is in the program");
+	analyzer.prepare_rename(file_id, offset)?;
+	let locations = analyzer.rename(file_id, offset, new_name)?;
+	Ok(locations
+		.into_iter()
+		.map(|location| {
+			let source = analyzer.source(location.file).unwrap();
+			(analyzer.path(location.file), source[..location.span.start].lines().count())
+		})
+		.collect())
+}
+
+fn at(path: &str, line: usize) -> (String, usize) { (path.to_string(), line) }
+
+#[test]
+fn renames_across_files() {
+	assert_eq!(rename("dst: exact", "destination"), Ok(vec![at("defs.p4", 2), at("main.p4", 13)]));
+	assert_eq!(
+		rename("meta_t meta, inout standard", "metadata_t"),
+		Ok(vec![at("defs.p4", 3), at("main.p4", 7), at("main.p4", 11)])
+	);
+}
+
+#[test]
+fn renames_members_states_actions_and_tables() {
+	assert_eq!(rename("parse_more;", "parse_next"), Ok(vec![at("main.p4", 8), at("main.p4", 9)]));
+	assert_eq!(rename("forward(", "fwd"), Ok(vec![at("main.p4", 12), at("main.p4", 13)]));
+	assert_eq!(rename("routes.apply", "ipv4_routes"), Ok(vec![at("main.p4", 13), at("main.p4", 15)]));
+	// the names of a structure-valued expression are renamed along with the fields
+	assert_eq!(rename("port = sm", "egress_port"), Ok(vec![at("defs.p4", 3), at("main.p4", 12), at("main.p4", 17)]));
+}
+
+#[test]
+fn refuses_read_only_declarations() {
+	assert_eq!(
+		rename("mark_to_drop", "drop"),
+		Err("`mark_to_drop` is declared in the read-only architecture header `v1model.p4`".to_string())
+	);
+	assert_eq!(
+		rename("packet_in", "packet"),
+		Err("`packet_in` is declared in the core library, which cannot be edited".to_string())
+	);
+}
+
+#[test]
+fn refuses_macro_expansions() {
+	assert_eq!(
+		rename("IS_IPV4)", "IS_V4"),
+		Err("the macro `IS_IPV4` cannot be renamed, since its uses are only known by name".to_string())
+	);
+	assert_eq!(
+		rename("hdr, inout meta_t meta, inout standard_metadata_t sm) {\n\t\taction", "headers"),
+		Err("`hdr` is used in the expansion of the macro `IS_IPV4`, which cannot be renamed".to_string())
+	);
+}
+
+#[test]
+fn checks_new_names() {
+	assert_eq!(rename("forward(", "action"), Err("`action` is not a valid name".to_string()));
+	assert_eq!(rename("forward(", "two words"), Err("`two words` is not a valid name".to_string()));
+	assert_eq!(
+		rename("routes.apply", "forward"),
+		Err("`forward` is already declared in the scope of `routes`".to_string())
+	);
+	assert_eq!(rename("apply {\n", "x"), Err("there is no symbol to rename here".to_string()));
+}
+
+#[test]
+fn renames_inactive_uses() {
+	let source = "control C() {\n\taction a() { }\n\tapply {\n#ifdef FAST\n\t\ta();\n#endif\n\t}\n}\n";
+	let analyzer = common::workspace(&[("foo.p4", source)]);
+	let file_id = analyzer.file_id("foo.p4");
+	let locations = analyzer.rename(file_id, source.find("a()").unwrap(), "b").unwrap();
+	let lines: Vec<_> = locations.iter().map(|location| source[..location.span.start].lines().count()).collect();
+	assert_eq!(lines, [2, 5]);
+}
@@ -0,0 +1,59 @@
+extern crate analyzer_core;
+
+use pretty_assertions::assert_eq;
+
+mod common;
+
+const PROGRAM: &str = r#"
+header h_t { bit<8> f; }
+
+control MyIngress(inout h_t h) {
+    action set(bit<8> value) { h.f = value; }
+    apply {
+        if (h.isValid()) {
+            set(h.f + 1);
+        }
+    }
+}
+"#;
+
+/// Returns the text selected in turn when expanding the selection at the first `marker` of `source`, after its first
+/// character.
+fn selections(source: &str, marker: &str) -> Vec<String> {
+	let analyzer = common::workspace(&[("foo.p4", source)]);
+	let file_id = analyzer.file_id("foo.p4");
+	let offset = source.find(marker).unwrap() + 1;
+	let spans = analyzer.selection_ranges(file_id, offset);
+	spans.into_iter().map(|span| source[span].to_string()).collect()
+}
+
+#[test]
+fn walks_outward_through_the_syntax_tree() {
+	let selections = selections(PROGRAM, "f + 1");
+	assert_eq!(selections[..5], ["f", "h.f", "h.f + 1", "set(h.f + 1)", "set(h.f + 1);"].map(String::from));
+	assert!(selections[5].starts_with("{\n            set"));
+	assert!(selections[6].starts_with("if (h.isValid())"));
+	assert!(selections.last().unwrap().starts_with("control MyIngress"));
+	assert!(selections.windows(2).all(|pair| pair[1].contains(pair[0].as_str()) && pair[1] != pair[0]));
+}
+
+#[test]
+fn selects_declarations() {
+	let action = selections(PROGRAM, "alue; }");
+	assert_eq!(
+		action[..4],
+		["value", "h.f = value;", "{ h.f = value; }", "action set(bit<8> value) { h.f = value; }"].map(String::from)
+	);
+
+	let field = selections(PROGRAM, "it<8> f;");
+	assert_eq!(field[..4], ["bit", "bit<8>", "bit<8> f;", "header h_t { bit<8> f; }"].map(String::from));
+}
+
+#[test]
+fn walks_outward_through_brackets_while_the_file_does_not_parse() {
+	let source = PROGRAM.replace("set(h.f + 1);", "set(h.f + 1;");
+	let selections = selections(&source, "f + 1");
+	assert_eq!(selections[..2], ["f", "set(h.f + 1;"].map(String::from));
+	assert_eq!(selections[2], "{\n            set(h.f + 1;\n        }");
+	assert!(selections[3].starts_with("if (h.isValid()) {"));
+}
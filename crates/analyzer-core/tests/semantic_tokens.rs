@@ -0,0 +1,95 @@
+extern crate analyzer_core;
+
+use analyzer_core::{
+	ast::Direction,
+	semantic_tokens::{SemanticToken, SemanticTokenKind},
+	symbols::SymbolKind,
+	*,
+};
+use pretty_assertions::assert_eq;
+
+mod common;
+
+/// Returns the text and classification of the semantic tokens of a file.
+fn tokens<'a>(analyzer: &Analyzer, source: &'a str) -> Vec<(&'a str, SemanticTokenKind, bool)> {
+	analyzer
+		.semantic_tokens(analyzer.file_id("foo.p4"))
+		.into_iter()
+		.map(|SemanticToken { span, kind, declaration, .. }| (&source[span], kind, declaration))
+		.collect()
+}
+
+fn symbol(text: &str, kind: SymbolKind, declaration: bool) -> (&str, SemanticTokenKind, bool) {
+	(text, SemanticTokenKind::Symbol(kind), declaration)
+}
+
+#[test]
+fn classifies_names_by_symbol() {
+	let source = r#"
+		header h_t { bit<8> f; }
+		control C(inout h_t h) {
+			action a() { h.f = 1; }
+			table t { actions = { a; } }
+			apply { t.apply(); }
+		}
+	"#;
+	let analyzer = common::workspace(&[("foo.p4", source)]);
+
+	assert_eq!(
+		tokens(&analyzer, source),
+		vec![
+			symbol("h_t", SymbolKind::Header, true),
+			symbol("f", SymbolKind::Field, true),
+			symbol("C", SymbolKind::Control, true),
+			symbol("h_t", SymbolKind::Header, false),
+			symbol("h", SymbolKind::Parameter(Some(Direction::InOut)), true),
+			symbol("a", SymbolKind::Action, true),
+			symbol("h", SymbolKind::Parameter(Some(Direction::InOut)), false),
+			symbol("f", SymbolKind::Field, false),
+			symbol("t", SymbolKind::Table, true),
+			symbol("a", SymbolKind::Action, false),
+			symbol("t", SymbolKind::Table, false),
+		]
+	);
+}
+
+#[test]
+fn classifies_parser_states() {
+	let source = r#"
+		parser P() {
+			state start { transition next; }
+			state next { transition accept; }
+		}
+	"#;
+	let analyzer = common::workspace(&[("foo.p4", source)]);
+	let tokens = tokens(&analyzer, source);
+
+	assert!(tokens.contains(&symbol("start", SymbolKind::State, true)));
+	assert!(tokens.contains(&symbol("next", SymbolKind::State, false)));
+	assert!(tokens.contains(&symbol("next", SymbolKind::State, true)));
+}
+
+#[test]
+fn classifies_macros_and_inactive_regions() {
+	let source = "#define WIDTH 8\n#if 0\nconst bit<8> dead = 1;\n#endif\nconst bit<WIDTH> live = 1;\n";
+	let analyzer = common::workspace(&[("foo.p4", source)]);
+	let tokens = tokens(&analyzer, source);
+
+	assert_eq!(tokens[0], symbol("WIDTH", SymbolKind::Macro, true));
+	let inactive: Vec<_> = tokens.iter().filter(|(_, kind, _)| *kind == SemanticTokenKind::Inactive).collect();
+	assert!(inactive.iter().any(|(text, ..)| *text == "dead"));
+	assert!(inactive.iter().all(|(text, ..)| source.find(text).unwrap() < source.find("#endif").unwrap()));
+	assert!(tokens.contains(&symbol("WIDTH", SymbolKind::Macro, false)));
+	assert!(tokens.contains(&symbol("live", SymbolKind::Constant, true)));
+}
+
+#[test]
+fn marks_library_symbols() {
+	let source = "extern E { E(); }\nparser P(packet_in pkt) { state start { transition accept; } }\n";
+	let analyzer = common::workspace(&[("foo.p4", source)]);
+	let tokens = analyzer.semantic_tokens(analyzer.file_id("foo.p4"));
+
+	let library: Vec<_> =
+		tokens.iter().filter(|token| token.library).map(|token| &source[token.span.clone()]).collect();
+	assert_eq!(library, vec!["packet_in"]);
+}
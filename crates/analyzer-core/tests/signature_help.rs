@@ -0,0 +1,96 @@
+extern crate analyzer_core;
+
+use pretty_assertions::assert_eq;
+
+mod common;
+
+const PROGRAM: &str = r#"
+	#include <core.p4>
+	#include <v1model.p4>
+
+	struct metadata_t { bit<32> index; }
+
+	control MyIngress(inout metadata_t meta) {
+		counter(1024, CounterType.packets) hits;
+
+		// Forwards a packet.
+		action forward(in bit<9> port, bit<32> index) { }
+
+		apply {
+			$
+		}
+	}
+"#;
+
+/// Returns the signature help at the end of `code` inserted in the `apply` block of [`PROGRAM`].
+fn signature_help(code: &str) -> Option<(Vec<String>, usize, Option<String>)> {
+	let source = PROGRAM.replace('$', code);
+	let analyzer = common::workspace(&[("foo.p4", &source)]);
+	let file_id = analyzer.file_id("foo.p4");
+
+	let offset = source.find(code).unwrap() + code.len();
+	let help = analyzer.signature_help(file_id, offset)?;
+	let signature = &help.signatures[help.active_signature];
+	let parameter = signature
+		.parameters
+		.get(help.active_parameter)
+		.map(|span| signature.label[span.clone()].to_string());
+	let labels = help.signatures.into_iter().map(|signature| signature.label).collect();
+	Some((labels, help.active_parameter, parameter))
+}
+
+#[test]
+fn shows_action_signatures() {
+	let (labels, active, parameter) = signature_help("forward(1, ").unwrap();
+	assert_eq!(labels, vec!["action forward(in bit<9> port, bit<32> index)"]);
+	assert_eq!((active, parameter.as_deref()), (1, Some("bit<32> index")));
+
+	let source = PROGRAM.replace('$', "forward(");
+	let analyzer = common::workspace(&[("foo.p4", &source)]);
+	let file_id = analyzer.file_id("foo.p4");
+	let help = analyzer.signature_help(file_id, source.find("forward(").unwrap() + "forward(".len()).unwrap();
+	assert_eq!(help.signatures[0].documentation.as_deref(), Some("Forwards a packet."));
+}
+
+#[test]
+fn shows_extern_method_signatures() {
+	let (labels, active, parameter) = signature_help("hits.count(").unwrap();
+	assert_eq!(labels, vec!["void count(in bit<32> index)"]);
+	assert_eq!((active, parameter.as_deref()), (0, Some("in bit<32> index")));
+}
+
+#[test]
+fn shows_the_innermost_call() {
+	let (labels, active, _) = signature_help("forward((bit<9>) meta.index, (bit<32>) hits.count(").unwrap();
+	assert_eq!(labels, vec!["void count(in bit<32> index)"]);
+	assert_eq!(active, 0);
+
+	let (labels, active, _) = signature_help("forward(meta.index < 2 ? 1 : 0, ").unwrap();
+	assert_eq!(labels, vec!["action forward(in bit<9> port, bit<32> index)"]);
+	assert_eq!(active, 1);
+
+	assert_eq!(signature_help("forward(1, 2); "), None);
+}
+
+#[test]
+fn shows_constructor_and_type_signatures() {
+	let (labels, active, parameter) = signature_help("register<bit<32>>(").unwrap();
+	assert_eq!(labels, vec!["register(bit<32> size)"]);
+	assert_eq!((active, parameter.as_deref()), (0, Some("bit<32> size")));
+
+	let (labels, active, parameter) = signature_help("register<").unwrap();
+	assert_eq!(labels, vec!["register<T>"]);
+	assert_eq!((active, parameter.as_deref()), (0, Some("T")));
+
+	let (labels, active, parameter) = signature_help("V1Switch<tuple<bit<8>, bit<8>>, ").unwrap();
+	assert_eq!(labels, vec!["V1Switch<H, M>"]);
+	assert_eq!((active, parameter.as_deref()), (1, Some("M")));
+}
+
+#[test]
+fn shows_package_signatures() {
+	let (labels, active, parameter) = signature_help("V1Switch(MyParser(), ").unwrap();
+	assert_eq!(labels.len(), 1);
+	assert!(labels[0].starts_with("package V1Switch<H, M>(Parser<H, M> p, VerifyChecksum<H, M> vr,"));
+	assert_eq!((active, parameter.as_deref()), (1, Some("VerifyChecksum<H, M> vr")));
+}
@@ -0,0 +1,186 @@
+extern crate analyzer_core;
+
+use analyzer_core::{symbols::SymbolKind, *};
+use pretty_assertions::assert_eq;
+
+mod common;
+
+/// Analyzes a program, allowing the accesses to headers that may be invalid, which the programs below do not check.
+fn analyze(s: &str) -> (Analyzer, base_abstractions::FileId) { common::analyze(s, &["invalid-header-access"]) }
+
+fn errors(s: &str) -> Vec<(String, String)> {
+	let (analyzer, file_id) = analyze(s);
+	analyzer.diagnostics(file_id).into_iter().map(|d| (s[d.location].to_string(), d.message)).collect()
+}
+
+/// Returns the kind and declaration offset of the symbol referenced at the first occurrence of `marker` in `s`.
+fn resolve(s: &str, marker: &str) -> Option<(SymbolKind, usize)> {
+	let (analyzer, file_id) = analyze(s);
+	let offset = s.find(marker).expect("marker not found");
+	let table = analyzer.symbols(file_id).unwrap();
+	let target = table.reference_at(file_id, offset)?.target?;
+	let symbol = table.symbol(target);
+	Some((symbol.kind.clone(), symbol.location.span.start))
+}
+
+const PROGRAM: &str = r"
+	const bit<16> TYPE_IPV4 = 0x800;
+	header ethernet_t { bit<48> dst; bit<16> etherType; }
+	struct headers { ethernet_t ethernet; }
+	enum bit<8> Color { Red = 1, Green = 2 }
+
+	parser P(packet_in packet, out headers hdr) {
+		state start {
+			packet.extract(hdr.ethernet);
+			transition select(hdr.ethernet.etherType) {
+				TYPE_IPV4: parse_more;
+				default: accept;
+			}
+		}
+		state parse_more {
+			verify(hdr.ethernet.dst != 0, error.NoMatch);
+			transition accept;
+		}
+	}
+
+	control C(inout headers hdr) {
+		action set(Color c) { hdr.ethernet.etherType = (bit<16>) c; }
+		table t {
+			key = { hdr.ethernet.dst: exact; }
+			actions = { set; NoAction; }
+			default_action = set(Color.Red);
+		}
+		apply {
+			bit<8> x = 1;
+			t.apply();
+		}
+	}
+";
+
+#[test]
+fn resolves_a_program() {
+	assert_eq!(errors(PROGRAM), vec![]);
+}
+
+#[test]
+fn resolves_forward_references() {
+	let declaration = PROGRAM.find("parse_more {").unwrap();
+	assert_eq!(resolve(PROGRAM, "parse_more;"), Some((SymbolKind::State, declaration)));
+
+	let declaration = PROGRAM.find("set(Color c)").unwrap();
+	assert_eq!(resolve(PROGRAM, "set;"), Some((SymbolKind::Action, declaration)));
+}
+
+#[test]
+fn resolves_members_of_enums_and_errors() {
+	let declaration = PROGRAM.find("Red = 1").unwrap();
+	assert_eq!(resolve(PROGRAM, "Red)"), Some((SymbolKind::EnumMember, declaration)));
+	assert!(matches!(resolve(PROGRAM, "NoMatch"), Some((SymbolKind::ErrorMember, _))));
+	assert!(matches!(resolve(PROGRAM, "exact"), Some((SymbolKind::MatchKind, _))));
+}
+
+#[test]
+fn resolves_innermost_declaration() {
+	let s = r"
+		const bit<8> x = 1;
+		action a(bit<8> x) {
+			bit<8> y = x;
+		}
+	";
+	let declaration = s.find("x)").unwrap();
+	assert_eq!(resolve(s, "x;"), Some((SymbolKind::Parameter(None), declaration)));
+}
+
+#[test]
+fn locals_are_visible_after_their_declaration() {
+	let s = r"
+		action a() {
+			y = 1;
+			bit<8> y = 2;
+			y = 3;
+		}
+	";
+	assert_eq!(errors(s), vec![("y".to_string(), "cannot find `y` in this scope".to_string())]);
+}
+
+#[test]
+fn reports_unresolved_names() {
+	let s = r"
+		struct s { undefined_t f; }
+		enum E { A }
+		const E e = E.B;
+		const error err = error.Nope;
+	";
+	assert_eq!(
+		errors(s),
+		vec![
+			("undefined_t".to_string(), "cannot find `undefined_t` in this scope".to_string()),
+			("B".to_string(), "no member `B` in `E`".to_string()),
+			("Nope".to_string(), "no member `Nope` in `error`".to_string()),
+		]
+	);
+}
+
+#[test]
+fn resolves_macros() {
+	let s = r"
+		#define WIDTH 8
+		const bit<WIDTH> x = 0;
+	";
+	assert_eq!(errors(s), vec![]);
+	assert!(matches!(resolve(s, "WIDTH>"), Some((SymbolKind::Macro, _))));
+}
+
+#[test]
+fn skips_empty_declarations() {
+	let s = format!("{}const bit<8> X = 1;\nconst bit<8> Y = X;;\n", ";".repeat(100_000));
+	assert_eq!(errors(&s), vec![]);
+	assert!(matches!(resolve(&s, "X;;"), Some((SymbolKind::Constant, _))));
+}
+
+#[test]
+fn groups_diagnostics_by_their_file() {
+	let header = "struct s { undefined_t f; }\n";
+	let main = "#include \"header.p4\"\ncontrol C(inout s v) { apply { v.f = y; } }\n";
+	let mut analyzer = common::workspace(&[("header.p4", header), ("main.p4", main)]);
+	let (header_id, main_id) = (analyzer.file_id("header.p4"), analyzer.file_id("main.p4"));
+
+	let groups = analyzer.diagnostics_by_file(main_id);
+	let groups: Vec<_> = groups
+		.into_iter()
+		.map(|(file, diagnostics)| {
+			let source = if file == main_id { main } else { header };
+			(file, diagnostics.into_iter().map(|d| source[d.location].to_string()).collect::<Vec<_>>())
+		})
+		.collect();
+	assert_eq!(groups, vec![(main_id, vec!["y".to_string()]), (header_id, vec!["undefined_t".to_string()])]);
+
+	// the header is listed without diagnostics once they are fixed
+	analyzer.update(header_id, &"struct s { bit<8> f; }\n".to_string());
+	assert_eq!(analyzer.diagnostics_by_file(main_id)[1], (header_id, vec![]));
+}
+
+#[test]
+fn merges_the_diagnostics_of_a_header_across_its_includers() {
+	let header = "#ifdef A\nstruct s { a_t f; }\n#else\nstruct s { b_t f; }\n#endif\n";
+	let files =
+		[("header.p4", header), ("a.p4", "#define A\n#include \"header.p4\"\n"), ("b.p4", "#include \"header.p4\"\n")];
+	let analyzer = common::workspace(&files);
+	let [header_id, a_id, b_id] = files.map(|(path, _)| analyzer.file_id(path));
+
+	// the header gets the diagnostics of both of its includers, whichever is analyzed
+	let header_texts = |groups: Vec<(_, Vec<base_abstractions::Diagnostic>)>| {
+		let (_, diagnostics) = groups.into_iter().find(|(file, _)| *file == header_id).unwrap();
+		let mut texts: Vec<_> = diagnostics.into_iter().map(|d| &header[d.location]).collect();
+		texts.sort();
+		texts
+	};
+	assert_eq!(header_texts(analyzer.diagnostics_by_file(a_id)), ["a_t", "b_t"]);
+	assert_eq!(header_texts(analyzer.diagnostics_by_file(b_id)), ["a_t", "b_t"]);
+
+	// a change to the header also affects the files including it
+	let files: Vec<_> = analyzer.diagnostics_by_file(header_id).into_iter().map(|(file, _)| file).collect();
+	assert_eq!(files.len(), 3);
+	assert_eq!(files[0], header_id);
+	assert!(files.contains(&a_id) && files.contains(&b_id));
+}
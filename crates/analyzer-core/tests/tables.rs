@@ -0,0 +1,170 @@
+extern crate analyzer_core;
+
+use pretty_assertions::assert_eq;
+
+mod common;
+
+fn errors(s: &str) -> Vec<(String, String)> {
+	let (analyzer, file_id) = common::analyze(s, &[]);
+	analyzer.diagnostics(file_id).into_iter().map(|d| (s[d.location].to_string(), d.message)).collect()
+}
+
+fn error(text: &str, message: &str) -> (String, String) { (text.to_string(), message.to_string()) }
+
+fn control(body: &str) -> String {
+	format!(
+		r"
+		header h_t {{ bit<16> kind; bit<32> addr; }}
+		struct headers {{ h_t h; }}
+		control C(inout headers hdr, inout bit<9> port) {{
+			action forward(bit<9> p) {{ port = p; }}
+			action set(inout bit<9> x, bit<9> p) {{ x = p; }}
+			action drop() {{ port = 0; }}
+			{body}
+		}}
+		"
+	)
+}
+
+#[test]
+fn checks_match_kinds() {
+	let program = r"
+		#include <v1model.p4>
+		match_kind { custom }
+		control C(in bit<8> x) {
+			action a() { }
+			table t { key = { x: exact; x: range; x: custom; x: a; } actions = { a; } }
+			apply { }
+		}
+	";
+	assert_eq!(
+		errors(program),
+		vec![
+			error("custom", "the architecture `v1model` doesn't support the match kind `custom`"),
+			error("a", "`a` is not a match kind"),
+		]
+	);
+}
+
+#[test]
+fn checks_actions() {
+	let program = control(
+		r"
+		action slice(varbit<32> v) { }
+		table t { actions = { forward; set; set(port); slice; } }
+		table u { key = { hdr.h.kind: exact; } }
+		apply { }
+		",
+	);
+	assert_eq!(
+		errors(&program),
+		vec![
+			error("set", "the action `set` expects 1 arguments, found 0"),
+			error(
+				"slice",
+				"the parameter `v` of the action `slice` cannot be supplied by the control plane, because its type is \
+				 `varbit<32>`"
+			),
+			error("u", "the table `u` has no `actions` property"),
+		]
+	);
+}
+
+#[test]
+fn checks_default_actions() {
+	let program = control(
+		r"
+		table t { actions = { forward; } default_action = drop(); }
+		table u { actions = { forward; } const default_action = forward; }
+		table v { actions = { forward; drop; } const default_action = forward(1); }
+		apply { }
+		",
+	);
+	assert_eq!(
+		errors(&program),
+		vec![
+			error("drop", "the default action `drop` is not one of the actions of the table `t`"),
+			error("forward", "the action `forward` expects 1 arguments, found 0"),
+		]
+	);
+}
+
+#[test]
+fn checks_entries() {
+	let program = control(
+		r"
+		table t {
+			key = { hdr.h.kind: exact; hdr.h.addr: ternary; }
+			actions = { forward; drop; }
+			const entries = {
+				(0x800, 0x0a000000 &&& 0xff000000): forward(1);
+				(8w1, _): drop();
+				(0x10000, 0): drop();
+				0x800: drop();
+				_: drop();
+				(1, 1): set(port, 1);
+			}
+		}
+		table u { actions = { drop; } entries = { 1: drop(); } }
+		apply { }
+		",
+	);
+	assert_eq!(
+		errors(&program),
+		vec![
+			error("8w1", "expected a key value of type `bit<16>` to match the width of the key, found `bit<8>`"),
+			error("0x10000", "the value 65536 doesn't fit in the key's type `bit<16>`"),
+			error("0x800", "expected an entry of 2 key values to match the key, found 1"),
+			error("set", "the entry action `set` is not one of the actions of the table `t`"),
+			error("entries = { 1: drop(); }", "the table `u` has entries but no key"),
+		]
+	);
+}
+
+#[test]
+fn reports_empty_entries() {
+	let program = control(
+		r"
+		table t {
+			key = { hdr.h.kind: exact; }
+			actions = { drop; }
+			const entries = { (): drop(); }
+		}
+		apply { }
+		",
+	);
+	assert_eq!(errors(&program), vec![error("()", "a keyset cannot be empty")]);
+}
+
+#[test]
+fn checks_sizes() {
+	let program = control(
+		r"
+		const bit<32> SIZE = 1024;
+		table t { actions = { drop; } size = SIZE * 2; }
+		table u { actions = { drop; } size = (bit<32>) port; }
+		apply { }
+		",
+	);
+	assert_eq!(
+		errors(&program),
+		vec![error("(bit<32>) port", "the `size` of a table must be a compile-time constant integer")]
+	);
+}
+
+#[test]
+fn checks_switch_labels() {
+	let program = control(
+		r"
+		table t { actions = { forward; drop; } }
+		apply {
+			switch (t.apply().action_run) {
+				forward: { }
+				set: { }
+				default: { }
+			}
+		}
+		",
+	);
+	assert_eq!(errors(&program), vec![error("set", "the table `t` has no action `set`")]);
+}
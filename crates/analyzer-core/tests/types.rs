@@ -0,0 +1,277 @@
+extern crate analyzer_core;
+
+use analyzer_core::{types::Type, *};
+use pretty_assertions::assert_eq;
+
+mod common;
+
+/// Analyzes a program, allowing the accesses to headers that may be invalid, which the programs below do not check.
+fn analyze(s: &str) -> (Analyzer, base_abstractions::FileId) { common::analyze(s, &["invalid-header-access"]) }
+
+fn errors(s: &str) -> Vec<(String, String)> {
+	let (analyzer, file_id) = analyze(s);
+	analyzer.diagnostics(file_id).into_iter().map(|d| (s[d.location].to_string(), d.message)).collect()
+}
+
+/// Returns the displayed type of the innermost expression at the first occurrence of `marker` in `s`.
+fn type_at(s: &str, marker: &str) -> Option<String> {
+	let (analyzer, file_id) = analyze(s);
+	let offset = s.find(marker).expect("marker not found");
+	let (_, ty) = analyzer.types(file_id)?.expression_at(file_id, offset)?;
+	let ty = ty.display(analyzer.symbols(file_id)?).to_string();
+	Some(ty)
+}
+
+fn error(text: &str, message: &str) -> (String, String) { (text.to_string(), message.to_string()) }
+
+const PROGRAM: &str = r"
+	const bit<16> TYPE_IPV4 = 0x800;
+	typedef bit<48> mac_t;
+	header ethernet_t { mac_t dst; bit<16> etherType; }
+	header ipv4_t { bit<4> version; bit<8> ttl; bit<32> src; }
+	struct headers { ethernet_t ethernet; ipv4_t[2] ipv4; }
+	enum bit<8> Color { Red = 1, Green = 2 }
+	extern Counter<T> { Counter(bit<32> size); void count(in T index); }
+
+	parser P(packet_in packet, out headers hdr) {
+		state start {
+			packet.extract(hdr.ethernet);
+			transition select(hdr.ethernet.etherType) {
+				TYPE_IPV4: parse_ipv4;
+				default: accept;
+			}
+		}
+		state parse_ipv4 {
+			packet.extract(hdr.ipv4.next);
+			verify(hdr.ipv4[0].version == 4, error.NoMatch);
+			transition accept;
+		}
+	}
+
+	control C(inout headers hdr) {
+		Counter<bit<8>>(256) counter;
+		action set(Color c) { hdr.ethernet.etherType = (bit<16>) c; }
+		table t {
+			key = { hdr.ethernet.dst: exact; }
+			actions = { set; NoAction; }
+			default_action = set(Color.Red);
+		}
+		apply {
+			bit<8> x = hdr.ipv4[1].ttl |-| 1;
+			bit<40> y = hdr.ipv4[0].src ++ x;
+			counter.count(x);
+			if (hdr.ethernet.isValid() && t.apply().hit) {
+				hdr.ethernet.dst = hdr.ethernet.dst[47:8] ++ 8w0;
+			}
+			switch (t.apply().action_run) {
+				set: { exit; }
+			}
+		}
+	}
+";
+
+#[test]
+fn checks_a_program() {
+	assert_eq!(errors(PROGRAM), vec![]);
+}
+
+#[test]
+fn infers_expression_types() {
+	assert_eq!(type_at(PROGRAM, "etherType) {").as_deref(), Some("bit<16>"));
+	assert_eq!(type_at(PROGRAM, "dst[47:8]").as_deref(), Some("bit<48>"));
+	assert_eq!(type_at(PROGRAM, "hdr.ipv4[1]").as_deref(), Some("headers"));
+	assert_eq!(type_at(PROGRAM, "ipv4.next").as_deref(), Some("ipv4_t[2]"));
+	assert_eq!(type_at(PROGRAM, "Red)").as_deref(), Some("Color"));
+	assert_eq!(type_at(PROGRAM, "8w0").as_deref(), Some("bit<8>"));
+	assert_eq!(type_at(PROGRAM, "0x800").as_deref(), Some("int"));
+}
+
+#[test]
+fn resolves_members() {
+	let (analyzer, file_id) = analyze(PROGRAM);
+	let types = analyzer.types(file_id).unwrap();
+	let table = analyzer.symbols(file_id).unwrap();
+
+	let offset = PROGRAM.find("etherType) {").unwrap();
+	let member = types.member_at(file_id, offset).unwrap();
+	let field = table.symbol(member.target.unwrap());
+	assert_eq!(field.location.span.start, PROGRAM.find("etherType; }").unwrap());
+	assert_eq!(types.symbol_type(member.target.unwrap()), Some(&Type::Bit(16)));
+
+	let offset = PROGRAM.find("count(x)").unwrap();
+	let method = types.member_at(file_id, offset).unwrap();
+	assert_eq!(member.qualifier.as_deref(), Some("ethernet_t"));
+	assert_eq!(table.symbol(method.target.unwrap()).location.span.start, PROGRAM.find("count(in").unwrap());
+}
+
+#[test]
+fn reports_width_mismatches() {
+	let program = r"
+		control C() {
+			apply {
+				bit<8> a = 1;
+				bit<16> b = a;
+				bit<16> c = b + a;
+				int<8> d = -1;
+				bool e = a == d;
+				bit<4> f = a[8:5];
+			}
+		}
+	";
+	assert_eq!(
+		errors(program),
+		vec![
+			error("a", "width mismatch: expected `bit<16>`, found `bit<8>` (8 bits instead of 16)"),
+			error("b + a", "width mismatch between `bit<16>` and `bit<8>`"),
+			error("a == d", "mismatched types `bit<8>` and `int<8>`"),
+			error("a[8:5]", "slice `[8:5]` is out of range for `bit<8>`"),
+		]
+	);
+}
+
+#[test]
+fn reports_invalid_implicit_casts() {
+	let program = r"
+		enum E { A, B }
+		enum bit<8> S { X = 1 }
+		header h_t { bit<8> f; }
+		control C() {
+			apply {
+				bool b = 1;
+				bit<8> x = E.A;
+				bit<8> y = S.X;
+				S s = 1;
+				h_t h = { 1, 2 };
+				bit<8> z = (bit<8>) true;
+			}
+		}
+	";
+	assert_eq!(
+		errors(program),
+		vec![
+			error("1", "cannot implicitly cast `int` to `bool`"),
+			error("E.A", "cannot implicitly cast `E` to `bit<8>`"),
+			error("1", "cannot implicitly cast `int` to `S`"),
+			error("{ 1, 2 }", "expected 1 fields to initialize `h_t`, found 2"),
+			error("(bit<8>) true", "cannot cast `bool` to `bit<8>`"),
+		]
+	);
+}
+
+#[test]
+fn reports_bad_operand_types() {
+	let program = r"
+		control C() {
+			apply {
+				bit<8> a = 1;
+				bool b = true;
+				bool c = a && b;
+				bit<8> d = a + b;
+				bool e = !a;
+				int f = 1 ++ 2;
+				if (a) { }
+			}
+		}
+	";
+	assert_eq!(
+		errors(program),
+		vec![
+			error("a && b", "cannot apply `&&` to a value of type `bit<8>`"),
+			error("a + b", "cannot apply `+` to a value of type `bool`"),
+			error("!a", "cannot apply `!` to a value of type `bit<8>`"),
+			error("1 ++ 2", "cannot concatenate a value of type `int`; only `bit<W>` and `int<W>` have a known width"),
+			error("a", "expected a condition of type `bool`, found `bit<8>`"),
+		]
+	);
+}
+
+#[test]
+fn reports_wrong_argument_counts() {
+	let program = r"
+		extern void f(in bit<8> x, in bool y);
+		extern E { E(); void m(in bit<8> x); void m(in bit<8> x, in bit<8> y); }
+		action a(bit<8> x, bit<8> y) { }
+		control C() {
+			E() e;
+			table t { actions = { a(1); } }
+			apply {
+				f(1);
+				e.m(1);
+				e.m(1, 2);
+				e.m(1, 2, 3);
+				a(1, 2);
+				f(1, 2);
+			}
+		}
+	";
+	assert_eq!(
+		errors(program),
+		vec![
+			error("a(1)", "the action `a` expects 0 arguments, found 1"),
+			error("f(1)", "`f` expects 2 arguments, found 1"),
+			error("e.m(1, 2, 3)", "`m` expects 1 arguments, found 3"),
+			error("2", "cannot implicitly cast `int` to `bool`"),
+		]
+	);
+}
+
+#[test]
+fn infers_type_arguments() {
+	let program = r"
+		extern void g<T>(in T x, in T y);
+		extern R<T> { R(bit<32> size); T read(in bit<32> index); }
+		control C() {
+			R<bit<16>>(4) r;
+			apply {
+				bit<8> a = 1;
+				g(a, 1);
+				bit<16> b = r.read(0);
+				bit<8> c = r.read(0);
+			}
+		}
+	";
+	assert_eq!(
+		errors(program),
+		vec![error("r.read(0)", "width mismatch: expected `bit<8>`, found `bit<16>` (16 bits instead of 8)")]
+	);
+}
+
+#[test]
+fn checks_type_declarations() {
+	let program = r"
+		struct s_t { bit<8> f; }
+		header h_t { s_t s; }
+		header_union u_t { s_t s; }
+		enum bool B { X = true }
+		enum bit<8> E { A }
+		typedef bit<8>[4] stack_t;
+		typedef R<bit<8>, bit<8>> r_t;
+		extern R<T> { }
+	";
+	assert_eq!(
+		errors(program),
+		vec![
+			error("s_t", "`s_t` cannot be the type of a field of a header"),
+			error("s_t", "`s_t` cannot be the type of a field of a header union"),
+			error("bool", "the underlying type of an enum must be `bit<W>` or `int<W>`, found `bool`"),
+			error("A", "the member `A` of a serializable enum must have a value"),
+			error("bit<8>", "the elements of a header stack must be headers, found `bit<8>`"),
+			error("R<bit<8>, bit<8>>", "`R` expects 1 type arguments, found 2"),
+		]
+	);
+}
+
+#[test]
+fn reports_unknown_members() {
+	let program = r"
+		header h_t { bit<8> f; }
+		control C(inout h_t h) {
+			apply {
+				h.g = 1;
+				h.f.x = 1;
+				h.setValid();
+			}
+		}
+	";
+	assert_eq!(errors(program), vec![error("g", "no member `g` in `h_t`"), error("x", "no member `x` in `bit<8>`")]);
+}
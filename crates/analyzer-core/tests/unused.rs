@@ -0,0 +1,104 @@
+extern crate analyzer_core;
+
+use analyzer_core::base_abstractions::DiagnosticTag;
+use pretty_assertions::assert_eq;
+
+mod common;
+
+/// Analyzes the files, returning the text and message of each unused declaration of the first one.
+fn unused(files: &[(&str, &str)]) -> Vec<(String, String)> {
+	let analyzer = common::workspace(files);
+
+	let (path, source) = files[0];
+	let diagnostics = analyzer.diagnostics(analyzer.file_id(path));
+	diagnostics
+		.into_iter()
+		.filter(|d| d.tags.contains(&DiagnosticTag::Unnecessary))
+		.map(|d| (source[d.location].to_string(), d.message))
+		.collect()
+}
+
+fn warning(text: &str, message: &str) -> (String, String) { (text.to_string(), message.to_string()) }
+
+#[test]
+fn reports_unused_declarations() {
+	let program = r"
+		typedef bit<8> byte_t;
+		typedef bit<16> port_t;
+		const byte_t LIMIT = 8;
+		const bit<8> UNUSED = 1;
+		header h_t { byte_t used; bit<8> unused; }
+		header unused_t { bit<8> f; }
+
+		control C(inout h_t h) {
+			bit<8> temp;
+			action set() { h.used = LIMIT; }
+			action listed() { }
+			action stray() { }
+			table applied { actions = { set; listed; } }
+			table stale { actions = { set; } }
+			apply { applied.apply(); }
+		}
+	";
+	assert_eq!(
+		unused(&[("foo.p4", program)]),
+		vec![
+			warning("port_t", "the type `port_t` is never used"),
+			warning("UNUSED", "the constant `UNUSED` is never used"),
+			warning("unused", "the field `unused` of the header `h_t` is never used"),
+			warning("temp", "the variable `temp` is never used"),
+			warning("stray", "the action `stray` is never used"),
+			warning("stale", "the table `stale` is never applied"),
+		]
+	);
+}
+
+#[test]
+fn reports_unused_includes() {
+	let program = r#"
+		#include "types.p4"
+		#include "unused.p4"
+		const port_t PORT = 1;
+		control C() { apply { bit<9> p = PORT; p = p + 1; } }
+	"#;
+	let types = "#include \"base.p4\"\ntypedef base_t port_t;";
+	let base = "typedef bit<9> base_t;";
+	let unused_include = "const bit<8> NOTHING = 0;";
+	assert_eq!(
+		unused(&[("foo.p4", program), ("types.p4", types), ("base.p4", base), ("unused.p4", unused_include)]),
+		vec![warning("#include \"unused.p4\"\n", "nothing declared in `unused.p4` is used")]
+	);
+}
+
+#[test]
+fn checks_declarations_against_the_files_including_them() {
+	let header = "const bit<8> SHARED = 1;\nconst bit<8> LOCAL = 2;";
+	let program = r#"
+		#include "header.p4"
+		control C() { apply { bit<8> x = SHARED; x = x + 1; } }
+	"#;
+	assert_eq!(
+		unused(&[("header.p4", header), ("foo.p4", program)]),
+		vec![warning("LOCAL", "the constant `LOCAL` is never used")]
+	);
+}
+
+#[test]
+fn ignores_the_listings_of_hidden_actions() {
+	let program = r"
+		control C() {
+			@hidden action hidden() { }
+			action listed() { }
+			action called() { }
+			table t { actions = { hidden; @hidden listed; @hidden called; } }
+			apply { t.apply(); called(); }
+		}
+	";
+	assert_eq!(
+		unused(&[("foo.p4", program)]),
+		vec![
+			warning("hidden", "the action `hidden` is never used"),
+			warning("listed", "the action `listed` is never used"),
+		]
+	);
+}
@@ -0,0 +1,141 @@
+extern crate analyzer_core;
+
+use pretty_assertions::assert_eq;
+
+mod common;
+
+fn warnings(s: &str) -> Vec<(String, String)> {
+	let (analyzer, file_id) = common::analyze(s, &[]);
+	analyzer.diagnostics(file_id).into_iter().map(|d| (s[d.location].to_string(), d.message)).collect()
+}
+
+fn warning(text: &str, message: &str) -> (String, String) { (text.to_string(), message.to_string()) }
+
+const HEADERS: &str = r"
+	header ethernet_t { bit<48> dst; bit<48> src; bit<16> etherType; }
+	header ipv4_t { bit<8> ttl; bit<32> src; bit<32> dst; }
+	header ipv6_t { bit<8> hopLimit; }
+	header_union ip_t { ipv4_t v4; ipv6_t v6; }
+	struct headers { ethernet_t ethernet; ipv4_t ipv4; ip_t ip; }
+	extern void update_checksum<T>(in bool condition, in T data, inout bit<16> checksum);
+";
+
+fn control(body: &str) -> String { format!("{HEADERS}\ncontrol C(inout headers hdr, inout bit<32> x) {{ {body} }}") }
+
+#[test]
+fn follows_extracts_through_parser_states() {
+	let program = format!(
+		r"{HEADERS}
+		parser P(packet_in packet, out headers hdr) {{
+			state start {{
+				packet.extract(hdr.ethernet);
+				transition select(hdr.ethernet.etherType) {{ 0x800: parse_ipv4; default: check; }}
+			}}
+			state parse_ipv4 {{ packet.extract(hdr.ipv4); transition check; }}
+			state check {{
+				transition select(hdr.ethernet.etherType, hdr.ipv4.ttl) {{ default: accept; }}
+			}}
+		}}
+		"
+	);
+	// `check` is reached both with and without the IPv4 header
+	assert_eq!(
+		warnings(&program),
+		vec![warning("hdr.ipv4.ttl", "`hdr.ipv4` may be invalid when its field `ttl` is read")]
+	);
+}
+
+#[test]
+fn follows_is_valid_guards() {
+	let program = control(
+		r"
+		apply {
+			if (hdr.ipv4.isValid()) { hdr.ipv4.ttl = hdr.ipv4.ttl - 1; }
+			if (!hdr.ethernet.isValid() || hdr.ethernet.etherType == 0) { exit; }
+			x = (bit<32>) hdr.ethernet.etherType;
+			x = hdr.ipv4.isValid() ? hdr.ipv4.src : 0;
+			update_checksum(hdr.ipv4.isValid(), { hdr.ipv4.ttl, hdr.ipv4.src }, hdr.ethernet.etherType);
+			hdr.ipv4.dst = 0;
+		}
+		",
+	);
+	assert_eq!(
+		warnings(&program),
+		vec![warning("hdr.ipv4.dst", "`hdr.ipv4` may be invalid when its field `dst` is written")]
+	);
+}
+
+#[test]
+fn follows_set_valid_and_set_invalid() {
+	let program = control(
+		r"
+		apply {
+			hdr.ipv4.setValid();
+			hdr.ipv4.ttl = 64;
+			hdr.ip.v4.setValid();
+			hdr.ip.v4.ttl = 64;
+			hdr.ip.v6.setValid();
+			hdr.ip.v6.hopLimit = 64;
+			hdr.ip.v4.ttl = 64;
+			hdr.ipv4.setInvalid();
+			x = hdr.ipv4.src;
+		}
+		",
+	);
+	assert_eq!(
+		warnings(&program),
+		vec![
+			warning("hdr.ip.v4.ttl", "`hdr.ip.v4` may be invalid when its field `ttl` is written"),
+			warning("hdr.ipv4.src", "`hdr.ipv4` may be invalid when its field `src` is read"),
+		]
+	);
+}
+
+#[test]
+fn follows_switch_statements() {
+	let program = control(
+		r"
+		action a() { }
+		action b() { }
+		table t { actions = { a; b; } }
+		apply {
+			switch (t.apply().action_run) {
+				a: { hdr.ipv4.setValid(); }
+				b: { hdr.ipv4.setValid(); hdr.ethernet.setValid(); }
+			}
+			hdr.ipv4.ttl = 1;
+			hdr.ethernet.etherType = 0x800;
+		}
+		",
+	);
+	assert_eq!(
+		warnings(&program),
+		vec![warning("hdr.ethernet.etherType", "`hdr.ethernet` may be invalid when its field `etherType` is written")]
+	);
+}
+
+#[test]
+fn checks_actions_where_they_are_invoked() {
+	let program = control(
+		r"
+		action decrement() { hdr.ipv4.ttl = hdr.ipv4.ttl - 1; }
+		action forward() { hdr.ethernet.dst = 1; }
+		action add_ipv4() { hdr.ipv4.setValid(); }
+		table routes { key = { hdr.ipv4.dst: exact; } actions = { decrement; } }
+		table macs { key = { hdr.ethernet.dst: exact; } actions = { forward; } }
+		apply {
+			if (hdr.ipv4.isValid()) { routes.apply(); }
+			macs.apply();
+			add_ipv4();
+			decrement();
+		}
+		",
+	);
+	assert_eq!(
+		warnings(&program),
+		vec![
+			warning("hdr.ethernet.dst", "`hdr.ethernet` may be invalid when its field `dst` is read"),
+			warning("hdr.ethernet.dst", "`hdr.ethernet` may be invalid when its field `dst` is written"),
+		]
+	);
+}
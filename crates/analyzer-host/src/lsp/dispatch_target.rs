@@ -2,7 +2,7 @@ use analyzer_abstractions::{async_trait::async_trait, tracing::error};
 use async_rwlock::RwLock as AsyncRwLock;
 use core::fmt::Debug;
 use serde::{de::DeserializeOwned, Serialize};
-use std::{future::Future, pin::Pin, sync::Arc};
+use std::{borrow::Cow, future::Future, pin::Pin, sync::Arc};
 
 use crate::json_rpc::{
 	from_json,
@@ -21,7 +21,7 @@ use dyn_clonable::*;
 /// An error that can be produced when processing a message.
 #[derive(Clone)]
 pub(crate) struct HandlerError {
-	message: &'static str,
+	message: Cow<'static, str>,
 
 	#[allow(dead_code)]
 	data: Option<serde_json::Value>,
@@ -30,12 +30,12 @@ pub(crate) struct HandlerError {
 impl HandlerError {
 	#[allow(dead_code)]
 	/// Initializes a new [`HandlerError`] with a given error message.
-	pub(crate) fn new(message: &'static str) -> Self { Self { message, data: None } }
+	pub(crate) fn new(message: impl Into<Cow<'static, str>>) -> Self { Self { message: message.into(), data: None } }
 
 	#[allow(dead_code)]
 	/// Initializes a new [`HandlerError`] with a given error message and serializable data.
 	pub(crate) fn new_with_data<TData: Serialize>(message: &'static str, data: Option<TData>) -> Self {
-		Self { message, data: data.map(|v| serde_json::to_value(v).unwrap()) }
+		Self { message: message.into(), data: data.map(|v| serde_json::to_value(v).unwrap()) }
 	}
 }
 
@@ -134,7 +134,7 @@ where
 						error!(method = method, "Error processing request '{}': {}", method, err.message);
 
 						(
-							Response::new_error(request.id.clone(), ErrorCode::InternalError as i32, err.message),
+							Response::new_error(request.id.clone(), ErrorCode::InternalError as i32, &err.message),
 							current_state,
 						)
 					}
@@ -96,6 +96,18 @@ impl WorkspaceManager {
 		}
 	}
 
+	/// Returns the URLs of the files of each [`Workspace`] that are open in the IDE.
+	pub fn open_files(&self) -> Vec<Url> {
+		self.workspaces
+			.values()
+			.flat_map(|workspace| {
+				let files = workspace.files.read().unwrap();
+				let open = files.values().filter(|file| file.is_open_in_ide());
+				open.map(|file| file.document_identifier.uri.clone()).collect::<Vec<_>>()
+			})
+			.collect()
+	}
+
 	/// Asynchronously indexes the contents of each [`Workspace`].
 	///
 	/// Returns immediately if the [`WorkspaceManager`] was not initialized with workspace folders.
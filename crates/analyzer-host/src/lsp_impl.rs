@@ -1,6 +1,8 @@
 pub(crate) mod active_initialized;
 pub(crate) mod active_uninitialized;
 pub(crate) mod initializing;
+pub(crate) mod semantic_tokens;
+pub(crate) mod settings;
 pub(crate) mod shutting_down;
 pub(crate) mod state;
 pub(crate) mod stopped;
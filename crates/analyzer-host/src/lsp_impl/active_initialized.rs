@@ -1,18 +1,45 @@
-use analyzer_core::{base_abstractions::FileId, lsp_file::ChangeEvent};
+use analyzer_core::{
+	base_abstractions::{self as core, FileId},
+	completion::CompletionKind,
+	folding::FoldingKind,
+	formatting::FormatOptions,
+	lsp_file::{ChangeEvent, LspFile},
+	Analyzer,
+};
 use async_rwlock::RwLock as AsyncRwLock;
-use std::sync::Arc;
+use serde::{Deserialize, Serialize};
+use std::{
+	collections::{hash_map::Entry, HashMap},
+	ops::Range as Span,
+	sync::{atomic::Ordering, Arc},
+};
 
 use analyzer_abstractions::{
 	lsp_types::{
 		notification::{
-			DidChangeTextDocument, DidChangeWatchedFiles, DidCloseTextDocument, DidOpenTextDocument,
-			DidSaveTextDocument, Exit, SetTrace,
+			DidChangeConfiguration, DidChangeTextDocument, DidChangeWatchedFiles, DidCloseTextDocument,
+			DidOpenTextDocument, DidSaveTextDocument, Exit, PublishDiagnostics, SetTrace,
+		},
+		request::{
+			Completion, DocumentHighlightRequest, DocumentSymbolRequest, FoldingRangeRequest, Formatting,
+			GotoDeclaration, GotoDefinition, GotoImplementation, GotoTypeDefinition, HoverRequest, OnTypeFormatting,
+			PrepareRenameRequest, RangeFormatting, References, Rename, Request, ResolveCompletionItem,
+			SelectionRangeRequest, SemanticTokensFullDeltaRequest, SemanticTokensFullRequest,
+			SemanticTokensRangeRequest, Shutdown, SignatureHelpRequest, WorkspaceSymbolRequest,
 		},
-		request::{Completion, HoverRequest, Shutdown},
 		CompletionItem, CompletionItemKind, CompletionList, CompletionParams, CompletionResponse,
-		DidChangeTextDocumentParams, DidChangeWatchedFilesParams, DidCloseTextDocumentParams,
-		DidOpenTextDocumentParams, DidSaveTextDocumentParams, FileChangeType, Hover, HoverContents, HoverParams,
-		MarkupContent, MarkupKind, Position, Range, SetTraceParams, Url,
+		DidChangeConfigurationParams, DidChangeTextDocumentParams, DidChangeWatchedFilesParams,
+		DidCloseTextDocumentParams, DidOpenTextDocumentParams, DidSaveTextDocumentParams, DocumentFormattingParams,
+		DocumentHighlight, DocumentHighlightKind, DocumentHighlightParams, DocumentOnTypeFormattingParams,
+		DocumentRangeFormattingParams, DocumentSymbol, DocumentSymbolParams, DocumentSymbolResponse, Documentation,
+		FileChangeType, FoldingRange, FoldingRangeKind, FoldingRangeParams, FormattingOptions, GotoDefinitionParams,
+		GotoDefinitionResponse, Hover, HoverContents, HoverParams, InsertTextFormat, Location, MarkupContent,
+		MarkupKind, ParameterInformation, ParameterLabel, Position, PrepareRenameResponse, PublishDiagnosticsParams,
+		Range, ReferenceParams, RenameParams, SelectionRange, SelectionRangeParams, SemanticTokens,
+		SemanticTokensDelta, SemanticTokensDeltaParams, SemanticTokensFullDeltaResult, SemanticTokensParams,
+		SemanticTokensRangeParams, SemanticTokensRangeResult, SemanticTokensResult, SetTraceParams, SignatureHelp,
+		SignatureHelpParams, SignatureInformation, SymbolInformation, SymbolKind, TextDocumentIdentifier,
+		TextDocumentPositionParams, TextEdit, Url, WorkspaceEdit, WorkspaceSymbolParams, WorkspaceSymbolResponse,
 	},
 	tracing::{error, info},
 };
@@ -26,7 +53,11 @@ use crate::{
 	},
 };
 
-use super::state::State;
+use super::{
+	semantic_tokens,
+	settings::{format_options, include_inactive_references, lint_config},
+	state::State,
+};
 
 /// Builds and then returns a dispatcher handling the [`LspServerState::ActiveUninitialized`] state.
 pub(crate) fn create_dispatcher() -> LspServerStateDispatcher {
@@ -37,12 +68,34 @@ pub(crate) fn create_dispatcher() -> LspServerStateDispatcher {
 			})
 			.for_request::<HoverRequest, _>(on_text_document_hover)
 			.for_request::<Completion, _>(on_text_document_completion)
+			.for_request::<ResolveCompletionItem, _>(on_completion_item_resolve)
+			.for_request::<SignatureHelpRequest, _>(on_text_document_signature_help)
+			.for_request::<GotoDefinition, _>(on_text_document_definition)
+			.for_request::<GotoDeclaration, _>(on_text_document_declaration)
+			.for_request::<GotoTypeDefinition, _>(on_text_document_type_definition)
+			.for_request::<GotoImplementation, _>(on_text_document_implementation)
+			.for_request::<References, _>(on_text_document_references)
+			.for_request::<DocumentHighlightRequest, _>(on_text_document_document_highlight)
+			.for_request::<PrepareRenameRequest, _>(on_text_document_prepare_rename)
+			.for_request::<Rename, _>(on_text_document_rename)
+			.for_request::<DocumentSymbolRequest, _>(on_text_document_document_symbol)
+			.for_request::<WorkspaceSymbolRequest, _>(on_workspace_symbol)
+			.for_request::<SemanticTokensFullRequest, _>(on_text_document_semantic_tokens_full)
+			.for_request::<SemanticTokensFullDeltaRequest, _>(on_text_document_semantic_tokens_full_delta)
+			.for_request::<SemanticTokensRangeRequest, _>(on_text_document_semantic_tokens_range)
+			.for_request::<FoldingRangeRequest, _>(on_text_document_folding_range)
+			.for_request::<SelectionRangeRequest, _>(on_text_document_selection_range)
+			.for_request::<Formatting, _>(on_text_document_formatting)
+			.for_request::<RangeFormatting, _>(on_text_document_range_formatting)
+			.for_request::<OnTypeFormatting, _>(on_text_document_on_type_formatting)
+			.for_request::<BuiltinFileContentsRequest, _>(on_builtin_file_contents)
 			.for_notification::<DidChangeTextDocument, _>(on_text_document_did_change)
 			.for_notification::<DidCloseTextDocument, _>(on_text_document_did_close)
 			.for_notification::<DidOpenTextDocument, _>(on_text_document_did_open)
 			.for_notification::<DidSaveTextDocument, _>(on_text_document_did_save)
 			.for_notification::<SetTrace, _>(on_set_trace)
 			.for_notification::<DidChangeWatchedFiles, _>(on_watched_file_change)
+			.for_notification::<DidChangeConfiguration, _>(on_did_change_configuration)
 			.for_notification_with_options::<Exit, _>(on_exit, |mut options| {
 				options.transition_to(LspServerState::Stopped)
 			})
@@ -55,70 +108,624 @@ async fn on_shutdown(_: LspServerState, _: (), _: Arc<AsyncRwLock<State>>) -> Ha
 async fn on_text_document_hover(
 	_: LspServerState,
 	params: HoverParams,
-	_: Arc<AsyncRwLock<State>>,
+	state: Arc<AsyncRwLock<State>>,
 ) -> HandlerResult<Option<Hover>> {
-	let line = params.text_document_position_params.position.line;
-	let character = params.text_document_position_params.position.character;
-
-	let hover = Hover {
-		range: None,
-		contents: HoverContents::Markup(MarkupContent {
-			kind: MarkupKind::Markdown,
-			value: format!("Hovering over Ln *{}*, Col *{}*.", line, character),
-		}),
-	};
+	let state = state.read().await;
+	let TextDocumentPositionParams { text_document, position } = params.text_document_position_params;
+
+	let hover = with_document(&state, &text_document.uri, "query hover information", |analyzer, lsp, file_id| {
+		Ok(analyzer.hover(file_id, lsp_offset(lsp, position)).map(|hover| Hover {
+			range: Some(lsp_range(lsp, &hover.span)),
+			contents: HoverContents::Markup(MarkupContent { kind: MarkupKind::Markdown, value: hover.contents }),
+		}))
+	});
+	Ok(hover.await?.flatten())
+}
+
+async fn on_text_document_signature_help(
+	_: LspServerState,
+	params: SignatureHelpParams,
+	state: Arc<AsyncRwLock<State>>,
+) -> HandlerResult<Option<SignatureHelp>> {
+	let state = state.read().await;
+	let TextDocumentPositionParams { text_document, position } = params.text_document_position_params;
+
+	let help = with_document(&state, &text_document.uri, "query signature help", |analyzer, lsp, file_id| {
+		Ok(analyzer.signature_help(file_id, lsp_offset(lsp, position)).map(|help| {
+			// the offsets of the parameters are counted in UTF-16 code units, as the positions
+			let utf16 = |text: &str| text.encode_utf16().count() as u32;
+			let signatures = help.signatures.into_iter().map(|signature| {
+				let parameters = signature.parameters.iter().map(|span| ParameterInformation {
+					label: ParameterLabel::LabelOffsets([
+						utf16(&signature.label[..span.start]),
+						utf16(&signature.label[..span.end]),
+					]),
+					documentation: None,
+				});
+				SignatureInformation {
+					parameters: Some(parameters.collect()),
+					label: signature.label,
+					documentation: signature
+						.documentation
+						.map(|value| Documentation::MarkupContent(MarkupContent { kind: MarkupKind::Markdown, value })),
+					active_parameter: None,
+				}
+			});
+			SignatureHelp {
+				signatures: signatures.collect(),
+				active_signature: Some(help.active_signature as u32),
+				active_parameter: Some(help.active_parameter as u32),
+			}
+		}))
+	});
+	Ok(help.await?.flatten())
+}
 
-	Ok(Some(hover))
+async fn on_text_document_definition(
+	_: LspServerState,
+	params: GotoDefinitionParams,
+	state: Arc<AsyncRwLock<State>>,
+) -> HandlerResult<Option<GotoDefinitionResponse>> {
+	goto(params, state, "definition", Analyzer::definition).await
 }
 
-async fn on_text_document_completion(
+async fn on_text_document_declaration(
 	_: LspServerState,
-	params: CompletionParams,
+	params: GotoDefinitionParams,
 	state: Arc<AsyncRwLock<State>>,
-) -> HandlerResult<Option<CompletionResponse>> {
-	use itertools::Itertools;
+) -> HandlerResult<Option<GotoDefinitionResponse>> {
+	goto(params, state, "declaration", Analyzer::declaration).await
+}
+
+async fn on_text_document_type_definition(
+	_: LspServerState,
+	params: GotoDefinitionParams,
+	state: Arc<AsyncRwLock<State>>,
+) -> HandlerResult<Option<GotoDefinitionResponse>> {
+	goto(params, state, "type definition", Analyzer::type_definition).await
+}
+
+async fn on_text_document_implementation(
+	_: LspServerState,
+	params: GotoDefinitionParams,
+	state: Arc<AsyncRwLock<State>>,
+) -> HandlerResult<Option<GotoDefinitionResponse>> {
+	goto(params, state, "implementation", Analyzer::implementations).await
+}
 
+async fn on_text_document_references(
+	_: LspServerState,
+	params: ReferenceParams,
+	state: Arc<AsyncRwLock<State>>,
+) -> HandlerResult<Option<Vec<Location>>> {
 	let state = state.read().await;
-	let uri = params.text_document_position.text_document.uri;
-	let file = state.workspaces().get_file(uri.clone());
+	let TextDocumentPositionParams { text_document, position } = params.text_document_position;
+	let include_declaration = params.context.include_declaration;
+	let include_inactive = state.include_inactive_references.load(Ordering::Relaxed);
+
+	with_document(&state, &text_document.uri, "query references", |analyzer, lsp, file_id| {
+		let offset = lsp_offset(lsp, position);
+		let references = analyzer.references(file_id, offset, include_declaration, include_inactive);
+		let mut locations = Locations::new(analyzer);
+		Ok(references.into_iter().filter_map(|location| locations.convert(location)).collect())
+	})
+	.await
+}
 
-	match file.get_parsed_unit().await {
-		Ok(file_id) => {
-			let analyzer = state.analyzer.unwrap();
+async fn on_text_document_document_highlight(
+	_: LspServerState,
+	params: DocumentHighlightParams,
+	state: Arc<AsyncRwLock<State>>,
+) -> HandlerResult<Option<Vec<DocumentHighlight>>> {
+	use analyzer_core::references::Access;
 
-			let (input, lexed) = match (analyzer.input(file_id), analyzer.preprocessed(file_id)) {
-				(Some(i), Some(l)) => (i, l),
-				_ => return Ok(Some(CompletionResponse::Array(vec![]))),
-			};
+	let state = state.read().await;
+	let TextDocumentPositionParams { text_document, position } = params.text_document_position_params;
+
+	with_document(&state, &text_document.uri, "query document highlights", |analyzer, lsp, file_id| {
+		let highlights = analyzer.highlights(file_id, lsp_offset(lsp, position));
+		let mut locations = Locations::new(analyzer);
+		Ok(highlights
+			.into_iter()
+			.filter_map(|occurrence| {
+				let Location { range, .. } = locations.convert(occurrence.location)?;
+				let kind = match occurrence.access {
+					Access::Write => DocumentHighlightKind::WRITE,
+					Access::Read => DocumentHighlightKind::READ,
+					Access::Declaration | Access::Inactive => DocumentHighlightKind::TEXT,
+				};
+				Some(DocumentHighlight { range, kind: Some(kind) })
+			})
+			.collect())
+	})
+	.await
+}
 
-			let items = lexed
-				.iter()
-				.flat_map(|(_, token, _)| match token {
-					analyzer_core::lexer::Token::Identifier(name) => Some(name),
-					_ => None,
-				})
-				.unique()
-				.map(|label| CompletionItem {
-					label: label.to_string(),
-					kind: Some(CompletionItemKind::FILE),
-					..Default::default()
-				})
-				.collect();
+async fn on_text_document_prepare_rename(
+	_: LspServerState,
+	params: TextDocumentPositionParams,
+	state: Arc<AsyncRwLock<State>>,
+) -> HandlerResult<Option<PrepareRenameResponse>> {
+	let state = state.read().await;
 
-			let data = CompletionList { is_incomplete: false, items };
+	with_document(&state, &params.text_document.uri, "prepare rename", |analyzer, lsp, file_id| {
+		// the reason why a symbol cannot be renamed is shown to the user
+		let span = analyzer.prepare_rename(file_id, lsp_offset(lsp, params.position)).map_err(HandlerError::new)?;
+		Ok(PrepareRenameResponse::Range(lsp_range(lsp, &span)))
+	})
+	.await
+}
 
-			let shown_tokens =
-				lexed.iter().map(|(file_id, tk, span)| format!("{tk:?}: {file_id:?} {span:?}")).collect::<Vec<_>>();
-			info!("input: {:?}\n{:?}", input, shown_tokens);
-			info!("files: {:?}", analyzer.files());
+async fn on_text_document_rename(
+	_: LspServerState,
+	params: RenameParams,
+	state: Arc<AsyncRwLock<State>>,
+) -> HandlerResult<Option<WorkspaceEdit>> {
+	let state = state.read().await;
+	let TextDocumentPositionParams { text_document, position } = params.text_document_position;
+	let new_name = params.new_name;
+
+	with_document(&state, &text_document.uri, "rename", |analyzer, lsp, file_id| {
+		let locations = analyzer.rename(file_id, lsp_offset(lsp, position), &new_name).map_err(HandlerError::new)?;
+		let mut changes: HashMap<Url, Vec<TextEdit>> = HashMap::new();
+		let mut converted = Locations::new(analyzer);
+		for Location { uri, range } in locations.into_iter().filter_map(|location| converted.convert(location)) {
+			changes.entry(uri).or_default().push(TextEdit { range, new_text: new_name.clone() });
+		}
+		Ok(WorkspaceEdit { changes: Some(changes), ..Default::default() })
+	})
+	.await
+}
 
-			Ok(Some(CompletionResponse::List(data)))
+async fn on_text_document_document_symbol(
+	_: LspServerState,
+	params: DocumentSymbolParams,
+	state: Arc<AsyncRwLock<State>>,
+) -> HandlerResult<Option<DocumentSymbolResponse>> {
+	let state = state.read().await;
+
+	with_document(&state, &params.text_document.uri, "query document symbols", |analyzer, lsp, file_id| {
+		let symbols = analyzer.document_symbols(file_id);
+		Ok(DocumentSymbolResponse::Nested(
+			symbols.iter().map(|symbol| to_document_symbol(lsp, symbol)).collect(),
+		))
+	})
+	.await
+}
+
+#[allow(deprecated)]
+fn to_document_symbol(lsp: &LspFile, symbol: &analyzer_core::outline::DocumentSymbol) -> DocumentSymbol {
+	DocumentSymbol {
+		name: symbol.name.clone(),
+		detail: symbol.detail.clone(),
+		kind: symbol_kind(&symbol.kind),
+		tags: None,
+		deprecated: None,
+		range: lsp_range(lsp, &symbol.span),
+		selection_range: lsp_range(lsp, &symbol.selection_span),
+		children: Some(symbol.children.iter().map(|child| to_document_symbol(lsp, child)).collect()),
+	}
+}
+
+async fn on_workspace_symbol(
+	_: LspServerState,
+	params: WorkspaceSymbolParams,
+	state: Arc<AsyncRwLock<State>>,
+) -> HandlerResult<Option<WorkspaceSymbolResponse>> {
+	let state = state.read().await;
+	let analyzer = state.analyzer.unwrap();
+
+	let mut locations = Locations::new(&analyzer);
+	#[allow(deprecated)]
+	let symbols = analyzer
+		.workspace_symbols(&params.query)
+		.into_iter()
+		.filter_map(|symbol| {
+			Some(SymbolInformation {
+				name: symbol.name,
+				kind: symbol_kind(&symbol.kind),
+				tags: None,
+				deprecated: None,
+				location: locations.convert(symbol.location)?,
+				container_name: symbol.container,
+			})
+		})
+		.collect();
+	Ok(Some(WorkspaceSymbolResponse::Flat(symbols)))
+}
+
+/// Returns the LSP kind of a kind of symbol.
+fn symbol_kind(kind: &analyzer_core::symbols::SymbolKind) -> SymbolKind {
+	use analyzer_core::symbols::SymbolKind as Kind;
+
+	match kind {
+		Kind::Constant | Kind::Macro => SymbolKind::CONSTANT,
+		Kind::Variable | Kind::Instance | Kind::Parameter(_) => SymbolKind::VARIABLE,
+		Kind::TypeParameter => SymbolKind::TYPE_PARAMETER,
+		Kind::Typedef | Kind::Type | Kind::Header | Kind::HeaderUnion | Kind::Struct => SymbolKind::STRUCT,
+		Kind::Field => SymbolKind::FIELD,
+		Kind::Enum => SymbolKind::ENUM,
+		Kind::EnumMember | Kind::ErrorMember | Kind::MatchKind => SymbolKind::ENUM_MEMBER,
+		Kind::ExternObject => SymbolKind::CLASS,
+		Kind::ExternFunction | Kind::Action | Kind::Function => SymbolKind::FUNCTION,
+		Kind::Method => SymbolKind::METHOD,
+		Kind::Parser | Kind::Control => SymbolKind::MODULE,
+		Kind::ParserType | Kind::ControlType | Kind::Package => SymbolKind::INTERFACE,
+		Kind::Table => SymbolKind::OBJECT,
+		Kind::State => SymbolKind::EVENT,
+	}
+}
+
+async fn on_text_document_semantic_tokens_full(
+	_: LspServerState,
+	params: SemanticTokensParams,
+	state: Arc<AsyncRwLock<State>>,
+) -> HandlerResult<Option<SemanticTokensResult>> {
+	let state = state.read().await;
+	let uri = params.text_document.uri;
+	let Some(tokens) = semantic_tokens(&state, &uri, None).await? else { return Ok(None) };
+
+	let (result_id, _) = state.semantic_tokens.lock().unwrap().insert(uri, tokens.clone());
+	Ok(Some(SemanticTokensResult::Tokens(SemanticTokens { result_id: Some(result_id), ..tokens })))
+}
+
+async fn on_text_document_semantic_tokens_full_delta(
+	_: LspServerState,
+	params: SemanticTokensDeltaParams,
+	state: Arc<AsyncRwLock<State>>,
+) -> HandlerResult<Option<SemanticTokensFullDeltaResult>> {
+	let state = state.read().await;
+	let uri = params.text_document.uri;
+	let Some(tokens) = semantic_tokens(&state, &uri, None).await? else { return Ok(None) };
+
+	let (result_id, previous) = state.semantic_tokens.lock().unwrap().insert(uri, tokens.clone());
+	match previous.filter(|previous| previous.result_id.as_ref() == Some(&params.previous_result_id)) {
+		// the client holds the previous tokens, which only need to be edited
+		Some(previous) => Ok(Some(SemanticTokensFullDeltaResult::TokensDelta(SemanticTokensDelta {
+			result_id: Some(result_id),
+			edits: semantic_tokens::diff(&previous, &tokens).into_iter().collect(),
+		}))),
+		None => {
+			Ok(Some(SemanticTokensFullDeltaResult::Tokens(SemanticTokens { result_id: Some(result_id), ..tokens })))
 		}
-		Err(err) => {
-			error!(file_uri = uri.as_str(), "Could not query completions. Index error: {}", err);
+	}
+}
+
+async fn on_text_document_semantic_tokens_range(
+	_: LspServerState,
+	params: SemanticTokensRangeParams,
+	state: Arc<AsyncRwLock<State>>,
+) -> HandlerResult<Option<SemanticTokensRangeResult>> {
+	let state = state.read().await;
+	let tokens = semantic_tokens(&state, &params.text_document.uri, Some(params.range)).await?;
+	Ok(tokens.map(SemanticTokensRangeResult::Tokens))
+}
 
-			Err(HandlerError::new("Could not query completions for document."))
+/// Computes the encoded semantic tokens of a document, or only those within `range`.
+async fn semantic_tokens(state: &State, uri: &Url, range: Option<Range>) -> HandlerResult<Option<SemanticTokens>> {
+	with_document(state, uri, "query semantic tokens", |analyzer, lsp, file_id| {
+		let mut tokens = analyzer.semantic_tokens(file_id);
+		if let Some(range) = range.map(|range| byte_range(lsp, range)) {
+			tokens.retain(|token| range.start <= token.span.start && token.span.end <= range.end);
 		}
+		Ok(SemanticTokens { result_id: None, data: semantic_tokens::encode(lsp, &tokens) })
+	})
+	.await
+}
+
+async fn on_text_document_folding_range(
+	_: LspServerState,
+	params: FoldingRangeParams,
+	state: Arc<AsyncRwLock<State>>,
+) -> HandlerResult<Option<Vec<FoldingRange>>> {
+	let state = state.read().await;
+
+	with_document(&state, &params.text_document.uri, "query folding ranges", |analyzer, lsp, file_id| {
+		let ranges = analyzer.folding_ranges(file_id).into_iter().filter_map(|range| {
+			let Range { start, end } = lsp_range(lsp, &range.span);
+			let kind = match range.kind {
+				FoldingKind::Block => None,
+				FoldingKind::Comment => Some(FoldingRangeKind::Comment),
+				FoldingKind::Includes => Some(FoldingRangeKind::Imports),
+				FoldingKind::Region => Some(FoldingRangeKind::Region),
+			};
+			(start.line < end.line).then_some(FoldingRange {
+				start_line: start.line,
+				end_line: end.line,
+				kind,
+				..Default::default()
+			})
+		});
+		Ok(ranges.collect())
+	})
+	.await
+}
+
+async fn on_text_document_selection_range(
+	_: LspServerState,
+	params: SelectionRangeParams,
+	state: Arc<AsyncRwLock<State>>,
+) -> HandlerResult<Option<Vec<SelectionRange>>> {
+	let state = state.read().await;
+	let positions = params.positions;
+
+	with_document(&state, &params.text_document.uri, "query selection ranges", |analyzer, lsp, file_id| {
+		let ranges = positions.into_iter().map(|position| {
+			let offset = lsp_offset(lsp, position);
+			// each range is the parent of the innermost one it contains
+			let mut selection = None;
+			for span in analyzer.selection_ranges(file_id, offset).into_iter().rev() {
+				let range = lsp_range(lsp, &span);
+				selection = Some(SelectionRange { range, parent: selection.map(Box::new) });
+			}
+			let empty = lsp_range(lsp, &(offset..offset));
+			selection.unwrap_or(SelectionRange { range: empty, parent: None })
+		});
+		Ok(ranges.collect())
+	})
+	.await
+}
+
+async fn on_text_document_formatting(
+	_: LspServerState,
+	params: DocumentFormattingParams,
+	state: Arc<AsyncRwLock<State>>,
+) -> HandlerResult<Option<Vec<TextEdit>>> {
+	let state = state.read().await;
+	format(&state, &params.text_document.uri, &params.options, |analyzer, _, file_id, options| {
+		analyzer.format(file_id, options, None)
+	})
+	.await
+}
+
+async fn on_text_document_range_formatting(
+	_: LspServerState,
+	params: DocumentRangeFormattingParams,
+	state: Arc<AsyncRwLock<State>>,
+) -> HandlerResult<Option<Vec<TextEdit>>> {
+	let state = state.read().await;
+	let range = params.range;
+	format(&state, &params.text_document.uri, &params.options, |analyzer, lsp, file_id, options| {
+		analyzer.format(file_id, options, Some(byte_range(lsp, range)))
+	})
+	.await
+}
+
+async fn on_text_document_on_type_formatting(
+	_: LspServerState,
+	params: DocumentOnTypeFormattingParams,
+	state: Arc<AsyncRwLock<State>>,
+) -> HandlerResult<Option<Vec<TextEdit>>> {
+	let state = state.read().await;
+	let Some(typed) = params.ch.chars().next() else { return Ok(None) };
+	let TextDocumentPositionParams { text_document, position } = params.text_document_position;
+	format(&state, &text_document.uri, &params.options, |analyzer, lsp, file_id, options| {
+		analyzer.format_on_type(file_id, options, lsp_offset(lsp, position), typed)
+	})
+	.await
+}
+
+/// Formats a document with the edits computed by `edit`, indenting it as requested by the formatting `options` and
+/// laying it out as configured in the settings.
+async fn format(
+	state: &State,
+	uri: &Url,
+	options: &FormattingOptions,
+	edit: impl FnOnce(&Analyzer, &LspFile, FileId, &FormatOptions) -> Vec<analyzer_core::formatting::TextEdit>,
+) -> HandlerResult<Option<Vec<TextEdit>>> {
+	let options = FormatOptions {
+		tab_size: options.tab_size as usize,
+		insert_spaces: options.insert_spaces,
+		..state.format_options.lock().unwrap().clone()
+	};
+
+	with_document(state, uri, "format", |analyzer, lsp, file_id| {
+		let edits = edit(analyzer, lsp, file_id, &options)
+			.into_iter()
+			.map(|edit| TextEdit { range: lsp_range(lsp, &edit.span), new_text: edit.text });
+		Ok(edits.collect())
+	})
+	.await
+}
+
+/// Responds to a 'go to' request with the locations found by `query` for the requested position.
+async fn goto(
+	params: GotoDefinitionParams,
+	state: Arc<AsyncRwLock<State>>,
+	what: &str,
+	query: fn(&Analyzer, FileId, usize) -> Vec<core::Location>,
+) -> HandlerResult<Option<GotoDefinitionResponse>> {
+	let state = state.read().await;
+	let TextDocumentPositionParams { text_document, position } = params.text_document_position_params;
+
+	with_document(&state, &text_document.uri, &format!("query {what}"), |analyzer, lsp, file_id| {
+		let mut converted = Locations::new(analyzer);
+		let locations = query(analyzer, file_id, lsp_offset(lsp, position))
+			.into_iter()
+			.filter_map(|location| converted.convert(location));
+		Ok(GotoDefinitionResponse::Array(locations.collect()))
+	})
+	.await
+}
+
+/// Runs `query` on a document once it is parsed, with the analyzer, the lines of the document and its [`FileId`].
+/// Responds with nothing if the analyzer has no buffer for the document, and with an error naming `what` was asked
+/// for if the document could not be parsed.
+async fn with_document<T>(
+	state: &State,
+	uri: &Url,
+	what: &str,
+	query: impl FnOnce(&Analyzer, &LspFile, FileId) -> HandlerResult<T>,
+) -> HandlerResult<Option<T>> {
+	let file = state.workspaces().get_file(uri.clone());
+	let file_id = file.get_parsed_unit().await.map_err(|err| {
+		error!(file_uri = uri.as_str(), "Could not {}. Index error: {}", what, err);
+		HandlerError::new(format!("Could not {what} for document."))
+	})?;
+
+	let analyzer = state.analyzer.unwrap();
+	if analyzer.buffer(file_id).is_none() {
+		return Ok(None);
+	}
+	query(&analyzer, analyzer.get_file(file_id), file_id).map(Some)
+}
+
+/// Converts the locations of the analyzer, which may be in files of the workspace or in embedded headers, to LSP
+/// locations. The lines of each embedded header are only indexed once.
+struct Locations<'a> {
+	analyzer: &'a Analyzer,
+	embedded: HashMap<FileId, LspFile>,
+}
+
+impl<'a> Locations<'a> {
+	fn new(analyzer: &'a Analyzer) -> Self { Self { analyzer, embedded: HashMap::new() } }
+
+	fn convert(&mut self, location: core::Location) -> Option<Location> {
+		let analyzer = self.analyzer;
+		let uri = Url::parse(&analyzer.path(location.file)).ok()?;
+		let file = match analyzer.buffer(location.file) {
+			Some(_) => analyzer.get_file(location.file),
+			None => match self.embedded.entry(location.file) {
+				Entry::Occupied(entry) => entry.into_mut(),
+				Entry::Vacant(entry) => entry.insert(LspFile::new(&analyzer.source(location.file)?.to_string())),
+			},
+		};
+		Some(Location { uri, range: lsp_range(file, &location.span) })
+	}
+}
+
+/// Retrieves the contents of a file embedded in the analyzer, such as the core library or an architecture header, which
+/// the locations returned by the server may point to but which the client cannot read by itself.
+#[derive(Debug)]
+pub(crate) enum BuiltinFileContentsRequest {}
+
+impl Request for BuiltinFileContentsRequest {
+	type Params = TextDocumentIdentifier;
+	type Result = Option<String>;
+	const METHOD: &'static str = "p4analyzer/builtinFileContents";
+}
+
+async fn on_builtin_file_contents(
+	_: LspServerState,
+	params: TextDocumentIdentifier,
+	_: Arc<AsyncRwLock<State>>,
+) -> HandlerResult<Option<String>> {
+	Ok(analyzer_core::builtin_source(params.uri.as_str()).map(str::to_string))
+}
+
+/// Converts a span of a file to an LSP range.
+fn lsp_range(file: &LspFile, span: &Span<usize>) -> Range {
+	let (start, end) = (file.byte_to_lsp(span.start), file.byte_to_lsp(span.end));
+	Range {
+		start: Position { line: start.line as u32, character: start.character as u32 },
+		end: Position { line: end.line as u32, character: end.character as u32 },
+	}
+}
+
+/// Converts an LSP position to an offset in a file.
+fn lsp_offset(file: &LspFile, Position { line, character }: Position) -> usize {
+	file.lsp_to_byte(&analyzer_core::lsp_file::Position::new(line as usize, character as usize))
+}
+
+/// Converts an LSP range to a span of a file.
+fn byte_range(file: &LspFile, Range { start, end }: Range) -> Span<usize> {
+	lsp_offset(file, start)..lsp_offset(file, end)
+}
+
+async fn on_text_document_completion(
+	_: LspServerState,
+	params: CompletionParams,
+	state: Arc<AsyncRwLock<State>>,
+) -> HandlerResult<Option<CompletionResponse>> {
+	let state = state.read().await;
+	let TextDocumentPositionParams { text_document, position } = params.text_document_position;
+	let uri = text_document.uri;
+
+	with_document(&state, &uri, "query completions", |analyzer, lsp, file_id| {
+		let items = analyzer
+			.completions(file_id, lsp_offset(lsp, position))
+			.into_iter()
+			.map(|completion| CompletionItem {
+				label: completion.label,
+				kind: Some(completion_item_kind(&completion.kind)),
+				detail: completion.detail,
+				insert_text: completion.insert_text,
+				insert_text_format: (completion.kind == CompletionKind::Snippet).then_some(InsertTextFormat::SNIPPET),
+				data: completion.symbol.map(|symbol| {
+					let data = CompletionData { uri: uri.clone(), file: analyzer.path(symbol.file), span: symbol.span };
+					serde_json::to_value(data).unwrap()
+				}),
+				..Default::default()
+			})
+			.collect();
+		Ok(CompletionResponse::List(CompletionList { is_incomplete: false, items }))
+	})
+	.await
+}
+
+/// The data of a completion item of a declaration, kept by the client until the item is resolved.
+#[derive(Serialize, Deserialize)]
+struct CompletionData {
+	/// The document in which the completion was offered.
+	uri: Url,
+	/// The path of the file declaring the completed symbol, and the span of its name.
+	file: String,
+	span: Span<usize>,
+}
+
+async fn on_completion_item_resolve(
+	_: LspServerState,
+	mut item: CompletionItem,
+	state: Arc<AsyncRwLock<State>>,
+) -> HandlerResult<CompletionItem> {
+	let data = item.data.clone().and_then(|data| serde_json::from_value::<CompletionData>(data).ok());
+	let Some(CompletionData { uri, file: path, span }) = data else { return Ok(item) };
+	let state = state.read().await;
+
+	let resolved = with_document(&state, &uri, "resolve completion", |analyzer, lsp, file_id| {
+		let symbol = core::Location::new(analyzer.file_id(&path), span);
+		let resolution = analyzer.resolve_completion(file_id, &symbol);
+		// the edit inserting the missing include is located in the document, whose lines are only known here
+		Ok(resolution.map(|resolution| {
+			let include = resolution.include.as_ref().map(|(offset, _)| lsp_range(lsp, &(*offset..*offset)));
+			(resolution, include)
+		}))
+	});
+	let Some((resolution, include)) = resolved.await?.flatten() else { return Ok(item) };
+
+	item.detail = resolution.detail.or(item.detail);
+	item.documentation = resolution
+		.documentation
+		.map(|value| Documentation::MarkupContent(MarkupContent { kind: MarkupKind::Markdown, value }));
+	if let (Some(range), Some((_, new_text))) = (include, resolution.include) {
+		item.additional_text_edits = Some(vec![TextEdit { range, new_text }]);
+	}
+	Ok(item)
+}
+
+fn completion_item_kind(kind: &analyzer_core::completion::CompletionKind) -> CompletionItemKind {
+	use analyzer_core::symbols::SymbolKind as Kind;
+
+	let kind = match kind {
+		CompletionKind::Keyword => return CompletionItemKind::KEYWORD,
+		CompletionKind::Snippet => return CompletionItemKind::SNIPPET,
+		CompletionKind::Symbol(kind) => kind,
+	};
+	match kind {
+		Kind::Constant | Kind::Macro => CompletionItemKind::CONSTANT,
+		Kind::Variable | Kind::Instance | Kind::Parameter(_) => CompletionItemKind::VARIABLE,
+		Kind::Typedef | Kind::Type | Kind::Header | Kind::HeaderUnion | Kind::Struct => CompletionItemKind::STRUCT,
+		Kind::Field => CompletionItemKind::FIELD,
+		Kind::Enum => CompletionItemKind::ENUM,
+		Kind::EnumMember | Kind::ErrorMember | Kind::MatchKind => CompletionItemKind::ENUM_MEMBER,
+		Kind::ExternObject => CompletionItemKind::CLASS,
+		Kind::ExternFunction | Kind::Action | Kind::Function => CompletionItemKind::FUNCTION,
+		Kind::Method => CompletionItemKind::METHOD,
+		Kind::Parser | Kind::Control => CompletionItemKind::MODULE,
+		Kind::ParserType | Kind::ControlType | Kind::Package => CompletionItemKind::INTERFACE,
+		Kind::TypeParameter => CompletionItemKind::TYPE_PARAMETER,
+		Kind::Table => CompletionItemKind::VALUE,
+		Kind::State => CompletionItemKind::EVENT,
 	}
 }
 
@@ -129,12 +736,16 @@ async fn on_text_document_did_open(
 ) -> HandlerResult<()> {
 	let state = state.write().await;
 	let file = state.workspaces().get_file(params.text_document.uri.clone());
-	let mut analyzer = state.analyzer.unwrap();
+	let diagnostics = {
+		let mut analyzer = state.analyzer.unwrap();
 
-	let file_id = analyzer.file_id(params.text_document.uri.as_str());
-	analyzer.update(file_id, &params.text_document.text);
+		let file_id = analyzer.file_id(params.text_document.uri.as_str());
+		analyzer.update(file_id, &params.text_document.text);
 
-	file.open_or_update(file_id);
+		file.open_or_update(file_id);
+		process_diagnostics(&analyzer, file_id)
+	};
+	publish_diagnostics(&state, diagnostics).await;
 
 	Ok(())
 }
@@ -146,43 +757,39 @@ async fn on_text_document_did_change(
 ) -> HandlerResult<()> {
 	let state = state.write().await;
 	let file = state.workspaces().get_file(params.text_document.uri.clone());
-	let mut analyzer = state.analyzer.unwrap();
+	let diagnostics = {
+		let mut analyzer = state.analyzer.unwrap();
 
-	let uri = params.text_document.uri.as_str();
-	let file_id = analyzer.file_id(uri);
-	// FIXME: potentially unnecessary allocation
-	let mut input = match analyzer.input(file_id) {
-		Some(i) => i.to_string(),
-		None => {
-			return Err(HandlerError::new_with_data("received a didChange notification for an unknown file", Some(uri)))
+		let uri = params.text_document.uri.as_str();
+		let file_id = analyzer.file_id(uri);
+		if analyzer.input(file_id).is_none() {
+			return Err(HandlerError::new_with_data(
+				"received a didChange notification for an unknown file",
+				Some(uri),
+			));
 		}
-	};
 
-	use analyzer_abstractions::lsp_types::TextDocumentContentChangeEvent;
-	let event_change = params
-		.content_changes
-		.into_iter()
-		.map(|TextDocumentContentChangeEvent { range, text, range_length: _ }| {
-			use analyzer_core::lsp_file as core;
-			let range = range.map(|Range { start, end }| core::Range {
-				start: core::Position { line: start.line as usize, character: start.character as usize },
-				end: core::Position { line: end.line as usize, character: end.character as usize },
-			});
-			ChangeEvent { range, text }
-		})
-		.collect();
+		use analyzer_abstractions::lsp_types::TextDocumentContentChangeEvent;
+		let event_change = params
+			.content_changes
+			.into_iter()
+			.map(|TextDocumentContentChangeEvent { range, text, range_length: _ }| {
+				use analyzer_core::lsp_file as core;
+				let range = range.map(|Range { start, end }| core::Range {
+					start: core::Position { line: start.line as usize, character: start.character as usize },
+					end: core::Position { line: end.line as usize, character: end.character as usize },
+				});
+				ChangeEvent { range, text }
+			})
+			.collect();
 
-	analyzer.file_change_event(file_id, &event_change);
+		analyzer.file_change_event(file_id, &event_change);
 
-	file.open_or_update(file_id);
-	let diagnostics = process_diagnostics(&analyzer, file_id, &input);
+		file.open_or_update(file_id);
+		process_diagnostics(&analyzer, file_id)
+	};
+	publish_diagnostics(&state, diagnostics).await;
 
-	// TODO: report diagnostics
-	// Ok(Some(PublishDiagnosticsParams {
-	// 	uri: params.text_document.uri,
-	// 	diagnostics,
-	// 	version: None,
-	// }))
 	Ok(())
 }
 
@@ -193,11 +800,15 @@ async fn on_text_document_did_close(
 ) -> HandlerResult<()> {
 	let state = state.write().await;
 	let file = state.workspaces().get_file(params.text_document.uri.clone());
-	let mut analyzer = state.analyzer.unwrap();
 
-	analyzer.delete(params.text_document.uri.as_str());
+	state.analyzer.unwrap().delete(params.text_document.uri.as_str());
+	state.semantic_tokens.lock().unwrap().remove(&params.text_document.uri);
 	file.close();
 
+	// the diagnostics of a closed document are no longer kept up to date
+	let uri = params.text_document.uri;
+	publish_diagnostics(&state, vec![PublishDiagnosticsParams { uri, diagnostics: vec![], version: None }]).await;
+
 	Ok(())
 }
 
@@ -209,14 +820,16 @@ async fn on_text_document_did_save(
 	if let Some(text) = params.text {
 		let state = state.write().await;
 		let file = state.workspaces().get_file(params.text_document.uri.clone());
-		let mut analyzer = state.analyzer.unwrap();
-
-		info!("Syncing buffer on save.");
-		let file_id = analyzer.file_id(params.text_document.uri.as_str());
-		let diagnostics = process_diagnostics(&analyzer, file_id, &text);
-		// TODO: report diagnostics, and process *after* the update below!
-		analyzer.update(file_id, &text);
-		file.open_or_update(file_id);
+		let diagnostics = {
+			let mut analyzer = state.analyzer.unwrap();
+
+			info!("Syncing buffer on save.");
+			let file_id = analyzer.file_id(params.text_document.uri.as_str());
+			analyzer.update(file_id, &text);
+			file.open_or_update(file_id);
+			process_diagnostics(&analyzer, file_id)
+		};
+		publish_diagnostics(&state, diagnostics).await;
 	}
 
 	Ok(())
@@ -230,6 +843,33 @@ async fn on_set_trace(_: LspServerState, params: SetTraceParams, state: Arc<Asyn
 	Ok(())
 }
 
+async fn on_did_change_configuration(
+	_: LspServerState,
+	params: DidChangeConfigurationParams,
+	state: Arc<AsyncRwLock<State>>,
+) -> HandlerResult<()> {
+	let state = state.read().await;
+
+	state
+		.include_inactive_references
+		.store(include_inactive_references(&params.settings), Ordering::Relaxed);
+	*state.format_options.lock().unwrap() = format_options(&params.settings);
+
+	// the levels of the lints change the diagnostics of the open documents
+	let diagnostics = {
+		let mut analyzer = state.analyzer.unwrap();
+		analyzer.configure_lints(lint_config(&params.settings));
+		let open_files = state.workspaces().open_files();
+		open_files
+			.iter()
+			.flat_map(|uri| process_diagnostics(&analyzer, analyzer.file_id(uri.as_str())))
+			.collect()
+	};
+	publish_diagnostics(&state, diagnostics).await;
+
+	Ok(())
+}
+
 async fn created_file(uri: &Url, state: &Arc<AsyncRwLock<State>>) {
 	// workspaces should be created in the initilize state
 	let file = state.write().await.workspaces().get_file(uri.clone());
@@ -285,34 +925,76 @@ async fn on_watched_file_change(
 /// Responds to an 'exit' notification from the LSP client.
 async fn on_exit(_: LspServerState, _: (), _: Arc<AsyncRwLock<State>>) -> HandlerResult<()> { Ok(()) }
 
-fn process_diagnostics(
-	analyzer: &analyzer_core::Analyzer,
-	file_id: FileId,
-	input: &str,
+/// Sends the diagnostics of each document to the LSP client, replacing those previously sent for it.
+async fn publish_diagnostics(state: &State, diagnostics: Vec<PublishDiagnosticsParams>) {
+	for params in diagnostics {
+		let uri = params.uri.clone();
+		if state.request_manager.send_notification::<PublishDiagnostics>(params).await.is_err() {
+			error!(uri = uri.as_str(), "Could not publish diagnostics.");
+		}
+	}
+}
+
+/// Converts the diagnostics of the documents affected by a change to a given [`FileId`], including the translation
+/// units rooted at the other documents including it, grouped by the document they are located in.
+fn process_diagnostics(analyzer: &Analyzer, file_id: FileId) -> Vec<PublishDiagnosticsParams> {
+	analyzer
+		.diagnostics_by_file(file_id)
+		.into_iter()
+		.filter_map(|(file, diagnostics)| {
+			let uri = Url::parse(&analyzer.path(file)).ok()?;
+			let diagnostics = lsp_diagnostics(analyzer, analyzer.get_file(file), diagnostics);
+			Some(PublishDiagnosticsParams { uri, diagnostics, version: None })
+		})
+		.collect()
+}
+
+fn lsp_diagnostics(
+	analyzer: &Analyzer,
+	lsp: &LspFile,
+	diagnostics: Vec<core::Diagnostic>,
 ) -> Vec<analyzer_abstractions::lsp_types::Diagnostic> {
-	let diagnostics = analyzer.diagnostics(file_id);
-	let lsp = analyzer.get_file(file_id);
 	diagnostics
 		.into_iter()
 		.map(|d| {
-			use analyzer_abstractions::lsp_types::{Diagnostic, DiagnosticSeverity};
+			use analyzer_abstractions::lsp_types::{
+				Diagnostic, DiagnosticRelatedInformation, DiagnosticSeverity, DiagnosticTag, NumberOrString,
+			};
 			use analyzer_core::base_abstractions::Severity;
 
-			let std::ops::Range { start, end } = d.location;
-			let (start, end) = (lsp.byte_to_lsp(start), lsp.byte_to_lsp(end));
-			let range = Range {
-				start: Position { line: start.line as u32, character: start.character as u32 },
-				end: Position { line: end.line as u32, character: end.character as u32 },
-			};
+			// the related locations may be in other files, which the client can only show if they are known
+			let related_information = d
+				.related
+				.into_iter()
+				.filter_map(|(location, message)| {
+					let uri = Url::parse(&analyzer.path(location.file)).ok()?;
+					analyzer.buffer(location.file)?;
+					let range = lsp_range(analyzer.get_file(location.file), &location.span);
+					Some(DiagnosticRelatedInformation { location: Location { uri, range }, message })
+				})
+				.collect::<Vec<_>>();
+
 			Diagnostic {
-				range,
+				range: lsp_range(lsp, &d.location),
 				severity: Some(match d.severity {
 					Severity::Info => DiagnosticSeverity::INFORMATION,
 					Severity::Hint => DiagnosticSeverity::HINT,
 					Severity::Warning => DiagnosticSeverity::WARNING,
 					Severity::Error => DiagnosticSeverity::ERROR,
 				}),
+				code: d.code.map(|code| NumberOrString::String(code.to_string())),
 				message: d.message,
+				related_information: Some(related_information).filter(|related| !related.is_empty()),
+				tags: Some(
+					d.tags
+						.into_iter()
+						.map(|tag| match tag {
+							core::DiagnosticTag::Unnecessary => DiagnosticTag::UNNECESSARY,
+							core::DiagnosticTag::Deprecated => DiagnosticTag::DEPRECATED,
+						})
+						.collect::<Vec<_>>(),
+				)
+				.filter(|tags| !tags.is_empty()),
 				..Default::default()
 			}
 		})
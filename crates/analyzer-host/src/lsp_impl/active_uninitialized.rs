@@ -1,12 +1,14 @@
 use async_rwlock::RwLock as AsyncRwLock;
-use std::sync::Arc;
+use std::sync::{atomic::Ordering, Arc};
 
 use analyzer_abstractions::lsp_types::{
-	notification::Exit, request::Initialize, CompletionOptions, DeclarationCapability, HoverProviderCapability,
-	ImplementationProviderCapability, InitializeParams, InitializeResult, OneOf, SaveOptions, ServerCapabilities,
-	ServerInfo, SignatureHelpOptions, TextDocumentSyncCapability, TextDocumentSyncKind, TextDocumentSyncOptions,
-	TextDocumentSyncSaveOptions, TypeDefinitionProviderCapability, WindowClientCapabilities, WorkDoneProgressOptions,
-	WorkspaceFolder, WorkspaceFoldersServerCapabilities, WorkspaceServerCapabilities,
+	notification::Exit, request::Initialize, CompletionOptions, DeclarationCapability, DocumentOnTypeFormattingOptions,
+	FoldingRangeProviderCapability, HoverProviderCapability, ImplementationProviderCapability, InitializeParams,
+	InitializeResult, OneOf, RenameOptions, SaveOptions, SelectionRangeProviderCapability, SemanticTokensFullOptions,
+	SemanticTokensOptions, SemanticTokensServerCapabilities, ServerCapabilities, ServerInfo, SignatureHelpOptions,
+	TextDocumentSyncCapability, TextDocumentSyncKind, TextDocumentSyncOptions, TextDocumentSyncSaveOptions,
+	TypeDefinitionProviderCapability, WindowClientCapabilities, WorkDoneProgressOptions, WorkspaceFolder,
+	WorkspaceFoldersServerCapabilities, WorkspaceServerCapabilities,
 };
 
 use crate::{
@@ -22,7 +24,11 @@ use crate::{
 	},
 };
 
-use super::state::State;
+use super::{
+	semantic_tokens,
+	settings::{format_options, include_inactive_references, lint_config},
+	state::State,
+};
 
 /// Builds and then returns a dispatcher handling the [`LspServerState::ActiveUninitialized`] state.
 pub(crate) fn create_dispatcher() -> LspServerStateDispatcher {
@@ -58,6 +64,12 @@ async fn on_initialize(
 		state.set_trace_value(trace_value);
 	}
 
+	if let Some(options) = &params.initialization_options {
+		state.analyzer.unwrap().configure_lints(lint_config(options));
+		state.include_inactive_references.store(include_inactive_references(options), Ordering::Relaxed);
+		*state.format_options.lock().unwrap() = format_options(options);
+	}
+
 	// If the server has been started without any workspace context, then simply return our 'default' capability.
 	if !state.has_workspaces() {
 		return Ok(create_initialize_result(false));
@@ -131,7 +143,7 @@ fn create_initialize_result(include_workspace_folders: bool) -> InitializeResult
 			}),
 			hover_provider: Some(HoverProviderCapability::Simple(true)),
 			signature_help_provider: Some(SignatureHelpOptions {
-				trigger_characters: Some(vec!["(".to_string(), ",".to_string()]),
+				trigger_characters: Some(vec!["(".to_string(), ",".to_string(), "<".to_string()]),
 				retrigger_characters: None,
 				work_done_progress_options: WorkDoneProgressOptions { work_done_progress: None },
 			}),
@@ -140,6 +152,29 @@ fn create_initialize_result(include_workspace_folders: bool) -> InitializeResult
 			type_definition_provider: Some(TypeDefinitionProviderCapability::Simple(true)),
 			implementation_provider: Some(ImplementationProviderCapability::Simple(true)),
 			references_provider: Some(OneOf::Left(true)),
+			document_highlight_provider: Some(OneOf::Left(true)),
+			document_symbol_provider: Some(OneOf::Left(true)),
+			workspace_symbol_provider: Some(OneOf::Left(true)),
+			semantic_tokens_provider: Some(SemanticTokensServerCapabilities::SemanticTokensOptions(
+				SemanticTokensOptions {
+					legend: semantic_tokens::legend(),
+					range: Some(true),
+					full: Some(SemanticTokensFullOptions::Delta { delta: Some(true) }),
+					..Default::default()
+				},
+			)),
+			rename_provider: Some(OneOf::Right(RenameOptions {
+				prepare_provider: Some(true),
+				work_done_progress_options: WorkDoneProgressOptions { work_done_progress: None },
+			})),
+			folding_range_provider: Some(FoldingRangeProviderCapability::Simple(true)),
+			selection_range_provider: Some(SelectionRangeProviderCapability::Simple(true)),
+			document_formatting_provider: Some(OneOf::Left(true)),
+			document_range_formatting_provider: Some(OneOf::Left(true)),
+			document_on_type_formatting_provider: Some(DocumentOnTypeFormattingOptions {
+				first_trigger_character: "}".to_string(),
+				more_trigger_character: Some(vec![";".to_string(), "\n".to_string()]),
+			}),
 			..Default::default()
 		},
 		server_info: Some(ServerInfo { name: String::from("P4 Analyzer"), version: Some(String::from("0.0.0")) }),
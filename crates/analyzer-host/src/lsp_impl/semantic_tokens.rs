@@ -0,0 +1,147 @@
+use analyzer_abstractions::lsp_types::{
+	SemanticToken, SemanticTokenModifier, SemanticTokenType, SemanticTokens, SemanticTokensEdit, SemanticTokensLegend,
+};
+use analyzer_core::{
+	ast::Direction,
+	lsp_file::LspFile,
+	semantic_tokens::{self as core, SemanticTokenKind},
+	symbols::SymbolKind,
+};
+
+/// The token types of the legend, in the order of their indices. The custom types are given a super type by the
+/// `'semanticTokenTypes'` contributed by the VS Code extension.
+const TOKEN_TYPES: &[SemanticTokenType] = &[
+	SemanticTokenType::TYPE,
+	SemanticTokenType::STRUCT,
+	SemanticTokenType::new("header"),
+	SemanticTokenType::PROPERTY,
+	SemanticTokenType::ENUM,
+	SemanticTokenType::ENUM_MEMBER,
+	SemanticTokenType::VARIABLE,
+	SemanticTokenType::PARAMETER,
+	SemanticTokenType::TYPE_PARAMETER,
+	SemanticTokenType::FUNCTION,
+	SemanticTokenType::METHOD,
+	SemanticTokenType::new("action"),
+	SemanticTokenType::new("table"),
+	SemanticTokenType::new("state"),
+	SemanticTokenType::new("extern"),
+	SemanticTokenType::CLASS,
+	SemanticTokenType::INTERFACE,
+	SemanticTokenType::MACRO,
+	// the code of inactive preprocessor regions, as reported by other C-family language servers
+	SemanticTokenType::COMMENT,
+];
+
+/// The token modifiers of the legend, in the order of their bits.
+const TOKEN_MODIFIERS: &[SemanticTokenModifier] = &[
+	SemanticTokenModifier::DECLARATION,
+	SemanticTokenModifier::READONLY,
+	SemanticTokenModifier::DEFAULT_LIBRARY,
+	SemanticTokenModifier::new("in"),
+	SemanticTokenModifier::new("out"),
+	SemanticTokenModifier::new("inout"),
+];
+
+/// Returns the legend of the semantic tokens sent to the client.
+pub(crate) fn legend() -> SemanticTokensLegend {
+	SemanticTokensLegend { token_types: TOKEN_TYPES.to_vec(), token_modifiers: TOKEN_MODIFIERS.to_vec() }
+}
+
+/// Encodes semantic tokens, in order, relatively to each other as required by the LSP.
+pub(crate) fn encode(lsp: &LspFile, tokens: &[core::SemanticToken]) -> Vec<SemanticToken> {
+	let mut encoded = vec![];
+	let (mut line, mut start) = (0, 0);
+	for token in tokens {
+		let range = lsp.byte_range_to_lsp_range(&token.span);
+		// the tokens are names and the other lexemes of inactive regions, which do not span lines but for comments
+		if range.start.line != range.end.line {
+			continue;
+		}
+		let (token_type, modifiers) = classify(token);
+		let (token_line, token_start) = (range.start.line as u32, range.start.character as u32);
+		encoded.push(SemanticToken {
+			delta_line: token_line - line,
+			delta_start: if token_line == line { token_start - start } else { token_start },
+			length: (range.end.character - range.start.character) as u32,
+			token_type,
+			token_modifiers_bitset: modifiers,
+		});
+		(line, start) = (token_line, token_start);
+	}
+	encoded
+}
+
+/// Returns the single edit turning the `previous` tokens into the `current` ones, replacing what lies between their
+/// common prefix and suffix, or `None` if they are the same.
+pub(crate) fn diff(previous: &SemanticTokens, current: &SemanticTokens) -> Option<SemanticTokensEdit> {
+	let (previous, current) = (&previous.data, &current.data);
+	let prefix = previous.iter().zip(current).take_while(|(a, b)| a == b).count();
+	if prefix == previous.len() && prefix == current.len() {
+		return None;
+	}
+	let suffix = previous[prefix..]
+		.iter()
+		.rev()
+		.zip(current[prefix..].iter().rev())
+		.take_while(|(a, b)| a == b)
+		.count();
+
+	// the edits are made to the integers of the encoding, five per token
+	Some(SemanticTokensEdit {
+		start: (prefix * 5) as u32,
+		delete_count: ((previous.len() - prefix - suffix) * 5) as u32,
+		data: Some(current[prefix..current.len() - suffix].to_vec()),
+	})
+}
+
+/// Returns the index of the type and the bits of the modifiers of a token in the legend.
+fn classify(token: &core::SemanticToken) -> (u32, u32) {
+	let index = |ty: SemanticTokenType| TOKEN_TYPES.iter().position(|t| *t == ty).unwrap() as u32;
+	let bit = |modifier: SemanticTokenModifier| 1 << TOKEN_MODIFIERS.iter().position(|m| *m == modifier).unwrap();
+
+	let mut modifiers = 0;
+	if token.declaration {
+		modifiers |= bit(SemanticTokenModifier::DECLARATION);
+	}
+	if token.library {
+		modifiers |= bit(SemanticTokenModifier::DEFAULT_LIBRARY);
+	}
+	let kind = match &token.kind {
+		SemanticTokenKind::Inactive => return (index(SemanticTokenType::COMMENT), 0),
+		SemanticTokenKind::Symbol(kind) => kind,
+	};
+	let ty = match kind {
+		SymbolKind::Constant => {
+			modifiers |= bit(SemanticTokenModifier::READONLY);
+			SemanticTokenType::VARIABLE
+		}
+		SymbolKind::Variable | SymbolKind::Instance => SemanticTokenType::VARIABLE,
+		SymbolKind::Parameter(direction) => {
+			modifiers |= match direction {
+				Some(Direction::In) => bit(SemanticTokenModifier::new("in")),
+				Some(Direction::Out) => bit(SemanticTokenModifier::new("out")),
+				Some(Direction::InOut) => bit(SemanticTokenModifier::new("inout")),
+				None => 0,
+			};
+			SemanticTokenType::PARAMETER
+		}
+		SymbolKind::Typedef | SymbolKind::Type => SemanticTokenType::TYPE,
+		SymbolKind::Header | SymbolKind::HeaderUnion => SemanticTokenType::new("header"),
+		SymbolKind::Struct => SemanticTokenType::STRUCT,
+		SymbolKind::Field => SemanticTokenType::PROPERTY,
+		SymbolKind::Enum => SemanticTokenType::ENUM,
+		SymbolKind::EnumMember | SymbolKind::ErrorMember | SymbolKind::MatchKind => SemanticTokenType::ENUM_MEMBER,
+		SymbolKind::ExternObject => SemanticTokenType::new("extern"),
+		SymbolKind::ExternFunction | SymbolKind::Function => SemanticTokenType::FUNCTION,
+		SymbolKind::Method => SemanticTokenType::METHOD,
+		SymbolKind::Action => SemanticTokenType::new("action"),
+		SymbolKind::Table => SemanticTokenType::new("table"),
+		SymbolKind::State => SemanticTokenType::new("state"),
+		SymbolKind::Parser | SymbolKind::Control => SemanticTokenType::CLASS,
+		SymbolKind::ParserType | SymbolKind::ControlType | SymbolKind::Package => SemanticTokenType::INTERFACE,
+		SymbolKind::TypeParameter => SemanticTokenType::TYPE_PARAMETER,
+		SymbolKind::Macro => SemanticTokenType::MACRO,
+	};
+	(index(ty), modifiers)
+}
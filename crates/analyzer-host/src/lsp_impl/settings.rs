@@ -0,0 +1,61 @@
+use analyzer_abstractions::tracing::warn;
+use analyzer_core::{
+	formatting::{BraceStyle, FormatOptions},
+	lints::{Level, LintConfig},
+};
+use serde_json::Value;
+
+/// Returns the settings of the P4 Analyzer, which may be nested in a `"p4analyzer"` section, or in the `"p4-analyzer"`
+/// section synchronized by the Visual Studio Code extension.
+///
+/// The settings are given as the initialization options of the `'initialize'` request, and as the settings of the
+/// `'workspace/didChangeConfiguration'` notification.
+fn section(settings: &Value) -> &Value {
+	settings.get("p4-analyzer").or_else(|| settings.get("p4analyzer")).unwrap_or(settings)
+}
+
+/// Reads the levels of the lints from the settings of the P4 Analyzer, such as `{ "lints": { "unused-action": "allow" } }`.
+pub(crate) fn lint_config(settings: &Value) -> LintConfig {
+	let settings = section(settings);
+	let mut config = LintConfig::default();
+	let Some(lints) = settings.get("lints").and_then(Value::as_object) else { return config };
+
+	for (name, level) in lints {
+		match level.as_str().and_then(Level::parse) {
+			Some(level) if config.set(name, level) => (),
+			Some(_) => warn!(lint = name.as_str(), "Ignoring the level of an unknown lint."),
+			None => warn!(lint = name.as_str(), "Ignoring an invalid lint level: {}", level),
+		}
+	}
+	config
+}
+
+/// Reads whether the references to a symbol include the identifiers named like it in inactive preprocessor regions,
+/// from settings such as `{ "references": { "includeInactive": true } }`. They are left out by default.
+pub(crate) fn include_inactive_references(settings: &Value) -> bool {
+	let settings = section(settings);
+	let references = settings.get("references");
+	references
+		.and_then(|references| references.get("includeInactive"))
+		.and_then(Value::as_bool)
+		.unwrap_or(false)
+}
+
+/// Reads the layout of formatted code from settings such as `{ "formatting": { "braceStyle": "nextLine",
+/// "maxLineWidth": 120 } }`. The indentation is given by each formatting request instead.
+pub(crate) fn format_options(settings: &Value) -> FormatOptions {
+	let settings = section(settings);
+	let mut options = FormatOptions::default();
+	let Some(formatting) = settings.get("formatting") else { return options };
+
+	match formatting.get("braceStyle").and_then(Value::as_str) {
+		Some("sameLine") => options.brace_style = BraceStyle::SameLine,
+		Some("nextLine") => options.brace_style = BraceStyle::NextLine,
+		Some(style) => warn!(style, "Ignoring an unknown brace style."),
+		None => (),
+	}
+	if let Some(width) = formatting.get("maxLineWidth").and_then(Value::as_u64) {
+		options.max_width = width as usize;
+	}
+	options
+}
@@ -1,16 +1,17 @@
 use std::{
 	cell::{RefCell, RefMut},
-	sync::Arc,
+	collections::HashMap,
+	sync::{atomic::AtomicBool, Arc, Mutex},
 };
 
 use analyzer_abstractions::{
 	fs::AnyEnumerableFileSystem,
 	futures::future::join_all,
 	futures_extensions::async_extensions::AsyncPool,
-	lsp_types::{TraceValue, Url},
+	lsp_types::{SemanticTokens, TraceValue, Url},
 	tracing::info,
 };
-use analyzer_core::base_abstractions::FileId;
+use analyzer_core::{base_abstractions::FileId, formatting::FormatOptions};
 use async_channel::{Receiver, Sender};
 use itertools::Itertools;
 
@@ -80,6 +81,16 @@ pub struct State {
 	/// The [`RequestManager`] instance to use when sending LSP client requests.
 	pub request_manager: RequestManager,
 
+	/// Whether the references to a symbol include the identifiers named like it in inactive preprocessor regions.
+	pub include_inactive_references: Arc<AtomicBool>,
+
+	/// The last semantic tokens sent for each document, which the next `'semanticTokens/full/delta'` request is
+	/// answered against.
+	pub semantic_tokens: Arc<Mutex<SemanticTokensCache>>,
+
+	/// The layout of formatted code, but for the indentation given by each formatting request.
+	pub format_options: Arc<Mutex<FormatOptions>>,
+
 	/// A [`ProgressManager`] instance that can be used to to report work done progress to the LSP client.
 	progress_manager: Option<ProgressManager>,
 
@@ -90,6 +101,26 @@ pub struct State {
 	background_parse_channel: (Sender<Url>, Receiver<Url>),
 }
 
+/// The semantic tokens last sent for each document, identified by their result id.
+#[derive(Default)]
+pub struct SemanticTokensCache {
+	next_result_id: usize,
+	documents: HashMap<Url, SemanticTokens>,
+}
+
+impl SemanticTokensCache {
+	/// Records the tokens sent for a document under a new result id, returning the previous ones.
+	pub fn insert(&mut self, uri: Url, mut tokens: SemanticTokens) -> (String, Option<SemanticTokens>) {
+		self.next_result_id += 1;
+		let result_id = self.next_result_id.to_string();
+		tokens.result_id = Some(result_id.clone());
+		(result_id, self.documents.insert(uri, tokens))
+	}
+
+	/// Forgets the tokens sent for a closed document.
+	pub fn remove(&mut self, uri: &Url) { self.documents.remove(uri); }
+}
+
 impl State {
 	/// Initializes a new [`State`] instance.
 	pub fn new(
@@ -105,6 +136,9 @@ impl State {
 			analyzer: Arc::new(AnalyzerWrapper::new(sender)),
 			file_system,
 			request_manager,
+			include_inactive_references: Arc::new(AtomicBool::new(false)),
+			semantic_tokens: Default::default(),
+			format_options: Default::default(),
 			progress_manager: None,
 			workspace_manager: None,
 			background_parse_channel,
@@ -1,7 +1,15 @@
-import { workspace, ConfigurationScope } from "vscode";
+import { workspace, ConfigurationScope, WorkspaceConfiguration } from "vscode";
 
 const CONFIG_BASE = "p4-analyzer";
 
+/**
+ * The section of the configuration that is synchronized with the P4 Analyzer server, as the initialization options of
+ * its `'initialize'` request and the settings of its `'workspace/didChangeConfiguration'` notifications.
+ *
+ * @internal
+ */
+export const SYNCHRONIZED_SECTION = CONFIG_BASE;
+
 /**
  * A utility type that provides keyed access to the values of a `WorkspaceConfiguration` object.
  *
@@ -64,3 +72,15 @@ export interface ServerConfiguration {
 export function getServerConfiguration(scope?: ConfigurationScope): WorkspaceConfigurationAccessor<ServerConfiguration> {
 	return workspace.getConfiguration(`${CONFIG_BASE}.server`, scope);
 }
+
+/**
+ * Retrieves the synchronized section of the configuration, keyed by its name like the settings of the
+ * `'workspace/didChangeConfiguration'` notifications.
+ *
+ * @returns An object holding the {@link SYNCHRONIZED_SECTION} of the configuration.
+ *
+ * @internal
+ */
+export function getSynchronizedConfiguration(): { [SYNCHRONIZED_SECTION]: WorkspaceConfiguration } {
+	return { [SYNCHRONIZED_SECTION]: workspace.getConfiguration(SYNCHRONIZED_SECTION) };
+}
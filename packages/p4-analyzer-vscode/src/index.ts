@@ -1,10 +1,15 @@
-import { window, ExtensionContext } from "vscode";
-import { LanguageClient, LanguageClientOptions, ServerOptions, TransportKind, Trace } from "vscode-languageclient/node";
-import { getServerConfiguration } from "./configuration";
+import { window, workspace, ExtensionContext } from "vscode";
+import { LanguageClient, LanguageClientOptions, ServerOptions, TextDocumentIdentifier, TransportKind, Trace } from "vscode-languageclient/node";
+import { getServerConfiguration, getSynchronizedConfiguration, SYNCHRONIZED_SECTION } from "./configuration";
 import "./extensions/LanguageClient";
 
 let client: LanguageClient | null = null;
 
+/**
+ * The scheme of the files embedded in the P4 Analyzer, such as the core library, which the server may refer to.
+ */
+const BUILTIN_SCHEME = "builtin";
+
 /**
  * The function that is invoked when the extension is activated.
  *
@@ -49,6 +54,10 @@ async function onTryActivate(context: ExtensionContext): Promise<void> {
 		documentSelector: [
 			{ scheme: "file", language: "p4" }
 		],
+		initializationOptions: getSynchronizedConfiguration,
+		synchronize: {
+			configurationSection: SYNCHRONIZED_SECTION
+		},
 		traceOutputChannel: window.createOutputChannel("P4 Analyzer Language Server - Trace", "p4")
 	}
 
@@ -56,6 +65,15 @@ async function onTryActivate(context: ExtensionContext): Promise<void> {
 	client.setTrace(Trace.Messages);
 	client.setP4AnalyzerHandlers();
 	client.start();
+
+	context.subscriptions.push(workspace.registerTextDocumentContentProvider(BUILTIN_SCHEME, {
+		provideTextDocumentContent: async (uri) => {
+			const params = TextDocumentIdentifier.create(uri.toString());
+			const contents = await client?.sendRequest<string | null>("p4analyzer/builtinFileContents", params);
+
+			return contents ?? "";
+		}
+	}));
 }
 
 